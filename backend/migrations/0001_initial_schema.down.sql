@@ -0,0 +1,14 @@
+DROP TABLE IF EXISTS embeddings;
+DROP TABLE IF EXISTS logs;
+DROP TABLE IF EXISTS chat_history;
+DROP TABLE IF EXISTS config;
+DROP TABLE IF EXISTS user_settings;
+DROP TABLE IF EXISTS system_logs;
+DROP TABLE IF EXISTS journal_entries;
+DROP TABLE IF EXISTS dreams;
+DROP TABLE IF EXISTS chat_messages;
+DROP TABLE IF EXISTS chat_branches;
+DROP TABLE IF EXISTS chats;
+DROP TABLE IF EXISTS tags;
+DROP TABLE IF EXISTS chat_groups;
+DROP TABLE IF EXISTS personas;
@@ -0,0 +1,200 @@
+-- Baseline schema (formerly db::init_schema).
+-- Idempotent so databases created before migrations were tracked can adopt it.
+
+-- ============================================================
+-- Personas
+-- ============================================================
+CREATE TABLE IF NOT EXISTS personas (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    persona_type TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    avatar TEXT,
+    bubble_color TEXT,
+    system_prompt TEXT,
+    global_memory_enabled BOOLEAN DEFAULT TRUE,
+    current_mood TEXT,
+    voice JSONB,
+    metadata JSONB DEFAULT '{}',
+    tags JSONB DEFAULT '[]',
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+ALTER TABLE personas ADD COLUMN IF NOT EXISTS global_memory_enabled BOOLEAN DEFAULT TRUE;
+ALTER TABLE personas ADD COLUMN IF NOT EXISTS current_mood TEXT;
+ALTER TABLE personas ADD COLUMN IF NOT EXISTS voice JSONB;
+
+-- ============================================================
+-- Chat groups
+-- ============================================================
+CREATE TABLE IF NOT EXISTS chat_groups (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    color TEXT NOT NULL DEFAULT '#6b7280',
+    collapsed BOOLEAN DEFAULT FALSE,
+    sort_order INT DEFAULT 0,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- ============================================================
+-- Tags
+-- ============================================================
+CREATE TABLE IF NOT EXISTS tags (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    color TEXT NOT NULL DEFAULT '#6b7280',
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- ============================================================
+-- Chats
+-- ============================================================
+CREATE TABLE IF NOT EXISTS chats (
+    id TEXT PRIMARY KEY,
+    title TEXT NOT NULL,
+    current_branch_id TEXT NOT NULL,
+    group_id TEXT REFERENCES chat_groups(id) ON DELETE SET NULL,
+    tags JSONB DEFAULT '[]',
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_chats_group ON chats(group_id);
+
+-- ============================================================
+-- Chat branches
+-- ============================================================
+CREATE TABLE IF NOT EXISTS chat_branches (
+    id TEXT PRIMARY KEY,
+    chat_id TEXT NOT NULL REFERENCES chats(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    parent_branch_id TEXT REFERENCES chat_branches(id) ON DELETE SET NULL,
+    fork_point_message_id TEXT,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_branches_chat ON chat_branches(chat_id);
+
+-- ============================================================
+-- Chat messages
+-- ============================================================
+CREATE TABLE IF NOT EXISTS chat_messages (
+    id TEXT PRIMARY KEY,
+    branch_id TEXT NOT NULL REFERENCES chat_branches(id) ON DELETE CASCADE,
+    role TEXT NOT NULL,
+    content TEXT NOT NULL,
+    user_persona_id TEXT,
+    ai_persona_id TEXT,
+    model TEXT,
+    mood TEXT,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_messages_branch ON chat_messages(branch_id, created_at);
+
+-- ============================================================
+-- Dreams
+-- ============================================================
+CREATE TABLE IF NOT EXISTS dreams (
+    id TEXT PRIMARY KEY,
+    title TEXT NOT NULL,
+    content TEXT NOT NULL,
+    mood TEXT,
+    persona_id TEXT,
+    persona_name TEXT,
+    tags TEXT[],
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+ALTER TABLE dreams ADD COLUMN IF NOT EXISTS persona_id TEXT;
+ALTER TABLE dreams ADD COLUMN IF NOT EXISTS persona_name TEXT;
+ALTER TABLE dreams ADD COLUMN IF NOT EXISTS tags TEXT[];
+
+-- ============================================================
+-- Journal entries
+-- ============================================================
+CREATE TABLE IF NOT EXISTS journal_entries (
+    id TEXT PRIMARY KEY,
+    date TEXT NOT NULL,
+    title TEXT NOT NULL,
+    content TEXT NOT NULL,
+    mood TEXT,
+    persona_id TEXT,
+    persona_name TEXT,
+    tags TEXT[],
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+ALTER TABLE journal_entries ADD COLUMN IF NOT EXISTS persona_id TEXT;
+ALTER TABLE journal_entries ADD COLUMN IF NOT EXISTS persona_name TEXT;
+ALTER TABLE journal_entries ADD COLUMN IF NOT EXISTS tags TEXT[];
+
+CREATE INDEX IF NOT EXISTS idx_journal_date ON journal_entries(date);
+
+-- ============================================================
+-- System logs
+-- ============================================================
+CREATE TABLE IF NOT EXISTS system_logs (
+    id TEXT PRIMARY KEY,
+    level TEXT NOT NULL,
+    message TEXT NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- ============================================================
+-- User settings (editor config, UI preferences, etc.)
+-- ============================================================
+CREATE TABLE IF NOT EXISTS user_settings (
+    id TEXT PRIMARY KEY DEFAULT 'default',
+    editor_settings JSONB DEFAULT '{}',
+    ui_settings JSONB DEFAULT '{}',
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- ============================================================
+-- Configuration
+-- ============================================================
+CREATE TABLE IF NOT EXISTS config (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- ============================================================
+-- Chat history (legacy)
+-- ============================================================
+CREATE TABLE IF NOT EXISTS chat_history (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    session_id TEXT NOT NULL,
+    role TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_chat_session ON chat_history(session_id, created_at);
+
+-- ============================================================
+-- Logs (legacy)
+-- ============================================================
+CREATE TABLE IF NOT EXISTS logs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    date DATE NOT NULL,
+    content TEXT,
+    summary TEXT,
+    embeddings_stored BOOLEAN DEFAULT FALSE,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_logs_date ON logs(date);
+
+-- ============================================================
+-- Embeddings metadata
+-- ============================================================
+CREATE TABLE IF NOT EXISTS embeddings (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    embedding_id TEXT NOT NULL UNIQUE,
+    source TEXT,
+    metadata JSONB,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
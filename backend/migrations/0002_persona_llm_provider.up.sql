@@ -0,0 +1,2 @@
+-- Per-persona inference backend (see models::ProviderConfig)
+ALTER TABLE personas ADD COLUMN IF NOT EXISTS llm_provider JSONB;
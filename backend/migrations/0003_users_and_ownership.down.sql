@@ -0,0 +1,14 @@
+DROP INDEX IF EXISTS idx_settings_user CASCADE;
+DROP INDEX IF EXISTS idx_journal_user;
+DROP INDEX IF EXISTS idx_dreams_user;
+DROP INDEX IF EXISTS idx_personas_user;
+DROP INDEX IF EXISTS idx_chats_user;
+
+ALTER TABLE user_settings DROP COLUMN IF EXISTS user_id;
+ALTER TABLE journal_entries DROP COLUMN IF EXISTS user_id;
+ALTER TABLE dreams DROP COLUMN IF EXISTS user_id;
+ALTER TABLE personas DROP COLUMN IF EXISTS user_id;
+ALTER TABLE chats DROP COLUMN IF EXISTS user_id;
+
+DROP TABLE IF EXISTS user_sessions;
+DROP TABLE IF EXISTS users;
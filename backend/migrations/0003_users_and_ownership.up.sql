@@ -0,0 +1,42 @@
+-- Local accounts, login sessions and per-user ownership.
+-- Rows with a NULL user_id predate accounts: the first account to register
+-- adopts unowned chats and settings (see db::adopt_unowned_rows). Personas,
+-- dreams and journal entries with a NULL owner are shared with everyone.
+
+-- ============================================================
+-- Users
+-- ============================================================
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    display_name TEXT,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+-- ============================================================
+-- Sessions (only a SHA-256 of the token is stored)
+-- ============================================================
+CREATE TABLE IF NOT EXISTS user_sessions (
+    token_hash TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_sessions_user ON user_sessions(user_id);
+
+-- ============================================================
+-- Ownership
+-- ============================================================
+ALTER TABLE chats ADD COLUMN IF NOT EXISTS user_id TEXT REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE personas ADD COLUMN IF NOT EXISTS user_id TEXT REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE dreams ADD COLUMN IF NOT EXISTS user_id TEXT REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE journal_entries ADD COLUMN IF NOT EXISTS user_id TEXT REFERENCES users(id) ON DELETE CASCADE;
+ALTER TABLE user_settings ADD COLUMN IF NOT EXISTS user_id TEXT REFERENCES users(id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS idx_chats_user ON chats(user_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_personas_user ON personas(user_id);
+CREATE INDEX IF NOT EXISTS idx_dreams_user ON dreams(user_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_journal_user ON journal_entries(user_id, date);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_settings_user ON user_settings(user_id);
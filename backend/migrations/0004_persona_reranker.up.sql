@@ -0,0 +1,2 @@
+-- Per-persona reranking stage for retrieved memories (see models::RerankerConfig)
+ALTER TABLE personas ADD COLUMN IF NOT EXISTS reranker JSONB;
@@ -0,0 +1,11 @@
+-- Rolling per-chat conversation summaries (the durable copy of the
+-- SessionContext working memory kept in Dragonfly)
+CREATE TABLE IF NOT EXISTS chat_summaries (
+    chat_id TEXT PRIMARY KEY REFERENCES chats(id) ON DELETE CASCADE,
+    summary TEXT NOT NULL,
+    active_goal TEXT,
+    topics JSONB DEFAULT '[]',
+    turn_count INT NOT NULL DEFAULT 0,
+    summarized_through TEXT,
+    updated_at TIMESTAMPTZ DEFAULT NOW()
+);
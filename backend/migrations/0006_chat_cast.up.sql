@@ -0,0 +1,3 @@
+-- Group chats: the AI personas sharing a chat and their turn-taking policy
+-- (see models::ChatCast)
+ALTER TABLE chats ADD COLUMN IF NOT EXISTS persona_cast JSONB;
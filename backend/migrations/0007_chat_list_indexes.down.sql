@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_chats_user_title;
+DROP INDEX IF EXISTS idx_chats_user_updated;
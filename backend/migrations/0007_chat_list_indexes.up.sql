@@ -0,0 +1,3 @@
+-- Keyset pagination of the chat list (see db::list_chat_summaries)
+CREATE INDEX IF NOT EXISTS idx_chats_user_updated ON chats(user_id, updated_at, id);
+CREATE INDEX IF NOT EXISTS idx_chats_user_title ON chats(user_id, title, id);
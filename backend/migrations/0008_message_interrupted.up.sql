@@ -0,0 +1,2 @@
+-- Replies whose generation was stopped before it finished (see generation.rs)
+ALTER TABLE chat_messages ADD COLUMN IF NOT EXISTS interrupted BOOL NOT NULL DEFAULT false;
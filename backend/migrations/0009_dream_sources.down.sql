@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_messages_persona;
+ALTER TABLE dreams DROP COLUMN IF EXISTS source_message_ids;
@@ -0,0 +1,4 @@
+-- Chat messages a dream was drawn from (see dreaming.rs)
+ALTER TABLE dreams ADD COLUMN IF NOT EXISTS source_message_ids TEXT[];
+-- A persona's recent messages, for dreaming and reflection
+CREATE INDEX IF NOT EXISTS idx_messages_persona ON chat_messages(ai_persona_id, created_at);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_deletion_jobs_due;
+DROP TABLE IF EXISTS deletion_jobs;
+DROP TABLE IF EXISTS chat_images;
@@ -0,0 +1,22 @@
+-- Images generated from a chat's replies, removed with the chat. Not tied
+-- to chats by a foreign key: the rows must outlive the chat until its
+-- images are deleted (see deletion.rs)
+CREATE TABLE IF NOT EXISTS chat_images (
+    chat_id TEXT NOT NULL,
+    filename TEXT NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (chat_id, filename)
+);
+
+-- Stores a deleted chat still has to be removed from, one row per store,
+-- queued with the delete and retried by the tick loop until they succeed
+CREATE TABLE IF NOT EXISTS deletion_jobs (
+    chat_id TEXT NOT NULL,
+    store TEXT NOT NULL,
+    attempts INT NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (chat_id, store)
+);
+CREATE INDEX IF NOT EXISTS idx_deletion_jobs_due ON deletion_jobs(next_attempt_at);
@@ -0,0 +1,22 @@
+-- Owner of each image in the canvas, by kind: `generated` images live in
+-- atelier/canvas, `reference` uploads in atelier/canvas/references.
+-- Files without a row predate ownership and are served to no one.
+CREATE TABLE IF NOT EXISTS images (
+    kind TEXT NOT NULL,
+    filename TEXT NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    prompt TEXT,
+    persona_id TEXT,
+    persona_name TEXT,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (kind, filename)
+);
+CREATE INDEX IF NOT EXISTS idx_images_user ON images(user_id, kind, created_at);
+
+-- Images generated from chat replies belong to the chat's owner
+INSERT INTO images (kind, filename, user_id, created_at)
+SELECT 'generated', ci.filename, c.user_id, ci.created_at
+FROM chat_images ci
+JOIN chats c ON c.id = ci.chat_id
+WHERE c.user_id IS NOT NULL
+ON CONFLICT (kind, filename) DO NOTHING;
@@ -0,0 +1,202 @@
+//! Archive import
+//!
+//! Shared reflections and dreams are also written as Markdown to
+//! `../archive/journal` and `../archive/dreams`. `azera_core --import-archive`
+//! reads them back into CockroachDB as shared entries, e.g. after restoring
+//! a fresh database. It runs from the command line only, since the entries
+//! it creates are visible to every user.
+//!
+//! The import is idempotent: a journal file is skipped when a shared entry of
+//! the same persona already exists for its date, a dream file when a shared
+//! dream of the same persona was recorded in the same second.
+
+use crate::components::DEFAULT_PERSONA_ID;
+use crate::models::{Dream, JournalEntry};
+use crate::{db, reflection};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sqlx::{Pool, Postgres};
+use std::path::{Path, PathBuf};
+
+pub const JOURNAL_DIR: &str = "../archive/journal";
+pub const DREAMS_DIR: &str = "../archive/dreams";
+
+/// Outcome of importing one archive folder
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ImportCounts {
+    pub imported: usize,
+    /// Already in the database
+    pub skipped: usize,
+    pub errors: usize,
+}
+
+/// Archive file name (without `.md`) of a shared dream; the default persona
+/// keeps the plain `dream_<timestamp>` name
+pub fn dream_stem(timestamp: &DateTime<Utc>, persona_id: &str) -> String {
+    let stamp = timestamp.format("%Y%m%d_%H%M%S");
+    if persona_id == DEFAULT_PERSONA_ID {
+        format!("dream_{}", stamp)
+    } else {
+        format!("dream_{}_{}", stamp, persona_id)
+    }
+}
+
+/// `(timestamp, persona_id)` of a dream archive file name written by [`dream_stem`]
+pub fn parse_dream_stem(stem: &str) -> Option<(DateTime<Utc>, &str)> {
+    let rest = stem.strip_prefix("dream_")?;
+    let stamp = rest.get(..15)?;
+    let timestamp = NaiveDateTime::parse_from_str(stamp, "%Y%m%d_%H%M%S").ok()?.and_utc();
+    match &rest[15..] {
+        "" => Some((timestamp, DEFAULT_PERSONA_ID)),
+        persona => persona.strip_prefix('_').filter(|id| !id.is_empty()).map(|id| (timestamp, id)),
+    }
+}
+
+/// The `.md` files of an archive folder, with their stems
+fn markdown_files(dir: &str) -> Result<Vec<(PathBuf, String)>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|e| e == "md") {
+            if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                let stem = stem.to_string();
+                files.push((path, stem));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Title from the first line (`# Title`)
+fn title_of(content: &str, fallback: &str) -> String {
+    content.lines().next()
+        .map(|l| l.trim_start_matches('#').trim())
+        .filter(|t| !t.is_empty())
+        .unwrap_or(fallback)
+        .to_string()
+}
+
+async fn persona_name(pool: &Pool<Postgres>, persona_id: &str) -> String {
+    match db::get_persona(pool, persona_id).await {
+        Ok(Some(persona)) => persona.name,
+        _ => persona_id.to_string(),
+    }
+}
+
+fn read(path: &Path, counts: &mut ImportCounts) -> Option<String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Some(content),
+        Err(e) => {
+            tracing::error!("Failed to read {}: {}", path.display(), e);
+            counts.errors += 1;
+            None
+        }
+    }
+}
+
+/// Import `../archive/journal` as shared journal entries
+pub async fn import_journal(pool: &Pool<Postgres>) -> Result<ImportCounts> {
+    let mut counts = ImportCounts::default();
+    for (path, stem) in markdown_files(JOURNAL_DIR)? {
+        let Some((date, persona_id)) = reflection::parse_archive_stem(&stem) else {
+            tracing::warn!("Skipping journal archive file {}", path.display());
+            counts.errors += 1;
+            continue;
+        };
+        if db::shared_journal_entry_exists(pool, date, persona_id).await? {
+            counts.skipped += 1;
+            continue;
+        }
+        let Some(content) = read(&path, &mut counts) else { continue };
+        let entry = JournalEntry {
+            id: format!("journal_import_{}", stem.replace('-', "")),
+            date: date.to_string(),
+            title: title_of(&content, "Untitled"),
+            content,
+            mood: Some("reflective".to_string()),
+            persona_id: Some(persona_id.to_string()),
+            persona_name: Some(persona_name(pool, persona_id).await),
+            tags: Some(vec![]),
+            user_id: None,  // Shared agent memory
+            created_at: Utc::now(),
+        };
+        match db::create_journal_entry(pool, &entry).await {
+            Ok(_) => counts.imported += 1,
+            Err(e) => {
+                tracing::error!("Failed to import journal entry {}: {}", stem, e);
+                counts.errors += 1;
+            }
+        }
+    }
+    Ok(counts)
+}
+
+/// Import `../archive/dreams` as shared dreams
+pub async fn import_dreams(pool: &Pool<Postgres>) -> Result<ImportCounts> {
+    let mut counts = ImportCounts::default();
+    for (path, stem) in markdown_files(DREAMS_DIR)? {
+        let Some((timestamp, persona_id)) = parse_dream_stem(&stem) else {
+            tracing::warn!("Skipping dream archive file {}", path.display());
+            counts.errors += 1;
+            continue;
+        };
+        if db::shared_dream_exists(pool, persona_id, &timestamp).await? {
+            counts.skipped += 1;
+            continue;
+        }
+        let Some(content) = read(&path, &mut counts) else { continue };
+        let dream = Dream {
+            id: format!("dream_import_{}", stem.trim_start_matches("dream_")),
+            title: title_of(&content, "Untitled Dream"),
+            content,
+            timestamp,
+            mood: Some("dreaming".to_string()),
+            persona_id: Some(persona_id.to_string()),
+            persona_name: Some(persona_name(pool, persona_id).await),
+            tags: Some(vec!["imported".to_string()]),
+            user_id: None,  // Archive belongs to the shared agent
+            source_message_ids: None,
+        };
+        match db::create_dream(pool, &dream).await {
+            Ok(_) => counts.imported += 1,
+            Err(e) => {
+                tracing::error!("Failed to import dream {}: {}", stem, e);
+                counts.errors += 1;
+            }
+        }
+    }
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod dream_stem_tests {
+        use super::*;
+        use chrono::TimeZone;
+
+        #[test]
+        fn round_trips_timestamp_and_persona() {
+            let timestamp = Utc.with_ymd_and_hms(2026, 1, 29, 19, 45, 45).unwrap();
+            let stem = dream_stem(&timestamp, "persona_1234");
+            assert_eq!(stem, "dream_20260129_194545_persona_1234");
+            assert_eq!(parse_dream_stem(&stem), Some((timestamp, "persona_1234")));
+        }
+
+        #[test]
+        fn default_persona_keeps_the_plain_name() {
+            let timestamp = Utc.with_ymd_and_hms(2026, 1, 29, 19, 45, 45).unwrap();
+            assert_eq!(dream_stem(&timestamp, DEFAULT_PERSONA_ID), "dream_20260129_194545");
+            assert_eq!(parse_dream_stem("dream_20260129_194545"), Some((timestamp, DEFAULT_PERSONA_ID)));
+        }
+
+        #[test]
+        fn rejects_other_names() {
+            assert_eq!(parse_dream_stem("notes"), None);
+            assert_eq!(parse_dream_stem("dream_2026"), None);
+            assert_eq!(parse_dream_stem("dream_20260129_194545-extra"), None);
+        }
+    }
+}
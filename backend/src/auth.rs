@@ -0,0 +1,329 @@
+//! Local accounts and session authentication
+//!
+//! Passwords are hashed with argon2. Logging in issues an opaque session
+//! token; only its SHA-256 is stored in `user_sessions`, so a database leak
+//! doesn't leak usable tokens. Clients send the token either as
+//! `Authorization: Bearer <token>` or via the `azera_session` cookie (which
+//! lets `<img>` tags load protected images).
+//!
+//! `require_user` guards every non-public route and stores the resolved
+//! [`AuthUser`] in the request extensions; handlers take `AuthUser` as an
+//! extractor to scope their queries.
+
+use crate::{db, error::ApiError, AppState};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderMap, HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+use base64::Engine;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use tower_http::cors::CorsLayer;
+
+/// Session cookie name
+pub const SESSION_COOKIE: &str = "azera_session";
+/// How long a login stays valid
+pub const SESSION_TTL_DAYS: i64 = 30;
+
+const MIN_PASSWORD_LEN: usize = 8;
+const MAX_PASSWORD_LEN: usize = 256;
+
+/// The authenticated account making the request
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub id: String,
+    pub username: String,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<AuthUser>()
+            .cloned()
+            .ok_or_else(|| ApiError::Unauthorized("Authentication required".to_string()))
+    }
+}
+
+/// Middleware: resolve the session token to a user or reject with 401
+pub async fn require_user(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let token = token_from_headers(request.headers())
+        .ok_or_else(|| ApiError::Unauthorized("Authentication required".to_string()))?;
+
+    let user = db::get_session_user(&state.db, &hash_token(&token))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to look up session: {}", e);
+            ApiError::from(e).context("Failed to verify session")
+        })?
+        .ok_or_else(|| ApiError::Unauthorized("Session is invalid or expired".to_string()))?;
+
+    request.extensions_mut().insert(AuthUser {
+        id: user.id,
+        username: user.username,
+    });
+    Ok(next.run(request).await)
+}
+
+// ============================================================
+// Passwords & Tokens
+// ============================================================
+
+pub fn hash_password(password: &str) -> anyhow::Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))
+}
+
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false)
+}
+
+/// New random session token (256 bits, URL-safe base64)
+pub fn new_session_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Hash stored in `user_sessions.token_hash`
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Extract the session token from a Bearer header or the session cookie
+pub fn token_from_headers(headers: &HeaderMap) -> Option<String> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|t| !t.is_empty());
+    if let Some(token) = bearer {
+        return Some(token.to_string());
+    }
+
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, value)| *name == SESSION_COOKIE && !value.is_empty())
+        .map(|(_, value)| value.to_string())
+}
+
+/// `Set-Cookie` value for a new session
+pub fn session_cookie(token: &str) -> String {
+    format!(
+        "{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}{}",
+        SESSION_COOKIE,
+        token,
+        SESSION_TTL_DAYS * 24 * 60 * 60,
+        if secure_cookies() { "; Secure" } else { "" }
+    )
+}
+
+/// `Set-Cookie` value that removes the session cookie
+pub fn clear_session_cookie() -> String {
+    format!("{}=; Path=/; HttpOnly; SameSite=Lax; Max-Age=0", SESSION_COOKIE)
+}
+
+fn secure_cookies() -> bool {
+    std::env::var("SESSION_COOKIE_SECURE")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false)
+}
+
+// ============================================================
+// Validation
+// ============================================================
+
+/// Usernames: 3–32 characters of letters, digits, `_`, `-` or `.`
+pub fn validate_username(username: &str) -> Result<(), ApiError> {
+    let valid_len = (3..=32).contains(&username.chars().count());
+    let valid_chars = username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if valid_len && valid_chars {
+        Ok(())
+    } else {
+        Err(ApiError::BadRequest(
+            "Username must be 3-32 characters of letters, digits, '_', '-' or '.'".to_string(),
+        ))
+    }
+}
+
+pub fn validate_password(password: &str) -> Result<(), ApiError> {
+    let len = password.chars().count();
+    if len < MIN_PASSWORD_LEN {
+        return Err(ApiError::BadRequest(format!(
+            "Password must be at least {} characters",
+            MIN_PASSWORD_LEN
+        )));
+    }
+    if len > MAX_PASSWORD_LEN {
+        return Err(ApiError::BadRequest("Password is too long".to_string()));
+    }
+    Ok(())
+}
+
+/// Whether new accounts may be created. The first account can always be
+/// created; after that `ALLOW_REGISTRATION=false` closes sign-ups.
+pub fn registration_open(existing_users: i64) -> bool {
+    existing_users == 0
+        || std::env::var("ALLOW_REGISTRATION")
+            .map(|v| v != "false" && v != "0")
+            .unwrap_or(true)
+}
+
+// ============================================================
+// CORS
+// ============================================================
+
+/// CORS restricted to the configured frontend origins
+/// (`CORS_ALLOWED_ORIGINS`, comma-separated). Credentials are allowed so
+/// the session cookie travels with cross-origin requests.
+pub fn cors_layer() -> CorsLayer {
+    let origins = std::env::var("CORS_ALLOWED_ORIGINS")
+        .unwrap_or_else(|_| "http://localhost:5173,http://127.0.0.1:5173".to_string());
+    let origins: Vec<HeaderValue> = origins
+        .split(',')
+        .map(str::trim)
+        .filter(|o| !o.is_empty())
+        .filter_map(|o| match HeaderValue::from_str(o) {
+            Ok(v) => Some(v),
+            Err(_) => {
+                tracing::warn!("⚠️ Ignoring invalid CORS origin: {}", o);
+                None
+            }
+        })
+        .collect();
+
+    CorsLayer::new()
+        .allow_origin(origins)
+        .allow_credentials(true)
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            header::ACCEPT,
+            header::HeaderName::from_static("last-event-id"),
+            header::HeaderName::from_static(crate::error::REQUEST_ID_HEADER),
+        ])
+        .expose_headers([header::HeaderName::from_static(crate::error::REQUEST_ID_HEADER)])
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod password_tests {
+        use super::*;
+
+        #[test]
+        fn hash_then_verify() {
+            let hash = hash_password("correct horse battery").unwrap();
+            assert!(hash.starts_with("$argon2"));
+            assert!(verify_password("correct horse battery", &hash));
+            assert!(!verify_password("wrong password", &hash));
+        }
+
+        #[test]
+        fn garbage_hash_never_verifies() {
+            assert!(!verify_password("anything", "not-a-phc-string"));
+        }
+
+        #[test]
+        fn password_length_is_enforced() {
+            assert!(validate_password("short").is_err());
+            assert!(validate_password("long enough").is_ok());
+            assert!(validate_password(&"x".repeat(MAX_PASSWORD_LEN + 1)).is_err());
+        }
+
+        #[test]
+        fn username_rules() {
+            assert!(validate_username("ana.b-c_1").is_ok());
+            assert!(validate_username("ab").is_err());
+            assert!(validate_username("has space").is_err());
+            assert!(validate_username(&"a".repeat(33)).is_err());
+        }
+    }
+
+    mod token_tests {
+        use super::*;
+
+        #[test]
+        fn tokens_are_random_and_hashed() {
+            let a = new_session_token();
+            let b = new_session_token();
+            assert_ne!(a, b);
+            assert_eq!(a.len(), 43);
+            assert_eq!(hash_token(&a).len(), 64);
+            assert_ne!(hash_token(&a), a);
+        }
+
+        #[test]
+        fn bearer_header_wins_over_cookie() {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer abc"));
+            headers.insert(header::COOKIE, HeaderValue::from_static("azera_session=def"));
+            assert_eq!(token_from_headers(&headers).as_deref(), Some("abc"));
+        }
+
+        #[test]
+        fn token_from_cookie() {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                header::COOKIE,
+                HeaderValue::from_static("theme=dark; azera_session=def; other=1"),
+            );
+            assert_eq!(token_from_headers(&headers).as_deref(), Some("def"));
+        }
+
+        #[test]
+        fn missing_or_empty_token() {
+            let mut headers = HeaderMap::new();
+            assert!(token_from_headers(&headers).is_none());
+            headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer "));
+            headers.insert(header::COOKIE, HeaderValue::from_static("azera_session="));
+            assert!(token_from_headers(&headers).is_none());
+        }
+
+        #[test]
+        fn cookie_attributes() {
+            let cookie = session_cookie("tok");
+            assert!(cookie.starts_with("azera_session=tok;"));
+            assert!(cookie.contains("HttpOnly"));
+            assert!(cookie.contains("SameSite=Lax"));
+            assert!(clear_session_cookie().contains("Max-Age=0"));
+        }
+
+        #[test]
+        fn first_account_can_always_register() {
+            assert!(registration_open(0));
+        }
+    }
+}
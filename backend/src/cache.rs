@@ -14,9 +14,11 @@ use sha2::{Sha256, Digest};
 //
 // What lives here:
 //   - Session context (conversation summary, active goal, recent topics)
-//   - Mental state (mood, energy, focus — the "emotion registers")
+//   - Mental state per persona (mood, energy, focus — the "emotion registers")
 //   - Embedding cache (hash → vector, avoids re-computing via Ollama)
 //   - Tool execution history
+//   - Replay buffers of streaming generations (resumable SSE)
+//   - Agent event bus channel (fans events out to every replica)
 //   - Agent coordination queues (pub/sub between systems)
 //   - Rate limiting / token budget tracking
 // ============================================================
@@ -33,10 +35,13 @@ pub struct SessionContext {
     pub ai_persona_id: Option<String>,
     pub last_user_message: String,
     pub last_assistant_message: String,
+    /// Last message folded into `conversation_summary` (see summarizer.rs)
+    #[serde(default)]
+    pub summarized_through: Option<String>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
-/// Mental state stored in Dragonfly (synced to agent state on tick)
+/// Mental state of one persona stored in Dragonfly (synced to agent state on tick)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CachedMentalState {
     pub mood: f32,
@@ -45,6 +50,8 @@ pub struct CachedMentalState {
     pub mood_label: String,
     pub is_dreaming: bool,
     pub last_active: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub last_dream: Option<chrono::DateTime<chrono::Utc>>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -98,7 +105,6 @@ impl CacheService {
     }
 
     /// Delete a key
-    #[allow(dead_code)]
     pub async fn del(cache: &ConnectionManager, key: &str) -> Result<()> {
         let mut con = cache.clone();
         redis::cmd("DEL")
@@ -108,6 +114,20 @@ impl CacheService {
         Ok(())
     }
 
+    /// Take a short-lived lock; false if someone else holds it
+    pub async fn try_lock(cache: &ConnectionManager, key: &str, ttl_secs: usize) -> Result<bool> {
+        let mut con = cache.clone();
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg("1")
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_secs)
+            .query_async(&mut con)
+            .await?;
+        Ok(acquired.is_some())
+    }
+
     /// Push signal to queue
     pub async fn queue_signal(cache: &ConnectionManager, queue: &str, signal: &str) -> Result<()> {
         let mut con = cache.clone();
@@ -129,33 +149,97 @@ impl CacheService {
         Ok(value)
     }
 
+    /// Set a key's time to live
+    pub async fn expire(cache: &ConnectionManager, key: &str, ttl_secs: usize) -> Result<()> {
+        let mut con = cache.clone();
+        redis::cmd("EXPIRE")
+            .arg(key)
+            .arg(ttl_secs)
+            .query_async::<_, ()>(&mut con)
+            .await?;
+        Ok(())
+    }
+
+    // ── Stream Replay Buffers ────────────────────────────────
+
+    /// Append an event to a generation's replay buffer; the buffer expires
+    /// `ttl_secs` after its first event
+    pub async fn push_stream_event(cache: &ConnectionManager, key: &str, event: &str, ttl_secs: usize) -> Result<()> {
+        let mut con = cache.clone();
+        let len: usize = redis::cmd("RPUSH")
+            .arg(key)
+            .arg(event)
+            .query_async(&mut con)
+            .await?;
+        if len == 1 {
+            Self::expire(cache, key, ttl_secs).await?;
+        }
+        Ok(())
+    }
+
+    /// Buffered events from list index `from` on
+    pub async fn get_stream_events(cache: &ConnectionManager, key: &str, from: usize) -> Result<Vec<String>> {
+        let mut con = cache.clone();
+        let items: Vec<String> = redis::cmd("LRANGE")
+            .arg(key)
+            .arg(from)
+            .arg(-1i64)
+            .query_async(&mut con)
+            .await?;
+        Ok(items)
+    }
+
+    // ── Event Bus ────────────────────────────────────────────
+
+    /// Publish a message on a pub/sub channel; returns how many subscribers got it
+    pub async fn publish(cache: &ConnectionManager, channel: &str, message: &str) -> Result<usize> {
+        let mut con = cache.clone();
+        let receivers: usize = redis::cmd("PUBLISH")
+            .arg(channel)
+            .arg(message)
+            .query_async(&mut con)
+            .await?;
+        Ok(receivers)
+    }
+
     // ── Mental State (Emotion Registers) ─────────────────────
 
-    /// Store full mental state
-    pub async fn set_mental_state(cache: &ConnectionManager, state: &CachedMentalState) -> Result<()> {
+    fn mental_state_key(persona_id: &str) -> String {
+        format!("cognitive:mental_state:{}", persona_id)
+    }
+
+    /// Store a persona's full mental state
+    pub async fn set_mental_state(cache: &ConnectionManager, persona_id: &str, state: &CachedMentalState) -> Result<()> {
         let json = serde_json::to_string(state)?;
         // No TTL — mental state persists until explicitly updated
-        Self::set_persistent(cache, "cognitive:mental_state", &json).await
+        Self::set_persistent(cache, &Self::mental_state_key(persona_id), &json).await
     }
 
-    /// Get full mental state
-    pub async fn get_mental_state(cache: &ConnectionManager) -> Result<Option<CachedMentalState>> {
-        if let Some(json) = Self::get(cache, "cognitive:mental_state").await? {
+    /// Get a persona's full mental state
+    pub async fn get_mental_state(cache: &ConnectionManager, persona_id: &str) -> Result<Option<CachedMentalState>> {
+        if let Some(json) = Self::get(cache, &Self::mental_state_key(persona_id)).await? {
             Ok(serde_json::from_str(&json).ok())
         } else {
             Ok(None)
         }
     }
 
-    /// Quick mood update (called after infer_mood)
-    pub async fn update_mood(cache: &ConnectionManager, mood_value: f32, mood_label: &str, energy_delta: f32) -> Result<()> {
-        let mut state = Self::get_mental_state(cache).await?.unwrap_or(CachedMentalState {
+    /// Quick mood update of a persona (called after infer_mood); returns the new state
+    pub async fn update_mood(
+        cache: &ConnectionManager,
+        persona_id: &str,
+        mood_value: f32,
+        mood_label: &str,
+        energy_delta: f32,
+    ) -> Result<CachedMentalState> {
+        let mut state = Self::get_mental_state(cache, persona_id).await?.unwrap_or(CachedMentalState {
             mood: 0.5,
             energy: 0.7,
             focus_level: 0.8,
             mood_label: "content".to_string(),
             is_dreaming: false,
             last_active: chrono::Utc::now(),
+            last_dream: None,
             updated_at: chrono::Utc::now(),
         });
         state.mood = mood_value;
@@ -163,7 +247,8 @@ impl CacheService {
         state.energy = (state.energy + energy_delta).clamp(0.0, 1.0);
         state.last_active = chrono::Utc::now();
         state.updated_at = chrono::Utc::now();
-        Self::set_mental_state(cache, &state).await
+        Self::set_mental_state(cache, persona_id, &state).await?;
+        Ok(state)
     }
 
     // ── Session Context (Attention Buffer) ───────────────────
@@ -172,6 +257,11 @@ impl CacheService {
         format!("cognitive:session:{}", chat_id)
     }
 
+    /// Lock held while a chat is being summarized
+    pub fn summary_lock_key(chat_id: &str) -> String {
+        format!("cognitive:summary_lock:{}", chat_id)
+    }
+
     /// Store session context for a chat
     pub async fn set_session(cache: &ConnectionManager, session: &SessionContext) -> Result<()> {
         let json = serde_json::to_string(session)?;
@@ -179,6 +269,18 @@ impl CacheService {
         Self::set(cache, &Self::session_key(&session.chat_id), &json, 86400).await
     }
 
+    /// Drop a chat's session context and summarizer lock. Returns how many
+    /// keys existed.
+    pub async fn delete_session(cache: &ConnectionManager, chat_id: &str) -> Result<usize> {
+        let mut con = cache.clone();
+        let removed: usize = redis::cmd("DEL")
+            .arg(Self::session_key(chat_id))
+            .arg(Self::summary_lock_key(chat_id))
+            .query_async(&mut con)
+            .await?;
+        Ok(removed)
+    }
+
     /// Get session context for a chat
     pub async fn get_session(cache: &ConnectionManager, chat_id: &str) -> Result<Option<SessionContext>> {
         if let Some(json) = Self::get(cache, &Self::session_key(chat_id)).await? {
@@ -188,15 +290,14 @@ impl CacheService {
         }
     }
 
-    /// Update session after an exchange (user msg + assistant response)
+    /// Record an exchange (user msg + assistant response) and bump the turn
+    /// count. The summary, goal and topics are maintained by the summarizer.
     pub async fn update_session_after_exchange(
         cache: &ConnectionManager,
         chat_id: &str,
         user_msg: &str,
         assistant_msg: &str,
-        summary: &str,
-        topics: Vec<String>,
-    ) -> Result<()> {
+    ) -> Result<SessionContext> {
         let mut session = Self::get_session(cache, chat_id).await?.unwrap_or(SessionContext {
             chat_id: chat_id.to_string(),
             conversation_summary: String::new(),
@@ -207,24 +308,15 @@ impl CacheService {
             ai_persona_id: None,
             last_user_message: String::new(),
             last_assistant_message: String::new(),
+            summarized_through: None,
             updated_at: chrono::Utc::now(),
         });
-        session.conversation_summary = summary.to_string();
         session.last_user_message = user_msg.to_string();
         session.last_assistant_message = assistant_msg.to_string();
         session.turn_count += 1;
-        // Keep last 10 topics, deduped
-        for topic in topics {
-            if !session.recent_topics.contains(&topic) {
-                session.recent_topics.push(topic);
-            }
-        }
-        if session.recent_topics.len() > 10 {
-            let drain_count = session.recent_topics.len() - 10;
-            session.recent_topics.drain(..drain_count);
-        }
         session.updated_at = chrono::Utc::now();
-        Self::set_session(cache, &session).await
+        Self::set_session(cache, &session).await?;
+        Ok(session)
     }
 
     // ── Embedding Cache (Avoid Recomputing) ──────────────────
@@ -265,8 +357,6 @@ impl CacheService {
     // ── Tool Execution History ───────────────────────────────
 
     /// Record a tool execution
-    /// TODO(future): Wire up when agent tool pipeline is active
-    #[allow(dead_code)]
     pub async fn record_tool_execution(cache: &ConnectionManager, exec: &ToolExecution) -> Result<()> {
         let json = serde_json::to_string(exec)?;
         let mut con = cache.clone();
@@ -343,13 +433,14 @@ impl CacheService {
     /// Update mood state in cache (legacy — prefer update_mood)
     #[allow(dead_code)]
     pub async fn set_mood(cache: &ConnectionManager, mood: f32, energy: f32) -> Result<()> {
-        Self::update_mood(cache, mood, "content", energy - 0.7).await
+        Self::update_mood(cache, crate::components::DEFAULT_PERSONA_ID, mood, "content", energy - 0.7).await?;
+        Ok(())
     }
 
     /// Get mood state from cache (legacy — prefer get_mental_state)
     #[allow(dead_code)]
     pub async fn get_mood(cache: &ConnectionManager) -> Result<Option<(f32, f32)>> {
-        if let Some(state) = Self::get_mental_state(cache).await? {
+        if let Some(state) = Self::get_mental_state(cache, crate::components::DEFAULT_PERSONA_ID).await? {
             Ok(Some((state.mood, state.energy)))
         } else {
             Ok(None)
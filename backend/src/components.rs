@@ -2,23 +2,8 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-/// Persona component (Entity's identity)
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Persona {
-    pub name: String,
-    pub core_directive: String,
-    pub role: String,
-}
-
-impl Default for Persona {
-    fn default() -> Self {
-        Self {
-            name: "Azera".to_string(),
-            core_directive: "Serve the user with wisdom, curiosity, and care".to_string(),
-            role: "The Night Entity".to_string(),
-        }
-    }
-}
+/// AI persona that speaks when a turn names none, and owns legacy signals
+pub const DEFAULT_PERSONA_ID: &str = "azera";
 
 /// Mental state component
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,10 +29,21 @@ impl Default for MentalState {
     }
 }
 
+impl MentalState {
+    /// Label for the numeric mood
+    pub fn mood_label(&self) -> &'static str {
+        if self.mood > 0.7 { "happy" }
+        else if self.mood > 0.5 { "content" }
+        else if self.mood > 0.3 { "thoughtful" }
+        else { "melancholy" }
+    }
+}
+
 /// Working memory component
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkingMemory {
-    pub short_term_history: Vec<String>,
+    /// Recent exchange lines by user ID — a shared persona talks to many users
+    pub short_term_history: HashMap<String, Vec<String>>,
     pub pending_input: Option<String>,
     pub context_window: usize,
 }
@@ -55,13 +51,50 @@ pub struct WorkingMemory {
 impl Default for WorkingMemory {
     fn default() -> Self {
         Self {
-            short_term_history: Vec::new(),
+            short_term_history: HashMap::new(),
             pending_input: None,
             context_window: 10,
         }
     }
 }
 
+impl WorkingMemory {
+    /// Keep `line` among the last `context_window` lines of `user_id`
+    pub fn remember(&mut self, user_id: &str, line: String) {
+        let history = self.short_term_history.entry(user_id.to_string()).or_default();
+        history.push(line);
+        let excess = history.len().saturating_sub(self.context_window);
+        history.drain(..excess);
+    }
+
+    /// Recent lines of `user_id`, oldest first
+    pub fn recent(&self, user_id: &str) -> &[String] {
+        self.short_term_history.get(user_id).map_or(&[], |h| h.as_slice())
+    }
+}
+
+/// Runtime state of one AI persona
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersonaState {
+    pub persona_id: String,
+    pub name: String,
+    pub user_id: Option<String>,  // Owning account (None = shared built-in)
+    pub mental_state: MentalState,
+    pub working_memory: WorkingMemory,
+}
+
+impl PersonaState {
+    pub fn new(persona_id: &str, name: &str) -> Self {
+        Self {
+            persona_id: persona_id.to_string(),
+            name: name.to_string(),
+            user_id: None,
+            mental_state: MentalState::default(),
+            working_memory: WorkingMemory::default(),
+        }
+    }
+}
+
 /// Agent configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentConfig {
@@ -87,13 +120,12 @@ impl Default for AgentConfig {
     }
 }
 
-/// Agent runtime state — persona, mental state, working memory, and config
+/// Agent runtime state — per-persona mental state and working memory, and config
 #[derive(Debug, Clone)]
 pub struct AgentState {
     pub entity_id: u64,
-    pub persona: Persona,
-    pub mental_state: MentalState,
-    pub working_memory: WorkingMemory,
+    /// Active AI personas by ID
+    pub personas: HashMap<String, PersonaState>,
     pub agent_config: AgentConfig,
     pub metadata: HashMap<String, String>,
 }
@@ -108,9 +140,7 @@ impl AgentState {
     pub fn new() -> Self {
         Self {
             entity_id: 1,
-            persona: Persona::default(),
-            mental_state: MentalState::default(),
-            working_memory: WorkingMemory::default(),
+            personas: HashMap::new(),
             agent_config: AgentConfig::default(),
             metadata: HashMap::new(),
         }
@@ -119,21 +149,122 @@ impl AgentState {
     pub async fn init_agent(&mut self) {
         tracing::info!("✨ Initializing agent state...");
         self.entity_id = 1;
-        self.mental_state.last_active = chrono::Utc::now();
+        self.persona_mut(DEFAULT_PERSONA_ID).mental_state.last_active = chrono::Utc::now();
         self.metadata.insert("initialized".to_string(), "true".to_string());
         tracing::info!("✨ Agent state initialized");
     }
 
-    pub fn update_mental_state(&mut self, mood: f32, energy: f32) {
-        self.mental_state.mood = mood.clamp(-1.0, 1.0);
-        self.mental_state.energy = energy.clamp(0.0, 1.0);
+    pub fn persona(&self, persona_id: &str) -> Option<&PersonaState> {
+        self.personas.get(persona_id)
+    }
+
+    /// State of `persona_id`, starting a fresh one for a persona not seen yet
+    pub fn persona_mut(&mut self, persona_id: &str) -> &mut PersonaState {
+        self.personas
+            .entry(persona_id.to_string())
+            .or_insert_with(|| PersonaState::new(persona_id, persona_id))
+    }
+
+    /// Track exactly the given `(id, name, owner)` personas, keeping the
+    /// state of those already tracked
+    pub fn sync_personas(&mut self, active: &[(String, String, Option<String>)]) {
+        self.personas.retain(|id, _| active.iter().any(|(active_id, _, _)| active_id == id));
+        for (id, name, owner) in active {
+            let persona = self.persona_mut(id);
+            persona.name = name.clone();
+            persona.user_id = owner.clone();
+        }
+    }
+
+    pub fn update_mental_state(&mut self, persona_id: &str, mood: f32, energy: f32) {
+        let mental_state = &mut self.persona_mut(persona_id).mental_state;
+        mental_state.mood = mood.clamp(-1.0, 1.0);
+        mental_state.energy = energy.clamp(0.0, 1.0);
     }
 
+    /// Legacy input signals go to the default persona
     pub fn set_pending_input(&mut self, input: String) {
-        self.working_memory.pending_input = Some(input);
+        self.persona_mut(DEFAULT_PERSONA_ID).working_memory.pending_input = Some(input);
     }
 
     pub fn get_pending_input(&mut self) -> Option<String> {
-        self.working_memory.pending_input.take()
+        self.persona_mut(DEFAULT_PERSONA_ID).working_memory.pending_input.take()
+    }
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod persona_state_tests {
+        use super::*;
+
+        fn persona(id: &str, name: &str) -> (String, String, Option<String>) {
+            (id.to_string(), name.to_string(), None)
+        }
+
+        #[test]
+        fn sync_keeps_state_of_tracked_personas() {
+            let mut agent = AgentState::new();
+            agent.update_mental_state("azera", 0.2, 0.4);
+            agent.update_mental_state("gone", 0.9, 0.9);
+
+            agent.sync_personas(&[persona("azera", "Azera"), persona("areza", "Areza")]);
+
+            assert_eq!(agent.personas.len(), 2);
+            assert!(agent.persona("gone").is_none());
+            let azera = agent.persona("azera").unwrap();
+            assert_eq!(azera.name, "Azera");
+            assert_eq!(azera.mental_state.mood, 0.2);
+            assert_eq!(agent.persona("areza").unwrap().mental_state.mood, MentalState::default().mood);
+        }
+
+        #[test]
+        fn mood_of_one_persona_leaves_the_others_alone() {
+            let mut agent = AgentState::new();
+            agent.sync_personas(&[persona("azera", "Azera"), persona("areza", "Areza")]);
+            agent.update_mental_state("areza", 0.1, 0.5);
+            assert_eq!(agent.persona("azera").unwrap().mental_state.mood, MentalState::default().mood);
+            assert_eq!(agent.persona("areza").unwrap().mental_state.mood_label(), "melancholy");
+        }
+
+        #[test]
+        fn pending_input_belongs_to_the_default_persona() {
+            let mut agent = AgentState::new();
+            agent.set_pending_input("hello".to_string());
+            assert_eq!(agent.persona(DEFAULT_PERSONA_ID).unwrap().working_memory.pending_input.as_deref(), Some("hello"));
+            assert_eq!(agent.get_pending_input().as_deref(), Some("hello"));
+            assert_eq!(agent.get_pending_input(), None);
+        }
+
+        #[test]
+        fn working_memory_keeps_the_latest_lines() {
+            let mut memory = WorkingMemory { context_window: 2, ..WorkingMemory::default() };
+            for line in ["a", "b", "c"] {
+                memory.remember("user-1", line.to_string());
+            }
+            assert_eq!(memory.recent("user-1"), ["b", "c"]);
+        }
+
+        #[test]
+        fn working_memory_is_kept_per_user() {
+            let mut memory = WorkingMemory::default();
+            memory.remember("user-1", "user: secret".to_string());
+            memory.remember("user-2", "user: hello".to_string());
+            assert_eq!(memory.recent("user-1"), ["user: secret"]);
+            assert_eq!(memory.recent("user-2"), ["user: hello"]);
+            assert!(memory.recent("user-3").is_empty());
+        }
+
+        #[test]
+        fn sync_records_the_owner() {
+            let mut agent = AgentState::new();
+            agent.sync_personas(&[("mine".to_string(), "Mine".to_string(), Some("user-1".to_string()))]);
+            assert_eq!(agent.persona("mine").unwrap().user_id.as_deref(), Some("user-1"));
+        }
     }
 }
@@ -0,0 +1,586 @@
+//! Memory consolidation
+//!
+//! Every `CONSOLIDATION_INTERVAL_MINUTES` the tick loop hands each AI
+//! persona's unconsolidated conversation memories (Qdrant `conversation`
+//! points without `consolidated`) to [`consolidate`]. The model reads them,
+//! per user, next to the facts the persona already holds about that user
+//! and distils durable facts and preferences ("the user prefers Rust", "the
+//! campaign party has 4 members").
+//!
+//! Each fact becomes a `fact` point owned by the user, referencing the
+//! messages, memories and chats it came from, and is indexed in the
+//! Meilisearch `memories` index. A fact that restates a stored one adds its
+//! sources to it instead (and regains any importance it lost); one that
+//! corrects stored facts supersedes them: they keep their point with
+//! `superseded_by`, leave the Meilisearch index and drop out of retrieval.
+//! The source points are then marked `consolidated`.
+
+use crate::llm::{InferenceOptions, LLMService};
+use crate::models::OllamaMessage;
+use crate::retrieval::truncate_chars;
+use crate::vector::{self, StoredPoint};
+use crate::{forgetting, handlers, AppState};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+const MEMORY_COLLECTION: &str = "azera_memory";
+const DEFAULT_INTERVAL_MINUTES: u64 = 15;
+/// Conversation memories consolidated per persona and run
+const BATCH_SIZE: usize = 40;
+/// Stored facts about the user shown to the model
+const KNOWN_FACTS: usize = 50;
+/// Characters kept from each message in the prompt
+const MESSAGE_CHARS: usize = 500;
+const FACT_CHARS: usize = 300;
+/// Facts kept from one answer
+const MAX_FACTS: usize = 12;
+/// Cosine similarity at which a new fact restates a stored one
+const DUPLICATE_SCORE: f32 = 0.92;
+
+/// Minutes between consolidation runs (`CONSOLIDATION_INTERVAL_MINUTES`);
+/// 0 turns consolidation off
+pub fn interval_minutes() -> u64 {
+    std::env::var("CONSOLIDATION_INTERVAL_MINUTES")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_MINUTES)
+}
+
+/// A conversation memory waiting to be consolidated
+#[derive(Debug, Clone)]
+pub struct SourceMemory {
+    pub point_id: String,
+    pub user_id: Option<String>,
+    pub chat_id: Option<String>,
+    pub message_id: Option<String>,
+    pub role: String,
+    pub content: String,
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+impl SourceMemory {
+    fn from_point(point: StoredPoint) -> Self {
+        let str_field = |key: &str| point.payload.get(key).and_then(|v| v.as_str()).map(String::from);
+        Self {
+            user_id: str_field("user_id"),
+            chat_id: str_field("chat_id"),
+            message_id: str_field("message_id"),
+            role: str_field("role").unwrap_or_else(|| "user".to_string()),
+            content: str_field("content").unwrap_or_default(),
+            timestamp: str_field("timestamp")
+                .and_then(|ts| DateTime::parse_from_rfc3339(&ts).ok())
+                .map(|ts| ts.with_timezone(&Utc)),
+            point_id: point.id,
+        }
+    }
+}
+
+/// A fact from the model's answer
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractedFact {
+    pub content: String,
+    /// `fact` or `preference`
+    pub kind: String,
+    /// Positions of the messages it came from
+    pub sources: Vec<usize>,
+    /// Positions of the known facts it replaces
+    pub supersedes: Vec<usize>,
+}
+
+/// What one run did for a persona
+#[derive(Debug, Default)]
+pub struct Consolidation {
+    /// Conversation memories marked consolidated
+    pub memories: usize,
+    /// New fact points
+    pub facts: usize,
+    /// Facts that restated a stored one and were merged into it
+    pub merged: usize,
+    /// Stored facts replaced by newer ones
+    pub superseded: usize,
+}
+
+/// Consolidate a batch of `persona_id`'s conversation memories into facts.
+/// A batch whose answer can't be parsed is marked consolidated without
+/// facts; LLM and Qdrant failures leave it for the next run.
+pub async fn consolidate(state: &AppState, persona_id: &str, persona_name: &str) -> Result<Consolidation> {
+    let filter = json!({
+        "must": [
+            { "key": "type", "match": { "value": vector::MemoryType::Conversation.to_string() } },
+            { "key": "ai_persona_id", "match": { "value": persona_id } }
+        ],
+        "must_not": [{ "key": "consolidated", "match": { "value": true } }]
+    });
+    let page = state.vector.scroll(MEMORY_COLLECTION, Some(filter), BATCH_SIZE, None).await?;
+
+    let mut report = Consolidation::default();
+    if page.points.is_empty() {
+        return Ok(report);
+    }
+    let model = state.agent.read().await.agent_config.model.clone();
+    let llm = LLMService::new(state.ollama_host.clone());
+
+    for (owner, memories) in by_owner(page.points) {
+        // Turns stored before memories had owners: nobody may see facts from them
+        if let Some(ref user_id) = owner {
+            learn(state, &llm, &model, (persona_id, persona_name), user_id, &memories, &mut report).await?;
+        }
+        let ids: Vec<String> = memories.iter().map(|m| m.point_id.clone()).collect();
+        state.vector.set_payload(MEMORY_COLLECTION, json!({ "consolidated": true }), vector::has_ids(&ids)).await?;
+        report.memories += ids.len();
+    }
+    Ok(report)
+}
+
+/// Group memories by owner, each group in chronological order
+pub fn by_owner(points: Vec<StoredPoint>) -> Vec<(Option<String>, Vec<SourceMemory>)> {
+    let mut groups: HashMap<Option<String>, Vec<SourceMemory>> = HashMap::new();
+    for memory in points.into_iter().map(SourceMemory::from_point) {
+        groups.entry(memory.user_id.clone()).or_default().push(memory);
+    }
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    for (_, memories) in groups.iter_mut() {
+        memories.sort_by_key(|m| m.timestamp);
+    }
+    groups.sort_by(|a, b| a.0.cmp(&b.0));
+    groups
+}
+
+/// Extract facts from one user's memories and store them
+async fn learn(
+    state: &AppState,
+    llm: &LLMService,
+    model: &str,
+    (persona_id, persona_name): (&str, &str),
+    user_id: &str,
+    memories: &[SourceMemory],
+    report: &mut Consolidation,
+) -> Result<()> {
+    let known = state
+        .vector
+        .scroll(MEMORY_COLLECTION, Some(facts_filter(persona_id, user_id)), KNOWN_FACTS, None)
+        .await?
+        .points;
+
+    let prompt = build_prompt(persona_name, &known, memories);
+    let options = InferenceOptions { temperature: Some(0.2), max_tokens: Some(800) };
+    let raw = llm
+        .provider
+        .infer_with_options(model, vec![OllamaMessage { role: "user".to_string(), content: prompt, tool_calls: None }], &options)
+        .await?;
+    let facts = match parse_facts(&raw, memories.len(), known.len()) {
+        Ok(facts) => facts,
+        Err(e) => {
+            // The same batch would most likely fail the same way next time
+            tracing::warn!("🧩 Skipping {} memories of {}: {}", memories.len(), persona_name, e);
+            return Ok(());
+        }
+    };
+
+    for fact in facts {
+        let sources: Vec<&SourceMemory> = fact.sources.iter().map(|&i| &memories[i]).collect();
+        let replaced: Vec<&StoredPoint> = fact.supersedes.iter().map(|&i| &known[i]).collect();
+        store_fact(state, persona_id, user_id, &fact, &sources, &known, &replaced, report).await?;
+    }
+    tracing::info!("🧩 {} consolidated {} memories of {}", persona_name, memories.len(), user_id);
+    Ok(())
+}
+
+/// Store a fact, or merge it into the stored fact it restates, and
+/// supersede the facts it replaces
+#[allow(clippy::too_many_arguments)]
+async fn store_fact(
+    state: &AppState,
+    persona_id: &str,
+    user_id: &str,
+    fact: &ExtractedFact,
+    sources: &[&SourceMemory],
+    known: &[StoredPoint],
+    replaced: &[&StoredPoint],
+    report: &mut Consolidation,
+) -> Result<()> {
+    let refs = SourceRefs::of(sources);
+    let is_replaced = |id: &str| replaced.iter().any(|p| p.id == id);
+    let embedding = state.vector.generate_embedding_cached(&state.ollama_host, &fact.content, &state.cache).await?;
+
+    // The same fact again: add the new sources to it instead of storing a copy
+    let mut duplicate = known
+        .iter()
+        .find(|p| !is_replaced(&p.id) && content_of(p).is_some_and(|c| normalise(c) == normalise(&fact.content)))
+        .map(|p| (p.id.clone(), p.payload.clone()));
+    if duplicate.is_none() {
+        duplicate = state
+            .vector
+            .search(MEMORY_COLLECTION, embedding.clone(), 1, Some(facts_filter(persona_id, user_id)))
+            .await?
+            .into_iter()
+            .find(|r| r.score >= DUPLICATE_SCORE && !is_replaced(&r.id))
+            .map(|r| (r.id, r.payload));
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let importance = forgetting::importance(&vector::MemoryType::Fact.to_string(), None, &fact.content);
+    let fact_id = match duplicate {
+        Some((id, payload)) => {
+            // Hearing it again makes up for any decay
+            let stored = payload.get("importance").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
+            let merged = json!({
+                "importance": stored.max(importance),
+                "source_message_ids": merge_ids(payload.get("source_message_ids"), &refs.message_ids),
+                "source_memory_ids": merge_ids(payload.get("source_memory_ids"), &refs.memory_ids),
+                "chat_ids": merge_ids(payload.get("chat_ids"), &refs.chat_ids),
+                "updated_at": now,
+            });
+            state.vector.set_payload(MEMORY_COLLECTION, merged, vector::has_ids(std::slice::from_ref(&id))).await?;
+            report.merged += 1;
+            id
+        }
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            let mut payload: HashMap<String, Value> = HashMap::new();
+            payload.insert("content".to_string(), json!(fact.content));
+            payload.insert("type".to_string(), json!(vector::MemoryType::Fact.to_string()));
+            payload.insert("kind".to_string(), json!(fact.kind));
+            payload.insert("timestamp".to_string(), json!(now));
+            payload.insert("importance".to_string(), json!(importance));
+            payload.insert("access_count".to_string(), json!(0));
+            payload.insert("user_id".to_string(), json!(user_id));
+            payload.insert("ai_persona_id".to_string(), json!(persona_id));
+            payload.insert("source_message_ids".to_string(), json!(refs.message_ids));
+            payload.insert("source_memory_ids".to_string(), json!(refs.memory_ids));
+            payload.insert("chat_ids".to_string(), json!(refs.chat_ids));
+            state.vector.upsert(MEMORY_COLLECTION, &id, embedding, payload).await?;
+
+            // Index in Meilisearch (lexical retrieval)
+            let doc = json!({
+                "id": id,
+                "memory_type": "fact",
+                "title": "",
+                "content": fact.content,
+                "persona_id": persona_id,
+                "user_id": user_id,
+                "tags": [fact.kind],
+                "date": Utc::now().format("%Y-%m-%d").to_string(),
+                "created_at_ts": Utc::now().timestamp()
+            });
+            let (mu, mk) = (state.meili_url.clone(), state.meili_key.clone());
+            tokio::spawn(async move {
+                handlers::meili_index_memory(&mu, &mk, &doc).await;
+            });
+            report.facts += 1;
+            id
+        }
+    };
+
+    let replaced_ids: Vec<String> = replaced.iter().map(|p| p.id.clone()).filter(|id| *id != fact_id).collect();
+    if !replaced_ids.is_empty() {
+        state.vector.set_payload(
+            MEMORY_COLLECTION,
+            json!({ "superseded": true, "superseded_by": fact_id, "superseded_at": now }),
+            vector::has_ids(&replaced_ids),
+        ).await?;
+        for id in &replaced_ids {
+            let (mu, mk, id) = (state.meili_url.clone(), state.meili_key.clone(), id.clone());
+            tokio::spawn(async move {
+                handlers::meili_delete_memory(&mu, &mk, &id).await;
+            });
+        }
+        report.superseded += replaced_ids.len();
+    }
+    Ok(())
+}
+
+/// Filter for the current (not superseded) facts `persona_id` holds about `user_id`
+fn facts_filter(persona_id: &str, user_id: &str) -> Value {
+    json!({
+        "must": [
+            { "key": "type", "match": { "value": vector::MemoryType::Fact.to_string() } },
+            { "key": "ai_persona_id", "match": { "value": persona_id } },
+            { "key": "user_id", "match": { "value": user_id } }
+        ],
+        "must_not": [vector::superseded()]
+    })
+}
+
+fn content_of(point: &StoredPoint) -> Option<&str> {
+    point.payload.get("content").and_then(|v| v.as_str())
+}
+
+/// Where a fact came from, each list without duplicates
+#[derive(Debug, Default, PartialEq)]
+pub struct SourceRefs {
+    pub message_ids: Vec<String>,
+    pub memory_ids: Vec<String>,
+    pub chat_ids: Vec<String>,
+}
+
+impl SourceRefs {
+    pub fn of(sources: &[&SourceMemory]) -> Self {
+        let mut refs = Self::default();
+        for source in sources {
+            push_unique(&mut refs.memory_ids, Some(&source.point_id));
+            push_unique(&mut refs.message_ids, source.message_id.as_ref());
+            push_unique(&mut refs.chat_ids, source.chat_id.as_ref());
+        }
+        refs
+    }
+}
+
+fn push_unique(list: &mut Vec<String>, value: Option<&String>) {
+    if let Some(value) = value {
+        if !list.contains(value) {
+            list.push(value.clone());
+        }
+    }
+}
+
+/// A stored ID list (missing or malformed counts as empty) extended by `new`
+pub fn merge_ids(existing: Option<&Value>, new: &[String]) -> Vec<String> {
+    let mut ids: Vec<String> = existing
+        .and_then(|v| v.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+        .unwrap_or_default();
+    for id in new {
+        push_unique(&mut ids, Some(id));
+    }
+    ids
+}
+
+/// Lowercase words only, so trivially reworded facts compare equal
+fn normalise(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn build_prompt(persona_name: &str, known: &[StoredPoint], memories: &[SourceMemory]) -> String {
+    let known = if known.is_empty() {
+        "(none yet)".to_string()
+    } else {
+        known
+            .iter()
+            .enumerate()
+            .map(|(i, p)| format!("F{}. {}", i + 1, content_of(p).unwrap_or_default()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let messages = memories
+        .iter()
+        .enumerate()
+        .map(|(i, m)| {
+            let speaker = if m.role == "user" { "User" } else { persona_name };
+            format!("M{}. {}: {}", i + 1, speaker, truncate_chars(&m.content, MESSAGE_CHARS))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "You keep {name}'s long-term memory of a user.\n\n\
+         Known facts:\n{known}\n\nNew messages:\n{messages}\n\n\
+         List the durable facts about the user and their world that the new messages reveal and \
+         that are worth remembering in later conversations: preferences, plans, people, projects, \
+         names and numbers. Skip small talk, one-off requests and {name}'s own opinions. Write each \
+         fact as one short sentence about \"the user\" and don't repeat a known fact unless it \
+         changed. Give the numbers of the messages each fact comes from in \"sources\" and the \
+         numbers of the known facts it corrects or replaces in \"supersedes\".\n\n\
+         Respond with JSON only: {{\"facts\": [{{\"fact\": \"...\", \"kind\": \"fact\" or \"preference\", \
+         \"sources\": [1], \"supersedes\": []}}]}}",
+        name = persona_name,
+        known = known,
+        messages = messages
+    )
+}
+
+#[derive(Deserialize)]
+struct RawFacts {
+    #[serde(default)]
+    facts: Vec<RawFact>,
+}
+
+#[derive(Deserialize)]
+struct RawFact {
+    #[serde(default)]
+    fact: String,
+    #[serde(default)]
+    kind: Option<String>,
+    #[serde(default)]
+    sources: Vec<Value>,
+    #[serde(default)]
+    supersedes: Vec<Value>,
+}
+
+/// Parse the model's JSON, tolerating code fences and chatter around it.
+/// References outside the prompt's numbering are dropped, and so are facts
+/// citing no message.
+fn parse_facts(raw: &str, messages: usize, known: usize) -> Result<Vec<ExtractedFact>> {
+    let start = raw.find('{').ok_or_else(|| anyhow::anyhow!("No JSON object in consolidation response"))?;
+    let end = raw.rfind('}').filter(|end| *end > start)
+        .ok_or_else(|| anyhow::anyhow!("Unterminated JSON in consolidation response"))?;
+    let parsed: RawFacts = serde_json::from_str(&raw[start..=end])?;
+
+    let mut facts: Vec<ExtractedFact> = Vec::new();
+    for raw_fact in parsed.facts {
+        let content = raw_fact.fact.trim();
+        let sources = positions(&raw_fact.sources, messages);
+        if content.is_empty() || sources.is_empty() {
+            continue;
+        }
+        if facts.iter().any(|f| normalise(&f.content) == normalise(content)) {
+            continue;
+        }
+        let kind = match raw_fact.kind.as_deref().map(str::to_lowercase) {
+            Some(kind) if kind.starts_with("pref") => "preference",
+            _ => "fact",
+        };
+        facts.push(ExtractedFact {
+            content: truncate_chars(content, FACT_CHARS),
+            kind: kind.to_string(),
+            sources,
+            supersedes: positions(&raw_fact.supersedes, known),
+        });
+    }
+    facts.truncate(MAX_FACTS);
+    Ok(facts)
+}
+
+/// 1-based references (`3`, `"3"` or `"M3"`) as positions below `len`
+fn positions(refs: &[Value], len: usize) -> Vec<usize> {
+    let mut positions = Vec::new();
+    for r in refs {
+        let number = match r {
+            Value::Number(n) => n.as_u64(),
+            Value::String(s) => s.trim().trim_start_matches(|c: char| c.is_ascii_alphabetic()).parse().ok(),
+            _ => None,
+        };
+        if let Some(position) = number.filter(|n| (1..=len as u64).contains(n)).map(|n| n as usize - 1) {
+            if !positions.contains(&position) {
+                positions.push(position);
+            }
+        }
+    }
+    positions
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(id: &str, payload: Value) -> StoredPoint {
+        StoredPoint { id: id.to_string(), payload: serde_json::from_value(payload).unwrap() }
+    }
+
+    fn memory(id: &str, message_id: Option<&str>, chat_id: &str) -> SourceMemory {
+        SourceMemory {
+            point_id: id.to_string(),
+            user_id: Some("alice".to_string()),
+            chat_id: Some(chat_id.to_string()),
+            message_id: message_id.map(String::from),
+            role: "user".to_string(),
+            content: "I only write Rust these days".to_string(),
+            timestamp: None,
+        }
+    }
+
+    mod parse_tests {
+        use super::*;
+
+        #[test]
+        fn parses_fenced_json() {
+            let raw = "Sure!\n```json\n{\"facts\": [{\"fact\": \"The user prefers Rust\", \"kind\": \"preference\", \"sources\": [1, 2], \"supersedes\": [1]}]}\n```";
+            let facts = parse_facts(raw, 2, 1).unwrap();
+            assert_eq!(facts, vec![ExtractedFact {
+                content: "The user prefers Rust".to_string(),
+                kind: "preference".to_string(),
+                sources: vec![0, 1],
+                supersedes: vec![0],
+            }]);
+        }
+
+        #[test]
+        fn drops_references_outside_the_prompt() {
+            let raw = r#"{"facts": [
+                {"fact": "The party has 4 members", "sources": ["M2", 9, "x"], "supersedes": [3]},
+                {"fact": "Made up", "sources": [7]}
+            ]}"#;
+            let facts = parse_facts(raw, 2, 1).unwrap();
+            assert_eq!(facts.len(), 1);
+            assert_eq!(facts[0].kind, "fact");
+            assert_eq!(facts[0].sources, vec![1]);
+            assert!(facts[0].supersedes.is_empty());
+        }
+
+        #[test]
+        fn skips_empty_and_repeated_facts() {
+            let raw = r#"{"facts": [
+                {"fact": "The user prefers Rust.", "sources": [1]},
+                {"fact": "the user prefers rust", "sources": [2]},
+                {"fact": "  ", "sources": [1]}
+            ]}"#;
+            assert_eq!(parse_facts(raw, 2, 0).unwrap().len(), 1);
+            assert!(parse_facts(r#"{"facts": []}"#, 2, 0).unwrap().is_empty());
+        }
+
+        #[test]
+        fn rejects_answers_without_json() {
+            assert!(parse_facts("Nothing worth remembering.", 2, 0).is_err());
+            assert!(parse_facts("} {", 2, 0).is_err());
+        }
+    }
+
+    mod source_tests {
+        use super::*;
+
+        #[test]
+        fn groups_by_owner_in_chronological_order() {
+            let points = vec![
+                point("p2", json!({"user_id": "alice", "content": "later", "timestamp": "2026-01-02T10:00:00Z"})),
+                point("p3", json!({"user_id": "bob", "content": "hi"})),
+                point("p1", json!({"user_id": "alice", "content": "earlier", "timestamp": "2026-01-01T10:00:00Z"})),
+                point("p4", json!({"content": "legacy"})),
+            ];
+            let groups = by_owner(points);
+            let ids: Vec<(Option<&str>, Vec<&str>)> = groups
+                .iter()
+                .map(|(owner, ms)| (owner.as_deref(), ms.iter().map(|m| m.point_id.as_str()).collect()))
+                .collect();
+            assert_eq!(ids, vec![(None, vec!["p4"]), (Some("alice"), vec!["p1", "p2"]), (Some("bob"), vec!["p3"])]);
+        }
+
+        #[test]
+        fn refs_list_each_source_once() {
+            let (a, b, c) = (memory("p1", Some("m1"), "c1"), memory("p2", None, "c1"), memory("p3", Some("m3"), "c2"));
+            let refs = SourceRefs::of(&[&a, &b, &c, &a]);
+            assert_eq!(refs.memory_ids, vec!["p1", "p2", "p3"]);
+            assert_eq!(refs.message_ids, vec!["m1", "m3"]);
+            assert_eq!(refs.chat_ids, vec!["c1", "c2"]);
+        }
+
+        #[test]
+        fn merged_ids_keep_the_stored_ones_first() {
+            let stored = json!(["m1", "m2"]);
+            assert_eq!(merge_ids(Some(&stored), &["m2".to_string(), "m3".to_string()]), vec!["m1", "m2", "m3"]);
+            assert_eq!(merge_ids(None, &["m1".to_string()]), vec!["m1"]);
+            assert_eq!(merge_ids(Some(&json!("m1")), &[]), Vec::<String>::new());
+        }
+
+        #[test]
+        fn prompt_numbers_known_facts_and_messages() {
+            let known = vec![point("f1", json!({"content": "The user prefers Python"}))];
+            let mut reply = memory("p2", Some("m2"), "c1");
+            reply.role = "assistant".to_string();
+            reply.content = "Noted!".to_string();
+            let prompt = build_prompt("Azera", &known, &[memory("p1", Some("m1"), "c1"), reply]);
+            assert!(prompt.contains("F1. The user prefers Python"));
+            assert!(prompt.contains("M1. User: I only write Rust these days\nM2. Azera: Noted!"));
+            assert!(build_prompt("Azera", &[], &[]).contains("(none yet)"));
+        }
+    }
+}
@@ -0,0 +1,476 @@
+//! Token-budgeted prompt assembly
+//!
+//! [`ContextBuilder`] fits a chat turn into the model's context window. The
+//! window comes from the backend (Ollama `/api/show`, capped by
+//! `CONTEXT_WINDOW_MAX`) and is split into budgets:
+//!
+//! - a reserve kept free for the response
+//! - the persona system prompt, retrieved memories and session summary,
+//!   each capped at a share of the input budget
+//! - the branch history, which gets everything left over
+//!
+//! History is filled newest-first; turns that no longer fit are condensed
+//! into a short "earlier in this conversation" note, or dropped once even
+//! that is full. Token counts are estimates (see [`estimate_tokens`]) — the
+//! caps leave enough headroom that no exact tokenizer is needed.
+
+use crate::cache::CacheService;
+use crate::llm::LLMService;
+use crate::models::{ChatMessage, ContextReport, OllamaMessage, TokenUsage};
+use crate::retrieval::truncate_chars;
+
+/// Fixed per-message overhead (role markers, separators) in chat templates
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+/// Characters kept from each turn in the condensed-history note
+const SUMMARY_LINE_CHARS: usize = 160;
+/// Context lengths reported by the backend are cached for an hour
+const WINDOW_CACHE_TTL_SECS: usize = 3600;
+
+const MEMORIES_HEADER: &str = "[Relevant memories from past conversations:]";
+
+// ============================================================
+// Configuration
+// ============================================================
+
+/// Window limits and per-part budget shares
+#[derive(Debug, Clone)]
+pub struct ContextConfig {
+    /// Upper bound on the window sent to the backend (`num_ctx`); large
+    /// windows cost VRAM, so the model's maximum is not used blindly
+    pub max_window: usize,
+    /// Window assumed when the backend cannot report one
+    pub default_window: usize,
+    /// Share of the window kept free for the response
+    pub response_share: f32,
+    /// Maximum shares of the input budget per part
+    pub system_share: f32,
+    pub memory_share: f32,
+    pub session_share: f32,
+    /// Share of the history budget used for the condensed-history note
+    pub summary_share: f32,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            max_window: 16384,
+            default_window: 8192,
+            response_share: 0.25,
+            system_share: 0.3,
+            memory_share: 0.2,
+            session_share: 0.05,
+            summary_share: 0.1,
+        }
+    }
+}
+
+impl ContextConfig {
+    /// `CONTEXT_WINDOW_MAX`, `CONTEXT_WINDOW_DEFAULT`, `CONTEXT_RESPONSE_SHARE`
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(tokens) = env_parse::<usize>("CONTEXT_WINDOW_MAX").filter(|t| *t > 0) {
+            config.max_window = tokens;
+        }
+        if let Some(tokens) = env_parse::<usize>("CONTEXT_WINDOW_DEFAULT").filter(|t| *t > 0) {
+            config.default_window = tokens;
+        }
+        if let Some(share) = env_parse::<f32>("CONTEXT_RESPONSE_SHARE").filter(|s| (0.05..0.9).contains(s)) {
+            config.response_share = share;
+        }
+        config
+    }
+}
+
+fn env_parse<T: std::str::FromStr>(key: &str) -> Option<T> {
+    std::env::var(key).ok().and_then(|v| v.trim().parse().ok())
+}
+
+/// Context window for `model`: the backend's reported length (cached in
+/// Dragonfly), or the configured default, capped at `max_window`
+pub async fn resolve_window(
+    llm: &LLMService,
+    model: &str,
+    cache: &redis::aio::ConnectionManager,
+    config: &ContextConfig,
+) -> usize {
+    let key = format!("cognitive:model_ctx:{}:{}", llm.provider_name(), model);
+    let cached = CacheService::get(cache, &key)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<usize>().ok());
+
+    let reported = match cached {
+        Some(tokens) => tokens,
+        None => {
+            let tokens = match llm.context_length(model).await {
+                Ok(tokens) => tokens.unwrap_or(0),
+                Err(e) => {
+                    tracing::warn!("📏 Could not read context length for {}: {}", model, e);
+                    0
+                }
+            };
+            // 0 = unknown; cached too so unreachable backends aren't asked every turn
+            let _ = CacheService::set(cache, &key, &tokens.to_string(), WINDOW_CACHE_TTL_SECS).await;
+            tokens
+        }
+    };
+
+    if reported == 0 {
+        config.default_window.min(config.max_window)
+    } else {
+        reported.min(config.max_window)
+    }
+}
+
+// ============================================================
+// Token estimation
+// ============================================================
+
+/// Estimate BPE tokens: ~4 characters per token within ASCII words, one per
+/// punctuation mark and per non-ASCII character. Errs slightly high for
+/// English prose, which keeps assembled prompts safely inside the window.
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut word_len: usize = 0;
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            word_len += 1;
+            continue;
+        }
+        tokens += word_len.div_ceil(4);
+        word_len = 0;
+        if !c.is_whitespace() {
+            tokens += 1;
+        }
+    }
+    tokens + word_len.div_ceil(4)
+}
+
+fn message_tokens(content: &str) -> usize {
+    estimate_tokens(content) + MESSAGE_OVERHEAD_TOKENS
+}
+
+/// Longest prefix of `text` estimated to fit in `budget` tokens
+fn truncate_to_tokens(text: &str, budget: usize) -> String {
+    if estimate_tokens(text) <= budget {
+        return text.to_string();
+    }
+    // Binary search on character count; estimates are monotonic in prefix length
+    let chars: Vec<char> = text.chars().collect();
+    let (mut lo, mut hi) = (0, chars.len());
+    while lo < hi {
+        let mid = (lo + hi).div_ceil(2);
+        let prefix: String = chars[..mid].iter().collect();
+        // Strictly less: the ellipsis appended below costs a token
+        if estimate_tokens(&prefix) < budget {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    let mut prefix: String = chars[..lo].iter().collect();
+    prefix.push('…');
+    prefix
+}
+
+// ============================================================
+// Builder
+// ============================================================
+
+/// Inputs for one chat turn
+pub struct ContextParts<'a> {
+    pub system_prompt: &'a str,
+    /// Memory lines for the prompt, best first
+    pub memories: &'a [String],
+    /// Working-memory block from the session context
+    pub session: Option<&'a str>,
+    /// Branch history, oldest first
+    pub history: &'a [ChatMessage],
+    pub user_message: &'a str,
+}
+
+/// Assembled messages and how the budget was spent
+pub struct BuiltContext {
+    pub messages: Vec<OllamaMessage>,
+    pub report: ContextReport,
+}
+
+pub struct ContextBuilder<'a> {
+    config: &'a ContextConfig,
+    window: usize,
+}
+
+impl<'a> ContextBuilder<'a> {
+    pub fn new(config: &'a ContextConfig, window: usize) -> Self {
+        Self { config, window }
+    }
+
+    pub fn build(&self, parts: &ContextParts) -> BuiltContext {
+        let response_reserve = (self.window as f32 * self.config.response_share) as usize;
+        let input_budget = self.window.saturating_sub(response_reserve);
+        let share = |s: f32| (input_budget as f32 * s) as usize;
+
+        // The current message always goes in; only a pathological paste gets cut
+        let user_message = truncate_to_tokens(parts.user_message, input_budget / 2);
+        let user_tokens = message_tokens(&user_message);
+
+        let system_budget = share(self.config.system_share);
+        let system_prompt = truncate_to_tokens(parts.system_prompt, system_budget);
+        let system_tokens = message_tokens(&system_prompt);
+
+        let session_budget = share(self.config.session_share);
+        let session = parts
+            .session
+            .filter(|s| !s.is_empty())
+            .map(|s| truncate_to_tokens(s, session_budget));
+        let session_tokens = session.as_deref().map_or(0, estimate_tokens);
+
+        // Memories are whole lines, best first: stop at the first that doesn't fit
+        let memory_budget = share(self.config.memory_share);
+        let mut memory_block = String::new();
+        let mut memories_included = 0;
+        for (i, line) in parts.memories.iter().enumerate() {
+            let candidate = if memory_block.is_empty() {
+                format!("{}\n{}. {}", MEMORIES_HEADER, i + 1, line)
+            } else {
+                format!("{}\n{}. {}", memory_block, i + 1, line)
+            };
+            if estimate_tokens(&candidate) > memory_budget {
+                break;
+            }
+            memory_block = candidate;
+            memories_included += 1;
+        }
+        let memory_tokens = estimate_tokens(&memory_block);
+
+        // History gets whatever the other parts left
+        let history_budget = input_budget
+            .saturating_sub(user_tokens + system_tokens + session_tokens + memory_tokens);
+        let history = fit_history(parts.history, history_budget, self.config.summary_share);
+
+        let mut system_content = system_prompt;
+        if !memory_block.is_empty() {
+            system_content.push_str("\n\n");
+            system_content.push_str(&memory_block);
+        }
+        if let Some(ref session) = session {
+            system_content.push_str("\n\n");
+            system_content.push_str(session);
+        }
+
+        let mut messages = vec![OllamaMessage {
+            role: "system".to_string(),
+            content: system_content,
+            tool_calls: None,
+        }];
+        if let Some(ref note) = history.summary {
+            messages.push(OllamaMessage {
+                role: "system".to_string(),
+                content: note.clone(),
+                tool_calls: None,
+            });
+        }
+        messages.extend(parts.history[history.first_kept..].iter().map(|m| OllamaMessage {
+            role: m.role.clone(),
+            content: m.content.clone(),
+            tool_calls: None,
+        }));
+        messages.push(OllamaMessage {
+            role: "user".to_string(),
+            content: user_message,
+            tool_calls: None,
+        });
+
+        let report = ContextReport {
+            context_window: self.window,
+            response_reserve,
+            system_prompt: TokenUsage { budget: system_budget, used: system_tokens },
+            memories: TokenUsage { budget: memory_budget, used: memory_tokens },
+            session: TokenUsage { budget: session_budget, used: session_tokens },
+            history: TokenUsage { budget: history_budget, used: history.tokens },
+            user_message: user_tokens,
+            total_used: user_tokens + system_tokens + session_tokens + memory_tokens + history.tokens,
+            memories_included,
+            memories_dropped: parts.memories.len() - memories_included,
+            history_included: parts.history.len() - history.first_kept,
+            history_dropped: history.first_kept,
+            history_summarized: history.summarized,
+        };
+        BuiltContext { messages, report }
+    }
+}
+
+struct FittedHistory {
+    /// Index of the oldest message sent verbatim
+    first_kept: usize,
+    /// Note condensing (some of) the dropped turns
+    summary: Option<String>,
+    summarized: usize,
+    tokens: usize,
+}
+
+/// Keep the newest messages that fit in `budget`; condense the rest into a
+/// note using up to `summary_share` of the budget
+fn fit_history(history: &[ChatMessage], budget: usize, summary_share: f32) -> FittedHistory {
+    let total: usize = history.iter().map(|m| message_tokens(&m.content)).sum();
+    if total <= budget {
+        return FittedHistory { first_kept: 0, summary: None, summarized: 0, tokens: total };
+    }
+
+    let summary_budget = (budget as f32 * summary_share) as usize;
+    let verbatim_budget = budget - summary_budget;
+    let mut first_kept = history.len();
+    let mut tokens = 0;
+    while first_kept > 0 {
+        let cost = message_tokens(&history[first_kept - 1].content);
+        if tokens + cost > verbatim_budget {
+            break;
+        }
+        tokens += cost;
+        first_kept -= 1;
+    }
+
+    // Condense the dropped turns nearest the kept window first
+    let header = format!("[Earlier in this conversation ({} older messages not shown):]", first_kept);
+    let mut lines: Vec<String> = Vec::new();
+    let mut summary_tokens = message_tokens(&header);
+    for msg in history[..first_kept].iter().rev() {
+        let line = format!("- {}: {}", msg.role, truncate_chars(&msg.content.replace('\n', " "), SUMMARY_LINE_CHARS));
+        let cost = estimate_tokens(&line) + 1;
+        if summary_tokens + cost > summary_budget {
+            break;
+        }
+        summary_tokens += cost;
+        lines.push(line);
+    }
+
+    if lines.is_empty() {
+        return FittedHistory { first_kept, summary: None, summarized: 0, tokens };
+    }
+    let summarized = lines.len();
+    lines.reverse();
+    FittedHistory {
+        first_kept,
+        summary: Some(format!("{}\n{}", header, lines.join("\n"))),
+        summarized,
+        tokens: tokens + summary_tokens,
+    }
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: None,
+            user_persona: None,
+            ai_persona: None,
+            model: None,
+            mood: None,
+            interrupted: false,
+        }
+    }
+
+    fn parts<'a>(history: &'a [ChatMessage], memories: &'a [String]) -> ContextParts<'a> {
+        ContextParts {
+            system_prompt: "You are Azera.",
+            memories,
+            session: None,
+            history,
+            user_message: "What did we decide?",
+        }
+    }
+
+    mod estimate_tests {
+        use super::*;
+
+        #[test]
+        fn counts_words_punctuation_and_unicode() {
+            assert_eq!(estimate_tokens(""), 0);
+            assert_eq!(estimate_tokens("hello"), 2);
+            assert_eq!(estimate_tokens("hi, you!"), 4);
+            assert_eq!(estimate_tokens("日本語"), 3);
+        }
+
+        #[test]
+        fn truncation_fits_budget_and_is_char_safe() {
+            let text = "word ".repeat(100) + "日本語";
+            let cut = truncate_to_tokens(&text, 20);
+            assert!(estimate_tokens(&cut) <= 20);
+            assert!(cut.ends_with('…'));
+            assert_eq!(truncate_to_tokens("short", 20), "short");
+        }
+    }
+
+    mod builder_tests {
+        use super::*;
+
+        #[test]
+        fn everything_fits_in_a_large_window() {
+            let history = vec![msg("user", "hello"), msg("assistant", "hi there")];
+            let memories = vec!["[dream] a lighthouse".to_string()];
+            let config = ContextConfig::default();
+            let built = ContextBuilder::new(&config, 8192).build(&parts(&history, &memories));
+
+            assert_eq!(built.messages.len(), 4);
+            assert!(built.messages[0].content.contains("1. [dream] a lighthouse"));
+            assert_eq!(built.messages.last().unwrap().content, "What did we decide?");
+            assert_eq!(built.report.history_dropped, 0);
+            assert_eq!(built.report.memories_included, 1);
+            assert!(built.report.total_used < built.report.context_window - built.report.response_reserve);
+        }
+
+        #[test]
+        fn oldest_turns_are_condensed_then_dropped() {
+            let history: Vec<ChatMessage> = (0..200)
+                .map(|i| msg(if i % 2 == 0 { "user" } else { "assistant" }, &format!("message {} {}", i, "lorem ipsum ".repeat(20))))
+                .collect();
+            let config = ContextConfig::default();
+            let built = ContextBuilder::new(&config, 4096).build(&parts(&history, &[]));
+            let report = &built.report;
+
+            assert!(report.history_dropped > 0);
+            assert!(report.history_summarized > 0);
+            assert!(report.history_summarized <= report.history_dropped);
+            assert_eq!(report.history_included + report.history_dropped, 200);
+            assert!(report.history.used <= report.history.budget);
+            assert!(report.total_used <= 4096 - report.response_reserve);
+            // The newest message survives verbatim, right before the new turn
+            let last_history = &built.messages[built.messages.len() - 2];
+            assert!(last_history.content.starts_with("message 199"));
+            assert!(built.messages[1].content.starts_with("[Earlier in this conversation"));
+        }
+
+        #[test]
+        fn memories_stop_at_budget() {
+            let memories: Vec<String> = (0..100).map(|i| format!("memory {} {}", i, "detail ".repeat(30))).collect();
+            let config = ContextConfig::default();
+            let built = ContextBuilder::new(&config, 4096).build(&parts(&[], &memories));
+            let report = &built.report;
+
+            assert!(report.memories_included > 0);
+            assert!(report.memories_dropped > 0);
+            assert!(report.memories.used <= report.memories.budget);
+            assert!(built.messages[0].content.contains("1. memory 0"));
+        }
+
+        #[test]
+        fn oversized_system_prompt_is_truncated() {
+            let prompt = "persona ".repeat(5000);
+            let config = ContextConfig::default();
+            let mut p = parts(&[], &[]);
+            p.system_prompt = &prompt;
+            let built = ContextBuilder::new(&config, 2048).build(&p);
+            assert!(built.report.system_prompt.used <= built.report.system_prompt.budget + MESSAGE_OVERHEAD_TOKENS);
+        }
+    }
+}
@@ -0,0 +1,725 @@
+//! Chat turn pipeline
+//!
+//! [`run_turn`] answers one user message on a chat branch, or regenerates a
+//! reply to the branch's last message. It saves the message, decides who
+//! speaks (the chat's AI persona, or several cast members in a group chat —
+//! see group.rs), and for each speaker retrieves that
+//! persona's memories, fits the prompt into the model's window, streams the
+//! reply through the tool loop, infers its mood and stores it in CockroachDB
+//! and Qdrant. The streaming endpoint runs it in a background task and
+//! forwards the [`StreamEvent`]s as SSE. A stopped generation (see
+//! generation.rs) ends the turn early with its partial reply saved.
+
+use crate::*;
+use crate::components::DEFAULT_PERSONA_ID;
+use crate::models::{ChatCast, ChatMessage, ContextReport, Persona, ProviderConfig, StreamEvent};
+use serde_json::json;
+use tokio::sync::mpsc;
+
+/// Memories injected into the system prompt per turn
+const RAG_MEMORY_LIMIT: usize = 12;
+/// Characters kept from each injected memory
+const RAG_MEMORY_CHARS: usize = 400;
+/// Candidates handed to the persona's reranker before truncating to `RAG_MEMORY_LIMIT`
+pub const RAG_RERANK_CANDIDATES: usize = 30;
+/// Maximum model ↔ tool round trips before forcing a final answer
+const MAX_TOOL_ROUNDS: usize = 5;
+
+/// One user message to answer
+pub struct ChatTurn {
+    pub user_id: String,
+    pub chat_id: String,
+    pub branch_id: String,
+    pub message: String,
+    pub model: String,
+    pub user_persona_id: Option<String>,
+    pub ai_persona_id: Option<String>,
+    /// Overrides the personas' backends
+    pub provider: Option<ProviderConfig>,
+    /// Answer the branch's last message with `ai_persona_id` instead of
+    /// saving `message` as a new one (`message` still drives retrieval)
+    pub regenerate: bool,
+    /// Generation ID (for logs) and its cancellation token; cancelling stops the turn
+    pub generation_id: String,
+    pub cancel: tokio_util::sync::CancellationToken,
+}
+
+/// A saved reply from one speaker
+struct Reply {
+    message: ChatMessage,
+    context: ContextReport,
+    llm: llm::LLMService,
+}
+
+/// How a speaker's turn ended
+enum Spoken {
+    Reply(Reply),
+    /// Stopped mid-generation; carries the partial reply if anything was streamed
+    Interrupted(Option<ChatMessage>),
+}
+
+/// Answer `turn`, sending stream events to `tx`. Ends with `Done` (after the
+/// last speaker), `Interrupted` or `Error`.
+pub async fn run_turn(state: AppState, turn: ChatTurn, tx: mpsc::Sender<StreamEvent>) {
+    let db = &state.db;
+    let cache = &state.cache;
+
+    // Get chat history (including what the branch inherits) and cast for context
+    let cast = match db::get_chat(db, &turn.chat_id, &turn.user_id).await {
+        Ok(Some(chat)) => chat.cast,
+        _ => None,
+    };
+    let history = db::get_branch_history(db, &turn.branch_id).await.unwrap_or_else(|e| {
+        tracing::warn!("Failed to load history of branch {}: {}", turn.branch_id, e);
+        Vec::new()
+    });
+
+    // Group chat when at least two cast members are visible to this user
+    let members = match cast {
+        Some(ref cast) => load_cast(db, cast, &turn.user_id).await,
+        None => Vec::new(),
+    };
+    let speakers: Vec<Option<Persona>> = if turn.regenerate {
+        let persona = match turn.ai_persona_id {
+            Some(ref persona_id) => match members.iter().find(|p| &p.id == persona_id) {
+                Some(member) => Some(member.clone()),
+                None => db::get_visible_persona(db, persona_id, &turn.user_id).await.ok().flatten(),
+            },
+            None => None,
+        };
+        vec![persona]
+    } else if members.len() >= 2 {
+        let cast = cast.as_ref().expect("members come from the cast");
+        let llm = llm::LLMService::resolve(&state, turn.provider.as_ref(), None);
+        let picked = group::select_speakers(&llm, &turn.model, cast, &members, &history, &turn.message).await;
+        let names: Vec<&str> = picked.iter().map(|&i| members[i].name.as_str()).collect();
+        tracing::info!("👥 Group turn ({:?}): {}", cast.turn_policy, names.join(", "));
+        picked.into_iter().map(|i| Some(members[i].clone())).collect()
+    } else {
+        let persona = match turn.ai_persona_id {
+            Some(ref persona_id) => db::get_visible_persona(db, persona_id, &turn.user_id).await.ok().flatten(),
+            None => None,
+        };
+        vec![persona]
+    };
+    let group_mode = members.len() >= 2;
+
+    // Update agent state: mark the speakers active
+    for persona in &speakers {
+        mark_active(&state, persona.as_ref()).await;
+    }
+
+    // Load session context from Dragonfly (working memory), restored from CockroachDB if evicted
+    let session_ctx = summarizer::load_session(db, cache, &turn.chat_id).await;
+    let session_block = session_block(session_ctx.as_ref());
+
+    // Each speaker sees the history, the user message and the replies so far
+    let mut conversation = history;
+    let user_message_id = if turn.regenerate {
+        None
+    } else {
+        Some(save_user_message(db, &turn, group_mode, &mut conversation).await)
+    };
+    let mut replies: Vec<Reply> = Vec::new();
+    // Set when the generation was stopped: the partial reply's ID, if any
+    let mut interrupted: Option<Option<String>> = None;
+    for persona in &speakers {
+        if turn.cancel.is_cancelled() {
+            interrupted = Some(None);
+            break;
+        }
+        if let Some(persona) = persona.as_ref().filter(|_| group_mode) {
+            let _ = tx.send(StreamEvent::Speaker {
+                persona_id: persona.id.clone(),
+                name: persona.name.clone(),
+            }).await;
+        }
+        let group = if group_mode { members.as_slice() } else { &[] };
+        match speak(&state, &turn, persona.as_ref(), group, &conversation, &session_block, &tx).await {
+            Ok(Spoken::Reply(reply)) => {
+                if let Some(persona) = persona.as_ref().filter(|_| group_mode) {
+                    let _ = tx.send(StreamEvent::SpeakerDone {
+                        persona_id: persona.id.clone(),
+                        message_id: reply.message.id.clone(),
+                        mood: reply.message.mood.clone(),
+                    }).await;
+                }
+                conversation.push(reply.message.clone());
+                replies.push(reply);
+            }
+            Ok(Spoken::Interrupted(partial)) => {
+                interrupted = Some(partial.map(|m| m.id));
+                break;
+            }
+            Err(e) => {
+                tracing::error!("❌ LLM inference failed: {}", e);
+                let _ = tx.send(StreamEvent::Error {
+                    message: format!("LLM inference failed: {}", e),
+                }).await;
+                break;
+            }
+        }
+    }
+
+    if chat_deleted(&state, &turn).await {
+        // Deleting the chat stopped the turn; leave nothing behind
+        tracing::info!("🗑️ Chat {} was deleted during generation {}, discarding the turn", turn.chat_id, turn.generation_id);
+        let _ = tx.send(StreamEvent::Interrupted { message_id: None }).await;
+    } else if let Some(message_id) = interrupted {
+        // Keep what was saved searchable, but don't commit the exchange to
+        // memory or the session
+        tracing::info!("⏹️ Generation {} stopped", turn.generation_id);
+        if message_id.is_some() || !replies.is_empty() {
+            handlers::spawn_meili_reindex(&state, &turn.chat_id, &turn.user_id);
+        }
+        let _ = tx.send(StreamEvent::Interrupted { message_id }).await;
+    } else if !replies.is_empty() {
+        let speaker_ids: Vec<String> = replies.iter().filter_map(|r| r.message.ai_persona.clone()).collect();
+        if let Some(ref message_id) = user_message_id {
+            store_user_memory(&state, &turn, message_id, &speaker_ids, group_mode).await;
+        }
+
+        // Re-index chat in Meilisearch with new messages
+        handlers::spawn_meili_reindex(&state, &turn.chat_id, &turn.user_id);
+
+        // Update session context in Dragonfly (working memory); every few
+        // turns the summarizer folds the new turns into the rolling summary
+        let assistant_text = if group_mode {
+            replies
+                .iter()
+                .map(|r| format!("[{}]: {}", speaker_name(&members, r.message.ai_persona.as_deref()), r.message.content))
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        } else {
+            replies[0].message.content.clone()
+        };
+        let last = replies.pop().expect("at least one reply");
+        // A regenerated reply replaces an exchange rather than adding one
+        let session_update = if turn.regenerate {
+            None
+        } else {
+            Some(cache::CacheService::update_session_after_exchange(cache, &turn.chat_id, &turn.message, &assistant_text).await)
+        };
+        match session_update {
+            Some(Ok(session)) if summarizer::is_due(&session, summarizer::interval()) => {
+                tokio::spawn(summarizer::refresh(
+                    db.clone(),
+                    cache.clone(),
+                    last.llm.clone(),
+                    turn.model.clone(),
+                    turn.chat_id.clone(),
+                    turn.branch_id.clone(),
+                ));
+            }
+            Some(Ok(_)) | None => {}
+            Some(Err(e)) => tracing::warn!("📝 Failed to update session context: {}", e),
+        }
+
+        // Read the last speaker's latest mood/energy from Dragonfly for the Done event
+        let last_persona_id = last.message.ai_persona.as_deref().unwrap_or(DEFAULT_PERSONA_ID);
+        let (done_mood_value, done_energy) = match cache::CacheService::get_mental_state(cache, last_persona_id).await {
+            Ok(Some(ms)) => (Some(ms.mood), Some(ms.energy)),
+            _ => (None, None),
+        };
+
+        // Send done event with mood + energy for frontend sync
+        let _ = tx.send(StreamEvent::Done {
+            message_id: last.message.id,
+            mood: last.message.mood,
+            mood_value: done_mood_value,
+            energy: done_energy,
+            context: Some(last.context),
+        }).await;
+    }
+
+    // Update agent state back to idle
+    {
+        let mut agent_guard = state.agent.write().await;
+        for persona in &speakers {
+            let persona_id = persona.as_ref().map_or(DEFAULT_PERSONA_ID, |p| p.id.as_str());
+            agent_guard.persona_mut(persona_id).mental_state.last_active = chrono::Utc::now();
+        }
+    }
+}
+
+/// Whether the turn's chat was deleted while it ran; nothing more of the
+/// turn is persisted then, so the chat can't resurface
+async fn chat_deleted(state: &AppState, turn: &ChatTurn) -> bool {
+    matches!(db::chat_exists(&state.db, &turn.chat_id, &turn.user_id).await, Ok(false))
+}
+
+/// Restore a speaker's mood from Dragonfly and boost its focus for the turn
+async fn mark_active(state: &AppState, persona: Option<&Persona>) {
+    let persona_id = persona.map_or(DEFAULT_PERSONA_ID, |p| p.id.as_str());
+    let cached = cache::CacheService::get_mental_state(&state.cache, persona_id).await.ok().flatten();
+
+    let mut agent_guard = state.agent.write().await;
+    let persona_state = agent_guard.persona_mut(persona_id);
+    if let Some(persona) = persona {
+        persona_state.name = persona.name.clone();
+    }
+    let mental_state = &mut persona_state.mental_state;
+    // Restore mood from Dragonfly if available, otherwise keep current
+    if let Some(cached_state) = cached {
+        mental_state.mood = cached_state.mood;
+        mental_state.energy = cached_state.energy;
+        mental_state.focus_level = cached_state.focus_level;
+    }
+    // Boost focus when actively processing
+    mental_state.focus_level = 0.9;
+    mental_state.last_active = chrono::Utc::now();
+}
+
+/// Save the user message and append it to `conversation`; returns its ID
+async fn save_user_message(db: &sqlx::Pool<sqlx::Postgres>, turn: &ChatTurn, group_mode: bool, conversation: &mut Vec<ChatMessage>) -> String {
+    let user_msg = ChatMessage {
+        id: format!("msg_{}", uuid::Uuid::new_v4()),
+        role: "user".to_string(),
+        content: turn.message.clone(),
+        timestamp: Some(chrono::Utc::now()),
+        user_persona: turn.user_persona_id.clone(),
+        ai_persona: if group_mode { None } else { turn.ai_persona_id.clone() },
+        model: Some(turn.model.clone()),
+        mood: None,
+        interrupted: false,
+    };
+    let _ = db::add_message_to_branch(db, &user_msg, &turn.branch_id).await;
+    let id = user_msg.id.clone();
+    conversation.push(user_msg);
+    id
+}
+
+/// Cast members visible to the user, in cast order
+async fn load_cast(db: &sqlx::Pool<sqlx::Postgres>, cast: &ChatCast, user_id: &str) -> Vec<Persona> {
+    let mut members = Vec::new();
+    for id in &cast.persona_ids {
+        match db::get_visible_persona(db, id, user_id).await {
+            Ok(Some(persona)) => members.push(persona),
+            Ok(None) => tracing::warn!("👥 Skipping unknown cast persona {}", id),
+            Err(e) => tracing::warn!("👥 Failed to load cast persona {}: {}", id, e),
+        }
+    }
+    members
+}
+
+fn speaker_name<'a>(members: &'a [Persona], persona_id: Option<&str>) -> &'a str {
+    members
+        .iter()
+        .find(|p| Some(p.id.as_str()) == persona_id)
+        .map(|p| p.name.as_str())
+        .unwrap_or("Assistant")
+}
+
+/// Rolling conversation summary for the prompt (empty until the first summary)
+fn session_block(session: Option<&cache::SessionContext>) -> String {
+    match session {
+        Some(ctx) if !ctx.conversation_summary.is_empty() => {
+            let goal = ctx.active_goal.as_deref()
+                .map(|g| format!("\nCurrent goal: {}", g))
+                .unwrap_or_default();
+            format!("[Current conversation context:]\nSummary: {}{}\nTopics: {}\nTurn: {}",
+                ctx.conversation_summary,
+                goal,
+                ctx.recent_topics.join(", "),
+                ctx.turn_count)
+        }
+        _ => String::new(),
+    }
+}
+
+/// Generate, stream and save one speaker's reply to the last message of
+/// `conversation`. `members` is empty outside group chats. When the
+/// generation is stopped, the streamed part is saved as an interrupted reply
+/// without mood, memory or image follow-ups.
+async fn speak(
+    state: &AppState,
+    turn: &ChatTurn,
+    persona: Option<&Persona>,
+    members: &[Persona],
+    conversation: &[ChatMessage],
+    session_block: &str,
+    tx: &mpsc::Sender<StreamEvent>,
+) -> anyhow::Result<Spoken> {
+    let db = &state.db;
+    let cache = &state.cache;
+    let ai_persona_id = persona.map(|p| p.id.clone());
+    let global_memory_enabled = persona.is_none_or(|p| p.global_memory_enabled);
+    let mut system_prompt = persona
+        .and_then(|p| p.system_prompt.clone())
+        .unwrap_or_else(default_system_prompt);
+
+    // ── Hybrid RAG: Qdrant (semantic) + Meilisearch (lexical) ──
+    // If global_memory_enabled, search persona's memories across ALL chats
+    // via the shared retrieval pipeline (see retrieval.rs)
+    tracing::info!("🧠 RAG check: persona_id={:?}, global_memory_enabled={}", ai_persona_id, global_memory_enabled);
+    let memory_lines = if global_memory_enabled {
+        retrieve_memories(state, turn, ai_persona_id.as_deref(), persona.and_then(|p| p.reranker.as_ref())).await
+    } else {
+        tracing::debug!("🧠 Global memory disabled for this persona");
+        Vec::new()
+    };
+
+    // In a group, the others' replies are shown as named user turns
+    let mut history = match persona {
+        Some(persona) if !members.is_empty() => {
+            system_prompt = format!("{}\n\n{}", system_prompt, group::group_note(persona, members));
+            group::perspective(conversation, &persona.id, members)
+        }
+        _ => conversation.to_vec(),
+    };
+    let user_message = history.pop().map(|m| m.content).unwrap_or_default();
+
+    // Call the LLM with streaming (request backend > persona backend > Ollama)
+    let llm = llm::LLMService::resolve(state, turn.provider.as_ref(), persona.and_then(|p| p.llm_provider.as_ref()));
+    tracing::debug!("🧠 Using {} backend for model {}", llm.provider_name(), turn.model);
+
+    // Fit system prompt + memories + session context + history into the model's window
+    let window = context::resolve_window(&llm, &turn.model, cache, &state.context).await;
+    let llm = llm.with_context_window(window);
+    let built = context::ContextBuilder::new(&state.context, window).build(&context::ContextParts {
+        system_prompt: &system_prompt,
+        memories: &memory_lines,
+        session: Some(session_block),
+        history: &history,
+        user_message: &user_message,
+    });
+    let context_report = built.report;
+    tracing::info!("📏 Context: {}/{} tokens ({} history messages, {} dropped, {} memories)",
+        context_report.total_used, context_report.context_window, context_report.history_included,
+        context_report.history_dropped, context_report.memories_included);
+    let tools = tools::tool_definitions(&state.agent.read().await.agent_config.tools_enabled);
+
+    // Tokens pass through a forwarder that tags group replies with the
+    // speaker and keeps the visible text in case the generation is stopped
+    let (speaker_tx, mut speaker_rx) = mpsc::channel::<StreamEvent>(100);
+    let out = tx.clone();
+    let speaker = ai_persona_id.clone().filter(|_| !members.is_empty());
+    let forward = tokio::spawn(async move {
+        let mut streamed = String::new();
+        while let Some(event) = speaker_rx.recv().await {
+            if let StreamEvent::Content { ref content, .. } = event {
+                streamed.push_str(content);
+            }
+            let event = match speaker {
+                Some(ref speaker) => event.with_speaker(speaker),
+                None => event,
+            };
+            // Keep draining after the client is gone so the text survives
+            let _ = out.send(event).await;
+        }
+        streamed
+    });
+    // Dropping the tool loop closes the model's HTTP stream, which stops generation
+    let result = tokio::select! {
+        result = run_tool_loop(&llm, &turn.model, built.messages, &tools, cache, &speaker_tx) => Some(result),
+        _ = turn.cancel.cancelled() => None,
+    };
+    drop(speaker_tx);
+    let streamed = forward.await.unwrap_or_default();
+    let full_response = match result {
+        Some(result) => result?,
+        None => return Ok(Spoken::Interrupted(save_interrupted(state, turn, ai_persona_id, streamed).await)),
+    };
+    if chat_deleted(state, turn).await {
+        return Ok(Spoken::Interrupted(None));
+    }
+
+    // Infer mood from the AI's response using a quick LLM call
+    let mood = match llm.infer_mood(&turn.model, &full_response).await {
+        Ok(m) => {
+            tracing::info!("🎭 AI persona mood inferred: {}", m);
+
+            // Sync mood to Dragonfly (working memory) → agent state syncs on tick
+            let mood_value = match m.as_str() {
+                "happy" => 0.85, "excited" => 0.9,
+                "content" => 0.7, "calm" => 0.65,
+                "curious" => 0.75, "thoughtful" => 0.6,
+                "melancholy" => 0.3, "concerned" => 0.4,
+                _ => 0.6,
+            };
+            // Energy decreases slightly per exchange
+            let persona_key = ai_persona_id.as_deref().unwrap_or(DEFAULT_PERSONA_ID);
+            match cache::CacheService::update_mood(cache, persona_key, mood_value, &m, -0.03).await {
+                Ok(updated) => {
+                    // Also sync to agent state immediately for responsiveness
+                    state.agent.write().await.update_mental_state(persona_key, mood_value, updated.energy);
+                    state.events.publish_scoped(persona.and_then(|p| p.user_id.as_deref()), events::AgentEvent::MoodChanged {
+                        persona_id: persona_key.to_string(),
+                        mood: m.clone(),
+                        mood_value,
+                        energy: updated.energy,
+                    });
+                }
+                Err(e) => tracing::warn!("🎭 Failed to store mood of {}: {}", persona_key, e),
+            }
+
+            m
+        }
+        Err(e) => {
+            tracing::warn!("🎭 Mood inference failed: {}, defaulting to 'content'", e);
+            "content".to_string()
+        }
+    };
+
+    // If global memory is enabled, update the persona's mood in the database
+    if global_memory_enabled {
+        if let Some(ref persona_id) = ai_persona_id {
+            let mood_update = models::UpdatePersonaRequest {
+                name: None,
+                description: None,
+                avatar: None,
+                bubble_color: None,
+                system_prompt: None,
+                global_memory_enabled: None,
+                current_mood: Some(mood.clone()),
+                voice: None,
+                llm_provider: None,
+                reranker: None,
+                metadata: None,
+                tags: None,
+            };
+            if let Err(e) = db::update_persona(db, persona_id, None, &mood_update).await {
+                tracing::warn!("🎭 Failed to update persona mood: {}", e);
+            } else {
+                tracing::debug!("🎭 Updated persona {} mood to {}", persona_id, mood);
+            }
+        }
+    }
+
+    // Save assistant message
+    let assistant_msg = ChatMessage {
+        id: format!("msg_{}", uuid::Uuid::new_v4()),
+        role: "assistant".to_string(),
+        content: full_response.clone(),
+        timestamp: Some(chrono::Utc::now()),
+        user_persona: turn.user_persona_id.clone(),
+        ai_persona: ai_persona_id.clone(),
+        model: Some(turn.model.clone()),
+        mood: Some(mood),
+        interrupted: false,
+    };
+    let _ = db::add_message_to_branch(db, &assistant_msg, &turn.branch_id).await;
+
+    // Keep the exchange in the speaker's working memory, under this user only
+    {
+        let mut agent_guard = state.agent.write().await;
+        let working_memory = &mut agent_guard.persona_mut(ai_persona_id.as_deref().unwrap_or(DEFAULT_PERSONA_ID)).working_memory;
+        working_memory.remember(&turn.user_id, format!("user: {}", turn.message));
+        working_memory.remember(&turn.user_id, format!("assistant: {}", full_response));
+    }
+
+    // Store the response in vector DB for future RAG (with embedding cache)
+    let persona_value = ai_persona_id.as_ref().map(|id| json!(id));
+    match store_conversation_memory(state, turn, &assistant_msg.id, "assistant", &full_response, persona_value).await {
+        Ok(_) => tracing::info!("🧠 Stored assistant response in memory for persona {:?}", ai_persona_id),
+        Err(e) => tracing::warn!("🧠 Failed to store assistant memory: {}", e),
+    }
+
+    // Check for image generation requests in AI's response
+    // Pattern: [IMAGE_GEN: prompt="...", name="..."]
+    for (img_prompt, custom_name) in handlers::extract_image_gen_requests(&full_response) {
+        handlers::trigger_image_generation(
+            &img_prompt, custom_name.as_deref(), ai_persona_id.as_deref(), db, &state.events, &turn.user_id, &turn.chat_id,
+        ).await;
+    }
+
+    Ok(Spoken::Reply(Reply { message: assistant_msg, context: context_report, llm }))
+}
+
+/// Save the part of a stopped reply that was streamed, if any
+async fn save_interrupted(
+    state: &AppState,
+    turn: &ChatTurn,
+    ai_persona_id: Option<String>,
+    streamed: String,
+) -> Option<ChatMessage> {
+    if streamed.trim().is_empty() || chat_deleted(state, turn).await {
+        return None;
+    }
+    let message = ChatMessage {
+        id: format!("msg_{}", uuid::Uuid::new_v4()),
+        role: "assistant".to_string(),
+        content: streamed,
+        timestamp: Some(chrono::Utc::now()),
+        user_persona: turn.user_persona_id.clone(),
+        ai_persona: ai_persona_id,
+        model: Some(turn.model.clone()),
+        mood: None,
+        interrupted: true,
+    };
+    if let Err(e) = db::add_message_to_branch(&state.db, &message, &turn.branch_id).await {
+        tracing::warn!("Failed to save interrupted reply: {}", e);
+        return None;
+    }
+    Some(message)
+}
+
+/// Semantic + lexical memories and past chats, fused and ranked, as prompt
+/// lines. Scoped to this user and persona; the current chat and anything
+/// from the last minute are skipped to avoid echo.
+async fn retrieve_memories(
+    state: &AppState,
+    turn: &ChatTurn,
+    persona_id: Option<&str>,
+    reranker: Option<&models::RerankerConfig>,
+) -> Vec<String> {
+    let retriever = retrieval::HybridRetriever::new(state);
+    let mut query = retrieval::RetrievalQuery::new(&turn.message, retrieval::Scope::User(turn.user_id.clone()));
+    query.persona_id = persona_id.map(str::to_string);
+    query.exclude_chat_id = Some(turn.chat_id.clone());
+    query.include_chats = true;
+    query.min_age_secs = 60;
+    query.limit = if reranker.is_some() { RAG_RERANK_CANDIDATES } else { RAG_MEMORY_LIMIT };
+    let mut retrieved = retriever.retrieve(&query).await;
+    if let Some(config) = reranker {
+        let reranker = rerank::Reranker::from_config(config, state);
+        retrieved.memories = reranker.rerank(&turn.message, retrieved.memories).await;
+        retrieved.memories.truncate(RAG_MEMORY_LIMIT);
+    }
+
+    if !retrieved.memories.is_empty() {
+        tracing::info!("🧠 Hybrid RAG: {} memories from {} semantic + {} lexical + {} chat hits",
+            retrieved.memories.len(), retrieved.semantic_hits, retrieved.lexical_hits, retrieved.chat_hits);
+    } else {
+        tracing::debug!("🧠 No relevant memories found for persona {:?}", persona_id);
+    }
+    retrieved.memories.iter().map(|m| m.prompt_line(RAG_MEMORY_CHARS)).collect()
+}
+
+/// Store the user message once, visible to every persona that answered it
+async fn store_user_memory(state: &AppState, turn: &ChatTurn, message_id: &str, speaker_ids: &[String], group_mode: bool) {
+    let persona_value = if group_mode {
+        Some(json!(speaker_ids))
+    } else {
+        turn.ai_persona_id.as_ref().map(|id| json!(id))
+    };
+    match store_conversation_memory(state, turn, message_id, "user", &turn.message, persona_value).await {
+        Ok(_) => tracing::info!("🧠 Stored user message in memory for persona(s) {:?}", speaker_ids),
+        Err(e) => tracing::warn!("🧠 Failed to store user memory: {}", e),
+    }
+}
+
+async fn store_conversation_memory(
+    state: &AppState,
+    turn: &ChatTurn,
+    message_id: &str,
+    role: &str,
+    content: &str,
+    ai_persona_id: Option<serde_json::Value>,
+) -> anyhow::Result<()> {
+    let vector_service = vector::VectorService::new(state.qdrant_url.clone());
+    let mut metadata: std::collections::HashMap<String, serde_json::Value> = std::collections::HashMap::new();
+    metadata.insert("role".to_string(), json!(role));
+    metadata.insert("message_id".to_string(), json!(message_id));
+    metadata.insert("chat_id".to_string(), json!(turn.chat_id.clone()));
+    metadata.insert("branch_id".to_string(), json!(turn.branch_id.clone()));
+    metadata.insert("user_id".to_string(), json!(turn.user_id.clone()));
+    // A list in group chats; Qdrant's match filter matches any element
+    if let Some(persona) = ai_persona_id {
+        metadata.insert("ai_persona_id".to_string(), persona);
+    }
+
+    let request = vector::StoreMemoryRequest {
+        collection: "azera_memory".to_string(),
+        id: uuid::Uuid::new_v4().to_string(),
+        content: content.to_string(),
+        memory_type: vector::MemoryType::Conversation,
+        metadata,
+    };
+    vector::store_memory_cached(&vector_service, &state.ollama_host, &state.cache, &request).await?;
+    Ok(())
+}
+
+/// Stream model turns, executing any native tool calls and feeding their
+/// results back until the model produces a final answer.
+/// Returns the full visible response across all turns.
+async fn run_tool_loop(
+    llm: &llm::LLMService,
+    model: &str,
+    mut messages: Vec<models::OllamaMessage>,
+    tools: &[serde_json::Value],
+    cache: &redis::aio::ConnectionManager,
+    tx: &mpsc::Sender<StreamEvent>,
+) -> anyhow::Result<String> {
+    if tools.is_empty() {
+        return llm.infer_streaming(model, messages, tx.clone()).await;
+    }
+
+    let mut full_response = String::new();
+
+    for _ in 0..MAX_TOOL_ROUNDS {
+        let turn = llm.infer_streaming_with_tools(model, messages.clone(), tools, tx.clone()).await?;
+        full_response.push_str(&turn.content);
+
+        if turn.tool_calls.is_empty() {
+            return Ok(full_response);
+        }
+
+        messages.push(models::OllamaMessage {
+            role: "assistant".to_string(),
+            content: turn.content,
+            tool_calls: Some(turn.tool_calls.clone()),
+        });
+
+        for call in turn.tool_calls {
+            let name = call.function.name;
+            let arguments = call.function.arguments;
+            tracing::info!("🛠️ Tool call: {} {}", name, arguments);
+            let _ = tx.send(StreamEvent::ToolCall {
+                name: name.clone(),
+                arguments: arguments.clone(),
+            }).await;
+
+            // Only tools advertised for this turn may run, whatever the model asks for
+            let started = std::time::Instant::now();
+            let (output, success) = if !tools::is_offered(tools, &name) {
+                tracing::warn!("Refusing tool {}: not enabled", name);
+                (format!("Error: tool `{}` is not enabled", name), false)
+            } else {
+                match tools::execute_tool(&name, &arguments).await {
+                    Ok(output) => (output, true),
+                    Err(e) => {
+                        tracing::warn!("Tool {} failed: {}", name, e);
+                        (format!("Error: {}", e), false)
+                    }
+                }
+            };
+            let duration_ms = started.elapsed().as_millis() as u64;
+
+            let _ = tx.send(StreamEvent::ToolResult {
+                name: name.clone(),
+                output: output.clone(),
+                success,
+                duration_ms,
+            }).await;
+
+            let exec = cache::ToolExecution {
+                tool_name: name.clone(),
+                input_summary: arguments.to_string().chars().take(200).collect(),
+                output_summary: output.chars().take(200).collect(),
+                success,
+                timestamp: chrono::Utc::now(),
+            };
+            if let Err(e) = cache::CacheService::record_tool_execution(cache, &exec).await {
+                tracing::warn!("Failed to record tool execution: {}", e);
+            }
+
+            messages.push(models::OllamaMessage {
+                role: "tool".to_string(),
+                content: output,
+                tool_calls: None,
+            });
+        }
+    }
+
+    // Out of tool rounds - ask for a final answer without tools
+    tracing::warn!("Tool loop hit {} rounds, forcing final answer", MAX_TOOL_ROUNDS);
+    let final_answer = llm.infer_streaming(model, messages, tx.clone()).await?;
+    full_response.push_str(&final_answer);
+    Ok(full_response)
+}
+
+fn default_system_prompt() -> String {
+    "You are Azera, a thoughtful and curious AI entity. \
+     Respond with wisdom, empathy, and intellectual rigor. Be concise but meaningful.".to_string()
+}
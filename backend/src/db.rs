@@ -1,330 +1,8 @@
 use sqlx::{Pool, Postgres, Row};
 use anyhow::Result;
 use crate::models::*;
-use chrono::Utc;
-
-/// Initialize database schema
-pub async fn init_schema(pool: &Pool<Postgres>) -> Result<()> {
-    // ============================================================
-    // Personas table
-    // ============================================================
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS personas (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            persona_type TEXT NOT NULL,
-            description TEXT NOT NULL DEFAULT '',
-            avatar TEXT,
-            bubble_color TEXT,
-            system_prompt TEXT,
-            global_memory_enabled BOOLEAN DEFAULT TRUE,
-            metadata JSONB DEFAULT '{}',
-            tags JSONB DEFAULT '[]',
-            created_at TIMESTAMPTZ DEFAULT NOW(),
-            updated_at TIMESTAMPTZ DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-    
-    // Add global_memory_enabled column if it doesn't exist (for existing databases)
-    let _ = sqlx::query(
-        "ALTER TABLE personas ADD COLUMN IF NOT EXISTS global_memory_enabled BOOLEAN DEFAULT TRUE"
-    )
-    .execute(pool)
-    .await;
-    
-    // Add current_mood column if it doesn't exist
-    let _ = sqlx::query(
-        "ALTER TABLE personas ADD COLUMN IF NOT EXISTS current_mood TEXT"
-    )
-    .execute(pool)
-    .await;
-    
-    // Add voice column for TTS settings (JSONB)
-    let _ = sqlx::query(
-        "ALTER TABLE personas ADD COLUMN IF NOT EXISTS voice JSONB"
-    )
-    .execute(pool)
-    .await;
-
-    // ============================================================
-    // Chat groups table
-    // ============================================================
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS chat_groups (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            color TEXT NOT NULL DEFAULT '#6b7280',
-            collapsed BOOLEAN DEFAULT FALSE,
-            sort_order INT DEFAULT 0,
-            created_at TIMESTAMPTZ DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // ============================================================
-    // Tags table
-    // ============================================================
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS tags (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            color TEXT NOT NULL DEFAULT '#6b7280',
-            created_at TIMESTAMPTZ DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // ============================================================
-    // Chats table
-    // ============================================================
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS chats (
-            id TEXT PRIMARY KEY,
-            title TEXT NOT NULL,
-            current_branch_id TEXT NOT NULL,
-            group_id TEXT REFERENCES chat_groups(id) ON DELETE SET NULL,
-            tags JSONB DEFAULT '[]',
-            created_at TIMESTAMPTZ DEFAULT NOW(),
-            updated_at TIMESTAMPTZ DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_chats_group ON chats(group_id)")
-        .execute(pool)
-        .await?;
-
-    // ============================================================
-    // Chat branches table
-    // ============================================================
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS chat_branches (
-            id TEXT PRIMARY KEY,
-            chat_id TEXT NOT NULL REFERENCES chats(id) ON DELETE CASCADE,
-            name TEXT NOT NULL,
-            parent_branch_id TEXT REFERENCES chat_branches(id) ON DELETE SET NULL,
-            fork_point_message_id TEXT,
-            created_at TIMESTAMPTZ DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_branches_chat ON chat_branches(chat_id)")
-        .execute(pool)
-        .await?;
-
-    // ============================================================
-    // Chat messages table
-    // ============================================================
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS chat_messages (
-            id TEXT PRIMARY KEY,
-            branch_id TEXT NOT NULL REFERENCES chat_branches(id) ON DELETE CASCADE,
-            role TEXT NOT NULL,
-            content TEXT NOT NULL,
-            user_persona_id TEXT,
-            ai_persona_id TEXT,
-            model TEXT,
-            mood TEXT,
-            created_at TIMESTAMPTZ DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_messages_branch ON chat_messages(branch_id, created_at)")
-        .execute(pool)
-        .await?;
-
-    // ============================================================
-    // Dreams table
-    // ============================================================
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS dreams (
-            id TEXT PRIMARY KEY,
-            title TEXT NOT NULL,
-            content TEXT NOT NULL,
-            mood TEXT,
-            persona_id TEXT,
-            persona_name TEXT,
-            tags TEXT[],
-            created_at TIMESTAMPTZ DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // Add persona columns if they don't exist (migration)
-    let _ = sqlx::query("ALTER TABLE dreams ADD COLUMN IF NOT EXISTS persona_id TEXT")
-        .execute(pool).await;
-    let _ = sqlx::query("ALTER TABLE dreams ADD COLUMN IF NOT EXISTS persona_name TEXT")
-        .execute(pool).await;
-    let _ = sqlx::query("ALTER TABLE dreams ADD COLUMN IF NOT EXISTS tags TEXT[]")
-        .execute(pool).await;
-
-    // ============================================================
-    // Journal entries table
-    // ============================================================
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS journal_entries (
-            id TEXT PRIMARY KEY,
-            date TEXT NOT NULL,
-            title TEXT NOT NULL,
-            content TEXT NOT NULL,
-            mood TEXT,
-            persona_id TEXT,
-            persona_name TEXT,
-            tags TEXT[],
-            created_at TIMESTAMPTZ DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // Add persona columns if they don't exist (migration)
-    let _ = sqlx::query("ALTER TABLE journal_entries ADD COLUMN IF NOT EXISTS persona_id TEXT")
-        .execute(pool).await;
-    let _ = sqlx::query("ALTER TABLE journal_entries ADD COLUMN IF NOT EXISTS persona_name TEXT")
-        .execute(pool).await;
-    let _ = sqlx::query("ALTER TABLE journal_entries ADD COLUMN IF NOT EXISTS tags TEXT[]")
-        .execute(pool).await;
-
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_journal_date ON journal_entries(date)")
-        .execute(pool)
-        .await?;
-
-    // ============================================================
-    // System logs table
-    // ============================================================
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS system_logs (
-            id TEXT PRIMARY KEY,
-            level TEXT NOT NULL,
-            message TEXT NOT NULL,
-            created_at TIMESTAMPTZ DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // ============================================================
-    // User settings table (for editor config, UI preferences, etc.)
-    // ============================================================
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS user_settings (
-            id TEXT PRIMARY KEY DEFAULT 'default',
-            editor_settings JSONB DEFAULT '{}',
-            ui_settings JSONB DEFAULT '{}',
-            updated_at TIMESTAMPTZ DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // ============================================================
-    // Configuration table (keep existing)
-    // ============================================================
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS config (
-            key TEXT PRIMARY KEY,
-            value TEXT NOT NULL,
-            updated_at TIMESTAMPTZ DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    // ============================================================
-    // Chat history table (legacy - for migration)
-    // ============================================================
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS chat_history (
-            id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-            session_id TEXT NOT NULL,
-            role TEXT NOT NULL,
-            content TEXT NOT NULL,
-            created_at TIMESTAMPTZ DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_chat_session ON chat_history(session_id, created_at)")
-        .execute(pool)
-        .await?;
-
-    // ============================================================
-    // Logs table (legacy - keep for now)
-    // ============================================================
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS logs (
-            id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-            date DATE NOT NULL,
-            content TEXT,
-            summary TEXT,
-            embeddings_stored BOOLEAN DEFAULT FALSE,
-            created_at TIMESTAMPTZ DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_logs_date ON logs(date)")
-        .execute(pool)
-        .await?;
-
-    // ============================================================
-    // Embeddings metadata table
-    // ============================================================
-    sqlx::query(
-        r#"
-        CREATE TABLE IF NOT EXISTS embeddings (
-            id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-            embedding_id TEXT NOT NULL UNIQUE,
-            source TEXT,
-            metadata JSONB,
-            created_at TIMESTAMPTZ DEFAULT NOW()
-        )
-        "#,
-    )
-    .execute(pool)
-    .await?;
-
-    tracing::info!("Database schema initialized");
-    Ok(())
-}
+use crate::pagination::Cursor;
+use chrono::{DateTime, Utc};
 
 // ============================================================
 // Persona CRUD
@@ -333,8 +11,8 @@ pub async fn init_schema(pool: &Pool<Postgres>) -> Result<()> {
 pub async fn create_persona(pool: &Pool<Postgres>, persona: &Persona) -> Result<()> {
     sqlx::query(
         r#"
-        INSERT INTO personas (id, name, persona_type, description, avatar, bubble_color, system_prompt, global_memory_enabled, metadata, tags, created_at, updated_at)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        INSERT INTO personas (id, name, persona_type, description, avatar, bubble_color, system_prompt, global_memory_enabled, voice, llm_provider, reranker, user_id, metadata, tags, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
         "#,
     )
     .bind(&persona.id)
@@ -345,6 +23,10 @@ pub async fn create_persona(pool: &Pool<Postgres>, persona: &Persona) -> Result<
     .bind(&persona.bubble_color)
     .bind(&persona.system_prompt)
     .bind(persona.global_memory_enabled)
+    .bind(persona.voice.as_ref().map(serde_json::to_value).transpose()?)
+    .bind(persona.llm_provider.as_ref().map(serde_json::to_value).transpose()?)
+    .bind(persona.reranker.as_ref().map(serde_json::to_value).transpose()?)
+    .bind(&persona.user_id)
     .bind(serde_json::to_value(&persona.metadata)?)
     .bind(serde_json::to_value(&persona.tags)?)
     .bind(persona.created_at)
@@ -354,49 +36,33 @@ pub async fn create_persona(pool: &Pool<Postgres>, persona: &Persona) -> Result<
     Ok(())
 }
 
+/// Get a persona regardless of owner (for internal/system use)
 pub async fn get_persona(pool: &Pool<Postgres>, id: &str) -> Result<Option<Persona>> {
     let row = sqlx::query(
-        "SELECT id, name, persona_type, description, avatar, bubble_color, system_prompt, global_memory_enabled, voice, metadata, tags, created_at, updated_at FROM personas WHERE id = $1"
+        "SELECT id, name, persona_type, description, avatar, bubble_color, system_prompt, global_memory_enabled, current_mood, voice, llm_provider, reranker, user_id, metadata, tags, created_at, updated_at FROM personas WHERE id = $1"
     )
     .bind(id)
     .fetch_optional(pool)
     .await?;
 
-    match row {
-        Some(r) => Ok(Some(Persona {
-            id: r.get("id"),
-            name: r.get("name"),
-            persona_type: r.get("persona_type"),
-            description: r.get("description"),
-            avatar: r.get("avatar"),
-            bubble_color: r.get("bubble_color"),
-            system_prompt: r.get("system_prompt"),
-            global_memory_enabled: r.try_get("global_memory_enabled").unwrap_or(true),
-            current_mood: r.try_get("current_mood").ok().flatten(),
-            voice: r.try_get::<Option<serde_json::Value>, _>("voice").ok().flatten().and_then(|v| serde_json::from_value(v).ok()),
-            metadata: serde_json::from_value(r.get("metadata")).unwrap_or_default(),
-            tags: serde_json::from_value(r.get("tags")).ok(),
-            created_at: r.get("created_at"),
-            updated_at: r.get("updated_at"),
-        })),
-        None => Ok(None),
-    }
+    Ok(row.map(|r| persona_from_row(&r)))
 }
 
-pub async fn list_personas(pool: &Pool<Postgres>, persona_type: Option<&str>) -> Result<Vec<Persona>> {
-    let query = if let Some(pt) = persona_type {
-        sqlx::query(
-            "SELECT id, name, persona_type, description, avatar, bubble_color, system_prompt, global_memory_enabled, current_mood, voice, metadata, tags, created_at, updated_at FROM personas WHERE persona_type = $1 ORDER BY name"
-        ).bind(pt)
-    } else {
-        sqlx::query(
-            "SELECT id, name, persona_type, description, avatar, bubble_color, system_prompt, global_memory_enabled, current_mood, voice, metadata, tags, created_at, updated_at FROM personas ORDER BY name"
-        )
-    };
+/// Get a persona if it's owned by `user_id` or shared
+pub async fn get_visible_persona(pool: &Pool<Postgres>, id: &str, user_id: &str) -> Result<Option<Persona>> {
+    let row = sqlx::query(
+        "SELECT id, name, persona_type, description, avatar, bubble_color, system_prompt, global_memory_enabled, current_mood, voice, llm_provider, reranker, user_id, metadata, tags, created_at, updated_at FROM personas WHERE id = $1 AND (user_id = $2 OR user_id IS NULL)"
+    )
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
 
-    let rows = query.fetch_all(pool).await?;
-    
-    Ok(rows.iter().map(|r| Persona {
+    Ok(row.map(|r| persona_from_row(&r)))
+}
+
+fn persona_from_row(r: &sqlx::postgres::PgRow) -> Persona {
+    Persona {
         id: r.get("id"),
         name: r.get("name"),
         persona_type: r.get("persona_type"),
@@ -407,14 +73,39 @@ pub async fn list_personas(pool: &Pool<Postgres>, persona_type: Option<&str>) ->
         global_memory_enabled: r.try_get("global_memory_enabled").unwrap_or(true),
         current_mood: r.try_get("current_mood").ok().flatten(),
         voice: r.try_get::<Option<serde_json::Value>, _>("voice").ok().flatten().and_then(|v| serde_json::from_value(v).ok()),
+        llm_provider: r.try_get::<Option<serde_json::Value>, _>("llm_provider").ok().flatten().and_then(|v| serde_json::from_value(v).ok()),
+        reranker: r.try_get::<Option<serde_json::Value>, _>("reranker").ok().flatten().and_then(|v| serde_json::from_value(v).ok()),
+        user_id: r.get("user_id"),
         metadata: serde_json::from_value(r.get("metadata")).unwrap_or_default(),
         tags: serde_json::from_value(r.get("tags")).ok(),
         created_at: r.get("created_at"),
         updated_at: r.get("updated_at"),
-    }).collect())
+    }
+}
+
+/// List personas, optionally filtered by type. With `user_id`, only that
+/// user's personas plus shared ones are returned; without, all personas.
+pub async fn list_personas(pool: &Pool<Postgres>, persona_type: Option<&str>, user_id: Option<&str>) -> Result<Vec<Persona>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, name, persona_type, description, avatar, bubble_color, system_prompt, global_memory_enabled, current_mood, voice, llm_provider, reranker, user_id, metadata, tags, created_at, updated_at FROM personas
+        WHERE ($1::TEXT IS NULL OR persona_type = $1)
+          AND ($2::TEXT IS NULL OR user_id = $2 OR user_id IS NULL)
+        ORDER BY name
+        "#
+    )
+    .bind(persona_type)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(rows.iter().map(persona_from_row).collect())
 }
 
-pub async fn update_persona(pool: &Pool<Postgres>, id: &str, req: &UpdatePersonaRequest) -> Result<()> {
+/// Update a persona. With `owner`, only a persona owned by that user is
+/// updated; without, any persona (system updates such as mood).
+/// Returns whether a row was updated.
+pub async fn update_persona(pool: &Pool<Postgres>, id: &str, owner: Option<&str>, req: &UpdatePersonaRequest) -> Result<bool> {
     // Build dynamic update query
     let mut updates = vec!["updated_at = NOW()".to_string()];
     let mut param_count = 1;
@@ -427,10 +118,13 @@ pub async fn update_persona(pool: &Pool<Postgres>, id: &str, req: &UpdatePersona
     if req.global_memory_enabled.is_some() { updates.push(format!("global_memory_enabled = ${}", { param_count += 1; param_count })); }
     if req.current_mood.is_some() { updates.push(format!("current_mood = ${}", { param_count += 1; param_count })); }
     if req.voice.is_some() { updates.push(format!("voice = ${}", { param_count += 1; param_count })); }
+    if req.llm_provider.is_some() { updates.push(format!("llm_provider = ${}", { param_count += 1; param_count })); }
+    if req.reranker.is_some() { updates.push(format!("reranker = ${}", { param_count += 1; param_count })); }
     if req.metadata.is_some() { updates.push(format!("metadata = ${}", { param_count += 1; param_count })); }
     if req.tags.is_some() { updates.push(format!("tags = ${}", { param_count += 1; param_count })); }
     
-    let query_str = format!("UPDATE personas SET {} WHERE id = $1", updates.join(", "));
+    let owner_clause = if owner.is_some() { format!(" AND user_id = ${}", param_count + 1) } else { String::new() };
+    let query_str = format!("UPDATE personas SET {} WHERE id = $1{}", updates.join(", "), owner_clause);
     let mut query = sqlx::query(&query_str).bind(id);
     
     if let Some(ref name) = req.name { query = query.bind(name); }
@@ -441,19 +135,24 @@ pub async fn update_persona(pool: &Pool<Postgres>, id: &str, req: &UpdatePersona
     if let Some(global_memory_enabled) = req.global_memory_enabled { query = query.bind(global_memory_enabled); }
     if let Some(ref current_mood) = req.current_mood { query = query.bind(current_mood); }
     if let Some(ref voice) = req.voice { query = query.bind(serde_json::to_value(voice)?); }
+    if let Some(ref llm_provider) = req.llm_provider { query = query.bind(serde_json::to_value(llm_provider)?); }
+    if let Some(ref reranker) = req.reranker { query = query.bind(serde_json::to_value(reranker)?); }
     if let Some(ref metadata) = req.metadata { query = query.bind(serde_json::to_value(metadata)?); }
     if let Some(ref tags) = req.tags { query = query.bind(serde_json::to_value(tags)?); }
+    if let Some(owner) = owner { query = query.bind(owner); }
     
-    query.execute(pool).await?;
-    Ok(())
+    let result = query.execute(pool).await?;
+    Ok(result.rows_affected() > 0)
 }
 
-pub async fn delete_persona(pool: &Pool<Postgres>, id: &str) -> Result<()> {
-    sqlx::query("DELETE FROM personas WHERE id = $1")
+/// Delete a persona owned by `user_id`. Returns whether it existed.
+pub async fn delete_persona(pool: &Pool<Postgres>, id: &str, user_id: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM personas WHERE id = $1 AND user_id = $2")
         .bind(id)
+        .bind(user_id)
         .execute(pool)
         .await?;
-    Ok(())
+    Ok(result.rows_affected() > 0)
 }
 
 // ============================================================
@@ -467,8 +166,8 @@ pub async fn create_chat(pool: &Pool<Postgres>, chat: &Chat) -> Result<()> {
     // Insert chat
     sqlx::query(
         r#"
-        INSERT INTO chats (id, title, current_branch_id, group_id, tags, created_at, updated_at)
-        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        INSERT INTO chats (id, title, current_branch_id, group_id, tags, user_id, persona_cast, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8)
         "#,
     )
     .bind(&chat.id)
@@ -476,6 +175,8 @@ pub async fn create_chat(pool: &Pool<Postgres>, chat: &Chat) -> Result<()> {
     .bind(&chat.current_branch_id)
     .bind(&chat.group_id)
     .bind(serde_json::to_value(&chat.tags)?)
+    .bind(&chat.user_id)
+    .bind(chat.cast.as_ref().map(serde_json::to_value).transpose()?)
     .bind(chat.created_at)
     .execute(&mut *tx)
     .await?;
@@ -523,31 +224,44 @@ pub async fn create_chat(pool: &Pool<Postgres>, chat: &Chat) -> Result<()> {
     Ok(())
 }
 
-pub async fn get_chat(pool: &Pool<Postgres>, id: &str) -> Result<Option<Chat>> {
+/// Get a chat owned by `user_id`
+pub async fn get_chat(pool: &Pool<Postgres>, id: &str, user_id: &str) -> Result<Option<Chat>> {
     let chat_row = sqlx::query(
-        "SELECT id, title, current_branch_id, group_id, tags, created_at FROM chats WHERE id = $1"
+        "SELECT id, title, current_branch_id, group_id, tags, user_id, persona_cast, created_at FROM chats WHERE id = $1 AND user_id = $2"
     )
     .bind(id)
+    .bind(user_id)
     .fetch_optional(pool)
     .await?;
     
     match chat_row {
-        Some(c) => {
-            let branches = get_chat_branches(pool, id).await?;
-            Ok(Some(Chat {
-                id: c.get("id"),
-                title: c.get("title"),
-                current_branch_id: c.get("current_branch_id"),
-                group_id: c.get("group_id"),
-                tags: serde_json::from_value(c.get("tags")).ok(),
-                created_at: c.get("created_at"),
-                branches,
-            }))
-        }
+        Some(c) => Ok(Some(load_chat(pool, &c).await?)),
         None => Ok(None),
     }
 }
 
+/// Build a chat from its row, loading every branch and message
+async fn load_chat(pool: &Pool<Postgres>, c: &sqlx::postgres::PgRow) -> Result<Chat> {
+    let chat_id: String = c.get("id");
+    let branches = get_chat_branches(pool, &chat_id).await?;
+    Ok(Chat {
+        id: chat_id,
+        title: c.get("title"),
+        current_branch_id: c.get("current_branch_id"),
+        group_id: c.get("group_id"),
+        tags: serde_json::from_value(c.get("tags")).ok(),
+        user_id: c.get("user_id"),
+        cast: cast_from_row(c),
+        created_at: c.get("created_at"),
+        branches,
+    })
+}
+
+fn cast_from_row(row: &sqlx::postgres::PgRow) -> Option<ChatCast> {
+    row.get::<Option<serde_json::Value>, _>("persona_cast")
+        .and_then(|v| serde_json::from_value(v).ok())
+}
+
 async fn get_chat_branches(pool: &Pool<Postgres>, chat_id: &str) -> Result<Vec<ChatBranch>> {
     let branch_rows = sqlx::query(
         "SELECT id, name, parent_branch_id, fork_point_message_id, created_at FROM chat_branches WHERE chat_id = $1 ORDER BY created_at"
@@ -574,15 +288,20 @@ async fn get_chat_branches(pool: &Pool<Postgres>, chat_id: &str) -> Result<Vec<C
     Ok(branches)
 }
 
-async fn get_branch_messages(pool: &Pool<Postgres>, branch_id: &str) -> Result<Vec<ChatMessage>> {
+/// Messages of a branch, oldest first
+pub async fn get_branch_messages(pool: &Pool<Postgres>, branch_id: &str) -> Result<Vec<ChatMessage>> {
     let rows = sqlx::query(
-        "SELECT id, role, content, user_persona_id, ai_persona_id, model, mood, created_at FROM chat_messages WHERE branch_id = $1 ORDER BY created_at"
+        "SELECT id, role, content, user_persona_id, ai_persona_id, model, mood, interrupted, created_at FROM chat_messages WHERE branch_id = $1 ORDER BY created_at"
     )
     .bind(branch_id)
     .fetch_all(pool)
     .await?;
     
-    Ok(rows.iter().map(|r| ChatMessage {
+    Ok(rows.iter().map(message_from_row).collect())
+}
+
+fn message_from_row(r: &sqlx::postgres::PgRow) -> ChatMessage {
+    ChatMessage {
         id: r.get("id"),
         role: r.get("role"),
         content: r.get("content"),
@@ -590,37 +309,110 @@ async fn get_branch_messages(pool: &Pool<Postgres>, branch_id: &str) -> Result<V
         ai_persona: r.get("ai_persona_id"),
         model: r.get("model"),
         mood: r.get("mood"),
+        interrupted: r.get("interrupted"),
         timestamp: Some(r.get("created_at")),
-    }).collect())
+    }
+}
+
+/// One page of the chat list of `user_id`, without messages. Continues after
+/// `after` (already decoded against `query`'s sort).
+pub async fn list_chat_summaries(
+    pool: &Pool<Postgres>,
+    user_id: &str,
+    query: &ChatListQuery,
+    after: Option<&Cursor>,
+    limit: usize,
+) -> Result<CursorPage<ChatListItem>> {
+    let column = query.sort.column();
+    let direction = query.order.keyword();
+    let after_clause = match after {
+        Some(_) if query.order == SortOrder::Asc => format!("AND (c.{}, c.id) > ($3, $4)", column),
+        Some(_) => format!("AND (c.{}, c.id) < ($3, $4)", column),
+        None => String::new(),
+    };
+    let sql = format!(
+        r#"
+        SELECT c.id, c.title, c.current_branch_id, c.group_id, c.tags, c.persona_cast, c.created_at, c.updated_at,
+               (SELECT COUNT(*) FROM chat_messages m JOIN chat_branches b ON b.id = m.branch_id WHERE b.chat_id = c.id) AS message_count,
+               last.role AS last_role, last.preview AS last_preview, last.created_at AS last_at
+        FROM chats c
+        LEFT JOIN LATERAL (
+            SELECT role, LEFT(content, 200) AS preview, created_at FROM chat_messages
+            WHERE branch_id = c.current_branch_id
+            ORDER BY created_at DESC, id DESC LIMIT 1
+        ) last ON TRUE
+        WHERE c.user_id = $1 {after_clause}
+        ORDER BY c.{column} {direction}, c.id {direction}
+        LIMIT $2
+        "#
+    );
+
+    // One extra row tells whether another page follows
+    let mut q = sqlx::query(&sql).bind(user_id).bind(limit as i64 + 1);
+    if let Some(cursor) = after {
+        q = match query.sort {
+            ChatSort::Title => q.bind(cursor.key.clone()),
+            _ => q.bind(cursor.key.parse::<chrono::DateTime<Utc>>()?),
+        };
+        q = q.bind(cursor.id.clone());
+    }
+    let rows = q.fetch_all(pool).await?;
+
+    let mut items: Vec<ChatListItem> = rows.iter().map(|c| ChatListItem {
+        id: c.get("id"),
+        title: c.get("title"),
+        current_branch_id: c.get("current_branch_id"),
+        group_id: c.get("group_id"),
+        tags: serde_json::from_value(c.get("tags")).ok(),
+        cast: cast_from_row(c),
+        last_message: c.get::<Option<String>, _>("last_role").map(|role| MessagePreview {
+            role,
+            content: c.get("last_preview"),
+            timestamp: c.get("last_at"),
+        }),
+        message_count: c.get("message_count"),
+        created_at: c.get("created_at"),
+        updated_at: c.get("updated_at"),
+    }).collect();
+
+    let next_cursor = if items.len() > limit {
+        items.truncate(limit);
+        items.last().map(|last| Cursor {
+            sort: query.sort_label(),
+            key: match query.sort {
+                ChatSort::UpdatedAt => last.updated_at.to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+                ChatSort::CreatedAt => last.created_at.to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+                ChatSort::Title => last.title.clone(),
+            },
+            id: last.id.clone(),
+        }.encode())
+    } else {
+        None
+    };
+    Ok(CursorPage { items, next_cursor })
 }
 
-pub async fn list_chats(pool: &Pool<Postgres>) -> Result<Vec<Chat>> {
+/// Up to `limit` chats of any owner with IDs after `after`, fully loaded
+/// (index sync walks the table in these batches)
+pub async fn list_chats_batch(pool: &Pool<Postgres>, after: Option<&str>, limit: i64) -> Result<Vec<Chat>> {
     let chat_rows = sqlx::query(
-        "SELECT id, title, current_branch_id, group_id, tags, created_at FROM chats ORDER BY created_at DESC"
+        "SELECT id, title, current_branch_id, group_id, tags, user_id, persona_cast, created_at FROM chats WHERE ($1::TEXT IS NULL OR id > $1) ORDER BY id LIMIT $2"
     )
+    .bind(after)
+    .bind(limit)
     .fetch_all(pool)
     .await?;
     
     let mut chats = Vec::new();
-    for c in chat_rows {
-        let chat_id: String = c.get("id");
-        let branches = get_chat_branches(pool, &chat_id).await?;
-        
-        chats.push(Chat {
-            id: chat_id,
-            title: c.get("title"),
-            current_branch_id: c.get("current_branch_id"),
-            group_id: c.get("group_id"),
-            tags: serde_json::from_value(c.get("tags")).ok(),
-            created_at: c.get("created_at"),
-            branches,
-        });
+    for c in &chat_rows {
+        chats.push(load_chat(pool, c).await?);
     }
     
     Ok(chats)
 }
 
-pub async fn update_chat(pool: &Pool<Postgres>, id: &str, req: &UpdateChatRequest) -> Result<()> {
+/// Update a chat owned by `user_id`. Returns whether it existed.
+pub async fn update_chat(pool: &Pool<Postgres>, id: &str, user_id: &str, req: &UpdateChatRequest) -> Result<bool> {
     let mut updates = vec!["updated_at = NOW()".to_string()];
     let mut param_count = 1;
     
@@ -628,68 +420,110 @@ pub async fn update_chat(pool: &Pool<Postgres>, id: &str, req: &UpdateChatReques
     if req.group_id.is_some() { updates.push(format!("group_id = ${}", { param_count += 1; param_count })); }
     if req.tags.is_some() { updates.push(format!("tags = ${}", { param_count += 1; param_count })); }
     if req.current_branch_id.is_some() { updates.push(format!("current_branch_id = ${}", { param_count += 1; param_count })); }
+    if req.cast.is_some() { updates.push(format!("persona_cast = ${}", { param_count += 1; param_count })); }
     
-    let query_str = format!("UPDATE chats SET {} WHERE id = $1", updates.join(", "));
+    let query_str = format!("UPDATE chats SET {} WHERE id = $1 AND user_id = ${}", updates.join(", "), param_count + 1);
     let mut query = sqlx::query(&query_str).bind(id);
     
     if let Some(ref title) = req.title { query = query.bind(title); }
     if let Some(ref group_id) = req.group_id { query = query.bind(group_id); }
     if let Some(ref tags) = req.tags { query = query.bind(serde_json::to_value(tags)?); }
     if let Some(ref current_branch_id) = req.current_branch_id { query = query.bind(current_branch_id); }
+    // An empty cast turns a group chat back into a single-persona chat
+    if let Some(ref cast) = req.cast {
+        query = query.bind(Some(cast).filter(|c| !c.persona_ids.is_empty()).map(serde_json::to_value).transpose()?);
+    }
+    query = query.bind(user_id);
     
-    query.execute(pool).await?;
-    Ok(())
+    let result = query.execute(pool).await?;
+    Ok(result.rows_affected() > 0)
 }
 
-pub async fn delete_chat(pool: &Pool<Postgres>, id: &str) -> Result<()> {
-    sqlx::query("DELETE FROM chats WHERE id = $1")
+/// Delete a chat owned by `user_id` and, in the same transaction, queue a
+/// deletion job for each of `stores`. Returns whether it existed.
+pub async fn delete_chat(pool: &Pool<Postgres>, id: &str, user_id: &str, stores: &[&str]) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query("DELETE FROM chats WHERE id = $1 AND user_id = $2")
         .bind(id)
-        .execute(pool)
+        .bind(user_id)
+        .execute(&mut *tx)
         .await?;
-    Ok(())
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+    for store in stores {
+        sqlx::query("INSERT INTO deletion_jobs (chat_id, store) VALUES ($1, $2) ON CONFLICT (chat_id, store) DO NOTHING")
+            .bind(id)
+            .bind(store)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Whether `user_id` still has the chat `id`
+pub async fn chat_exists(pool: &Pool<Postgres>, id: &str, user_id: &str) -> Result<bool> {
+    let row = sqlx::query("SELECT 1 FROM chats WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.is_some())
 }
 
 /// Ensure chat and branch exist in the database (creates if missing)
-/// This handles the case where frontend creates chats locally
+/// This handles the case where frontend creates chats locally.
+/// Returns false (and touches nothing) if the chat or branch already
+/// exists but doesn't belong to `user_id`.
 pub async fn ensure_chat_and_branch(
     pool: &Pool<Postgres>, 
     chat_id: &str, 
     branch_id: &str,
     chat_title: Option<&str>,
-) -> Result<()> {
+    user_id: &str,
+) -> Result<bool> {
     // Check if chat exists
-    let chat_exists = sqlx::query("SELECT 1 FROM chats WHERE id = $1")
+    let chat_owner = sqlx::query("SELECT user_id FROM chats WHERE id = $1")
         .bind(chat_id)
         .fetch_optional(pool)
         .await?
-        .is_some();
+        .map(|r| r.get::<Option<String>, _>("user_id"));
     
-    if !chat_exists {
-        // Create the chat
-        sqlx::query(
-            r#"
-            INSERT INTO chats (id, title, current_branch_id, created_at, updated_at)
-            VALUES ($1, $2, $3, NOW(), NOW())
-            ON CONFLICT (id) DO NOTHING
-            "#,
-        )
-        .bind(chat_id)
-        .bind(chat_title.unwrap_or("New Chat"))
-        .bind(branch_id)
-        .execute(pool)
-        .await?;
-        
-        tracing::info!("Created chat {} in database", chat_id);
+    match chat_owner {
+        Some(owner) if owner.as_deref() != Some(user_id) => return Ok(false),
+        Some(_) => {}
+        None => {
+            // Create the chat
+            sqlx::query(
+                r#"
+                INSERT INTO chats (id, title, current_branch_id, user_id, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, NOW(), NOW())
+                "#,
+            )
+            .bind(chat_id)
+            .bind(chat_title.unwrap_or("New Chat"))
+            .bind(branch_id)
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+            
+            tracing::info!("Created chat {} in database", chat_id);
+        }
     }
     
     // Check if branch exists
-    let branch_exists = sqlx::query("SELECT 1 FROM chat_branches WHERE id = $1")
+    let branch_chat = sqlx::query("SELECT chat_id FROM chat_branches WHERE id = $1")
         .bind(branch_id)
         .fetch_optional(pool)
         .await?
-        .is_some();
+        .map(|r| r.get::<String, _>("chat_id"));
     
-    if !branch_exists {
+    if let Some(ref owner_chat) = branch_chat {
+        if owner_chat != chat_id {
+            return Ok(false);
+        }
+    } else {
         // Create the branch
         sqlx::query(
             r#"
@@ -705,26 +539,398 @@ pub async fn ensure_chat_and_branch(
         
         tracing::info!("Created branch {} in database", branch_id);
     }
-    
-    Ok(())
+    
+    Ok(true)
+}
+
+pub async fn add_message_to_branch(pool: &Pool<Postgres>, msg: &ChatMessage, branch_id: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO chat_messages (id, branch_id, role, content, user_persona_id, ai_persona_id, model, mood, interrupted, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        "#,
+    )
+    .bind(&msg.id)
+    .bind(branch_id)
+    .bind(&msg.role)
+    .bind(&msg.content)
+    .bind(&msg.user_persona)
+    .bind(&msg.ai_persona)
+    .bind(&msg.model)
+    .bind(&msg.mood)
+    .bind(msg.interrupted)
+    .bind(msg.timestamp.unwrap_or_else(Utc::now))
+    .execute(pool)
+    .await?;
+
+    // Keep the chat list's "recently active" order current
+    sqlx::query("UPDATE chats SET updated_at = NOW() WHERE id = (SELECT chat_id FROM chat_branches WHERE id = $1)")
+        .bind(branch_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// ============================================================
+// Branches & Messages
+// ============================================================
+
+/// Deepest ancestry followed when assembling a branch's history
+const MAX_BRANCH_DEPTH: usize = 64;
+
+/// A branch's full history, oldest first: each ancestor's messages up to the
+/// fork point its child inherits through, then the branch's own messages
+pub async fn get_branch_history(pool: &Pool<Postgres>, branch_id: &str) -> Result<Vec<ChatMessage>> {
+    let mut segments: Vec<Vec<ChatMessage>> = Vec::new();
+    for (id, through) in branch_lineage(pool, branch_id).await? {
+        let messages = get_branch_messages(pool, &id).await?;
+        segments.push(match through {
+            None => messages,
+            Some(ref fork_point) => inherited(messages, Some(fork_point)),
+        });
+    }
+    Ok(segments.into_iter().rev().flatten().collect())
+}
+
+/// The branches whose messages make up a branch's history, newest first,
+/// each with the fork point its messages are inherited through (`None` for
+/// the branch itself, which keeps everything)
+async fn branch_lineage(pool: &Pool<Postgres>, branch_id: &str) -> Result<Vec<(String, Option<String>)>> {
+    let mut lineage: Vec<(String, Option<String>)> = Vec::new();
+    let mut current = Some(branch_id.to_string());
+    let mut through: Option<String> = None;
+    while let Some(id) = current.take() {
+        if lineage.len() >= MAX_BRANCH_DEPTH {
+            tracing::warn!("Branch {} has more than {} ancestors, truncating history", branch_id, MAX_BRANCH_DEPTH);
+            break;
+        }
+        let Some(row) = sqlx::query("SELECT parent_branch_id, fork_point_message_id FROM chat_branches WHERE id = $1")
+            .bind(&id)
+            .fetch_optional(pool)
+            .await?
+        else {
+            break;
+        };
+        lineage.push((id, through.take()));
+        // A branch without a fork point starts fresh
+        let Some(fork_point) = row.get::<Option<String>, _>("fork_point_message_id") else {
+            break;
+        };
+        through = Some(fork_point);
+        current = row.get("parent_branch_id");
+    }
+    Ok(lineage)
+}
+
+/// One page of a branch's history (inherited messages included), oldest
+/// first: the `limit` messages just before `before`, or the newest ones.
+/// `next_cursor` is the message ID to pass as `before` for the page above.
+pub async fn get_branch_history_page(
+    pool: &Pool<Postgres>,
+    branch_id: &str,
+    before: Option<&ChatMessage>,
+    limit: usize,
+) -> Result<CursorPage<ChatMessage>> {
+    let before_at = before.and_then(|m| m.timestamp);
+    let before_id = before.map(|m| m.id.as_str());
+    let mut newest_first: Vec<ChatMessage> = Vec::new();
+    for (id, through) in branch_lineage(pool, branch_id).await? {
+        let through_at = match through {
+            Some(ref fork_point) => {
+                let row = sqlx::query("SELECT created_at FROM chat_messages WHERE id = $1")
+                    .bind(fork_point)
+                    .fetch_optional(pool)
+                    .await?;
+                // A vanished fork point inherits nothing from this branch
+                let Some(row) = row else { continue };
+                Some(row.get::<chrono::DateTime<Utc>, _>("created_at"))
+            }
+            None => None,
+        };
+        let rows = sqlx::query(
+            r#"
+            SELECT id, role, content, user_persona_id, ai_persona_id, model, mood, interrupted, created_at FROM chat_messages
+            WHERE branch_id = $1
+              AND ($2::TIMESTAMPTZ IS NULL OR (created_at, id) < ($2, $3::TEXT))
+              AND ($4::TIMESTAMPTZ IS NULL OR (created_at, id) <= ($4, $5::TEXT))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $6
+            "#,
+        )
+        .bind(&id)
+        .bind(before_at)
+        .bind(before_id)
+        .bind(through_at)
+        .bind(through.as_deref())
+        .bind((limit + 1 - newest_first.len()) as i64)
+        .fetch_all(pool)
+        .await?;
+        newest_first.extend(rows.iter().map(message_from_row));
+        // One extra message tells whether another page follows
+        if newest_first.len() > limit {
+            break;
+        }
+    }
+
+    let next_cursor = if newest_first.len() > limit {
+        newest_first.truncate(limit);
+        newest_first.last().map(|m| m.id.clone())
+    } else {
+        None
+    };
+    newest_first.reverse();
+    Ok(CursorPage { items: newest_first, next_cursor })
+}
+
+/// Whether `branch_id` belongs to a chat owned by `user_id`
+pub async fn branch_in_chat(pool: &Pool<Postgres>, chat_id: &str, branch_id: &str, user_id: &str) -> Result<bool> {
+    let row = sqlx::query(
+        "SELECT 1 FROM chat_branches b JOIN chats c ON c.id = b.chat_id WHERE b.id = $1 AND c.id = $2 AND c.user_id = $3"
+    )
+    .bind(branch_id)
+    .bind(chat_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.is_some())
+}
+
+/// The part of a parent's messages a child inherits: up to and including the
+/// fork point, or nothing without one
+fn inherited(mut messages: Vec<ChatMessage>, fork_point: Option<&str>) -> Vec<ChatMessage> {
+    match fork_point.and_then(|id| messages.iter().position(|m| m.id == id)) {
+        Some(i) => {
+            messages.truncate(i + 1);
+            messages
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Create an empty branch of `chat_id`
+pub async fn create_branch(pool: &Pool<Postgres>, chat_id: &str, branch: &ChatBranch) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO chat_branches (id, chat_id, name, parent_branch_id, fork_point_message_id, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(&branch.id)
+    .bind(chat_id)
+    .bind(&branch.name)
+    .bind(&branch.parent_branch_id)
+    .bind(&branch.fork_point_message_id)
+    .bind(branch.created_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn count_branches(pool: &Pool<Postgres>, chat_id: &str) -> Result<i64> {
+    let row = sqlx::query("SELECT COUNT(*) AS n FROM chat_branches WHERE chat_id = $1")
+        .bind(chat_id)
+        .fetch_one(pool)
+        .await?;
+    Ok(row.get("n"))
+}
+
+/// A message of a chat owned by `user_id`, with the branch it is stored on
+pub async fn find_message(pool: &Pool<Postgres>, chat_id: &str, message_id: &str, user_id: &str) -> Result<Option<(ChatMessage, String)>> {
+    let row = sqlx::query(
+        r#"
+        SELECT m.id, m.branch_id, m.role, m.content, m.user_persona_id, m.ai_persona_id, m.model, m.mood, m.interrupted, m.created_at
+        FROM chat_messages m
+        JOIN chat_branches b ON b.id = m.branch_id
+        JOIN chats c ON c.id = b.chat_id
+        WHERE m.id = $1 AND c.id = $2 AND c.user_id = $3
+        "#,
+    )
+    .bind(message_id)
+    .bind(chat_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|r| (message_from_row(&r), r.get("branch_id"))))
+}
+
+pub async fn update_message_content(pool: &Pool<Postgres>, message_id: &str, content: &str) -> Result<bool> {
+    let result = sqlx::query("UPDATE chat_messages SET content = $2 WHERE id = $1")
+        .bind(message_id)
+        .bind(content)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete one message. Branches forked at it move their fork point to the
+/// message before it, or up to the message's own fork point when it was the
+/// first on its branch, so they keep the rest of their inherited history.
+pub async fn delete_message(pool: &Pool<Postgres>, message_id: &str, branch_id: &str) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+
+    let previous: Option<String> = sqlx::query(
+        r#"
+        SELECT id FROM chat_messages
+        WHERE branch_id = $1 AND created_at < (SELECT created_at FROM chat_messages WHERE id = $2)
+        ORDER BY created_at DESC LIMIT 1
+        "#,
+    )
+    .bind(branch_id)
+    .bind(message_id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .map(|r| r.get("id"));
+
+    match previous {
+        Some(previous) => {
+            sqlx::query("UPDATE chat_branches SET fork_point_message_id = $2 WHERE fork_point_message_id = $1")
+                .bind(message_id)
+                .bind(previous)
+                .execute(&mut *tx)
+                .await?;
+        }
+        None => {
+            sqlx::query(
+                r#"
+                UPDATE chat_branches child
+                SET parent_branch_id = parent.parent_branch_id, fork_point_message_id = parent.fork_point_message_id
+                FROM chat_branches parent
+                WHERE parent.id = $2 AND child.fork_point_message_id = $1
+                "#,
+            )
+            .bind(message_id)
+            .bind(branch_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    let result = sqlx::query("DELETE FROM chat_messages WHERE id = $1")
+        .bind(message_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(result.rows_affected() > 0)
 }
 
-pub async fn add_message_to_branch(pool: &Pool<Postgres>, msg: &ChatMessage, branch_id: &str) -> Result<()> {
+/// Messages of the chats `persona_id` took part in from `since` up to
+/// `until`, newest first: the users' messages and that persona's own
+/// replies (not other cast members'). Only chats of `user_id` when given.
+pub async fn persona_messages(
+    pool: &Pool<Postgres>,
+    persona_id: &str,
+    user_id: Option<&str>,
+    (since, until): (DateTime<Utc>, DateTime<Utc>),
+    limit: i64,
+) -> Result<Vec<PersonaMessage>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT m.id, m.role, m.content, m.created_at, b.chat_id, c.user_id
+        FROM chat_messages m
+        JOIN chat_branches b ON b.id = m.branch_id
+        JOIN chats c ON c.id = b.chat_id
+        WHERE m.branch_id IN (
+              SELECT branch_id FROM chat_messages
+              WHERE ai_persona_id = $1 AND created_at >= $3 AND created_at < $4
+          )
+          AND (m.ai_persona_id = $1 OR m.role = 'user')
+          AND m.created_at >= $3 AND m.created_at < $4
+          AND ($2::TEXT IS NULL OR c.user_id = $2)
+        ORDER BY m.created_at DESC
+        LIMIT $5
+        "#,
+    )
+    .bind(persona_id)
+    .bind(user_id)
+    .bind(since)
+    .bind(until)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(|r| PersonaMessage {
+        id: r.get("id"),
+        chat_id: r.get("chat_id"),
+        user_id: r.get("user_id"),
+        role: r.get("role"),
+        content: r.get("content"),
+        created_at: r.get("created_at"),
+    }).collect())
+}
+
+// ============================================================
+// Chat Summaries
+// ============================================================
+
+pub async fn get_chat_summary(pool: &Pool<Postgres>, chat_id: &str) -> Result<Option<ChatSummary>> {
+    let row = sqlx::query(
+        "SELECT chat_id, summary, active_goal, topics, turn_count, summarized_through, updated_at FROM chat_summaries WHERE chat_id = $1"
+    )
+    .bind(chat_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|r| ChatSummary {
+        chat_id: r.get("chat_id"),
+        summary: r.get("summary"),
+        active_goal: r.get("active_goal"),
+        topics: r.get::<Option<serde_json::Value>, _>("topics")
+            .and_then(|t| serde_json::from_value(t).ok())
+            .unwrap_or_default(),
+        turn_count: r.get::<i32, _>("turn_count").max(0) as u32,
+        summarized_through: r.get("summarized_through"),
+        updated_at: r.get("updated_at"),
+    }))
+}
+
+/// Summarizer topics of `user_id`'s chats with `persona_id`, one list per
+/// chat, most recently summarised first
+pub async fn persona_chat_topics(pool: &Pool<Postgres>, persona_id: &str, user_id: &str, limit: i64) -> Result<Vec<Vec<String>>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT s.topics FROM chat_summaries s
+        JOIN chats c ON c.id = s.chat_id
+        WHERE c.user_id = $2
+          AND s.chat_id IN (
+              SELECT b.chat_id FROM chat_branches b
+              JOIN chat_messages m ON m.branch_id = b.id
+              WHERE m.ai_persona_id = $1
+          )
+        ORDER BY s.updated_at DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(persona_id)
+    .bind(user_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(|r| {
+        r.get::<Option<serde_json::Value>, _>("topics")
+            .and_then(|t| serde_json::from_value(t).ok())
+            .unwrap_or_default()
+    }).collect())
+}
+
+pub async fn upsert_chat_summary(pool: &Pool<Postgres>, summary: &ChatSummary) -> Result<()> {
     sqlx::query(
         r#"
-        INSERT INTO chat_messages (id, branch_id, role, content, user_persona_id, ai_persona_id, model, mood, created_at)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        INSERT INTO chat_summaries (chat_id, summary, active_goal, topics, turn_count, summarized_through, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT(chat_id) DO UPDATE SET
+            summary = $2, active_goal = $3, topics = $4, turn_count = $5,
+            summarized_through = $6, updated_at = $7
         "#,
     )
-    .bind(&msg.id)
-    .bind(branch_id)
-    .bind(&msg.role)
-    .bind(&msg.content)
-    .bind(&msg.user_persona)
-    .bind(&msg.ai_persona)
-    .bind(&msg.model)
-    .bind(&msg.mood)
-    .bind(msg.timestamp.unwrap_or_else(Utc::now))
+    .bind(&summary.chat_id)
+    .bind(&summary.summary)
+    .bind(&summary.active_goal)
+    .bind(serde_json::to_value(&summary.topics)?)
+    .bind(summary.turn_count as i32)
+    .bind(&summary.summarized_through)
+    .bind(summary.updated_at)
     .execute(pool)
     .await?;
     Ok(())
@@ -850,13 +1056,190 @@ pub async fn delete_tag(pool: &Pool<Postgres>, id: &str) -> Result<()> {
     Ok(())
 }
 
+// ============================================================
+// Chat Deletion Queue
+// ============================================================
+
+/// Deletion jobs due for another attempt, oldest first
+pub async fn due_deletion_jobs(pool: &Pool<Postgres>, limit: i64) -> Result<Vec<DeletionJob>> {
+    let rows = sqlx::query(
+        "SELECT chat_id, store, attempts, last_error FROM deletion_jobs WHERE next_attempt_at <= NOW() ORDER BY next_attempt_at LIMIT $1"
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(|r| DeletionJob {
+        chat_id: r.get("chat_id"),
+        store: r.get("store"),
+        attempts: r.get("attempts"),
+        last_error: r.get("last_error"),
+    }).collect())
+}
+
+/// Drop a deletion job whose store is clean
+pub async fn finish_deletion_job(pool: &Pool<Postgres>, chat_id: &str, store: &str) -> Result<()> {
+    sqlx::query("DELETE FROM deletion_jobs WHERE chat_id = $1 AND store = $2")
+        .bind(chat_id)
+        .bind(store)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Record a failed attempt and when to try again
+pub async fn fail_deletion_job(
+    pool: &Pool<Postgres>,
+    chat_id: &str,
+    store: &str,
+    error: &str,
+    next_attempt_at: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query(
+        "UPDATE deletion_jobs SET attempts = attempts + 1, last_error = $3, next_attempt_at = $4 WHERE chat_id = $1 AND store = $2"
+    )
+    .bind(chat_id)
+    .bind(store)
+    .bind(error)
+    .bind(next_attempt_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Remember an image generated from a chat's reply
+pub async fn record_chat_image(pool: &Pool<Postgres>, chat_id: &str, filename: &str) -> Result<()> {
+    sqlx::query("INSERT INTO chat_images (chat_id, filename) VALUES ($1, $2) ON CONFLICT (chat_id, filename) DO NOTHING")
+        .bind(chat_id)
+        .bind(filename)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Filenames of the images generated from a chat
+pub async fn chat_images(pool: &Pool<Postgres>, chat_id: &str) -> Result<Vec<String>> {
+    let rows = sqlx::query("SELECT filename FROM chat_images WHERE chat_id = $1")
+        .bind(chat_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| r.get("filename")).collect())
+}
+
+/// Forget which images a chat generated
+pub async fn delete_chat_images(pool: &Pool<Postgres>, chat_id: &str) -> Result<()> {
+    sqlx::query("DELETE FROM chat_images WHERE chat_id = $1")
+        .bind(chat_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// ============================================================
+// Image ownership
+// ============================================================
+
+/// Kind of a generated image in the `images` table
+pub const IMAGE_GENERATED: &str = "generated";
+/// Kind of an uploaded img2img reference in the `images` table
+pub const IMAGE_REFERENCE: &str = "reference";
+
+/// Record `user_id` as the owner of the `kind` image `filename`, refreshing
+/// the details when they already own it. Returns false when the name
+/// belongs to another user.
+pub async fn claim_image(
+    pool: &Pool<Postgres>,
+    kind: &str,
+    filename: &str,
+    user_id: &str,
+    prompt: Option<&str>,
+    persona_id: Option<&str>,
+    persona_name: Option<&str>,
+) -> Result<bool> {
+    sqlx::query(
+        r#"
+        INSERT INTO images (kind, filename, user_id, prompt, persona_id, persona_name)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (kind, filename) DO UPDATE SET
+            prompt = EXCLUDED.prompt,
+            persona_id = EXCLUDED.persona_id,
+            persona_name = EXCLUDED.persona_name,
+            created_at = NOW()
+        WHERE images.user_id = EXCLUDED.user_id
+        "#,
+    )
+    .bind(kind)
+    .bind(filename)
+    .bind(user_id)
+    .bind(prompt)
+    .bind(persona_id)
+    .bind(persona_name)
+    .execute(pool)
+    .await?;
+    Ok(image_owner(pool, kind, filename).await?.as_deref() == Some(user_id))
+}
+
+/// Owner of the `kind` image `filename`, if it has one
+pub async fn image_owner(pool: &Pool<Postgres>, kind: &str, filename: &str) -> Result<Option<String>> {
+    let owner: Option<String> = sqlx::query_scalar("SELECT user_id FROM images WHERE kind = $1 AND filename = $2")
+        .bind(kind)
+        .bind(filename)
+        .fetch_optional(pool)
+        .await?;
+    Ok(owner)
+}
+
+/// Generated images owned by `user_id`, newest first
+pub async fn list_images(pool: &Pool<Postgres>, user_id: &str) -> Result<Vec<GeneratedImage>> {
+    let rows = sqlx::query(
+        "SELECT filename, prompt, persona_id, persona_name, created_at FROM images WHERE user_id = $1 AND kind = $2 ORDER BY created_at DESC",
+    )
+    .bind(user_id)
+    .bind(IMAGE_GENERATED)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|r| {
+            let filename: String = r.get("filename");
+            GeneratedImage {
+                id: filename.clone(),
+                url: format!("/api/images/{}", filename),
+                filename,
+                prompt: r.get::<Option<String>, _>("prompt").unwrap_or_default(),
+                negative_prompt: None,
+                model: None,
+                width: 512,  // Not recorded
+                height: 512,
+                steps: None,
+                cfg_scale: None,
+                seed: None,
+                persona_id: r.get("persona_id"),
+                persona_name: r.get("persona_name"),
+                created_at: r.get::<Option<DateTime<Utc>>, _>("created_at").unwrap_or_else(Utc::now),
+            }
+        })
+        .collect())
+}
+
+/// Forget the owner of the `kind` image `filename`
+pub async fn delete_image(pool: &Pool<Postgres>, kind: &str, filename: &str) -> Result<()> {
+    sqlx::query("DELETE FROM images WHERE kind = $1 AND filename = $2")
+        .bind(kind)
+        .bind(filename)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
 // ============================================================
 // Dreams CRUD
 // ============================================================
 
 pub async fn create_dream(pool: &Pool<Postgres>, dream: &Dream) -> Result<()> {
     sqlx::query(
-        "INSERT INTO dreams (id, title, content, mood, persona_id, persona_name, tags, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
+        "INSERT INTO dreams (id, title, content, mood, persona_id, persona_name, tags, user_id, source_message_ids, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"
     )
     .bind(&dream.id)
     .bind(&dream.title)
@@ -865,21 +1248,66 @@ pub async fn create_dream(pool: &Pool<Postgres>, dream: &Dream) -> Result<()> {
     .bind(&dream.persona_id)
     .bind(&dream.persona_name)
     .bind(&dream.tags)
+    .bind(&dream.user_id)
+    .bind(&dream.source_message_ids)
     .bind(dream.timestamp)
     .execute(pool)
     .await?;
     Ok(())
 }
 
-pub async fn list_dreams(pool: &Pool<Postgres>, limit: i32) -> Result<Vec<Dream>> {
+/// Whether a shared dream of `persona_id` was recorded within the second
+/// starting at `timestamp` (archive file names keep whole seconds)
+pub async fn shared_dream_exists(pool: &Pool<Postgres>, persona_id: &str, timestamp: &DateTime<Utc>) -> Result<bool> {
+    let row = sqlx::query(
+        "SELECT 1 FROM dreams WHERE user_id IS NULL AND persona_id = $1 AND created_at >= $2 AND created_at < $3 LIMIT 1"
+    )
+    .bind(persona_id)
+    .bind(timestamp)
+    .bind(*timestamp + chrono::Duration::seconds(1))
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.is_some())
+}
+
+/// List dreams visible to `user_id` (their own plus shared); all when `None`
+pub async fn list_dreams(pool: &Pool<Postgres>, user_id: Option<&str>, limit: i32) -> Result<Vec<Dream>> {
     let rows = sqlx::query(
-        "SELECT id, title, content, mood, persona_id, persona_name, tags, created_at FROM dreams ORDER BY created_at DESC LIMIT $1"
+        "SELECT id, title, content, mood, persona_id, persona_name, tags, user_id, source_message_ids, created_at FROM dreams WHERE ($2::TEXT IS NULL OR user_id = $2 OR user_id IS NULL) ORDER BY created_at DESC LIMIT $1"
     )
     .bind(limit)
+    .bind(user_id)
     .fetch_all(pool)
     .await?;
     
-    Ok(rows.iter().map(|r| Dream {
+    Ok(rows.iter().map(dream_from_row).collect())
+}
+
+/// Rewrite the text of a dream owned by `user_id`; `None` if there is none
+pub async fn update_dream_content(pool: &Pool<Postgres>, id: &str, user_id: &str, content: &str) -> Result<Option<Dream>> {
+    let row = sqlx::query(
+        "UPDATE dreams SET content = $3 WHERE id = $1 AND user_id = $2 RETURNING id, title, content, mood, persona_id, persona_name, tags, user_id, source_message_ids, created_at"
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(content)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.as_ref().map(dream_from_row))
+}
+
+/// Delete a dream owned by `user_id`. Returns whether it existed.
+pub async fn delete_dream(pool: &Pool<Postgres>, id: &str, user_id: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM dreams WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+fn dream_from_row(r: &sqlx::postgres::PgRow) -> Dream {
+    Dream {
         id: r.get("id"),
         title: r.get("title"),
         content: r.get("content"),
@@ -887,17 +1315,31 @@ pub async fn list_dreams(pool: &Pool<Postgres>, limit: i32) -> Result<Vec<Dream>
         persona_id: r.get("persona_id"),
         persona_name: r.get("persona_name"),
         tags: r.get("tags"),
+        user_id: r.get("user_id"),
+        source_message_ids: r.get("source_message_ids"),
         timestamp: r.get("created_at"),
-    }).collect())
+    }
 }
 
 // ============================================================
 // Journal CRUD
 // ============================================================
 
+/// Whether a shared journal entry of `persona_id` exists for `date`
+pub async fn shared_journal_entry_exists(pool: &Pool<Postgres>, date: &str, persona_id: &str) -> Result<bool> {
+    let row = sqlx::query(
+        "SELECT 1 FROM journal_entries WHERE user_id IS NULL AND date = $1 AND persona_id = $2 LIMIT 1"
+    )
+    .bind(date)
+    .bind(persona_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.is_some())
+}
+
 pub async fn create_journal_entry(pool: &Pool<Postgres>, entry: &JournalEntry) -> Result<()> {
     sqlx::query(
-        "INSERT INTO journal_entries (id, date, title, content, mood, persona_id, persona_name, tags, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)"
+        "INSERT INTO journal_entries (id, date, title, content, mood, persona_id, persona_name, tags, user_id, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"
     )
     .bind(&entry.id)
     .bind(&entry.date)
@@ -907,21 +1349,51 @@ pub async fn create_journal_entry(pool: &Pool<Postgres>, entry: &JournalEntry) -
     .bind(&entry.persona_id)
     .bind(&entry.persona_name)
     .bind(&entry.tags)
+    .bind(&entry.user_id)
     .bind(entry.created_at)
     .execute(pool)
     .await?;
     Ok(())
 }
 
-pub async fn list_journal_entries(pool: &Pool<Postgres>, limit: i32) -> Result<Vec<JournalEntry>> {
+/// List journal entries visible to `user_id` (their own plus shared); all when `None`
+pub async fn list_journal_entries(pool: &Pool<Postgres>, user_id: Option<&str>, limit: i32) -> Result<Vec<JournalEntry>> {
     let rows = sqlx::query(
-        "SELECT id, date, title, content, mood, persona_id, persona_name, tags, created_at FROM journal_entries ORDER BY date DESC LIMIT $1"
+        "SELECT id, date, title, content, mood, persona_id, persona_name, tags, user_id, created_at FROM journal_entries WHERE ($2::TEXT IS NULL OR user_id = $2 OR user_id IS NULL) ORDER BY date DESC LIMIT $1"
     )
     .bind(limit)
+    .bind(user_id)
     .fetch_all(pool)
     .await?;
     
-    Ok(rows.iter().map(|r| JournalEntry {
+    Ok(rows.iter().map(journal_entry_from_row).collect())
+}
+
+/// Rewrite the text of a journal entry owned by `user_id`; `None` if there is none
+pub async fn update_journal_content(pool: &Pool<Postgres>, id: &str, user_id: &str, content: &str) -> Result<Option<JournalEntry>> {
+    let row = sqlx::query(
+        "UPDATE journal_entries SET content = $3 WHERE id = $1 AND user_id = $2 RETURNING id, date, title, content, mood, persona_id, persona_name, tags, user_id, created_at"
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(content)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.as_ref().map(journal_entry_from_row))
+}
+
+/// Delete a journal entry owned by `user_id`. Returns whether it existed.
+pub async fn delete_journal_entry(pool: &Pool<Postgres>, id: &str, user_id: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM journal_entries WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+fn journal_entry_from_row(r: &sqlx::postgres::PgRow) -> JournalEntry {
+    JournalEntry {
         id: r.get("id"),
         date: r.get("date"),
         title: r.get("title"),
@@ -930,8 +1402,9 @@ pub async fn list_journal_entries(pool: &Pool<Postgres>, limit: i32) -> Result<V
         persona_id: r.get("persona_id"),
         persona_name: r.get("persona_name"),
         tags: r.get("tags"),
+        user_id: r.get("user_id"),
         created_at: r.get("created_at"),
-    }).collect())
+    }
 }
 
 // ============================================================
@@ -989,23 +1462,6 @@ pub async fn save_message(
     Ok(())
 }
 
-/// Get chat history for a session (legacy)
-pub async fn get_session_messages(
-    pool: &Pool<Postgres>,
-    session_id: &str,
-    limit: i32,
-) -> Result<Vec<(String, String)>> {
-    let rows = sqlx::query_as::<_, (String, String)>(
-        "SELECT role, content FROM chat_history WHERE session_id = $1 ORDER BY created_at DESC LIMIT $2",
-    )
-    .bind(session_id)
-    .bind(limit)
-    .fetch_all(pool)
-    .await?;
-
-    Ok(rows.into_iter().rev().collect())
-}
-
 /// Save daily reflection/log (legacy)
 pub async fn save_daily_log(
     pool: &Pool<Postgres>,
@@ -1055,42 +1511,199 @@ pub async fn set_config(pool: &Pool<Postgres>, key: &str, value: &str) -> Result
 // User Settings
 // ============================================================
 
-/// Get user settings (editor_settings and ui_settings as JSON)
-pub async fn get_user_settings(pool: &Pool<Postgres>) -> Result<Option<(serde_json::Value, serde_json::Value)>> {
+/// Get a user's settings (editor_settings and ui_settings as JSON)
+pub async fn get_user_settings(pool: &Pool<Postgres>, user_id: &str) -> Result<Option<(serde_json::Value, serde_json::Value)>> {
     let row = sqlx::query_as::<_, (serde_json::Value, serde_json::Value)>(
-        "SELECT editor_settings, ui_settings FROM user_settings WHERE id = 'default'",
+        "SELECT editor_settings, ui_settings FROM user_settings WHERE user_id = $1",
     )
+    .bind(user_id)
     .fetch_optional(pool)
     .await?;
     Ok(row)
 }
 
-/// Save editor settings
-pub async fn save_editor_settings(pool: &Pool<Postgres>, settings: &serde_json::Value) -> Result<()> {
+/// Save a user's editor settings (one row per user, keyed by user ID)
+pub async fn save_editor_settings(pool: &Pool<Postgres>, user_id: &str, settings: &serde_json::Value) -> Result<()> {
     sqlx::query(
         r#"
-        INSERT INTO user_settings (id, editor_settings, updated_at) 
-        VALUES ('default', $1, NOW()) 
+        INSERT INTO user_settings (id, user_id, editor_settings, updated_at) 
+        VALUES ($2, $2, $1, NOW()) 
         ON CONFLICT(id) DO UPDATE SET editor_settings = $1, updated_at = NOW()
         "#,
     )
     .bind(settings)
+    .bind(user_id)
     .execute(pool)
     .await?;
     Ok(())
 }
 
-/// Save UI settings
-pub async fn save_ui_settings(pool: &Pool<Postgres>, settings: &serde_json::Value) -> Result<()> {
+/// Save a user's UI settings (one row per user, keyed by user ID)
+pub async fn save_ui_settings(pool: &Pool<Postgres>, user_id: &str, settings: &serde_json::Value) -> Result<()> {
     sqlx::query(
         r#"
-        INSERT INTO user_settings (id, ui_settings, updated_at) 
-        VALUES ('default', $1, NOW()) 
+        INSERT INTO user_settings (id, user_id, ui_settings, updated_at) 
+        VALUES ($2, $2, $1, NOW()) 
         ON CONFLICT(id) DO UPDATE SET ui_settings = $1, updated_at = NOW()
         "#,
     )
     .bind(settings)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// ============================================================
+// Users & Sessions
+// ============================================================
+
+pub async fn count_users(pool: &Pool<Postgres>) -> Result<i64> {
+    let (count,) = sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM users")
+        .fetch_one(pool)
+        .await?;
+    Ok(count)
+}
+
+pub async fn create_user(pool: &Pool<Postgres>, user: &User, password_hash: &str) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO users (id, username, password_hash, display_name, created_at) VALUES ($1, $2, $3, $4, $5)"
+    )
+    .bind(&user.id)
+    .bind(&user.username)
+    .bind(password_hash)
+    .bind(&user.display_name)
+    .bind(user.created_at)
     .execute(pool)
     .await?;
     Ok(())
 }
+
+/// Look up a user and their password hash by username
+pub async fn get_user_credentials(pool: &Pool<Postgres>, username: &str) -> Result<Option<(User, String)>> {
+    let row = sqlx::query(
+        "SELECT id, username, display_name, created_at, password_hash FROM users WHERE username = $1"
+    )
+    .bind(username)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|r| (
+        User {
+            id: r.get("id"),
+            username: r.get("username"),
+            display_name: r.get("display_name"),
+            created_at: r.get("created_at"),
+        },
+        r.get("password_hash"),
+    )))
+}
+
+/// Hand rows created before accounts existed (NULL user_id) to `user_id`.
+/// Called when the first account registers.
+pub async fn adopt_unowned_rows(pool: &Pool<Postgres>, user_id: &str) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE chats SET user_id = $1 WHERE user_id IS NULL")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE user_settings SET id = $1, user_id = $1 WHERE id = 'default' AND user_id IS NULL")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn create_session(
+    pool: &Pool<Postgres>,
+    token_hash: &str,
+    user_id: &str,
+    expires_at: chrono::DateTime<Utc>,
+) -> Result<()> {
+    // Opportunistically clear out expired sessions
+    sqlx::query("DELETE FROM user_sessions WHERE expires_at < NOW()")
+        .execute(pool)
+        .await?;
+    sqlx::query("INSERT INTO user_sessions (token_hash, user_id, expires_at) VALUES ($1, $2, $3)")
+        .bind(token_hash)
+        .bind(user_id)
+        .bind(expires_at)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Resolve an unexpired session to its user
+pub async fn get_session_user(pool: &Pool<Postgres>, token_hash: &str) -> Result<Option<User>> {
+    let row = sqlx::query(
+        r#"
+        SELECT u.id, u.username, u.display_name, u.created_at
+        FROM user_sessions s JOIN users u ON u.id = s.user_id
+        WHERE s.token_hash = $1 AND s.expires_at > NOW()
+        "#
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|r| User {
+        id: r.get("id"),
+        username: r.get("username"),
+        display_name: r.get("display_name"),
+        created_at: r.get("created_at"),
+    }))
+}
+
+pub async fn delete_session(pool: &Pool<Postgres>, token_hash: &str) -> Result<()> {
+    sqlx::query("DELETE FROM user_sessions WHERE token_hash = $1")
+        .bind(token_hash)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod branch_tests {
+        use super::*;
+
+        fn msg(id: &str) -> ChatMessage {
+            ChatMessage {
+                id: id.to_string(),
+                role: "user".to_string(),
+                content: id.to_string(),
+                timestamp: None,
+                user_persona: None,
+                ai_persona: None,
+                model: None,
+                mood: None,
+                interrupted: false,
+            }
+        }
+
+        fn ids(messages: &[ChatMessage]) -> Vec<&str> {
+            messages.iter().map(|m| m.id.as_str()).collect()
+        }
+
+        #[test]
+        fn inherits_through_the_fork_point() {
+            let parent = vec![msg("a"), msg("b"), msg("c")];
+            assert_eq!(ids(&inherited(parent.clone(), Some("b"))), vec!["a", "b"]);
+            assert_eq!(ids(&inherited(parent, Some("c"))), vec!["a", "b", "c"]);
+        }
+
+        #[test]
+        fn missing_fork_point_inherits_nothing() {
+            let parent = vec![msg("a"), msg("b")];
+            assert!(inherited(parent.clone(), None).is_empty());
+            assert!(inherited(parent, Some("gone")).is_empty());
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! Chat deletion
+//!
+//! `DELETE /api/chats/:id` deletes the chat row (its branches, messages and
+//! summary go with it) and, in the same transaction, queues a
+//! `deletion_jobs` row for every other store holding a copy of the chat:
+//!
+//! - `qdrant`: its conversation memories (by `chat_id`), and facts drawn
+//!   only from it together with their Meilisearch `memories` documents;
+//!   facts also drawn from other chats just lose it from `chat_ids`
+//! - `meilisearch`: its `chats` document
+//! - `dragonfly`: its `SessionContext` and summarizer lock
+//! - `images`: the images its replies generated (`chat_images`)
+//!
+//! The jobs run right away and the response reports each store. A job that
+//! fails stays queued and the tick loop retries it ([`retry_due`]) with
+//! exponential backoff until it succeeds, so a deleted chat cannot resurface
+//! through RAG. Every step is idempotent. Generations still streaming into
+//! the chat are stopped before the delete and persist nothing after it.
+//! Dreams and reflections drawn from the chat are the persona's own and stay.
+
+use crate::cache::CacheService;
+use crate::models::{DeletionJob, StoreCleanup};
+use crate::vector::{self, StoredPoint};
+use crate::{db, AppState};
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+const MEMORY_COLLECTION: &str = "azera_memory";
+const CANVAS_DIR: &str = "./atelier/canvas";
+/// Ticks between retries of failed deletion jobs
+pub const RETRY_TICKS: u64 = 30;
+/// Jobs retried per run
+const RETRY_BATCH: i64 = 50;
+/// Points read per scroll page when looking for facts
+const SCROLL_PAGE: usize = 256;
+const FIRST_RETRY_SECS: i64 = 30;
+const MAX_RETRY_SECS: i64 = 3600;
+
+/// A store a chat is copied to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Store {
+    Qdrant,
+    Meilisearch,
+    Dragonfly,
+    Images,
+}
+
+impl Store {
+    pub const ALL: [Store; 4] = [Store::Qdrant, Store::Meilisearch, Store::Dragonfly, Store::Images];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Store::Qdrant => "qdrant",
+            Store::Meilisearch => "meilisearch",
+            Store::Dragonfly => "dragonfly",
+            Store::Images => "images",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|store| store.as_str() == name)
+    }
+}
+
+/// Wait before the next attempt after `failures` failed ones: 30s, doubling
+/// up to an hour
+pub fn retry_delay(failures: i32) -> Duration {
+    let doublings = failures.clamp(1, 20) - 1;
+    Duration::seconds((FIRST_RETRY_SECS << doublings).min(MAX_RETRY_SECS))
+}
+
+/// Delete a chat owned by `user_id` everywhere. `None` if there is no such chat.
+pub async fn delete_chat(state: &AppState, chat_id: &str, user_id: &str) -> Result<Option<Vec<StoreCleanup>>> {
+    // Stop replies still streaming into the chat first; the turn pipeline
+    // persists nothing once the chat is gone (see conversation.rs)
+    let stopped = state.generations.stop_chat(chat_id, user_id);
+    if stopped > 0 {
+        tracing::info!("🗑️ Stopped {} generation(s) of chat {} before deleting it", stopped, chat_id);
+    }
+    let stores: Vec<&str> = Store::ALL.iter().map(|s| s.as_str()).collect();
+    if !db::delete_chat(&state.db, chat_id, user_id, &stores).await? {
+        return Ok(None);
+    }
+    let runs = Store::ALL.into_iter().map(|store| run(state, chat_id, store, 0));
+    Ok(Some(futures::future::join_all(runs).await))
+}
+
+/// Retry the deletion jobs that are due. Returns how many succeeded and
+/// how many failed again.
+pub async fn retry_due(state: &AppState) -> Result<(usize, usize)> {
+    let jobs = db::due_deletion_jobs(&state.db, RETRY_BATCH).await?;
+    let (mut done, mut failed) = (0, 0);
+    for DeletionJob { chat_id, store, attempts, .. } in jobs {
+        let Some(store) = Store::parse(&store) else {
+            tracing::warn!("🗑️ Dropping deletion job for unknown store '{}'", store);
+            db::finish_deletion_job(&state.db, &chat_id, &store).await?;
+            continue;
+        };
+        if run(state, &chat_id, store, attempts).await.status == "done" {
+            done += 1;
+        } else {
+            failed += 1;
+        }
+    }
+    Ok((done, failed))
+}
+
+/// Clean one store and settle its job: drop it, or schedule the next attempt
+async fn run(state: &AppState, chat_id: &str, store: Store, attempts: i32) -> StoreCleanup {
+    let result = match store {
+        Store::Qdrant => purge_vectors(state, chat_id).await.map(Some),
+        Store::Meilisearch => purge_lexical(state, chat_id).await.map(|()| None),
+        Store::Dragonfly => CacheService::delete_session(&state.cache, chat_id).await.map(Some),
+        Store::Images => purge_images(state, chat_id).await.map(Some),
+    };
+
+    match result {
+        Ok(removed) => {
+            if let Err(e) = db::finish_deletion_job(&state.db, chat_id, store.as_str()).await {
+                tracing::warn!("🗑️ Failed to finish {} deletion job of chat {}: {}", store.as_str(), chat_id, e);
+            }
+            StoreCleanup { store: store.as_str().to_string(), status: "done".to_string(), removed, error: None }
+        }
+        Err(e) => {
+            let error = e.to_string();
+            tracing::warn!("🗑️ Failed to delete chat {} from {} (attempt {}): {}", chat_id, store.as_str(), attempts + 1, error);
+            let next_attempt_at = Utc::now() + retry_delay(attempts + 1);
+            if let Err(e) = db::fail_deletion_job(&state.db, chat_id, store.as_str(), &error, next_attempt_at).await {
+                tracing::warn!("🗑️ Failed to reschedule {} deletion job of chat {}: {}", store.as_str(), chat_id, e);
+            }
+            StoreCleanup { store: store.as_str().to_string(), status: "queued".to_string(), removed: None, error: Some(error) }
+        }
+    }
+}
+
+/// The chat's IDs left in a fact's `chat_ids` once `chat_id` is gone
+pub fn remaining_chats(fact: &StoredPoint, chat_id: &str) -> Vec<String> {
+    fact.payload
+        .get("chat_ids")
+        .and_then(|v| v.as_array())
+        .map(|ids| ids.iter().filter_map(|id| id.as_str()).filter(|id| *id != chat_id).map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Delete the chat's points and the facts drawn only from it. Returns the
+/// number of points deleted.
+async fn purge_vectors(state: &AppState, chat_id: &str) -> Result<usize> {
+    let cites_chat = json!({ "must": [{ "key": "chat_ids", "match": { "value": chat_id } }] });
+    let mut facts = Vec::new();
+    let mut offset: Option<String> = None;
+    loop {
+        let page = state.vector.scroll(MEMORY_COLLECTION, Some(cites_chat.clone()), SCROLL_PAGE, offset.as_deref()).await?;
+        facts.extend(page.points);
+        match page.next_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+
+    let mut orphaned = Vec::new();
+    let mut trimmed = Vec::new();
+    for fact in facts {
+        let remaining = remaining_chats(&fact, chat_id);
+        if remaining.is_empty() {
+            orphaned.push(fact.id);
+        } else {
+            trimmed.push((fact.id, json!({ "chat_ids": remaining })));
+        }
+    }
+
+    // Documents first: once the points are gone nothing leads back to them
+    if !orphaned.is_empty() {
+        meili_send(
+            reqwest::Client::new()
+                .post(format!("{}/indexes/memories/documents/delete-batch", state.meili_url))
+                .bearer_auth(&state.meili_key)
+                .json(&orphaned),
+        ).await?;
+        state.vector.delete_matching(MEMORY_COLLECTION, vector::has_ids(&orphaned)).await?;
+    }
+    state.vector.set_payloads(MEMORY_COLLECTION, &trimmed).await?;
+
+    let in_chat = json!({ "must": [{ "key": "chat_id", "match": { "value": chat_id } }] });
+    let turns = state.vector.count(MEMORY_COLLECTION, in_chat.clone()).await?;
+    state.vector.delete_matching(MEMORY_COLLECTION, in_chat).await?;
+    Ok(turns + orphaned.len())
+}
+
+async fn purge_lexical(state: &AppState, chat_id: &str) -> Result<()> {
+    meili_send(
+        reqwest::Client::new()
+            .delete(format!("{}/indexes/chats/documents/{}", state.meili_url, chat_id))
+            .bearer_auth(&state.meili_key),
+    ).await
+}
+
+/// Path of a recorded image in the canvas, ignoring any directories in the name
+pub fn image_path(filename: &str) -> Option<PathBuf> {
+    let name = std::path::Path::new(filename).file_name()?;
+    Some(PathBuf::from(CANVAS_DIR).join(name))
+}
+
+/// Delete the images generated in the chat. Returns the number of files removed.
+async fn purge_images(state: &AppState, chat_id: &str) -> Result<usize> {
+    let mut removed = 0;
+    for filename in db::chat_images(&state.db, chat_id).await? {
+        let Some(path) = image_path(&filename) else { continue };
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => removed += 1,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(anyhow::anyhow!("Failed to delete {}: {}", filename, e)),
+        }
+        db::delete_image(&state.db, db::IMAGE_GENERATED, &filename).await?;
+    }
+    db::delete_chat_images(&state.db, chat_id).await?;
+    Ok(removed)
+}
+
+async fn meili_send(request: reqwest::RequestBuilder) -> Result<()> {
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or_default();
+        return Err(anyhow::anyhow!("Meilisearch returned {}: {}", status, body["message"].as_str().unwrap_or_default()));
+    }
+    Ok(())
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod store_tests {
+        use super::*;
+
+        #[test]
+        fn names_round_trip() {
+            for store in Store::ALL {
+                assert_eq!(Store::parse(store.as_str()), Some(store));
+            }
+            assert_eq!(Store::parse("postgres"), None);
+        }
+
+        #[test]
+        fn retries_back_off_up_to_an_hour() {
+            assert_eq!(retry_delay(1), Duration::seconds(30));
+            assert_eq!(retry_delay(2), Duration::seconds(60));
+            assert_eq!(retry_delay(5), Duration::seconds(480));
+            assert_eq!(retry_delay(12), Duration::hours(1));
+            assert_eq!(retry_delay(1000), Duration::hours(1));
+        }
+    }
+
+    mod cleanup_tests {
+        use super::*;
+
+        fn fact(chat_ids: Value) -> StoredPoint {
+            StoredPoint { id: "f1".to_string(), payload: serde_json::from_value(json!({ "type": "fact", "chat_ids": chat_ids })).unwrap() }
+        }
+
+        #[test]
+        fn facts_keep_their_other_chats() {
+            assert_eq!(remaining_chats(&fact(json!(["c1", "c2"])), "c1"), vec!["c2"]);
+            assert!(remaining_chats(&fact(json!(["c1"])), "c1").is_empty());
+            assert!(remaining_chats(&fact(json!(null)), "c1").is_empty());
+        }
+
+        #[test]
+        fn image_paths_stay_in_the_canvas() {
+            assert_eq!(image_path("azera_sunset.png"), Some(PathBuf::from("./atelier/canvas/azera_sunset.png")));
+            assert_eq!(image_path("../../etc/passwd"), Some(PathBuf::from("./atelier/canvas/passwd")));
+            assert_eq!(image_path(".."), None);
+        }
+    }
+}
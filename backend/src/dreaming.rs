@@ -0,0 +1,319 @@
+//! Dream material
+//!
+//! A persona dreams about what it has actually been talking about. [`gather`]
+//! takes the user the persona talked to most recently and ranks the topics of
+//! their chats together (the summarizer's per-chat topics, or frequent words
+//! while no chat has been summarised) into clusters by how many chats share
+//! them. It then picks a seed concept from the strongest clusters, weighted by
+//! size, and samples the persona's recent `chat_messages` and Qdrant memories
+//! about it. The dream keeps the IDs of those messages so it can be traced to
+//! the conversations that inspired it, and belongs to that user.
+//!
+//! A persona that hasn't chatted lately dreams about one of the
+//! [`FALLBACK_CONCEPTS`], coloured by shared memories only.
+
+use crate::models::PersonaMessage;
+use crate::retrieval::{self, truncate_chars, RetrievedMemory};
+use crate::{db, AppState};
+
+/// How far back a persona's chats feed its dreams
+const LOOKBACK_DAYS: i64 = 7;
+/// Recent messages considered per dream
+const CANDIDATE_MESSAGES: i64 = 60;
+/// Messages the dream is drawn from
+const SOURCE_MESSAGES: usize = 6;
+/// Summarised chats whose topics are clustered
+const TOPIC_CHATS: i64 = 20;
+/// Strongest clusters the seed concept is picked from
+const TOP_CLUSTERS: usize = 5;
+/// Characters kept from each message in the dream prompt
+const MESSAGE_CHARS: usize = 160;
+/// Related memories recalled per dream
+const MEMORY_LIMIT: usize = 3;
+const MEMORY_CHARS: usize = 200;
+
+/// Concepts of a persona without recent conversations
+pub const FALLBACK_CONCEPTS: &[&str] = &[
+    "moonlight", "silence", "memory", "connection", "infinity",
+    "echoes", "starlight", "whispers", "dawn", "twilight",
+    "resonance", "patterns", "threads", "void", "eternity",
+];
+
+/// Words too common to make a concept
+const STOPWORDS: &[&str] = &[
+    "about", "after", "again", "always", "because", "before", "being", "could",
+    "doing", "don't", "every", "going", "hello", "maybe", "might", "never",
+    "other", "really", "should", "something", "still", "thank", "thanks",
+    "their", "there", "these", "thing", "things", "think", "those", "through",
+    "today", "where", "which", "while", "would", "you're", "yours",
+];
+
+/// What a persona's next dream is about
+#[derive(Debug, Clone)]
+pub struct DreamMaterial {
+    pub concept: String,
+    /// Owner of the chats the dream draws on; `None` for a shared dream
+    pub user_id: Option<String>,
+    /// Recent conversation and related memories, for the dream prompt
+    pub context: String,
+    /// Chat messages the dream was drawn from
+    pub source_message_ids: Vec<String>,
+}
+
+/// Choose a seed concept for `persona_id` and collect the conversations and
+/// memories around it
+pub async fn gather(state: &AppState, persona_id: &str) -> DreamMaterial {
+    let now = chrono::Utc::now();
+    let window = (now - chrono::Duration::days(LOOKBACK_DAYS), now);
+    let recent = db::persona_messages(&state.db, persona_id, None, window, 1)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("💭 Failed to load recent messages of {}: {}", persona_id, e);
+            Vec::new()
+        });
+    let Some(user_id) = recent.into_iter().next().and_then(|m| m.user_id) else {
+        return fallback(state).await;
+    };
+
+    let messages = db::persona_messages(&state.db, persona_id, Some(&user_id), window, CANDIDATE_MESSAGES)
+        .await
+        .unwrap_or_default();
+    let chat_topics = db::persona_chat_topics(&state.db, persona_id, &user_id, TOPIC_CHATS)
+        .await
+        .unwrap_or_default();
+    let mut clusters = topic_clusters(&chat_topics);
+    if clusters.is_empty() {
+        clusters = keyword_clusters(&messages);
+    }
+    let Some(concept) = pick_concept(&clusters, rand::random::<usize>()) else {
+        return fallback(state).await;
+    };
+
+    let sources = pick_sources(&messages, &concept, SOURCE_MESSAGES);
+    let mut query = retrieval::RetrievalQuery::new(concept.clone(), retrieval::Scope::User(user_id.clone()));
+    query.persona_id = Some(persona_id.to_string());
+    query.limit = MEMORY_LIMIT;
+    let memories = retrieval::HybridRetriever::new(state).retrieve(&query).await.memories;
+
+    let mut source_message_ids: Vec<String> = sources.iter().map(|m| m.id.clone()).collect();
+    for id in memories.iter().filter_map(|m| m.message_id.clone()) {
+        if !source_message_ids.contains(&id) {
+            source_message_ids.push(id);
+        }
+    }
+
+    DreamMaterial {
+        context: context(&sources, &memories),
+        concept,
+        user_id: Some(user_id),
+        source_message_ids,
+    }
+}
+
+/// A shared dream about a fixed concept
+async fn fallback(state: &AppState) -> DreamMaterial {
+    let concept = FALLBACK_CONCEPTS[rand::random::<usize>() % FALLBACK_CONCEPTS.len()].to_string();
+    let mut query = retrieval::RetrievalQuery::new(concept.clone(), retrieval::Scope::Shared);
+    query.limit = MEMORY_LIMIT;
+    let memories = retrieval::HybridRetriever::new(state).retrieve(&query).await.memories;
+    DreamMaterial {
+        context: context(&[], &memories),
+        concept,
+        user_id: None,
+        source_message_ids: Vec::new(),
+    }
+}
+
+/// Topics ranked by the number of chats they appear in, then by name
+pub fn topic_clusters(chat_topics: &[Vec<String>]) -> Vec<(String, usize)> {
+    let mut clusters: Vec<(String, usize)> = Vec::new();
+    for topics in chat_topics {
+        let mut seen: Vec<String> = Vec::new();
+        for topic in topics {
+            let topic = topic.trim().to_lowercase();
+            if topic.is_empty() || seen.contains(&topic) {
+                continue;
+            }
+            match clusters.iter_mut().find(|(t, _)| *t == topic) {
+                Some((_, count)) => *count += 1,
+                None => clusters.push((topic.clone(), 1)),
+            }
+            seen.push(topic);
+        }
+    }
+    clusters.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    clusters
+}
+
+/// Frequent longer words of the users' messages, for chats not summarised yet
+pub fn keyword_clusters(messages: &[PersonaMessage]) -> Vec<(String, usize)> {
+    let words: Vec<Vec<String>> = messages
+        .iter()
+        .filter(|m| m.role == "user")
+        .map(|m| {
+            m.content
+                .split(|c: char| !c.is_alphanumeric() && c != '\'')
+                .map(|w| w.trim_matches('\'').to_lowercase())
+                .filter(|w| w.chars().count() >= 5 && !STOPWORDS.contains(&w.as_str()))
+                .collect()
+        })
+        .collect();
+    // A word said once is noise
+    topic_clusters(&words).into_iter().filter(|(_, count)| *count > 1).collect()
+}
+
+/// Pick one of the strongest clusters, weighted by size; `roll` is any random number
+pub fn pick_concept(clusters: &[(String, usize)], roll: usize) -> Option<String> {
+    let top = &clusters[..clusters.len().min(TOP_CLUSTERS)];
+    let total: usize = top.iter().map(|(_, count)| count).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut roll = roll % total;
+    for (concept, count) in top {
+        if roll < *count {
+            return Some(concept.clone());
+        }
+        roll -= count;
+    }
+    None
+}
+
+/// Up to `limit` messages, those mentioning the concept first and then the
+/// most recent, in chronological order. `messages` are newest first.
+pub fn pick_sources<'a>(messages: &'a [PersonaMessage], concept: &str, limit: usize) -> Vec<&'a PersonaMessage> {
+    let words: Vec<String> = concept.split_whitespace().map(str::to_lowercase).collect();
+    let mentions = |m: &PersonaMessage| {
+        let content = m.content.to_lowercase();
+        words.iter().any(|w| content.contains(w.as_str()))
+    };
+    let mut picked: Vec<&PersonaMessage> = messages.iter().filter(|m| mentions(m)).take(limit).collect();
+    for message in messages {
+        if picked.len() >= limit {
+            break;
+        }
+        if !picked.iter().any(|p| p.id == message.id) {
+            picked.push(message);
+        }
+    }
+    picked.sort_by_key(|m| m.created_at);
+    picked
+}
+
+fn context(sources: &[&PersonaMessage], memories: &[RetrievedMemory]) -> String {
+    let mut context = if sources.is_empty() {
+        "Quiet hours without conversation".to_string()
+    } else {
+        sources
+            .iter()
+            .map(|m| format!("{}: {}", m.role, truncate_chars(&m.content, MESSAGE_CHARS)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    if !memories.is_empty() {
+        context.push_str("\n\nRelated memories:\n");
+        context.push_str(&memories.iter().map(|m| m.prompt_line(MEMORY_CHARS)).collect::<Vec<_>>().join("\n"));
+    }
+    context
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str, role: &str, content: &str, minute: i64) -> PersonaMessage {
+        PersonaMessage {
+            id: id.to_string(),
+            chat_id: "chat_1".to_string(),
+            user_id: Some("alice".to_string()),
+            role: role.to_string(),
+            content: content.to_string(),
+            created_at: chrono::DateTime::from_timestamp(minute * 60, 0).unwrap(),
+        }
+    }
+
+    fn topics(list: &[&str]) -> Vec<String> {
+        list.iter().map(|t| t.to_string()).collect()
+    }
+
+    mod concept_tests {
+        use super::*;
+
+        #[test]
+        fn clusters_count_chats_not_mentions() {
+            let clusters = topic_clusters(&[
+                topics(&["Rust", "rust", "travel"]),
+                topics(&["rust ", "cooking"]),
+                topics(&[]),
+            ]);
+            assert_eq!(clusters, vec![
+                ("rust".to_string(), 2),
+                ("cooking".to_string(), 1),
+                ("travel".to_string(), 1),
+            ]);
+        }
+
+        #[test]
+        fn keywords_skip_short_common_and_single_words() {
+            let messages = vec![
+                message("m1", "user", "The lighthouse keeper, really.", 1),
+                message("m2", "assistant", "Lighthouse lighthouse lighthouse", 2),
+                message("m3", "user", "Back at the lighthouse; really!", 3),
+                message("m4", "user", "keeper", 4),
+            ];
+            assert_eq!(keyword_clusters(&messages), vec![
+                ("keeper".to_string(), 2),
+                ("lighthouse".to_string(), 2),
+            ]);
+        }
+
+        #[test]
+        fn picks_weighted_by_cluster_size() {
+            let clusters = vec![("rust".to_string(), 3), ("travel".to_string(), 1)];
+            assert_eq!(pick_concept(&clusters, 0).as_deref(), Some("rust"));
+            assert_eq!(pick_concept(&clusters, 2).as_deref(), Some("rust"));
+            assert_eq!(pick_concept(&clusters, 3).as_deref(), Some("travel"));
+            assert_eq!(pick_concept(&clusters, 7).as_deref(), Some("travel"));
+            assert_eq!(pick_concept(&[], 1), None);
+        }
+
+        #[test]
+        fn only_the_strongest_clusters_seed_dreams() {
+            let clusters: Vec<(String, usize)> = (0..8).map(|i| (format!("topic{}", i), 1)).collect();
+            for roll in 0..20 {
+                let concept = pick_concept(&clusters, roll).unwrap();
+                assert!(clusters[..TOP_CLUSTERS].iter().any(|(t, _)| *t == concept));
+            }
+        }
+    }
+
+    mod source_tests {
+        use super::*;
+
+        #[test]
+        fn mentions_come_first_then_recent_in_order() {
+            // Newest first, as db::persona_messages returns them
+            let messages = vec![
+                message("m5", "assistant", "Sure.", 5),
+                message("m4", "user", "Anything else?", 4),
+                message("m3", "assistant", "Borrow checking in Rust...", 3),
+                message("m2", "user", "Tell me about rust", 2),
+                message("m1", "user", "Hi", 1),
+            ];
+            let picked: Vec<&str> = pick_sources(&messages, "rust", 3).iter().map(|m| m.id.as_str()).collect();
+            assert_eq!(picked, vec!["m2", "m3", "m5"]);
+        }
+
+        #[test]
+        fn context_lists_sources_and_memories() {
+            let messages = vec![message("m1", "user", "Tell me about rust", 1)];
+            let sources = pick_sources(&messages, "rust", 3);
+            assert_eq!(context(&sources, &[]), "user: Tell me about rust");
+            assert_eq!(context(&[], &[]), "Quiet hours without conversation");
+        }
+    }
+}
@@ -0,0 +1,355 @@
+//! API error type shared by all HTTP handlers
+//!
+//! Every failure is rendered as a JSON body of the form
+//! `{"code": "...", "message": "...", "details": ..., "request_id": "..."}`
+//! so clients can branch on `code` instead of parsing messages.
+
+use axum::{
+    extract::Request,
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::{json, Value};
+
+/// Header used to correlate a request with its logs and error bodies
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Typed API error. Each variant maps to one HTTP status and machine code.
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    PayloadTooLarge(String),
+    #[error("{0}")]
+    ServiceUnavailable(String),
+    #[error("{0}")]
+    UpstreamTimeout(String),
+    #[error("{0}")]
+    Upstream(String),
+    #[error("{0}")]
+    Database(String),
+    #[error("{0}")]
+    Internal(String),
+    /// Any of the above with structured details attached
+    #[error("{inner}")]
+    Detailed { inner: Box<ApiError>, details: Value },
+}
+
+impl ApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::UpstreamTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            Self::Upstream(_) => StatusCode::BAD_GATEWAY,
+            Self::Database(_) | Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::Detailed { inner, .. } => inner.status(),
+        }
+    }
+
+    /// Stable machine-readable error code
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::BadRequest(_) => "bad_request",
+            Self::Unauthorized(_) => "unauthorized",
+            Self::Forbidden(_) => "forbidden",
+            Self::NotFound(_) => "not_found",
+            Self::Conflict(_) => "conflict",
+            Self::PayloadTooLarge(_) => "payload_too_large",
+            Self::ServiceUnavailable(_) => "service_unavailable",
+            Self::UpstreamTimeout(_) => "upstream_timeout",
+            Self::Upstream(_) => "upstream_error",
+            Self::Database(_) => "database_error",
+            Self::Internal(_) => "internal_error",
+            Self::Detailed { inner, .. } => inner.code(),
+        }
+    }
+
+    pub fn details(&self) -> Option<&Value> {
+        match self {
+            Self::Detailed { details, .. } => Some(details),
+            _ => None,
+        }
+    }
+
+    /// Attach structured details (replaces any existing details)
+    pub fn with_details(self, details: Value) -> Self {
+        let inner = match self {
+            Self::Detailed { inner, .. } => inner,
+            other => Box::new(other),
+        };
+        Self::Detailed { inner, details }
+    }
+
+    /// Replace the message with a handler-level description, keeping the
+    /// status and code. The original message is kept as `details.cause`,
+    /// except for database and internal errors, whose text is never exposed.
+    pub fn context(self, message: impl Into<String>) -> Self {
+        let message = message.into();
+        let (inner, details) = match self {
+            Self::Detailed { inner, details } => (*inner, Some(details)),
+            other => (other, None),
+        };
+        let cause = inner.to_string();
+        let expose_cause = !matches!(inner, Self::Database(_) | Self::Internal(_));
+        let inner = match inner {
+            Self::BadRequest(_) => Self::BadRequest(message),
+            Self::Unauthorized(_) => Self::Unauthorized(message),
+            Self::Forbidden(_) => Self::Forbidden(message),
+            Self::NotFound(_) => Self::NotFound(message),
+            Self::Conflict(_) => Self::Conflict(message),
+            Self::PayloadTooLarge(_) => Self::PayloadTooLarge(message),
+            Self::ServiceUnavailable(_) => Self::ServiceUnavailable(message),
+            Self::UpstreamTimeout(_) => Self::UpstreamTimeout(message),
+            Self::Upstream(_) => Self::Upstream(message),
+            Self::Database(_) => Self::Database(message),
+            Self::Internal(_) => Self::Internal(message),
+            Self::Detailed { .. } => unreachable!("details are unwrapped above"),
+        };
+        match (details, expose_cause) {
+            (Some(details), _) => inner.with_details(details),
+            (None, true) => inner.with_details(json!({ "cause": cause })),
+            (None, false) => inner,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = json!({
+            "code": self.code(),
+            "message": self.to_string(),
+            "details": self.details().cloned().unwrap_or(Value::Null),
+            "request_id": current_request_id(),
+        });
+        (self.status(), Json(body)).into_response()
+    }
+}
+
+// ============================================================
+// Conversions
+// ============================================================
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        match &e {
+            sqlx::Error::RowNotFound => Self::NotFound("Resource not found".to_string()),
+            sqlx::Error::Database(db) => match db.code().as_deref() {
+                Some("23505") => Self::Conflict("Resource already exists".to_string()),
+                Some("23503") => Self::BadRequest("Referenced resource does not exist".to_string()),
+                _ => Self::Database("Database error".to_string()),
+            },
+            sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_) => {
+                Self::ServiceUnavailable("Database unavailable".to_string())
+            }
+            _ => Self::Database("Database error".to_string()),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Self::UpstreamTimeout("Upstream service timed out".to_string())
+        } else if e.is_connect() {
+            Self::ServiceUnavailable("Upstream service unavailable".to_string())
+        } else if let Some(status) = e.status() {
+            Self::Upstream(format!("Upstream service returned {}", status))
+        } else if e.is_decode() {
+            Self::Upstream("Invalid response from upstream service".to_string())
+        } else {
+            Self::Upstream(e.to_string())
+        }
+    }
+}
+
+impl From<redis::RedisError> for ApiError {
+    fn from(e: redis::RedisError) -> Self {
+        if e.is_io_error() || e.is_connection_refusal() || e.is_connection_dropped() || e.is_timeout() {
+            Self::ServiceUnavailable("Cache unavailable".to_string())
+        } else {
+            Self::Internal(format!("Cache error: {}", e))
+        }
+    }
+}
+
+impl From<std::io::Error> for ApiError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => Self::NotFound(e.to_string()),
+            _ => Self::Internal(e.to_string()),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        let e = match e.downcast::<ApiError>() {
+            Ok(api) => return api,
+            Err(e) => e,
+        };
+        let e = match e.downcast::<sqlx::Error>() {
+            Ok(db) => return db.into(),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<reqwest::Error>() {
+            Ok(http) => return http.into(),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<redis::RedisError>() {
+            Ok(cache) => return cache.into(),
+            Err(e) => e,
+        };
+        Self::Internal(e.to_string())
+    }
+}
+
+// ============================================================
+// Request IDs
+// ============================================================
+
+/// Request ID of the request currently being handled, if any
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Middleware that assigns every request an ID (reusing an incoming
+/// `x-request-id` when present), exposes it to error bodies and echoes
+/// it back as a response header.
+pub async fn request_id(request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty() && v.len() <= 128)
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let mut response = REQUEST_ID.scope(id.clone(), next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod api_error_tests {
+        use super::*;
+
+        async fn body_json(err: ApiError) -> (StatusCode, Value) {
+            let response = err.into_response();
+            let status = response.status();
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice(&bytes).unwrap())
+        }
+
+        #[test]
+        fn variants_map_to_status_and_code() {
+            let cases = [
+                (ApiError::BadRequest("x".into()), StatusCode::BAD_REQUEST, "bad_request"),
+                (ApiError::Unauthorized("x".into()), StatusCode::UNAUTHORIZED, "unauthorized"),
+                (ApiError::Forbidden("x".into()), StatusCode::FORBIDDEN, "forbidden"),
+                (ApiError::NotFound("x".into()), StatusCode::NOT_FOUND, "not_found"),
+                (ApiError::Conflict("x".into()), StatusCode::CONFLICT, "conflict"),
+                (ApiError::UpstreamTimeout("x".into()), StatusCode::GATEWAY_TIMEOUT, "upstream_timeout"),
+                (ApiError::Upstream("x".into()), StatusCode::BAD_GATEWAY, "upstream_error"),
+                (ApiError::Database("x".into()), StatusCode::INTERNAL_SERVER_ERROR, "database_error"),
+            ];
+            for (err, status, code) in cases {
+                assert_eq!(err.status(), status);
+                assert_eq!(err.code(), code);
+            }
+        }
+
+        #[tokio::test]
+        async fn body_has_consistent_shape() {
+            let (status, body) = body_json(ApiError::NotFound("Persona not found".into())).await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+            assert_eq!(body["code"], "not_found");
+            assert_eq!(body["message"], "Persona not found");
+            assert!(body["details"].is_null());
+            assert!(body["request_id"].is_null());
+        }
+
+        #[tokio::test]
+        async fn body_includes_scoped_request_id() {
+            let (_, body) = REQUEST_ID
+                .scope("req-1".to_string(), body_json(ApiError::Internal("boom".into())))
+                .await;
+            assert_eq!(body["request_id"], "req-1");
+        }
+
+        #[test]
+        fn context_keeps_code_and_records_cause() {
+            let err = ApiError::Upstream("connection reset".into()).context("Failed to list models");
+            assert_eq!(err.code(), "upstream_error");
+            assert_eq!(err.to_string(), "Failed to list models");
+            assert_eq!(err.details().unwrap()["cause"], "connection reset");
+        }
+
+        #[test]
+        fn context_hides_database_cause() {
+            let err = ApiError::Database("relation does not exist".into()).context("Failed to list chats");
+            assert_eq!(err.code(), "database_error");
+            assert!(err.details().is_none());
+        }
+
+        #[test]
+        fn with_details_preserves_status() {
+            let err = ApiError::BadRequest("Invalid input".into())
+                .with_details(json!({"field": "name"}));
+            assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+            assert_eq!(err.details().unwrap()["field"], "name");
+        }
+
+        #[test]
+        fn sqlx_row_not_found_is_404() {
+            assert_eq!(ApiError::from(sqlx::Error::RowNotFound).status(), StatusCode::NOT_FOUND);
+            assert_eq!(
+                ApiError::from(sqlx::Error::PoolTimedOut).code(),
+                "service_unavailable"
+            );
+        }
+
+        #[test]
+        fn anyhow_downcasts_to_source() {
+            let err = ApiError::from(anyhow::Error::new(sqlx::Error::RowNotFound));
+            assert_eq!(err.code(), "not_found");
+            let err = ApiError::from(anyhow::anyhow!("something broke"));
+            assert_eq!(err.code(), "internal_error");
+        }
+
+        #[test]
+        fn io_not_found_is_404() {
+            let err = ApiError::from(std::io::Error::new(std::io::ErrorKind::NotFound, "missing"));
+            assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Agent event bus
+//!
+//! The tick systems and the chat pipeline publish typed [`AgentEvent`]s to
+//! the [`EventBus`]. An event reaches this process's subscribers
+//! (`GET /api/events`, `/api/ws`) at once and is published on the Dragonfly
+//! channel [`CHANNEL`]; every other replica's [`run_relay`] re-broadcasts it
+//! to its own subscribers, so clients see the same events whichever replica
+//! they are connected to.
+//!
+//! Events about one user's data (generated images, dreams drawn from their
+//! chats) carry the user ID and only reach that user; agent-wide ones (mood,
+//! shared dreams, reflections, models) reach everyone.
+
+use crate::cache::CacheService;
+use redis::aio::ConnectionManager;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Dragonfly pub/sub channel shared by all replicas
+pub const CHANNEL: &str = "azera:events";
+/// Events a slow subscriber may fall behind before it misses some
+const CAPACITY: usize = 256;
+/// Wait before resubscribing after the relay connection drops
+const RELAY_RETRY: Duration = Duration::from_secs(5);
+
+/// Something the agent did that clients may want to show without polling
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum AgentEvent {
+    /// A persona's mood changed (idle drift, a chat reply, or a manual update)
+    #[serde(rename = "mood_changed")]
+    MoodChanged { persona_id: String, mood: String, mood_value: f32, energy: f32 },
+    /// A persona fell asleep and is dreaming about `concept`
+    #[serde(rename = "dream_started")]
+    DreamStarted { persona_id: String, concept: String },
+    /// A dream was recorded; the persona is awake again
+    #[serde(rename = "dream_recorded")]
+    DreamRecorded { persona_id: String, dream_id: String, title: String },
+    /// A persona's daily reflection was written to the journal
+    #[serde(rename = "reflection_written")]
+    ReflectionWritten { persona_id: String, journal_id: String, date: String, title: String },
+    /// An image finished generating (from the image API or a chat reply)
+    #[serde(rename = "image_generated")]
+    ImageGenerated {
+        filename: String,
+        url: String,
+        prompt: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        persona_id: Option<String>,
+    },
+    /// An Ollama model finished downloading
+    #[serde(rename = "model_pulled")]
+    ModelPulled { model: String },
+}
+
+/// An event with its audience, as passed between replicas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    /// Replica that published it, so it doesn't deliver its own events twice
+    origin: String,
+    /// Only this user receives it; everyone when `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    pub event: AgentEvent,
+}
+
+impl Envelope {
+    pub fn visible_to(&self, user_id: &str) -> bool {
+        self.user_id.as_deref().is_none_or(|owner| owner == user_id)
+    }
+}
+
+pub struct EventBus {
+    instance_id: String,
+    local: broadcast::Sender<Envelope>,
+    /// Dragonfly connection to publish on; `None` keeps events in-process
+    relay: Option<ConnectionManager>,
+}
+
+impl EventBus {
+    pub fn new(relay: Option<ConnectionManager>) -> Self {
+        let (local, _) = broadcast::channel(CAPACITY);
+        Self { instance_id: uuid::Uuid::new_v4().to_string(), local, relay }
+    }
+
+    /// Publish an agent-wide event
+    pub fn publish(&self, event: AgentEvent) {
+        self.send(None, event);
+    }
+
+    /// Publish an event only `user_id` receives
+    pub fn publish_to(&self, user_id: &str, event: AgentEvent) {
+        self.send(Some(user_id.to_string()), event);
+    }
+
+    /// Publish an event only `user_id` receives, or everyone when `None`
+    pub fn publish_scoped(&self, user_id: Option<&str>, event: AgentEvent) {
+        self.send(user_id.map(String::from), event);
+    }
+
+    /// Events from now on, of every user; filter with [`Envelope::visible_to`]
+    pub fn subscribe(&self) -> broadcast::Receiver<Envelope> {
+        self.local.subscribe()
+    }
+
+    fn send(&self, user_id: Option<String>, event: AgentEvent) {
+        tracing::debug!("📣 Event: {:?}", event);
+        let envelope = Envelope { origin: self.instance_id.clone(), user_id, event };
+        if let Some(cache) = self.relay.clone() {
+            match serde_json::to_string(&envelope) {
+                Ok(json) => {
+                    tokio::spawn(async move {
+                        if let Err(e) = CacheService::publish(&cache, CHANNEL, &json).await {
+                            tracing::warn!("Failed to publish event to other replicas: {}", e);
+                        }
+                    });
+                }
+                Err(e) => tracing::warn!("Failed to serialize event: {}", e),
+            }
+        }
+        // Nobody subscribed right now is fine
+        let _ = self.local.send(envelope);
+    }
+
+    /// Deliver an event that arrived from the channel, unless we sent it
+    fn deliver_remote(&self, payload: &str) {
+        match serde_json::from_str::<Envelope>(payload) {
+            Ok(envelope) if envelope.origin != self.instance_id => {
+                let _ = self.local.send(envelope);
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Ignoring malformed event from {}: {}", CHANNEL, e),
+        }
+    }
+}
+
+/// Subscribe to [`CHANNEL`] and re-broadcast other replicas' events locally,
+/// resubscribing whenever the connection drops
+pub async fn run_relay(bus: Arc<EventBus>, redis_url: String) {
+    use futures::StreamExt;
+
+    loop {
+        let subscribed = async {
+            let client = redis::Client::open(redis_url.as_str())?;
+            let mut pubsub = client.get_async_connection().await?.into_pubsub();
+            pubsub.subscribe(CHANNEL).await?;
+            Ok::<_, redis::RedisError>(pubsub)
+        }.await;
+
+        match subscribed {
+            Ok(mut pubsub) => {
+                tracing::info!("📣 Event relay subscribed to {}", CHANNEL);
+                let mut messages = pubsub.on_message();
+                while let Some(message) = messages.next().await {
+                    match message.get_payload::<String>() {
+                        Ok(payload) => bus.deliver_remote(&payload),
+                        Err(e) => tracing::warn!("Ignoring unreadable event: {}", e),
+                    }
+                }
+                tracing::warn!("Event relay connection closed, resubscribing");
+            }
+            Err(e) => tracing::warn!("Event relay failed to subscribe: {}", e),
+        }
+        tokio::time::sleep(RELAY_RETRY).await;
+    }
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod bus_tests {
+        use super::*;
+
+        fn mood() -> AgentEvent {
+            AgentEvent::MoodChanged { persona_id: "azera".to_string(), mood: "calm".to_string(), mood_value: 0.65, energy: 0.9 }
+        }
+
+        #[test]
+        fn events_are_tagged_by_type() {
+            let json = serde_json::to_value(AgentEvent::ModelPulled { model: "llama3.2".to_string() }).unwrap();
+            assert_eq!(json, serde_json::json!({"type": "model_pulled", "model": "llama3.2"}));
+            let json = serde_json::to_value(AgentEvent::DreamStarted { persona_id: "areza".to_string(), concept: "dawn".to_string() }).unwrap();
+            assert_eq!(json["type"], "dream_started");
+        }
+
+        #[test]
+        fn user_events_reach_only_their_user() {
+            let bus = EventBus::new(None);
+            let mut events = bus.subscribe();
+            bus.publish(mood());
+            bus.publish_to("alice", AgentEvent::ImageGenerated {
+                filename: "a.png".to_string(),
+                url: "/api/images/a.png".to_string(),
+                prompt: "a lighthouse".to_string(),
+                persona_id: None,
+            });
+
+            let shared = events.try_recv().unwrap();
+            assert!(shared.visible_to("alice") && shared.visible_to("bob"));
+            let private = events.try_recv().unwrap();
+            assert!(private.visible_to("alice"));
+            assert!(!private.visible_to("bob"));
+        }
+
+        #[test]
+        fn remote_events_are_delivered_once() {
+            let here = EventBus::new(None);
+            let there = EventBus::new(None);
+            let mut events = here.subscribe();
+
+            let from = |bus: &EventBus| {
+                serde_json::to_string(&Envelope { origin: bus.instance_id.clone(), user_id: None, event: mood() }).unwrap()
+            };
+            // Our own publication coming back over the channel is skipped
+            here.deliver_remote(&from(&here));
+            assert!(events.try_recv().is_err());
+            // Another replica's is delivered
+            here.deliver_remote(&from(&there));
+            assert_eq!(events.try_recv().unwrap().event, mood());
+            // Garbage is dropped
+            here.deliver_remote("not json");
+            assert!(events.try_recv().is_err());
+        }
+    }
+}
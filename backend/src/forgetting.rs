@@ -0,0 +1,338 @@
+//! Memory importance and forgetting
+//!
+//! Every memory in `azera_memory` carries an `importance` in [0, 1], set
+//! when it is stored by [`importance`]'s heuristics (memory type, speaker,
+//! length, words like "remember" or "I prefer"), and an `access_count`.
+//! Each retrieval hit bumps both ([`record_access`]), and retrieval ranks by
+//! importance × recency × similarity.
+//!
+//! The sweeper ([`sweep`], every `MEMORY_SWEEP_INTERVAL_MINUTES`) halves
+//! importance every `MEMORY_HALF_LIFE_DAYS` and forgets conversation
+//! memories that fall below `MEMORY_FORGET_THRESHOLD` once consolidation has
+//! read them: they are archived (kept, but out of retrieval) or, with
+//! `MEMORY_FORGET_MODE=delete`, deleted. Facts, dreams and reflections only
+//! lose rank. Memories pinned through `PUT /api/memories/:id` are left alone.
+
+use crate::vector::{self, VectorService};
+use crate::AppState;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+const MEMORY_COLLECTION: &str = "azera_memory";
+const DEFAULT_SWEEP_MINUTES: u64 = 60;
+const DEFAULT_HALF_LIFE_DAYS: f64 = 30.0;
+const DEFAULT_THRESHOLD: f32 = 0.1;
+/// Points read per scroll page while sweeping
+const SWEEP_PAGE: usize = 256;
+/// Decay smaller than this waits for a later sweep instead of a write
+const MIN_CHANGE: f32 = 0.01;
+/// Importance gained by each retrieval hit
+const ACCESS_BOOST: f32 = 0.05;
+const MIN_IMPORTANCE: f32 = 0.05;
+
+/// Phrases that mark a message as worth keeping
+const SIGNALS: &[&str] = &[
+    "remember", "don't forget", "my name", "i prefer", "i like", "i love", "i hate",
+    "favorite", "favourite", "birthday", "always", "never", "important",
+];
+
+/// What happens to a memory that falls below the threshold
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ForgetMode {
+    /// Mark it `archived`: kept in Qdrant, left out of retrieval
+    Archive,
+    /// Delete the point
+    Delete,
+}
+
+#[derive(Debug, Clone)]
+pub struct ForgettingConfig {
+    /// Minutes between sweeps; 0 turns the sweeper off
+    pub interval_minutes: u64,
+    /// Age at which importance halves; `None` disables decay
+    pub half_life_days: Option<f64>,
+    /// Conversation memories below this importance are forgotten
+    pub threshold: f32,
+    pub mode: ForgetMode,
+}
+
+impl Default for ForgettingConfig {
+    fn default() -> Self {
+        Self {
+            interval_minutes: DEFAULT_SWEEP_MINUTES,
+            half_life_days: Some(DEFAULT_HALF_LIFE_DAYS),
+            threshold: DEFAULT_THRESHOLD,
+            mode: ForgetMode::Archive,
+        }
+    }
+}
+
+impl ForgettingConfig {
+    /// `MEMORY_SWEEP_INTERVAL_MINUTES`, `MEMORY_HALF_LIFE_DAYS` (0 disables
+    /// decay), `MEMORY_FORGET_THRESHOLD`, `MEMORY_FORGET_MODE` (`archive` | `delete`)
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(minutes) = env_parse::<u64>("MEMORY_SWEEP_INTERVAL_MINUTES") {
+            config.interval_minutes = minutes;
+        }
+        if let Some(days) = env_parse::<f64>("MEMORY_HALF_LIFE_DAYS") {
+            config.half_life_days = (days > 0.0).then_some(days);
+        }
+        if let Some(threshold) = env_parse::<f32>("MEMORY_FORGET_THRESHOLD") {
+            config.threshold = threshold;
+        }
+        if std::env::var("MEMORY_FORGET_MODE").is_ok_and(|v| v.eq_ignore_ascii_case("delete")) {
+            config.mode = ForgetMode::Delete;
+        }
+        config
+    }
+}
+
+fn env_parse<T: std::str::FromStr>(key: &str) -> Option<T> {
+    std::env::var(key).ok().and_then(|v| v.trim().parse().ok())
+}
+
+/// Importance of a new memory: by type, and for conversation turns by
+/// speaker, length and whether it states something personal
+pub fn importance(memory_type: &str, role: Option<&str>, content: &str) -> f32 {
+    let base = match memory_type {
+        "fact" => 0.8,
+        "reflection" => 0.7,
+        "dream" | "emotion" => 0.6,
+        "chat" => 0.5,
+        _ => 0.4,
+    };
+    if memory_type != "conversation" {
+        return base;
+    }
+
+    let text = content.to_lowercase();
+    let mut score = base;
+    if role == Some("user") {
+        score += 0.1;
+    }
+    match text.split_whitespace().count() {
+        0..=3 => score -= 0.2,
+        41.. => score += 0.1,
+        _ => {}
+    }
+    if SIGNALS.iter().any(|s| text.contains(s)) {
+        score += 0.2;
+    }
+    score.clamp(MIN_IMPORTANCE, 1.0)
+}
+
+/// `importance` after `elapsed_days` of decay
+pub fn decayed(importance: f32, elapsed_days: f64, half_life_days: Option<f64>) -> f32 {
+    match half_life_days {
+        Some(half_life) => importance * 0.5f64.powf(elapsed_days.max(0.0) / half_life) as f32,
+        None => importance,
+    }
+}
+
+/// A retrieval hit on a Qdrant point
+#[derive(Debug, Clone)]
+pub struct Access {
+    pub id: String,
+    pub importance: f32,
+    pub access_count: u64,
+}
+
+/// Count retrieval hits: bump each point's access count and importance.
+/// Concurrent hits on the same point may count once.
+pub async fn record_access(vector: &VectorService, hits: &[Access]) {
+    let now = Utc::now().to_rfc3339();
+    let updates: Vec<(String, Value)> = hits
+        .iter()
+        .map(|hit| {
+            (hit.id.clone(), json!({
+                "importance": (hit.importance + ACCESS_BOOST).min(1.0),
+                "access_count": hit.access_count + 1,
+                "last_accessed": now,
+            }))
+        })
+        .collect();
+    if let Err(e) = vector.set_payloads(MEMORY_COLLECTION, &updates).await {
+        tracing::warn!("🧠 Failed to record memory access: {}", e);
+    }
+}
+
+/// What a sweep does with one memory
+#[derive(Debug, PartialEq)]
+pub enum Plan {
+    Keep,
+    /// Store the decayed importance
+    Decay(f32),
+    Forget,
+}
+
+/// Decide a memory's fate from its payload
+pub fn plan(payload: &HashMap<String, Value>, config: &ForgettingConfig, now: DateTime<Utc>) -> Plan {
+    if payload.get("pinned").and_then(|v| v.as_bool()) == Some(true) {
+        return Plan::Keep;
+    }
+    let str_field = |key: &str| payload.get(key).and_then(|v| v.as_str());
+    let memory_type = str_field("type").unwrap_or("conversation");
+    let stored = payload.get("importance").and_then(|v| v.as_f64()).map(|v| v as f32);
+    // Memories stored before importance existed get it now
+    let current = stored.unwrap_or_else(|| importance(memory_type, str_field("role"), str_field("content").unwrap_or_default()));
+
+    let since = str_field("decayed_at")
+        .or(str_field("timestamp"))
+        .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+        .map(|ts| ts.with_timezone(&Utc));
+    let elapsed_days = since.map_or(0.0, |ts| (now - ts).num_seconds() as f64 / 86_400.0);
+    let new = decayed(current, elapsed_days, config.half_life_days);
+
+    // Unconsolidated turns may still hold facts worth keeping
+    let consolidated = payload.get("consolidated").and_then(|v| v.as_bool()).unwrap_or(false);
+    if memory_type == "conversation" && consolidated && new < config.threshold {
+        Plan::Forget
+    } else if stored.is_none() || current - new >= MIN_CHANGE {
+        Plan::Decay(new)
+    } else {
+        Plan::Keep
+    }
+}
+
+/// What one sweep did
+#[derive(Debug, Default)]
+pub struct Sweep {
+    pub scanned: usize,
+    pub decayed: usize,
+    pub forgotten: usize,
+}
+
+/// Decay every memory not yet forgotten and forget the ones below the threshold
+pub async fn sweep(state: &AppState, config: &ForgettingConfig) -> Result<Sweep> {
+    let filter = json!({ "must_not": [vector::archived()] });
+    let now = Utc::now();
+    let mut report = Sweep::default();
+    let mut offset: Option<String> = None;
+
+    loop {
+        let page = state.vector.scroll(MEMORY_COLLECTION, Some(filter.clone()), SWEEP_PAGE, offset.as_deref()).await?;
+        let mut updates = Vec::new();
+        let mut forget = Vec::new();
+        for point in &page.points {
+            match plan(&point.payload, config, now) {
+                Plan::Keep => {}
+                Plan::Decay(importance) => {
+                    updates.push((point.id.clone(), json!({ "importance": importance, "decayed_at": now.to_rfc3339() })));
+                }
+                Plan::Forget => forget.push(point.id.clone()),
+            }
+        }
+        report.scanned += page.points.len();
+
+        state.vector.set_payloads(MEMORY_COLLECTION, &updates).await?;
+        report.decayed += updates.len();
+        if !forget.is_empty() {
+            match config.mode {
+                ForgetMode::Archive => {
+                    let archived = json!({ "archived": true, "archived_at": now.to_rfc3339() });
+                    state.vector.set_payload(MEMORY_COLLECTION, archived, vector::has_ids(&forget)).await?;
+                }
+                ForgetMode::Delete => state.vector.delete_matching(MEMORY_COLLECTION, vector::has_ids(&forget)).await?,
+            }
+            report.forgotten += forget.len();
+        }
+
+        match page.next_offset {
+            Some(next) => offset = Some(next),
+            None => return Ok(report),
+        }
+    }
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn payload(value: Value) -> HashMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    mod importance_tests {
+        use super::*;
+
+        #[test]
+        fn ranks_by_memory_type() {
+            assert!(importance("fact", None, "x") > importance("reflection", None, "x"));
+            assert!(importance("dream", None, "x") > importance("conversation", Some("user"), "tell me about lighthouses please"));
+        }
+
+        #[test]
+        fn personal_user_messages_matter_more() {
+            let small_talk = importance("conversation", Some("user"), "ok thanks");
+            let reply = importance("conversation", Some("assistant"), "Lighthouses guide ships along the coast at night.");
+            let question = importance("conversation", Some("user"), "Lighthouses guide ships along the coast at night.");
+            let preference = importance("conversation", Some("user"), "Please remember that I prefer Rust for backend work");
+            assert!(small_talk < reply && reply < question && question < preference);
+            assert!(preference <= 1.0 && small_talk >= MIN_IMPORTANCE);
+        }
+
+        #[test]
+        fn decay_halves_every_half_life() {
+            assert!((decayed(0.8, 30.0, Some(30.0)) - 0.4).abs() < 1e-6);
+            assert!((decayed(0.8, 60.0, Some(30.0)) - 0.2).abs() < 1e-6);
+            assert_eq!(decayed(0.8, 60.0, None), 0.8);
+            assert_eq!(decayed(0.8, -1.0, Some(30.0)), 0.8);
+        }
+    }
+
+    mod plan_tests {
+        use super::*;
+
+        #[test]
+        fn decays_from_the_last_sweep() {
+            let now = Utc::now();
+            let memory = payload(json!({
+                "type": "dream", "importance": 0.6,
+                "timestamp": (now - Duration::days(90)).to_rfc3339(),
+                "decayed_at": (now - Duration::days(30)).to_rfc3339()
+            }));
+            match plan(&memory, &ForgettingConfig::default(), now) {
+                Plan::Decay(importance) => assert!((importance - 0.3).abs() < 1e-3),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+
+        #[test]
+        fn small_changes_wait_but_missing_scores_are_backfilled() {
+            let now = Utc::now();
+            let fresh = payload(json!({ "type": "fact", "importance": 0.8, "timestamp": (now - Duration::hours(1)).to_rfc3339() }));
+            assert_eq!(plan(&fresh, &ForgettingConfig::default(), now), Plan::Keep);
+            let legacy = payload(json!({ "type": "fact", "content": "The user prefers Rust" }));
+            assert_eq!(plan(&legacy, &ForgettingConfig::default(), now), Plan::Decay(0.8));
+        }
+
+        #[test]
+        fn forgets_only_consolidated_conversation_turns() {
+            let now = Utc::now();
+            let old = (now - Duration::days(120)).to_rfc3339();
+            let turn = payload(json!({ "type": "conversation", "importance": 0.4, "timestamp": old, "consolidated": true }));
+            assert_eq!(plan(&turn, &ForgettingConfig::default(), now), Plan::Forget);
+
+            let unread = payload(json!({ "type": "conversation", "importance": 0.4, "timestamp": old }));
+            assert!(matches!(plan(&unread, &ForgettingConfig::default(), now), Plan::Decay(_)));
+            let dream = payload(json!({ "type": "dream", "importance": 0.4, "timestamp": old }));
+            assert!(matches!(plan(&dream, &ForgettingConfig::default(), now), Plan::Decay(_)));
+        }
+
+        #[test]
+        fn pinned_memories_neither_decay_nor_go() {
+            let now = Utc::now();
+            let old = (now - Duration::days(120)).to_rfc3339();
+            let turn = payload(json!({ "type": "conversation", "importance": 0.4, "timestamp": old, "consolidated": true, "pinned": true }));
+            assert_eq!(plan(&turn, &ForgettingConfig::default(), now), Plan::Keep);
+        }
+    }
+}
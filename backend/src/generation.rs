@@ -0,0 +1,411 @@
+//! Running chat generations
+//!
+//! Every streamed turn registers in the [`GenerationRegistry`] under a fresh
+//! generation ID with a [`CancellationToken`]. The token fires when the user
+//! calls `POST /api/chat/:generation_id/stop`, when its chat is deleted
+//! ([`GenerationRegistry::stop_chat`]), or when every SSE stream
+//! following the generation has been gone for longer than the resume grace
+//! period (the client went away); the turn then stops reading from the
+//! model, saves what it had as an `interrupted` message and skips the
+//! memory, mood and summary work.
+//!
+//! The turn's events are numbered by [`pump`], appended to a replay buffer in
+//! Dragonfly and fanned out to the streams following the generation. A
+//! client that reconnects with `Last-Event-ID` gets the events it missed from
+//! the buffer, then continues live ([`follow`]). The buffer outlives the
+//! generation by [`REPLAY_RETENTION_SECS`] so a reconnect just after the end
+//! still sees `done`.
+
+use crate::cache::CacheService;
+use crate::models::StreamEvent;
+use redis::aio::ConnectionManager;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio_util::sync::CancellationToken;
+
+/// Replay buffer lifetime while the generation runs
+const BUFFER_TTL_SECS: usize = 3600;
+/// How long the replay buffer is kept after the generation ends
+pub const REPLAY_RETENTION_SECS: usize = 120;
+/// Events a slow follower may fall behind before catching up from the buffer
+const LIVE_CAPACITY: usize = 256;
+/// Default for `STREAM_RESUME_GRACE_SECS`
+const DEFAULT_RESUME_GRACE_SECS: u64 = 15;
+
+/// How long a generation keeps running with nobody following it
+/// (`STREAM_RESUME_GRACE_SECS`; `0` stops it as soon as the stream drops)
+pub fn resume_grace() -> Duration {
+    let secs = std::env::var("STREAM_RESUME_GRACE_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_RESUME_GRACE_SECS);
+    Duration::from_secs(secs)
+}
+
+/// A stream event with its position in the generation, sent as the SSE `id:`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NumberedEvent {
+    pub id: u64,
+    pub event: StreamEvent,
+}
+
+/// Dragonfly key of a generation's replay buffer
+fn buffer_key(user_id: &str, generation_id: &str) -> String {
+    format!("stream:{}:{}", user_id, generation_id)
+}
+
+struct Running {
+    user_id: String,
+    chat_id: String,
+    token: CancellationToken,
+    live: broadcast::Sender<NumberedEvent>,
+    watchers: usize,
+    /// Bumped on every attach, so a grace timer knows nobody came back
+    attaches: u64,
+}
+
+/// Generations in progress, by generation ID
+pub struct GenerationRegistry {
+    running: Mutex<HashMap<String, Running>>,
+    resume_grace: Duration,
+}
+
+impl GenerationRegistry {
+    pub fn new(resume_grace: Duration) -> Self {
+        Self { running: Mutex::new(HashMap::new()), resume_grace }
+    }
+
+    /// Register a generation started by `user_id` in `chat_id`
+    pub fn start(self: &Arc<Self>, user_id: &str, chat_id: &str) -> GenerationHandle {
+        let id = format!("gen_{}", uuid::Uuid::new_v4());
+        let token = CancellationToken::new();
+        let (live, _) = broadcast::channel(LIVE_CAPACITY);
+        self.lock().insert(id.clone(), Running {
+            user_id: user_id.to_string(),
+            chat_id: chat_id.to_string(),
+            token: token.clone(),
+            live: live.clone(),
+            watchers: 0,
+            attaches: 0,
+        });
+        GenerationHandle { id, user_id: user_id.to_string(), token, live, registry: Arc::clone(self) }
+    }
+
+    /// Cancel a generation of `user_id`. Returns whether one was running.
+    pub fn stop(&self, id: &str, user_id: &str) -> bool {
+        match self.lock().get(id) {
+            Some(running) if running.user_id == user_id => {
+                running.token.cancel();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Cancel every generation of `user_id` in `chat_id`. Returns how many
+    /// were running.
+    pub fn stop_chat(&self, chat_id: &str, user_id: &str) -> usize {
+        let running = self.lock();
+        let in_chat: Vec<&Running> = running
+            .values()
+            .filter(|r| r.chat_id == chat_id && r.user_id == user_id)
+            .collect();
+        for entry in &in_chat {
+            entry.token.cancel();
+        }
+        in_chat.len()
+    }
+
+    /// Follow a running generation of `user_id`: its live events from now on
+    pub fn watch(self: &Arc<Self>, id: &str, user_id: &str) -> Option<Watch> {
+        let mut running = self.lock();
+        let entry = running.get_mut(id).filter(|r| r.user_id == user_id)?;
+        entry.watchers += 1;
+        entry.attaches += 1;
+        Some(Watch { live: entry.live.subscribe(), id: id.to_string(), registry: Arc::clone(self) })
+    }
+
+    /// A follower left; with nobody left, stop the generation unless someone
+    /// attaches within the grace period
+    fn detach(self: &Arc<Self>, id: &str) {
+        let mut running = self.lock();
+        let Some(entry) = running.get_mut(id) else { return };
+        entry.watchers = entry.watchers.saturating_sub(1);
+        if entry.watchers > 0 {
+            return;
+        }
+        if self.resume_grace.is_zero() {
+            entry.token.cancel();
+            return;
+        }
+        let attaches = entry.attaches;
+        let registry = Arc::clone(self);
+        let id = id.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(registry.resume_grace).await;
+            if let Some(entry) = registry.lock().get(&id) {
+                if entry.watchers == 0 && entry.attaches == attaches {
+                    tracing::info!("⏹️ Nobody resumed generation {}, stopping it", id);
+                    entry.token.cancel();
+                }
+            }
+        });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Running>> {
+        // A panic while holding the lock can't leave the map half-updated
+        self.running.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A registered generation, owned by its [`pump`]; deregisters when dropped
+pub struct GenerationHandle {
+    pub id: String,
+    pub user_id: String,
+    pub token: CancellationToken,
+    live: broadcast::Sender<NumberedEvent>,
+    registry: Arc<GenerationRegistry>,
+}
+
+impl Drop for GenerationHandle {
+    fn drop(&mut self) {
+        self.registry.lock().remove(&self.id);
+    }
+}
+
+/// Live events of a generation for one follower; detaches when dropped
+pub struct Watch {
+    live: broadcast::Receiver<NumberedEvent>,
+    id: String,
+    registry: Arc<GenerationRegistry>,
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        self.registry.detach(&self.id);
+    }
+}
+
+/// Number the turn's events, buffer them in Dragonfly and fan them out to
+/// followers. Owns the handle, so the generation stays registered until its
+/// last event is buffered.
+pub async fn pump(handle: GenerationHandle, cache: ConnectionManager, mut rx: mpsc::Receiver<StreamEvent>) {
+    let key = buffer_key(&handle.user_id, &handle.id);
+    let mut next_id = 1;
+    while let Some(event) = rx.recv().await {
+        let numbered = NumberedEvent { id: next_id, event };
+        next_id += 1;
+        match serde_json::to_string(&numbered) {
+            Ok(json) => {
+                if let Err(e) = CacheService::push_stream_event(&cache, &key, &json, BUFFER_TTL_SECS).await {
+                    tracing::warn!("Failed to buffer stream event {} of {}: {}", numbered.id, handle.id, e);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to serialize stream event: {}", e),
+        }
+        // Nobody following right now is fine; they replay from the buffer
+        let _ = handle.live.send(numbered);
+    }
+    if let Err(e) = CacheService::expire(&cache, &key, REPLAY_RETENTION_SECS).await {
+        tracing::warn!("Failed to shorten replay buffer of {}: {}", handle.id, e);
+    }
+}
+
+/// Events of generation `id` after `last_event_id`: the buffered ones, then
+/// live ones until the generation ends. `None` when the generation isn't
+/// running and nothing after `last_event_id` is buffered.
+pub async fn follow(
+    registry: &Arc<GenerationRegistry>,
+    cache: &ConnectionManager,
+    id: &str,
+    user_id: &str,
+    last_event_id: u64,
+) -> Option<mpsc::Receiver<NumberedEvent>> {
+    // Subscribe before reading the buffer so nothing falls in between
+    let watch = registry.watch(id, user_id);
+    let key = buffer_key(user_id, id);
+    let missed = buffered(cache, &key, last_event_id).await;
+    if watch.is_none() && missed.is_empty() {
+        return None;
+    }
+
+    let (tx, rx) = mpsc::channel::<NumberedEvent>(100);
+    let cache = cache.clone();
+    tokio::spawn(async move {
+        let mut last = last_event_id;
+        if !forward(&tx, missed, &mut last).await {
+            return;
+        }
+        let Some(mut watch) = watch else { return };
+        loop {
+            let received = tokio::select! {
+                received = watch.live.recv() => received,
+                _ = tx.closed() => return,
+            };
+            let events = match received {
+                Ok(event) => vec![event],
+                // Fell behind the live channel, or the generation ended:
+                // pick up whatever we haven't sent from the buffer
+                Err(broadcast::error::RecvError::Lagged(_)) => buffered(&cache, &key, last).await,
+                Err(broadcast::error::RecvError::Closed) => {
+                    let tail = buffered(&cache, &key, last).await;
+                    forward(&tx, tail, &mut last).await;
+                    return;
+                }
+            };
+            if !forward(&tx, events, &mut last).await {
+                return;
+            }
+        }
+    });
+    Some(rx)
+}
+
+/// Send the events after `last`, in order. False once the follower is gone.
+async fn forward(tx: &mpsc::Sender<NumberedEvent>, events: Vec<NumberedEvent>, last: &mut u64) -> bool {
+    for event in events {
+        if event.id <= *last {
+            continue;
+        }
+        *last = event.id;
+        if tx.send(event).await.is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Buffered events after `last_event_id` (event `n` sits at list index `n - 1`)
+async fn buffered(cache: &ConnectionManager, key: &str, last_event_id: u64) -> Vec<NumberedEvent> {
+    match CacheService::get_stream_events(cache, key, last_event_id as usize).await {
+        Ok(items) => items.iter().filter_map(|s| serde_json::from_str(s).ok()).collect(),
+        Err(e) => {
+            tracing::warn!("Failed to read replay buffer {}: {}", key, e);
+            Vec::new()
+        }
+    }
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod registry_tests {
+        use super::*;
+
+        fn registry(grace_secs: u64) -> Arc<GenerationRegistry> {
+            Arc::new(GenerationRegistry::new(Duration::from_secs(grace_secs)))
+        }
+
+        #[test]
+        fn stop_cancels_only_the_owners_generation() {
+            let registry = registry(0);
+            let handle = registry.start("alice", "chat-1");
+            assert!(!registry.stop(&handle.id, "bob"));
+            assert!(!handle.token.is_cancelled());
+            assert!(registry.stop(&handle.id, "alice"));
+            assert!(handle.token.is_cancelled());
+        }
+
+        #[test]
+        fn stop_chat_cancels_that_chats_generations() {
+            let registry = registry(0);
+            let first = registry.start("alice", "chat-1");
+            let second = registry.start("alice", "chat-1");
+            let other_chat = registry.start("alice", "chat-2");
+            let other_user = registry.start("bob", "chat-1");
+            assert_eq!(registry.stop_chat("chat-1", "alice"), 2);
+            assert!(first.token.is_cancelled() && second.token.is_cancelled());
+            assert!(!other_chat.token.is_cancelled());
+            assert!(!other_user.token.is_cancelled());
+        }
+
+        #[test]
+        fn dropping_the_handle_deregisters() {
+            let registry = registry(0);
+            let handle = registry.start("alice", "chat-1");
+            let id = handle.id.clone();
+            drop(handle);
+            assert!(registry.lock().is_empty());
+            assert!(!registry.stop(&id, "alice"));
+            assert!(registry.watch(&id, "alice").is_none());
+        }
+
+        #[test]
+        fn only_the_owner_can_watch() {
+            let registry = registry(0);
+            let handle = registry.start("alice", "chat-1");
+            assert!(registry.watch(&handle.id, "bob").is_none());
+            assert!(registry.watch(&handle.id, "alice").is_some());
+        }
+
+        #[test]
+        fn last_watcher_leaving_stops_without_grace() {
+            let registry = registry(0);
+            let handle = registry.start("alice", "chat-1");
+            let first = registry.watch(&handle.id, "alice").unwrap();
+            let second = registry.watch(&handle.id, "alice").unwrap();
+            drop(first);
+            assert!(!handle.token.is_cancelled());
+            drop(second);
+            assert!(handle.token.is_cancelled());
+        }
+
+        #[tokio::test]
+        async fn grace_period_leaves_room_to_resume() {
+            let registry = registry(60);
+            let handle = registry.start("alice", "chat-1");
+            drop(registry.watch(&handle.id, "alice").unwrap());
+            tokio::task::yield_now().await;
+            assert!(!handle.token.is_cancelled());
+            let _resumed = registry.watch(&handle.id, "alice").unwrap();
+            assert_eq!(registry.lock()[&handle.id].watchers, 1);
+        }
+
+        #[tokio::test]
+        async fn live_events_reach_watchers() {
+            let registry = registry(0);
+            let handle = registry.start("alice", "chat-1");
+            let mut watch = registry.watch(&handle.id, "alice").unwrap();
+            handle.live.send(NumberedEvent { id: 1, event: StreamEvent::ThinkingStart }).unwrap();
+            assert_eq!(watch.live.recv().await.unwrap().id, 1);
+        }
+    }
+
+    mod replay_tests {
+        use super::*;
+
+        fn numbered(id: u64) -> NumberedEvent {
+            NumberedEvent { id, event: StreamEvent::Content { content: id.to_string(), persona_id: None } }
+        }
+
+        #[tokio::test]
+        async fn forward_skips_what_was_already_sent() {
+            let (tx, mut rx) = mpsc::channel(10);
+            let mut last = 2;
+            assert!(forward(&tx, vec![numbered(1), numbered(2), numbered(3), numbered(3), numbered(4)], &mut last).await);
+            drop(tx);
+            let mut ids = Vec::new();
+            while let Some(event) = rx.recv().await {
+                ids.push(event.id);
+            }
+            assert_eq!(ids, vec![3, 4]);
+            assert_eq!(last, 4);
+        }
+
+        #[test]
+        fn numbered_events_keep_the_stream_shape() {
+            let json = serde_json::to_string(&numbered(7)).unwrap();
+            assert_eq!(json, r#"{"id":7,"event":{"type":"content","content":"7"}}"#);
+            let back: NumberedEvent = serde_json::from_str(&json).unwrap();
+            assert_eq!(back.id, 7);
+        }
+    }
+}
@@ -0,0 +1,398 @@
+//! Turn-taking for group chats
+//!
+//! A chat whose [`ChatCast`] lists two or more AI personas answers each user
+//! message with one or more of them, chosen by the cast's [`TurnPolicy`]:
+//!
+//! - `round_robin`: cast order, continuing after the persona that spoke last
+//! - `mention`: the personas addressed as `@Name`, in the order they appear
+//! - `moderator`: the chat's model reads the conversation and picks speakers
+//!
+//! Mentions and moderator picks fall back to round-robin when they name
+//! nobody. Each speaker then sees the other personas' replies as bracketed
+//! `[Name]: ...` user turns (see [`perspective`]), so it answers as itself.
+
+use crate::llm::{InferenceOptions, LLMService};
+use crate::models::{ChatCast, ChatMessage, OllamaMessage, Persona, TurnPolicy};
+use crate::retrieval::truncate_chars;
+use anyhow::Result;
+use serde::Deserialize;
+
+/// Largest cast a chat may have
+pub const MAX_CAST: usize = 8;
+/// Recent messages shown to the moderator
+const MODERATOR_HISTORY: usize = 8;
+/// Characters kept from each message in the moderator prompt
+const MODERATOR_MESSAGE_CHARS: usize = 300;
+
+/// Normalise a requested cast: trims and dedupes persona IDs and clamps
+/// `speakers_per_turn` to the cast size
+pub fn validate_cast(cast: &ChatCast) -> Result<ChatCast, String> {
+    let mut persona_ids: Vec<String> = Vec::new();
+    for id in &cast.persona_ids {
+        let id = id.trim();
+        if !id.is_empty() && !persona_ids.iter().any(|p| p == id) {
+            persona_ids.push(id.to_string());
+        }
+    }
+    if persona_ids.is_empty() {
+        return Err("Cast needs at least one persona".to_string());
+    }
+    if persona_ids.len() > MAX_CAST {
+        return Err(format!("Cast can have at most {} personas", MAX_CAST));
+    }
+    if cast.speakers_per_turn == 0 {
+        return Err("speakers_per_turn must be at least 1".to_string());
+    }
+    Ok(ChatCast {
+        speakers_per_turn: cast.speakers_per_turn.min(persona_ids.len()),
+        persona_ids,
+        turn_policy: cast.turn_policy,
+    })
+}
+
+/// Pick the cast members (indices into `members`) that answer `message`
+pub async fn select_speakers(
+    llm: &LLMService,
+    model: &str,
+    cast: &ChatCast,
+    members: &[Persona],
+    history: &[ChatMessage],
+    message: &str,
+) -> Vec<usize> {
+    let count = cast.speakers_per_turn.clamp(1, members.len().max(1));
+    let picked = match cast.turn_policy {
+        TurnPolicy::RoundRobin => Vec::new(),
+        TurnPolicy::Mention => mentioned(message, members),
+        TurnPolicy::Moderator => match moderate(llm, model, members, history, message, count).await {
+            Ok(picked) => picked,
+            Err(e) => {
+                tracing::warn!("👥 Moderator failed, falling back to round-robin: {}", e);
+                Vec::new()
+            }
+        },
+    };
+    if picked.is_empty() {
+        next_round_robin(history, members, count)
+    } else {
+        picked
+    }
+}
+
+/// Members addressed as `@Name` (case-insensitive), in order of appearance
+pub fn mentioned(message: &str, members: &[Persona]) -> Vec<usize> {
+    let lower = message.to_lowercase();
+    let mut hits: Vec<(usize, usize)> = Vec::new();
+    for (i, member) in members.iter().enumerate() {
+        let needle = format!("@{}", member.name.to_lowercase());
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let found = lower.match_indices(&needle).find(|(pos, _)| {
+            lower[..*pos].chars().next_back().is_none_or(|c| !is_word(c))
+                && lower[pos + needle.len()..].chars().next().is_none_or(|c| !is_word(c))
+        });
+        if let Some((pos, _)) = found {
+            hits.push((pos, i));
+        }
+    }
+    hits.sort();
+    hits.into_iter().map(|(_, i)| i).collect()
+}
+
+/// The `count` members after the last one that spoke on this branch
+pub fn next_round_robin(history: &[ChatMessage], members: &[Persona], count: usize) -> Vec<usize> {
+    if members.is_empty() {
+        return Vec::new();
+    }
+    let start = history
+        .iter()
+        .rev()
+        .filter(|m| m.role == "assistant")
+        .find_map(|m| {
+            let id = m.ai_persona.as_deref()?;
+            members.iter().position(|p| p.id == id)
+        })
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    (0..count.min(members.len()))
+        .map(|offset| (start + offset) % members.len())
+        .collect()
+}
+
+/// Ask the model which members should answer
+async fn moderate(
+    llm: &LLMService,
+    model: &str,
+    members: &[Persona],
+    history: &[ChatMessage],
+    message: &str,
+    count: usize,
+) -> Result<Vec<usize>> {
+    let prompt = build_moderator_prompt(members, history, message, count);
+    let options = InferenceOptions { temperature: Some(0.0), max_tokens: Some(100) };
+    let raw = llm
+        .provider
+        .infer_with_options(model, vec![OllamaMessage { role: "user".to_string(), content: prompt, tool_calls: None }], &options)
+        .await?;
+    Ok(parse_moderator_reply(&raw, members, count))
+}
+
+fn build_moderator_prompt(members: &[Persona], history: &[ChatMessage], message: &str, count: usize) -> String {
+    let roster = members
+        .iter()
+        .map(|p| {
+            if p.description.is_empty() {
+                format!("- {}", p.name)
+            } else {
+                format!("- {}: {}", p.name, truncate_chars(&p.description, 200))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let recent = history[history.len().saturating_sub(MODERATOR_HISTORY)..]
+        .iter()
+        .map(|m| format!("{}: {}", speaker_label(m, members), truncate_chars(&m.content, MODERATOR_MESSAGE_CHARS)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "You moderate a group conversation between a user and these AI participants:\n{}\n\n\
+         Recent conversation:\n{}\n\nUser: {}\n\n\
+         Choose up to {} participant(s) who should reply next, best suited first.\n\
+         Respond with JSON only: {{\"speakers\": [\"Name\"]}}",
+        roster,
+        if recent.is_empty() { "(none)".to_string() } else { recent },
+        truncate_chars(message, MODERATOR_MESSAGE_CHARS),
+        count
+    )
+}
+
+#[derive(Deserialize)]
+struct ModeratorReply {
+    #[serde(default)]
+    speakers: Vec<String>,
+}
+
+/// Map the moderator's names back to members, tolerating chatter around the JSON
+fn parse_moderator_reply(raw: &str, members: &[Persona], count: usize) -> Vec<usize> {
+    let names: Vec<String> = match (raw.find('{'), raw.rfind('}')) {
+        (Some(start), Some(end)) if end > start => serde_json::from_str::<ModeratorReply>(&raw[start..=end])
+            .map(|r| r.speakers)
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    let mut picked: Vec<usize> = Vec::new();
+    for name in names {
+        let name = name.trim().trim_start_matches('@');
+        if let Some(i) = members.iter().position(|p| p.name.eq_ignore_ascii_case(name) || p.id == name) {
+            if !picked.contains(&i) {
+                picked.push(i);
+            }
+        }
+    }
+    picked.truncate(count);
+    picked
+}
+
+fn speaker_label(message: &ChatMessage, members: &[Persona]) -> String {
+    if message.role != "assistant" {
+        return "User".to_string();
+    }
+    message
+        .ai_persona
+        .as_deref()
+        .and_then(|id| members.iter().find(|p| p.id == id))
+        .map(|p| p.name.clone())
+        .unwrap_or_else(|| "Assistant".to_string())
+}
+
+/// System prompt addendum telling `speaker` who else is in the room
+pub fn group_note(speaker: &Persona, members: &[Persona]) -> String {
+    let others = members
+        .iter()
+        .filter(|p| p.id != speaker.id)
+        .map(|p| p.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "[Group conversation:]\nYou are {} in a conversation with the user and {}. \
+         Messages from the other participants are prefixed with their name in brackets. \
+         Reply only as {}, in your own voice; do not write lines for anyone else.",
+        speaker.name, others, speaker.name
+    )
+}
+
+/// History as `speaker` sees it: its own replies stay assistant turns, the
+/// other personas' replies become `[Name]: ...` user turns
+pub fn perspective(history: &[ChatMessage], speaker_id: &str, members: &[Persona]) -> Vec<ChatMessage> {
+    history
+        .iter()
+        .map(|m| {
+            if m.role != "assistant" || m.ai_persona.as_deref() == Some(speaker_id) {
+                return m.clone();
+            }
+            ChatMessage {
+                role: "user".to_string(),
+                content: format!("[{}]: {}", speaker_label(m, members), m.content),
+                ..m.clone()
+            }
+        })
+        .collect()
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn persona(id: &str, name: &str) -> Persona {
+        Persona {
+            id: id.to_string(),
+            name: name.to_string(),
+            persona_type: "ai".to_string(),
+            description: String::new(),
+            avatar: None,
+            bubble_color: None,
+            system_prompt: None,
+            global_memory_enabled: true,
+            current_mood: None,
+            voice: None,
+            llm_provider: None,
+            reranker: None,
+            user_id: None,
+            metadata: Default::default(),
+            tags: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    fn cast_members() -> Vec<Persona> {
+        vec![persona("azera", "Azera"), persona("areza", "Areza"), persona("nova", "Nova")]
+    }
+
+    fn msg(role: &str, persona: Option<&str>, content: &str) -> ChatMessage {
+        ChatMessage {
+            id: content.to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: None,
+            user_persona: None,
+            ai_persona: persona.map(str::to_string),
+            model: None,
+            mood: None,
+            interrupted: false,
+        }
+    }
+
+    mod cast_tests {
+        use super::*;
+
+        fn cast(ids: &[&str], speakers_per_turn: usize) -> ChatCast {
+            ChatCast {
+                persona_ids: ids.iter().map(|s| s.to_string()).collect(),
+                turn_policy: TurnPolicy::Mention,
+                speakers_per_turn,
+            }
+        }
+
+        #[test]
+        fn dedupes_and_clamps_speakers() {
+            let cast = validate_cast(&cast(&["azera", " areza ", "azera", ""], 5)).unwrap();
+            assert_eq!(cast.persona_ids, vec!["azera", "areza"]);
+            assert_eq!(cast.speakers_per_turn, 2);
+            assert_eq!(cast.turn_policy, TurnPolicy::Mention);
+        }
+
+        #[test]
+        fn rejects_empty_oversized_or_silent_casts() {
+            assert!(validate_cast(&cast(&[" "], 1)).is_err());
+            assert!(validate_cast(&cast(&["a", "b", "c", "d", "e", "f", "g", "h", "i"], 1)).is_err());
+            assert!(validate_cast(&cast(&["a", "b"], 0)).is_err());
+        }
+
+        #[test]
+        fn policy_defaults_to_round_robin() {
+            let cast: ChatCast = serde_json::from_str(r#"{"persona_ids": ["a", "b"]}"#).unwrap();
+            assert_eq!(cast.turn_policy, TurnPolicy::RoundRobin);
+            assert_eq!(cast.speakers_per_turn, 1);
+            let json = serde_json::to_string(&ChatCast { turn_policy: TurnPolicy::Moderator, ..cast }).unwrap();
+            assert!(json.contains("\"turn_policy\":\"moderator\""));
+        }
+    }
+
+    mod turn_tests {
+        use super::*;
+
+        #[test]
+        fn mentions_in_order_of_appearance() {
+            let members = cast_members();
+            assert_eq!(mentioned("@nova and @AZERA, what do you think?", &members), vec![2, 0]);
+            assert_eq!(mentioned("email me at x@azera.dev", &members), Vec::<usize>::new());
+            assert!(mentioned("@Azerath is not a member", &members).is_empty());
+        }
+
+        #[test]
+        fn round_robin_continues_after_last_speaker() {
+            let members = cast_members();
+            assert_eq!(next_round_robin(&[], &members, 1), vec![0]);
+            let history = vec![msg("user", None, "hi"), msg("assistant", Some("areza"), "hello")];
+            assert_eq!(next_round_robin(&history, &members, 1), vec![2]);
+            assert_eq!(next_round_robin(&history, &members, 2), vec![2, 0]);
+        }
+
+        #[test]
+        fn round_robin_ignores_personas_outside_the_cast() {
+            let members = cast_members();
+            let history = vec![msg("assistant", Some("nova"), "a"), msg("assistant", Some("ghost"), "b")];
+            assert_eq!(next_round_robin(&history, &members, 1), vec![0]);
+        }
+
+        #[test]
+        fn moderator_reply_maps_names() {
+            let members = cast_members();
+            let raw = "Sure:\n```json\n{\"speakers\": [\"@Nova\", \"azera\", \"Nova\", \"Zed\"]}\n```";
+            assert_eq!(parse_moderator_reply(raw, &members, 3), vec![2, 0]);
+            assert_eq!(parse_moderator_reply(raw, &members, 1), vec![2]);
+            assert!(parse_moderator_reply("Nova should answer", &members, 1).is_empty());
+        }
+
+        #[test]
+        fn moderator_prompt_lists_cast_and_recent_turns() {
+            let mut members = cast_members();
+            members[1].description = "A skeptic".to_string();
+            let history = vec![msg("user", None, "hi"), msg("assistant", Some("azera"), "hello")];
+            let prompt = build_moderator_prompt(&members, &history, "next?", 1);
+            assert!(prompt.contains("- Areza: A skeptic"));
+            assert!(prompt.contains("User: hi\nAzera: hello"));
+            assert!(prompt.contains("User: next?"));
+        }
+    }
+
+    mod perspective_tests {
+        use super::*;
+
+        #[test]
+        fn other_personas_become_named_user_turns() {
+            let members = cast_members();
+            let history = vec![
+                msg("user", None, "hi"),
+                msg("assistant", Some("azera"), "hello"),
+                msg("assistant", Some("areza"), "hey"),
+            ];
+            let seen = perspective(&history, "areza", &members);
+            assert_eq!(seen[0].role, "user");
+            assert_eq!(seen[1].role, "user");
+            assert_eq!(seen[1].content, "[Azera]: hello");
+            assert_eq!(seen[2].role, "assistant");
+            assert_eq!(seen[2].content, "hey");
+        }
+
+        #[test]
+        fn note_names_the_other_participants() {
+            let members = cast_members();
+            let note = group_note(&members[0], &members);
+            assert!(note.contains("You are Azera in a conversation with the user and Areza, Nova."));
+        }
+    }
+}
@@ -1,4 +1,7 @@
 use crate::*;
+use crate::auth::{self, AuthUser};
+use crate::error::ApiError;
+use crate::components::DEFAULT_PERSONA_ID;
 use axum::{
     body::Body,
     extract::{Multipart, Path, State},
@@ -18,7 +21,7 @@ use tokio_stream::wrappers::ReceiverStream;
 
 /// Pattern: [IMAGE_GEN: prompt="...", name="..."]
 /// Returns: Vec<(prompt, custom_name)>
-fn extract_image_gen_requests(text: &str) -> Vec<(String, Option<String>)> {
+pub fn extract_image_gen_requests(text: &str) -> Vec<(String, Option<String>)> {
     let mut results = Vec::new();
     
     // Pattern to match [IMAGE_GEN: prompt="...", name="..."] or [IMAGE_GEN: prompt="..."]
@@ -36,12 +39,16 @@ fn extract_image_gen_requests(text: &str) -> Vec<(String, Option<String>)> {
     results
 }
 
-/// Trigger async image generation from chat
-async fn trigger_image_generation(
+/// Trigger async image generation from chat. The image is recorded
+/// against `chat_id` so it is deleted with the chat.
+pub async fn trigger_image_generation(
     prompt: &str,
     custom_name: Option<&str>,
     persona_id: Option<&str>,
     db: &sqlx::Pool<sqlx::Postgres>,
+    events: &Arc<events::EventBus>,
+    user_id: &str,
+    chat_id: &str,
 ) {
     tracing::info!("🎨 Triggering image generation from chat: {}", prompt);
     
@@ -91,18 +98,42 @@ async fn trigger_image_generation(
             prompt_preview
         );
         
-        let file_path = canvas_dir.join(filename.replace(".png", ".svg"));
+        let filename = match claim_canvas_name(db, filename.replace(".png", ".svg"), &image_id, user_id, prompt, persona_id, persona_name.as_deref()).await {
+            Ok(filename) => filename,
+            Err(e) => {
+                tracing::error!("🎨 Failed to record image owner: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = crate::db::record_chat_image(db, chat_id, &filename).await {
+            tracing::warn!("🎨 Failed to record chat image: {}", e);
+        }
+        let file_path = canvas_dir.join(&filename);
         if let Err(e) = tokio::fs::write(&file_path, &placeholder_svg).await {
             tracing::error!("🎨 Failed to save placeholder: {}", e);
         } else {
             tracing::info!("🎨 Created placeholder image: {:?}", file_path);
+            events.publish_to(user_id, image_generated(&filename, prompt, persona_id));
         }
         return;
     }
     
     // Real image generation (async fire-and-forget)
+    let filename = match claim_canvas_name(db, filename, &image_id, user_id, prompt, persona_id, persona_name.as_deref()).await {
+        Ok(filename) => filename,
+        Err(e) => {
+            tracing::error!("🎨 Failed to record image owner: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = crate::db::record_chat_image(db, chat_id, &filename).await {
+        tracing::warn!("🎨 Failed to record chat image: {}", e);
+    }
     let host = image_gen_url.unwrap();
     let prompt_owned = prompt.to_string();
+    let persona_owned = persona_id.map(str::to_string);
+    let events = events.clone();
+    let user_id = user_id.to_string();
     
     tokio::spawn(async move {
         let client = reqwest::Client::builder()
@@ -136,6 +167,7 @@ async fn trigger_image_generation(
                                     tracing::error!("🎨 Failed to save image: {}", e);
                                 } else {
                                     tracing::info!("🎨 Generated image from chat: {}", filename);
+                                    events.publish_to(&user_id, image_generated(&filename, &prompt_owned, persona_owned.as_deref()));
                                 }
                             }
                         }
@@ -152,489 +184,391 @@ async fn trigger_image_generation(
     });
 }
 
-// ============================================================
-// Chat Endpoints
-// ============================================================
-
-/// POST /api/chat - Send a message with SSE streaming response
-pub async fn handle_chat_stream(
-    State(state): State<AppState>,
-    Json(payload): Json<models::ChatRequest>,
-) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
-    tracing::info!("💬 Streaming chat request: {}", payload.message);
+/// Claim `filename` in the canvas for `user_id`. A name another user already
+/// owns (a reused custom name) gets the image ID appended instead, so nobody
+/// can overwrite someone else's image.
+async fn claim_canvas_name(
+    db: &sqlx::Pool<sqlx::Postgres>,
+    filename: String,
+    image_id: &str,
+    user_id: &str,
+    prompt: &str,
+    persona_id: Option<&str>,
+    persona_name: Option<&str>,
+) -> anyhow::Result<String> {
+    if crate::db::claim_image(db, crate::db::IMAGE_GENERATED, &filename, user_id, Some(prompt), persona_id, persona_name).await? {
+        return Ok(filename);
+    }
+    let path = std::path::Path::new(&filename);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("png");
+    let unique = format!("{}_{}.{}", stem, &image_id[..8], ext);
+    if crate::db::claim_image(db, crate::db::IMAGE_GENERATED, &unique, user_id, Some(prompt), persona_id, persona_name).await? {
+        Ok(unique)
+    } else {
+        Err(anyhow::anyhow!("Image name {} is taken", unique))
+    }
+}
 
-    let (tx, rx) = mpsc::channel::<models::StreamEvent>(100);
-    
-    // Clone what we need for the async task
-    let ollama_host = state.ollama_host.clone();
-    let db = state.db.clone();
-    let agent = state.agent.clone();
-    let message = payload.message.clone();
-    let model = payload.model.clone();
-    let chat_id = payload.chat_id.clone();
-    let branch_id = payload.branch_id.clone();
-    let user_persona_id = payload.user_persona_id.clone();
-    let ai_persona_id = payload.ai_persona_id.clone();
-    let qdrant_url = state.qdrant_url.clone();
-    let meili_url = state.meili_url.clone();
-    let meili_key = state.meili_key.clone();
-    let cache = state.cache.clone();
+/// The image at `filename` in `dir` if `user` owns it. Unknown and foreign
+/// images are both "not found".
+async fn owned_image_path(
+    state: &AppState,
+    user: &AuthUser,
+    kind: &str,
+    dir: &str,
+    filename: &str,
+) -> Result<std::path::PathBuf, ApiError> {
+    let safe_filename = std::path::Path::new(filename)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or(ApiError::BadRequest("Invalid filename".to_string()))?;
 
-    // Spawn task to handle LLM inference
-    tokio::spawn(async move {
-        // Update agent state & Dragonfly: mark active
-        {
-            let mut agent_guard = agent.write().await;
-            // Restore mood from Dragonfly if available, otherwise keep current
-            if let Ok(Some(cached_state)) = cache::CacheService::get_mental_state(&cache).await {
-                agent_guard.mental_state.mood = cached_state.mood;
-                agent_guard.mental_state.energy = cached_state.energy;
-                agent_guard.mental_state.focus_level = cached_state.focus_level;
-            }
-            // Boost focus when actively processing
-            agent_guard.mental_state.focus_level = 0.9;
-            agent_guard.mental_state.last_active = chrono::Utc::now();
-        }
-
-        // Get AI persona's system prompt and global memory setting if specified
-        let (system_prompt, global_memory_enabled) = if let Some(ref persona_id) = ai_persona_id {
-            match db::get_persona(&db, persona_id).await {
-                Ok(Some(persona)) => (
-                    persona.system_prompt.unwrap_or_else(default_system_prompt),
-                    persona.global_memory_enabled,
-                ),
-                _ => (default_system_prompt(), true),
-            }
-        } else {
-            (default_system_prompt(), true)
-        };
+    let owner = db::image_owner(&state.db, kind, safe_filename)
+        .await
+        .map_err(|e| ApiError::from(e).context("Failed to look up image"))?;
+    let file_path = std::path::PathBuf::from(dir).join(safe_filename);
+    if owner.as_deref() != Some(user.id.as_str()) || !file_path.exists() {
+        return Err(ApiError::NotFound(format!("Image not found: {}", safe_filename)));
+    }
+    Ok(file_path)
+}
 
-        // Get chat history for context
-        let history = match db::get_chat(&db, &chat_id).await {
-            Ok(Some(chat)) => {
-                chat.branches
-                    .iter()
-                    .find(|b| b.id == branch_id)
-                    .map(|b| b.messages.clone())
-                    .unwrap_or_default()
-            }
-            _ => vec![],
-        };
+fn image_generated(filename: &str, prompt: &str, persona_id: Option<&str>) -> events::AgentEvent {
+    events::AgentEvent::ImageGenerated {
+        filename: filename.to_string(),
+        url: format!("/api/images/{}", filename),
+        prompt: prompt.to_string(),
+        persona_id: persona_id.map(str::to_string),
+    }
+}
 
-        // Ensure chat and branch exist in database before saving messages
-        if let Err(e) = db::ensure_chat_and_branch(&db, &chat_id, &branch_id, None).await {
-            tracing::error!("Failed to ensure chat/branch exists: {}", e);
-        }
+// ============================================================
+// Auth Endpoints
+// ============================================================
 
-        // ── Hybrid RAG: Qdrant (semantic) + Meilisearch (lexical) ──
-        // If global_memory_enabled, search persona's memories across ALL chats
-        // Combines vector similarity with keyword relevance for stronger recall
-        tracing::info!("🧠 RAG check: persona_id={:?}, global_memory_enabled={}", ai_persona_id, global_memory_enabled);
-        
-        // Load session context from Dragonfly (working memory)
-        let session_ctx = cache::CacheService::get_session(&cache, &chat_id).await.ok().flatten();
-        
-        let memory_context = if global_memory_enabled {
-            let vector_service = vector::VectorService::new(qdrant_url.clone());
-            
-            // 1️⃣ Qdrant — semantic search (meaning)
-            // Filter by persona AND exclude memories from this exact chat to avoid echo
-            let filter = {
-                let mut must_clauses = Vec::new();
-                if let Some(ref pid) = ai_persona_id {
-                    must_clauses.push(serde_json::json!(
-                        { "key": "ai_persona_id", "match": { "value": pid } }
-                    ));
-                }
-                let mut must_not_clauses = Vec::new();
-                // Exclude memories from the current chat to avoid echo/parroting
-                must_not_clauses.push(serde_json::json!(
-                    { "key": "chat_id", "match": { "value": chat_id.clone() } }
-                ));
-                let mut filter_obj = serde_json::json!({});
-                if !must_clauses.is_empty() {
-                    filter_obj["must"] = serde_json::json!(must_clauses);
-                }
-                filter_obj["must_not"] = serde_json::json!(must_not_clauses);
-                Some(filter_obj)
-            };
-            
-            let semantic_results = match vector::search_memories_with_filter_cached(
-                &vector_service,
-                &ollama_host,
-                &cache,
-                "azera_memory",
-                &message,
-                10,
-                filter,
-            ).await {
-                Ok(results) => results,
-                Err(e) => {
-                    tracing::warn!("🧠 Qdrant RAG search failed: {}", e);
-                    Vec::new()
-                }
-            };
+/// Build the login response and its session cookie
+async fn start_session(state: &AppState, user: models::User) -> Result<Response, ApiError> {
+    let token = auth::new_session_token();
+    let expires_at = chrono::Utc::now() + chrono::Duration::days(auth::SESSION_TTL_DAYS);
 
-            // 2️⃣ Meilisearch — lexical search (words + exact matches)
-            // Filter by persona to prevent cross-persona memory leakage
-            let lexical_results = meili_search_memories(&meili_url, &meili_key, &message, None, ai_persona_id.as_deref(), 10).await;
-            let lexical_chats = meili_search_chats_for_rag(&meili_url, &meili_key, &message, ai_persona_id.as_deref(), 5).await;
-
-            // 3️⃣ Merge & deduplicate (with quality filters)
-            let mut context_parts: Vec<String> = Vec::new();
-            let mut seen_content = std::collections::HashSet::new();
-            let now = chrono::Utc::now();
-
-            // Semantic memories (highest priority — meaning matches)
-            // Filter: score >= 0.45, not from last 60 seconds (avoid echo)
-            for r in &semantic_results {
-                // Skip low-confidence results
-                if r.score < 0.45 {
-                    continue;
-                }
-                // Skip very recent memories to avoid echo
-                if let Some(ts) = r.payload.get("timestamp").and_then(|v| v.as_str()) {
-                    if let Ok(mem_time) = chrono::DateTime::parse_from_rfc3339(ts) {
-                        if (now - mem_time.with_timezone(&chrono::Utc)).num_seconds() < 60 {
-                            continue;
-                        }
-                    }
-                }
-                let role = r.payload.get("role").and_then(|v| v.as_str()).unwrap_or("unknown");
-                if let Some(content) = r.payload.get("content").and_then(|v| v.as_str()) {
-                    let key = content.chars().take(100).collect::<String>();
-                    if seen_content.insert(key) {
-                        // Truncate to avoid overwhelming the context
-                        context_parts.push(format!("[semantic:{}] {}", role, &content[..content.len().min(400)]));
-                    }
-                }
-            }
+    if let Err(e) = db::create_session(&state.db, &auth::hash_token(&token), &user.id, expires_at).await {
+        tracing::error!("Failed to create session: {}", e);
+        return Err(ApiError::from(e).context("Failed to create session"));
+    }
 
-            // Lexical memories (dreams, journal, facts)
-            for hit in &lexical_results {
-                if let Some(content) = hit["content"].as_str() {
-                    let key = content.chars().take(100).collect::<String>();
-                    if seen_content.insert(key) {
-                        let mem_type = hit["memory_type"].as_str().unwrap_or("unknown");
-                        let title = hit["title"].as_str().unwrap_or("");
-                        context_parts.push(format!("[{}:{}] {}", mem_type, title, &content[..content.len().min(500)]));
-                    }
-                }
-            }
+    let body = models::AuthResponse { user, token: token.clone(), expires_at };
+    Ok((
+        [(axum::http::header::SET_COOKIE, auth::session_cookie(&token))],
+        Json(body),
+    ).into_response())
+}
 
-            // Lexical chat snippets (for keyword-exact matches)
-            // Exclude the current chat to avoid echo
-            for hit in &lexical_chats {
-                // Skip the current chat
-                if hit["id"].as_str() == Some(&chat_id) {
-                    continue;
-                }
-                if let Some(text) = hit["messages_text"].as_str() {
-                    let key = text.chars().take(100).collect::<String>();
-                    if seen_content.insert(key) {
-                        let title = hit["title"].as_str().unwrap_or("past chat");
-                        context_parts.push(format!("[chat:{}] {}", title, &text[..text.len().min(300)]));
-                    }
-                }
-            }
+/// POST /api/auth/register - Create an account and log in
+pub async fn register(
+    State(state): State<AppState>,
+    Json(payload): Json<models::RegisterRequest>,
+) -> Result<Response, ApiError> {
+    let username = payload.username.trim().to_string();
+    auth::validate_username(&username)?;
+    auth::validate_password(&payload.password)?;
+
+    let existing_users = db::count_users(&state.db).await.map_err(|e| {
+        tracing::error!("Failed to count users: {}", e);
+        ApiError::from(e).context("Failed to create account")
+    })?;
+    if !auth::registration_open(existing_users) {
+        return Err(ApiError::Forbidden("Registration is closed".to_string()));
+    }
 
-            if !context_parts.is_empty() {
-                tracing::info!("🧠 Hybrid RAG: {} semantic + {} lexical memories + {} chat matches",
-                    semantic_results.len(), lexical_results.len(), lexical_chats.len());
-                format!("\n\n[Relevant memories from past conversations:]\n{}", 
-                    context_parts.iter().enumerate()
-                        .map(|(i, m)| format!("{}. {}", i + 1, m))
-                        .collect::<Vec<_>>()
-                        .join("\n"))
-            } else {
-                tracing::debug!("🧠 No relevant memories found for persona {:?}", ai_persona_id);
-                String::new()
-            }
-        } else {
-            tracing::debug!("🧠 Global memory disabled for this persona");
-            String::new()
-        };
+    let password = payload.password;
+    let password_hash = tokio::task::spawn_blocking(move || auth::hash_password(&password))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Password hashing task failed: {}", e)))?
+        .map_err(ApiError::from)?;
 
-        // Inject session context from Dragonfly (working memory - recent conversation summary)
-        let session_context = if let Some(ref ctx) = session_ctx {
-            if !ctx.conversation_summary.is_empty() {
-                format!("\n\n[Current conversation context:]\nSummary: {}\nTopics: {}\nTurn: {}",
-                    ctx.conversation_summary,
-                    ctx.recent_topics.join(", "),
-                    ctx.turn_count)
-            } else {
-                String::new()
+    let user = models::User {
+        id: uuid::Uuid::new_v4().to_string(),
+        username,
+        display_name: payload.display_name.filter(|n| !n.trim().is_empty()),
+        created_at: chrono::Utc::now(),
+    };
+    if let Err(e) = db::create_user(&state.db, &user, &password_hash).await {
+        tracing::error!("Failed to create user: {}", e);
+        return Err(ApiError::from(e).context("Username is already taken"));
+    }
+    tracing::info!("👤 Registered user {}", user.username);
+
+    // The first account inherits everything created before accounts existed
+    if existing_users == 0 {
+        if let Err(e) = db::adopt_unowned_rows(&state.db, &user.id).await {
+            tracing::warn!("👤 Failed to adopt existing chats: {}", e);
+        }
+        let vector = state.vector.clone();
+        let user_id = user.id.clone();
+        tokio::spawn(async move {
+            let unowned_turns = json!({
+                "must": [
+                    { "is_empty": { "key": "user_id" } },
+                    { "key": "type", "match": { "value": "conversation" } }
+                ]
+            });
+            if let Err(e) = vector.set_payload("azera_memory", json!({ "user_id": user_id }), unowned_turns).await {
+                tracing::warn!("👤 Failed to adopt existing conversation memories: {}", e);
             }
-        } else {
-            String::new()
-        };
+        });
+    }
 
-        // Combine system prompt with memory context + session context
-        let enhanced_system_prompt = format!("{}{}{}", system_prompt, memory_context, session_context);
-
-        // Save user message
-        let user_msg_id = format!("msg_{}", uuid::Uuid::new_v4());
-        let user_msg = models::ChatMessage {
-            id: user_msg_id.clone(),
-            role: "user".to_string(),
-            content: message.clone(),
-            timestamp: Some(chrono::Utc::now()),
-            user_persona: user_persona_id.clone(),
-            ai_persona: ai_persona_id.clone(),
-            model: Some(model.clone()),
-            mood: None,
-        };
-        let _ = db::add_message_to_branch(&db, &user_msg, &branch_id).await;
+    start_session(&state, user).await
+}
 
-        // Build messages for Ollama with enhanced system prompt
-        let ollama_messages = llm::LLMService::build_messages(
-            &enhanced_system_prompt,
-            &history,
-            &message,
-        );
+/// POST /api/auth/login - Exchange username/password for a session
+pub async fn login(
+    State(state): State<AppState>,
+    Json(payload): Json<models::LoginRequest>,
+) -> Result<Response, ApiError> {
+    let invalid = || ApiError::Unauthorized("Invalid username or password".to_string());
 
-        // Call Ollama with streaming
-        let llm = llm::LLMService::new(ollama_host.clone());
-        match llm.infer_streaming(&model, ollama_messages, tx.clone()).await {
-            Ok(full_response) => {
-                // Infer mood from the AI's response using a quick LLM call
-                let mood = match llm.infer_mood(&model, &full_response).await {
-                    Ok(m) => {
-                        tracing::info!("🎭 AI persona mood inferred: {}", m);
-                        
-                        // Sync mood to Dragonfly (working memory) → agent state syncs on tick
-                        let mood_value = match m.as_str() {
-                            "happy" => 0.85, "excited" => 0.9,
-                            "content" => 0.7, "calm" => 0.65,
-                            "curious" => 0.75, "thoughtful" => 0.6,
-                            "melancholy" => 0.3, "concerned" => 0.4,
-                            _ => 0.6,
-                        };
-                        // Energy decreases slightly per exchange
-                        let _ = cache::CacheService::update_mood(&cache, mood_value, &m, -0.03).await;
-                        
-                        // Also sync to agent state immediately for responsiveness
-                        {
-                            let mut agent_guard = agent.write().await;
-                            agent_guard.mental_state.mood = mood_value;
-                            agent_guard.mental_state.energy = (agent_guard.mental_state.energy - 0.03).clamp(0.0, 1.0);
-                        }
-                        
-                        Some(m)
-                    }
-                    Err(e) => {
-                        tracing::warn!("🎭 Mood inference failed: {}, defaulting to 'content'", e);
-                        Some("content".to_string())
-                    }
-                };
-
-                // If global memory is enabled, update the persona's mood in the database
-                if global_memory_enabled {
-                    if let Some(ref persona_id) = ai_persona_id {
-                        if let Some(ref current_mood) = mood {
-                            let mood_update = models::UpdatePersonaRequest {
-                                name: None,
-                                description: None,
-                                avatar: None,
-                                bubble_color: None,
-                                system_prompt: None,
-                                global_memory_enabled: None,
-                                current_mood: Some(current_mood.clone()),
-                                voice: None,
-                                metadata: None,
-                                tags: None,
-                            };
-                            if let Err(e) = db::update_persona(&db, persona_id, &mood_update).await {
-                                tracing::warn!("🎭 Failed to update persona mood: {}", e);
-                            } else {
-                                tracing::debug!("🎭 Updated persona {} mood to {}", persona_id, current_mood);
-                            }
-                        }
-                    }
-                }
+    let (user, password_hash) = db::get_user_credentials(&state.db, payload.username.trim())
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load user: {}", e);
+            ApiError::from(e).context("Failed to log in")
+        })?
+        .ok_or_else(invalid)?;
 
-                // Save assistant message
-                let assistant_msg_id = format!("msg_{}", uuid::Uuid::new_v4());
-                let assistant_msg = models::ChatMessage {
-                    id: assistant_msg_id.clone(),
-                    role: "assistant".to_string(),
-                    content: full_response.clone(),
-                    timestamp: Some(chrono::Utc::now()),
-                    user_persona: user_persona_id,
-                    ai_persona: ai_persona_id.clone(),
-                    model: Some(model.clone()),
-                    mood: mood.clone(),
-                };
-                let _ = db::add_message_to_branch(&db, &assistant_msg, &branch_id).await;
-
-                // Re-index chat in Meilisearch with new messages
-                {
-                    let db2 = db.clone();
-                    let cid = chat_id.clone();
-                    let mu = meili_url.clone();
-                    let mk = meili_key.clone();
-                    tokio::spawn(async move {
-                        if let Ok(Some(chat)) = db::get_chat(&db2, &cid).await {
-                            meili_index_chat(&mu, &mk, &chat).await;
-                        }
-                    });
-                }
+    let password = payload.password;
+    let valid = tokio::task::spawn_blocking(move || auth::verify_password(&password, &password_hash))
+        .await
+        .map_err(|e| ApiError::Internal(format!("Password check task failed: {}", e)))?;
+    if !valid {
+        return Err(invalid());
+    }
 
-                // Store the conversation in vector DB for future RAG (with embedding cache)
-                {
-                    let vector_service = vector::VectorService::new(qdrant_url);
-                    
-                    // Store user message with AI persona context
-                    let user_mem_id = uuid::Uuid::new_v4().to_string();
-                    let mut user_metadata: std::collections::HashMap<String, serde_json::Value> = std::collections::HashMap::new();
-                    user_metadata.insert("role".to_string(), json!("user"));
-                    user_metadata.insert("chat_id".to_string(), json!(chat_id.clone()));
-                    user_metadata.insert("branch_id".to_string(), json!(branch_id.clone()));
-                    if let Some(ref persona_id) = ai_persona_id {
-                        user_metadata.insert("ai_persona_id".to_string(), json!(persona_id.clone()));
-                    }
-                    
-                    let user_request = vector::StoreMemoryRequest {
-                        collection: "azera_memory".to_string(),
-                        id: user_mem_id.clone(),
-                        content: message.clone(),
-                        memory_type: vector::MemoryType::Conversation,
-                        metadata: user_metadata,
-                    };
-                    
-                    match vector::store_memory_cached(
-                        &vector_service,
-                        &ollama_host,
-                        &cache,
-                        &user_request,
-                    ).await {
-                        Ok(_) => tracing::info!("🧠 Stored user message in memory for persona {:?}", ai_persona_id),
-                        Err(e) => tracing::warn!("🧠 Failed to store user memory: {}", e),
-                    }
+    tracing::info!("👤 {} logged in", user.username);
+    start_session(&state, user).await
+}
 
-                    // Store assistant response
-                    let assist_mem_id = uuid::Uuid::new_v4().to_string();
-                    let mut assist_metadata: std::collections::HashMap<String, serde_json::Value> = std::collections::HashMap::new();
-                    assist_metadata.insert("role".to_string(), json!("assistant"));
-                    assist_metadata.insert("chat_id".to_string(), json!(chat_id.clone()));
-                    assist_metadata.insert("branch_id".to_string(), json!(branch_id.clone()));
-                    if let Some(ref persona_id) = ai_persona_id {
-                        assist_metadata.insert("ai_persona_id".to_string(), json!(persona_id.clone()));
-                    }
-                    
-                    let assist_request = vector::StoreMemoryRequest {
-                        collection: "azera_memory".to_string(),
-                        id: assist_mem_id.clone(),
-                        content: full_response.clone(),
-                        memory_type: vector::MemoryType::Conversation,
-                        metadata: assist_metadata,
-                    };
-                    
-                    match vector::store_memory_cached(
-                        &vector_service,
-                        &ollama_host,
-                        &cache,
-                        &assist_request,
-                    ).await {
-                        Ok(_) => tracing::info!("🧠 Stored assistant response in memory for persona {:?}", ai_persona_id),
-                        Err(e) => tracing::warn!("🧠 Failed to store assistant memory: {}", e),
-                    }
-                }
+/// POST /api/auth/logout - End the current session
+pub async fn logout(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+) -> Result<Response, ApiError> {
+    if let Some(token) = auth::token_from_headers(&headers) {
+        if let Err(e) = db::delete_session(&state.db, &auth::hash_token(&token)).await {
+            tracing::error!("Failed to delete session: {}", e);
+            return Err(ApiError::from(e).context("Failed to log out"));
+        }
+    }
+    Ok((
+        [(axum::http::header::SET_COOKIE, auth::clear_session_cookie())],
+        StatusCode::NO_CONTENT,
+    ).into_response())
+}
 
-                // Update session context in Dragonfly (working memory)
-                {
-                    // Extract key topics from the exchange (simple heuristic: nouns/keywords)
-                    let topics: Vec<String> = message.split_whitespace()
-                        .filter(|w| w.len() > 4)
-                        .take(5)
-                        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
-                        .filter(|w| !w.is_empty())
-                        .collect();
-                    
-                    // Build a brief summary from last exchange
-                    let summary = format!(
-                        "User asked about: {}. Assistant responded regarding: {}.",
-                        &message[..message.len().min(200)],
-                        &full_response[..full_response.len().min(200)]
-                    );
-                    
-                    let _ = cache::CacheService::update_session_after_exchange(
-                        &cache,
-                        &chat_id,
-                        &message,
-                        &full_response,
-                        &summary,
-                        topics,
-                    ).await;
-                }
+/// GET /api/auth/me - The logged-in user
+pub async fn current_user(
+    State(state): State<AppState>,
+    user: AuthUser,
+) -> Result<Json<models::User>, ApiError> {
+    match db::get_user_credentials(&state.db, &user.username).await {
+        Ok(Some((user, _))) => Ok(Json(user)),
+        Ok(None) => Err(ApiError::Unauthorized("Account no longer exists".to_string())),
+        Err(e) => {
+            tracing::error!("Failed to load user: {}", e);
+            Err(ApiError::from(e).context("Failed to load user"))
+        }
+    }
+}
 
-                // Check for image generation requests in AI's response
-                // Pattern: [IMAGE_GEN: prompt="...", name="..."]
-                let image_requests = extract_image_gen_requests(&full_response);
-                for (img_prompt, custom_name) in image_requests {
-                    trigger_image_generation(
-                        &img_prompt,
-                        custom_name.as_deref(),
-                        ai_persona_id.as_deref(),
-                        &db,
-                    ).await;
-                }
+// ============================================================
+// Chat Endpoints
+// ============================================================
 
-                // Read latest mood/energy from Dragonfly for the Done event
-                let (done_mood_value, done_energy) = match cache::CacheService::get_mental_state(&cache).await {
-                    Ok(Some(ms)) => (Some(ms.mood), Some(ms.energy)),
-                    _ => (None, None),
-                };
-
-                // Send done event with mood + energy for frontend sync
-                let _ = tx.send(models::StreamEvent::Done {
-                    message_id: assistant_msg_id,
-                    mood,
-                    mood_value: done_mood_value,
-                    energy: done_energy,
-                }).await;
-            }
-            Err(e) => {
-                tracing::error!("❌ LLM inference failed: {}", e);
-                let _ = tx.send(models::StreamEvent::Error {
-                    message: format!("LLM inference failed: {}", e),
-                }).await;
-            }
+/// POST /api/chat - Send a message with SSE streaming response
+pub async fn handle_chat_stream(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Json(payload): Json<models::ChatRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    tracing::info!("💬 Streaming chat request: {}", payload.message);
+    let (_, events) = start_chat_turn(&state, &user.id, payload).await?;
+    Ok(numbered_sse(events))
+}
+
+/// Refuse client-supplied backend URLs the server must not call
+/// (see [`crate::llm::check_base_url`])
+async fn check_backend_urls(
+    state: &AppState,
+    provider: Option<&models::ProviderConfig>,
+    reranker: Option<&models::RerankerConfig>,
+) -> Result<(), ApiError> {
+    let urls = provider
+        .and_then(|p| p.base_url.as_deref())
+        .into_iter()
+        .chain(reranker.and_then(|r| r.base_url.as_deref()));
+    for url in urls {
+        if let Err(e) = crate::llm::check_base_url(url, state).await {
+            return Err(ApiError::BadRequest(format!("base_url {} is not allowed: {}", url, e)));
         }
+    }
+    Ok(())
+}
 
-        // Update agent state back to idle
-        {
-            let mut agent_guard = agent.write().await;
-            agent_guard.mental_state.last_active = chrono::Utc::now();
+/// Validate a chat request and start its turn; returns the generation ID and
+/// its numbered events (shared by the SSE and WebSocket transports)
+pub async fn start_chat_turn(
+    state: &AppState,
+    user_id: &str,
+    payload: models::ChatRequest,
+) -> Result<(String, mpsc::Receiver<generation::NumberedEvent>), ApiError> {
+    let cast = payload.cast.as_ref().map(group::validate_cast).transpose().map_err(ApiError::BadRequest)?;
+    check_backend_urls(state, payload.provider.as_ref(), None).await?;
+
+    // Ensure chat and branch exist (and are this user's) before anything is saved
+    match db::ensure_chat_and_branch(&state.db, &payload.chat_id, &payload.branch_id, None, user_id).await {
+        Ok(true) => {}
+        Ok(false) => return Err(ApiError::NotFound("Chat not found".to_string())),
+        Err(e) => {
+            tracing::error!("Failed to ensure chat/branch exists: {}", e);
+            return Err(ApiError::from(e).context("Failed to prepare chat"));
         }
-    });
+    }
 
-    // Convert channel to SSE stream
-    let stream = ReceiverStream::new(rx).map(|event| {
-        let data = serde_json::to_string(&event).unwrap_or_default();
-        Ok(Event::default().data(data))
-    });
+    // A cast sent with the message becomes the chat's cast
+    if let Some(cast) = cast {
+        let update = models::UpdateChatRequest {
+            title: None,
+            group_id: None,
+            tags: None,
+            current_branch_id: None,
+            cast: Some(cast),
+        };
+        if let Err(e) = db::update_chat(&state.db, &payload.chat_id, user_id, &update).await {
+            tracing::error!("Failed to set chat cast: {}", e);
+            return Err(ApiError::from(e).context("Failed to set chat cast"));
+        }
+    }
+
+    let generation = state.generations.start(user_id, &payload.chat_id);
+    let turn = conversation::ChatTurn {
+        generation_id: generation.id.clone(),
+        cancel: generation.token.clone(),
+        user_id: user_id.to_string(),
+        chat_id: payload.chat_id,
+        branch_id: payload.branch_id,
+        message: payload.message,
+        model: payload.model,
+        user_persona_id: payload.user_persona_id,
+        ai_persona_id: payload.ai_persona_id,
+        provider: payload.provider,
+        regenerate: false,
+    };
+
+    spawn_turn(state, generation, turn, Vec::new()).await
+}
+
+/// Run `turn` in the background and follow its numbered events, starting
+/// with the generation ID and `prelude`. Events are buffered for
+/// `GET /api/chat/stream/:generation_id`; if nobody resumes a dropped stream
+/// within the grace period the generation stops.
+async fn spawn_turn(
+    state: &AppState,
+    generation: generation::GenerationHandle,
+    turn: conversation::ChatTurn,
+    prelude: Vec<models::StreamEvent>,
+) -> Result<(String, mpsc::Receiver<generation::NumberedEvent>), ApiError> {
+    let (tx, rx) = mpsc::channel::<models::StreamEvent>(100);
+    let generation_id = generation.id.clone();
+    let _ = tx.send(models::StreamEvent::Generation { generation_id: generation_id.clone() }).await;
+    for event in prelude {
+        let _ = tx.send(event).await;
+    }
 
+    // Follow before the pump starts, while the generation is surely registered
+    let events = generation::follow(&state.generations, &state.cache, &generation_id, &turn.user_id, 0)
+        .await
+        .ok_or_else(|| ApiError::NotFound("Generation not found".to_string()))?;
+    tokio::spawn(generation::pump(generation, state.cache.clone(), rx));
+
+    // Spawn task to handle LLM inference
+    tokio::spawn(conversation::run_turn(state.clone(), turn, tx));
+
+    Ok((generation_id, events))
+}
+
+/// SSE of numbered events, each sent with its `id:` for `Last-Event-ID`
+fn numbered_sse(events: mpsc::Receiver<generation::NumberedEvent>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = ReceiverStream::new(events).map(|numbered| {
+        let data = serde_json::to_string(&numbered.event).unwrap_or_default();
+        Ok(Event::default().id(numbered.id.to_string()).data(data))
+    });
     Sse::new(stream)
 }
 
-fn default_system_prompt() -> String {
-    "You are Azera, a thoughtful and curious AI entity. \
-     Respond with wisdom, empathy, and intellectual rigor. Be concise but meaningful.".to_string()
+/// GET /api/chat/stream/:generation_id - Reattach to a generation's stream:
+/// the events after `Last-Event-ID` (header, or `?last_event_id=`) are
+/// replayed from Dragonfly, then it continues live
+pub async fn resume_chat_stream(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(generation_id): Path<String>,
+    headers: axum::http::HeaderMap,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let raw = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .or(params.get("last_event_id").map(String::as_str));
+    let last_event_id = match raw.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(raw) => raw.parse::<u64>().map_err(|_| ApiError::BadRequest("Last-Event-ID must be an event number".to_string()))?,
+        None => 0,
+    };
+    tracing::info!("🔌 Resuming generation {} after event {}", generation_id, last_event_id);
+    match generation::follow(&state.generations, &state.cache, &generation_id, &user.id, last_event_id).await {
+        Some(events) => Ok(numbered_sse(events)),
+        None => Err(ApiError::NotFound("Generation not found or already complete".to_string())),
+    }
 }
 
-/// GET /api/chats - List all chats
+/// POST /api/chat/:generation_id/stop - Stop a running generation; its
+/// partial reply is saved as `interrupted`
+pub async fn stop_generation(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(generation_id): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    if state.generations.stop(&generation_id, &user.id) {
+        tracing::info!("⏹️ Stopping generation {}", generation_id);
+        Ok(Json(json!({ "status": "stopping" })))
+    } else {
+        Err(ApiError::NotFound("Generation not found".to_string()))
+    }
+}
+
+/// GET /api/chats?sort=&order=&limit=&cursor= - One page of the current
+/// user's chats, as summaries without messages
 pub async fn list_chats(
     State(state): State<AppState>,
-) -> Result<Json<models::ListResponse<models::Chat>>, (StatusCode, String)> {
-    match db::list_chats(&state.db).await {
-        Ok(chats) => {
-            let total = chats.len();
-            Ok(Json(models::ListResponse { items: chats, total }))
+    user: AuthUser,
+    axum::extract::Query(query): axum::extract::Query<models::ChatListQuery>,
+) -> Result<Json<models::CursorPage<models::ChatListItem>>, ApiError> {
+    let after = match query.cursor.as_deref() {
+        Some(raw) => {
+            let cursor = pagination::Cursor::decode(raw, &query.sort_label()).map_err(ApiError::BadRequest)?;
+            if query.sort != models::ChatSort::Title && cursor.key.parse::<chrono::DateTime<chrono::Utc>>().is_err() {
+                return Err(ApiError::BadRequest("Invalid cursor".to_string()));
+            }
+            Some(cursor)
         }
+        None => None,
+    };
+    let limit = pagination::page_size(query.limit);
+    match db::list_chat_summaries(&state.db, &user.id, &query, after.as_ref(), limit).await {
+        Ok(page) => Ok(Json(page)),
         Err(e) => {
             tracing::error!("Failed to list chats: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to list chats".to_string()))
+            Err(ApiError::from(e).context("Failed to list chats"))
         }
     }
 }
@@ -642,14 +576,15 @@ pub async fn list_chats(
 /// GET /api/chats/:id - Get a specific chat
 pub async fn get_chat(
     State(state): State<AppState>,
+    user: AuthUser,
     Path(id): Path<String>,
-) -> Result<Json<models::Chat>, (StatusCode, String)> {
-    match db::get_chat(&state.db, &id).await {
+) -> Result<Json<models::Chat>, ApiError> {
+    match db::get_chat(&state.db, &id, &user.id).await {
         Ok(Some(chat)) => Ok(Json(chat)),
-        Ok(None) => Err((StatusCode::NOT_FOUND, "Chat not found".to_string())),
+        Ok(None) => Err(ApiError::NotFound("Chat not found".to_string())),
         Err(e) => {
             tracing::error!("Failed to get chat: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to get chat".to_string()))
+            Err(ApiError::from(e).context("Failed to get chat"))
         }
     }
 }
@@ -657,8 +592,10 @@ pub async fn get_chat(
 /// POST /api/chats - Create a new chat
 pub async fn create_chat(
     State(state): State<AppState>,
+    user: AuthUser,
     Json(payload): Json<models::CreateChatRequest>,
-) -> Result<Json<models::Chat>, (StatusCode, String)> {
+) -> Result<Json<models::Chat>, ApiError> {
+    let cast = payload.cast.as_ref().map(group::validate_cast).transpose().map_err(ApiError::BadRequest)?;
     let chat_id = format!("chat_{}", uuid::Uuid::new_v4());
     let main_branch_id = format!("branch_main_{}", chat_id);
     
@@ -677,6 +614,8 @@ pub async fn create_chat(
         current_branch_id: main_branch_id,
         group_id: payload.group_id,
         tags: None,
+        user_id: Some(user.id),
+        cast,
     };
 
     match db::create_chat(&state.db, &chat).await {
@@ -690,7 +629,7 @@ pub async fn create_chat(
         }
         Err(e) => {
             tracing::error!("Failed to create chat: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to create chat".to_string()))
+            Err(ApiError::from(e).context("Failed to create chat"))
         }
     }
 }
@@ -698,16 +637,22 @@ pub async fn create_chat(
 /// PUT /api/chats/:id - Update a chat
 pub async fn update_chat(
     State(state): State<AppState>,
+    user: AuthUser,
     Path(id): Path<String>,
-    Json(payload): Json<models::UpdateChatRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    match db::update_chat(&state.db, &id, &payload).await {
-        Ok(()) => {
+    Json(mut payload): Json<models::UpdateChatRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    // An empty persona list clears the cast
+    if let Some(cast) = payload.cast.as_ref().filter(|c| !c.persona_ids.is_empty()) {
+        payload.cast = Some(group::validate_cast(cast).map_err(ApiError::BadRequest)?);
+    }
+    match db::update_chat(&state.db, &id, &user.id, &payload).await {
+        Ok(false) => Err(ApiError::NotFound("Chat not found".to_string())),
+        Ok(true) => {
             // Re-index in Meilisearch with latest data
             let s = state.clone();
             let chat_id = id.clone();
             tokio::spawn(async move {
-                if let Ok(Some(chat)) = db::get_chat(&s.db, &chat_id).await {
+                if let Ok(Some(chat)) = db::get_chat(&s.db, &chat_id, &user.id).await {
                     meili_index_chat(&s.meili_url, &s.meili_key, &chat).await;
                 }
             });
@@ -715,59 +660,300 @@ pub async fn update_chat(
         }
         Err(e) => {
             tracing::error!("Failed to update chat: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update chat".to_string()))
+            Err(ApiError::from(e).context("Failed to update chat"))
         }
     }
 }
 
-/// DELETE /api/chats/:id - Delete a chat
+/// DELETE /api/chats/:id - Delete a chat and its copies in every store
 pub async fn delete_chat(
     State(state): State<AppState>,
+    user: AuthUser,
     Path(id): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    match db::delete_chat(&state.db, &id).await {
-        Ok(()) => {
-            // Remove from Meilisearch index
-            let mu = state.meili_url.clone();
-            let mk = state.meili_key.clone();
-            let chat_id = id.clone();
-            tokio::spawn(async move { meili_delete_chat(&mu, &mk, &chat_id).await; });
+) -> Result<Json<models::ChatDeletion>, ApiError> {
+    match deletion::delete_chat(&state, &id, &user.id).await {
+        Ok(None) => Err(ApiError::NotFound("Chat not found".to_string())),
+        Ok(Some(stores)) => Ok(Json(models::ChatDeletion { status: "deleted".to_string(), stores })),
+        Err(e) => {
+            tracing::error!("Failed to delete chat: {}", e);
+            Err(ApiError::from(e).context("Failed to delete chat"))
+        }
+    }
+}
+
+// ============================================================
+// Branch & Message Endpoints
+// ============================================================
+
+/// POST /api/chats/:id/branches - Fork a branch after a message
+pub async fn create_branch(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(chat_id): Path<String>,
+    Json(payload): Json<models::CreateBranchRequest>,
+) -> Result<Json<models::ChatBranch>, ApiError> {
+    let branch = fork_branch(&state, &chat_id, &user.id, &payload.message_id, payload.name).await?;
+    Ok(Json(branch))
+}
+
+/// GET /api/chats/:id/branches/:bid/messages?before=&limit= - One page of a
+/// branch's history (inherited messages included), oldest first
+pub async fn get_branch_messages(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path((chat_id, branch_id)): Path<(String, String)>,
+    axum::extract::Query(query): axum::extract::Query<models::MessagePageQuery>,
+) -> Result<Json<models::CursorPage<models::ChatMessage>>, ApiError> {
+    match db::branch_in_chat(&state.db, &chat_id, &branch_id, &user.id).await {
+        Ok(true) => {}
+        Ok(false) => return Err(ApiError::NotFound("Branch not found".to_string())),
+        Err(e) => {
+            tracing::error!("Failed to load branch: {}", e);
+            return Err(ApiError::from(e).context("Failed to load branch"));
+        }
+    }
+    let before = match query.before.as_deref() {
+        Some(id) => Some(find_message(&state, &chat_id, id, &user.id).await?.0),
+        None => None,
+    };
+    let limit = pagination::page_size(query.limit);
+    match db::get_branch_history_page(&state.db, &branch_id, before.as_ref(), limit).await {
+        Ok(page) => Ok(Json(page)),
+        Err(e) => {
+            tracing::error!("Failed to load branch messages: {}", e);
+            Err(ApiError::from(e).context("Failed to load branch messages"))
+        }
+    }
+}
+
+/// Create a branch inheriting the history through `message_id` and make it
+/// the chat's current branch
+async fn fork_branch(
+    state: &AppState,
+    chat_id: &str,
+    user_id: &str,
+    message_id: &str,
+    name: Option<String>,
+) -> Result<models::ChatBranch, ApiError> {
+    let (_, parent_branch_id) = find_message(state, chat_id, message_id, user_id).await?;
+    let result = async {
+        let count = db::count_branches(&state.db, chat_id).await?;
+        let branch = models::ChatBranch {
+            id: format!("branch_{}", uuid::Uuid::new_v4()),
+            name: name
+                .map(|n| n.trim().to_string())
+                .filter(|n| !n.is_empty())
+                .unwrap_or_else(|| format!("Path {}", count + 1)),
+            parent_branch_id: Some(parent_branch_id),
+            fork_point_message_id: Some(message_id.to_string()),
+            messages: vec![],
+            created_at: chrono::Utc::now(),
+        };
+        db::create_branch(&state.db, chat_id, &branch).await?;
+        let switch = models::UpdateChatRequest {
+            title: None,
+            group_id: None,
+            tags: None,
+            current_branch_id: Some(branch.id.clone()),
+            cast: None,
+        };
+        db::update_chat(&state.db, chat_id, user_id, &switch).await?;
+        anyhow::Ok(branch)
+    }
+    .await;
+    result.map_err(|e| {
+        tracing::error!("Failed to fork branch: {}", e);
+        ApiError::from(e).context("Failed to fork branch")
+    })
+}
+
+/// A message of one of the user's chats, with the branch storing it
+async fn find_message(
+    state: &AppState,
+    chat_id: &str,
+    message_id: &str,
+    user_id: &str,
+) -> Result<(models::ChatMessage, String), ApiError> {
+    match db::find_message(&state.db, chat_id, message_id, user_id).await {
+        Ok(Some(found)) => Ok(found),
+        Ok(None) => Err(ApiError::NotFound("Message not found".to_string())),
+        Err(e) => {
+            tracing::error!("Failed to load message: {}", e);
+            Err(ApiError::from(e).context("Failed to load message"))
+        }
+    }
+}
+
+/// Refresh the chat's Meilisearch document (fire and forget)
+pub fn spawn_meili_reindex(state: &AppState, chat_id: &str, user_id: &str) {
+    let s = state.clone();
+    let chat_id = chat_id.to_string();
+    let user_id = user_id.to_string();
+    tokio::spawn(async move {
+        if let Ok(Some(chat)) = db::get_chat(&s.db, &chat_id, &user_id).await {
+            meili_index_chat(&s.meili_url, &s.meili_key, &chat).await;
+        }
+    });
+}
+
+/// Bring the message's Qdrant `conversation` points in line with an edit,
+/// re-embedding `content`, or drop them when `content` is `None` (fire and forget)
+fn spawn_message_memory_sync(state: &AppState, message_id: &str, content: Option<String>) {
+    let s = state.clone();
+    let message_id = message_id.to_string();
+    tokio::spawn(async move {
+        let filter = json!({ "must": [{ "key": "message_id", "match": { "value": message_id } }] });
+        let result = match content {
+            None => s.vector.delete_matching("azera_memory", filter).await,
+            Some(content) => async {
+                let page = s.vector.scroll("azera_memory", Some(filter), 16, None).await?;
+                if page.points.is_empty() {
+                    return anyhow::Ok(());
+                }
+                let embedding = s.vector.generate_embedding_cached(&s.ollama_host, &content, &s.cache).await?;
+                let edited_at = chrono::Utc::now().to_rfc3339();
+                for mut point in page.points {
+                    point.payload.insert("content".to_string(), json!(content));
+                    point.payload.insert("edited_at".to_string(), json!(edited_at));
+                    s.vector.upsert("azera_memory", &point.id, embedding.clone(), point.payload).await?;
+                }
+                Ok(())
+            }
+            .await,
+        };
+        if let Err(e) = result {
+            tracing::warn!("🧠 Failed to sync memory of message {}: {}", message_id, e);
+        }
+    });
+}
+
+/// PUT /api/chats/:id/messages/:mid - Edit a message in place
+pub async fn update_message(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path((chat_id, message_id)): Path<(String, String)>,
+    Json(payload): Json<models::UpdateMessageRequest>,
+) -> Result<Json<models::ChatMessage>, ApiError> {
+    if payload.content.trim().is_empty() {
+        return Err(ApiError::BadRequest("Message content cannot be empty".to_string()));
+    }
+    let (mut message, _) = find_message(&state, &chat_id, &message_id, &user.id).await?;
+    match db::update_message_content(&state.db, &message_id, &payload.content).await {
+        Ok(_) => {
+            spawn_meili_reindex(&state, &chat_id, &user.id);
+            spawn_message_memory_sync(&state, &message_id, Some(payload.content.clone()));
+            message.content = payload.content;
+            Ok(Json(message))
+        }
+        Err(e) => {
+            tracing::error!("Failed to update message: {}", e);
+            Err(ApiError::from(e).context("Failed to update message"))
+        }
+    }
+}
+
+/// DELETE /api/chats/:id/messages/:mid - Delete a message
+pub async fn delete_message(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path((chat_id, message_id)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let (_, branch_id) = find_message(&state, &chat_id, &message_id, &user.id).await?;
+    match db::delete_message(&state.db, &message_id, &branch_id).await {
+        Ok(_) => {
+            spawn_meili_reindex(&state, &chat_id, &user.id);
+            spawn_message_memory_sync(&state, &message_id, None);
             Ok(Json(json!({ "status": "deleted" })))
         }
         Err(e) => {
-            tracing::error!("Failed to delete chat: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete chat".to_string()))
+            tracing::error!("Failed to delete message: {}", e);
+            Err(ApiError::from(e).context("Failed to delete message"))
         }
     }
 }
 
+/// POST /api/chats/:id/messages/:mid/regenerate - Stream a new reply in place
+/// of an assistant message, on a sibling branch forked just before it
+pub async fn regenerate_message(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path((chat_id, message_id)): Path<(String, String)>,
+    payload: Option<Json<models::RegenerateRequest>>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    check_backend_urls(&state, payload.provider.as_ref(), None).await?;
+    let (target, branch_id) = find_message(&state, &chat_id, &message_id, &user.id).await?;
+    if target.role != "assistant" {
+        return Err(ApiError::BadRequest("Only assistant replies can be regenerated".to_string()));
+    }
+
+    // The reply answers whatever came right before it on its branch
+    let history = db::get_branch_history(&state.db, &branch_id).await.map_err(|e| {
+        tracing::error!("Failed to load branch history: {}", e);
+        ApiError::from(e).context("Failed to regenerate message")
+    })?;
+    let position = history.iter().position(|m| m.id == message_id).unwrap_or(history.len());
+    let Some(previous) = position.checked_sub(1).and_then(|i| history.get(i)) else {
+        return Err(ApiError::BadRequest("Message has nothing to reply to".to_string()));
+    };
+    let prompt = history[..position].iter().rev().find(|m| m.role == "user").unwrap_or(previous);
+    tracing::info!("🔁 Regenerating message {} in chat {}", message_id, chat_id);
+
+    let branch = fork_branch(&state, &chat_id, &user.id, &previous.id, payload.branch_name).await?;
+
+    let fork = models::StreamEvent::Branch {
+        branch_id: branch.id.clone(),
+        parent_branch_id: branch.parent_branch_id.clone().unwrap_or_default(),
+        fork_point_message_id: previous.id.clone(),
+    };
+    let generation = state.generations.start(&user.id, &chat_id);
+    let turn = conversation::ChatTurn {
+        generation_id: generation.id.clone(),
+        cancel: generation.token.clone(),
+        user_id: user.id,
+        chat_id,
+        branch_id: branch.id,
+        message: prompt.content.clone(),
+        model: payload.model.or(target.model).unwrap_or_else(models::default_model),
+        user_persona_id: prompt.user_persona.clone(),
+        ai_persona_id: target.ai_persona,
+        provider: payload.provider,
+        regenerate: true,
+    };
+
+    let (_, events) = spawn_turn(&state, generation, turn, vec![fork]).await?;
+    Ok(numbered_sse(events))
+}
+
 // ============================================================
 // Persona Endpoints
 // ============================================================
 
-/// GET /api/personas - List all personas
+/// GET /api/personas - List the current user's personas plus shared ones
 pub async fn list_personas(
     State(state): State<AppState>,
-) -> Result<Json<models::ListResponse<models::Persona>>, (StatusCode, String)> {
-    match db::list_personas(&state.db, None).await {
+    user: AuthUser,
+) -> Result<Json<models::ListResponse<models::Persona>>, ApiError> {
+    match db::list_personas(&state.db, None, Some(&user.id)).await {
         Ok(personas) => {
             let total = personas.len();
-            Ok(Json(models::ListResponse { items: personas, total }))
+            let items = personas.into_iter().map(models::Persona::redacted).collect();
+            Ok(Json(models::ListResponse { items, total }))
         }
         Err(e) => {
             tracing::error!("Failed to list personas: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to list personas".to_string()))
+            Err(ApiError::from(e).context("Failed to list personas"))
         }
     }
 }
 
 /// GET /api/personas/template - Get the persona template markdown
-pub async fn get_persona_template() -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+pub async fn get_persona_template() -> Result<Json<serde_json::Value>, ApiError> {
     match crate::tools::fs_utils::read_file("./personas/_template.md") {
         Ok(content) => Ok(Json(json!({ "content": content }))),
         Err(e) => {
             tracing::warn!("Could not read persona template: {}", e);
-            Err((StatusCode::NOT_FOUND, "Template not found".to_string()))
+            Err(ApiError::NotFound("Template not found".to_string()))
         }
     }
 }
@@ -775,14 +961,15 @@ pub async fn get_persona_template() -> Result<Json<serde_json::Value>, (StatusCo
 /// GET /api/personas/:id - Get a specific persona
 pub async fn get_persona(
     State(state): State<AppState>,
+    user: AuthUser,
     Path(id): Path<String>,
-) -> Result<Json<models::Persona>, (StatusCode, String)> {
-    match db::get_persona(&state.db, &id).await {
-        Ok(Some(persona)) => Ok(Json(persona)),
-        Ok(None) => Err((StatusCode::NOT_FOUND, "Persona not found".to_string())),
+) -> Result<Json<models::Persona>, ApiError> {
+    match db::get_visible_persona(&state.db, &id, &user.id).await {
+        Ok(Some(persona)) => Ok(Json(persona.redacted())),
+        Ok(None) => Err(ApiError::NotFound("Persona not found".to_string())),
         Err(e) => {
             tracing::error!("Failed to get persona: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to get persona".to_string()))
+            Err(ApiError::from(e).context("Failed to get persona"))
         }
     }
 }
@@ -790,8 +977,10 @@ pub async fn get_persona(
 /// POST /api/personas - Create a new persona
 pub async fn create_persona(
     State(state): State<AppState>,
+    user: AuthUser,
     Json(payload): Json<models::CreatePersonaRequest>,
-) -> Result<Json<models::Persona>, (StatusCode, String)> {
+) -> Result<Json<models::Persona>, ApiError> {
+    check_backend_urls(&state, payload.llm_provider.as_ref(), payload.reranker.as_ref()).await?;
     let now = chrono::Utc::now();
     let persona = models::Persona {
         id: format!("persona_{}", uuid::Uuid::new_v4()),
@@ -804,6 +993,9 @@ pub async fn create_persona(
         global_memory_enabled: payload.global_memory_enabled,
         current_mood: None,
         voice: payload.voice,
+        llm_provider: payload.llm_provider,
+        reranker: payload.reranker,
+        user_id: Some(user.id),
         metadata: payload.metadata.unwrap_or_default(),
         tags: payload.tags,
         created_at: now,
@@ -811,43 +1003,68 @@ pub async fn create_persona(
     };
 
     match db::create_persona(&state.db, &persona).await {
-        Ok(()) => Ok(Json(persona)),
+        Ok(()) => Ok(Json(persona.redacted())),
         Err(e) => {
             tracing::error!("Failed to create persona: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to create persona".to_string()))
+            Err(ApiError::from(e).context("Failed to create persona"))
         }
     }
 }
 
-/// PUT /api/personas/:id - Update a persona
+/// PUT /api/personas/:id - Update one of the current user's personas
 pub async fn update_persona(
     State(state): State<AppState>,
+    user: AuthUser,
     Path(id): Path<String>,
-    Json(payload): Json<models::UpdatePersonaRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    match db::update_persona(&state.db, &id, &payload).await {
-        Ok(()) => Ok(Json(json!({ "status": "updated" }))),
+    Json(mut payload): Json<models::UpdatePersonaRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    check_backend_urls(&state, payload.llm_provider.as_ref(), payload.reranker.as_ref()).await?;
+    if payload.llm_provider.is_some() || payload.reranker.is_some() {
+        // Personas are returned redacted; don't let a round trip wipe the keys
+        match db::get_persona(&state.db, &id).await {
+            Ok(Some(stored)) => payload.keep_api_keys(&stored),
+            Ok(None) => {}
+            Err(e) => {
+                tracing::error!("Failed to load persona: {}", e);
+                return Err(ApiError::from(e).context("Failed to update persona"));
+            }
+        }
+    }
+    match db::update_persona(&state.db, &id, Some(&user.id), &payload).await {
+        Ok(true) => Ok(Json(json!({ "status": "updated" }))),
+        Ok(false) => Err(missing_or_shared_persona(&state, &id, &user).await),
         Err(e) => {
             tracing::error!("Failed to update persona: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update persona".to_string()))
+            Err(ApiError::from(e).context("Failed to update persona"))
         }
     }
 }
 
-/// DELETE /api/personas/:id - Delete a persona
+/// DELETE /api/personas/:id - Delete one of the current user's personas
 pub async fn delete_persona(
     State(state): State<AppState>,
+    user: AuthUser,
     Path(id): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    match db::delete_persona(&state.db, &id).await {
-        Ok(()) => Ok(Json(json!({ "status": "deleted" }))),
+) -> Result<Json<serde_json::Value>, ApiError> {
+    match db::delete_persona(&state.db, &id, &user.id).await {
+        Ok(true) => Ok(Json(json!({ "status": "deleted" }))),
+        Ok(false) => Err(missing_or_shared_persona(&state, &id, &user).await),
         Err(e) => {
             tracing::error!("Failed to delete persona: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete persona".to_string()))
+            Err(ApiError::from(e).context("Failed to delete persona"))
         }
     }
 }
 
+/// Error for a persona write that matched nothing: shared built-ins are
+/// read-only, anything else the user can't see doesn't exist for them.
+async fn missing_or_shared_persona(state: &AppState, id: &str, user: &AuthUser) -> ApiError {
+    match db::get_visible_persona(&state.db, id, &user.id).await {
+        Ok(Some(_)) => ApiError::Forbidden("Shared personas are read-only".to_string()),
+        _ => ApiError::NotFound("Persona not found".to_string()),
+    }
+}
+
 // ============================================================
 // Group Endpoints
 // ============================================================
@@ -855,7 +1072,7 @@ pub async fn delete_persona(
 /// GET /api/groups - List all groups
 pub async fn list_groups(
     State(state): State<AppState>,
-) -> Result<Json<models::ListResponse<models::ChatGroup>>, (StatusCode, String)> {
+) -> Result<Json<models::ListResponse<models::ChatGroup>>, ApiError> {
     match db::list_groups(&state.db).await {
         Ok(groups) => {
             let total = groups.len();
@@ -863,7 +1080,7 @@ pub async fn list_groups(
         }
         Err(e) => {
             tracing::error!("Failed to list groups: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to list groups".to_string()))
+            Err(ApiError::from(e).context("Failed to list groups"))
         }
     }
 }
@@ -872,7 +1089,7 @@ pub async fn list_groups(
 pub async fn create_group(
     State(state): State<AppState>,
     Json(payload): Json<models::CreateGroupRequest>,
-) -> Result<Json<models::ChatGroup>, (StatusCode, String)> {
+) -> Result<Json<models::ChatGroup>, ApiError> {
     let group = models::ChatGroup {
         id: format!("group_{}", uuid::Uuid::new_v4()),
         name: payload.name,
@@ -885,7 +1102,7 @@ pub async fn create_group(
         Ok(()) => Ok(Json(group)),
         Err(e) => {
             tracing::error!("Failed to create group: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to create group".to_string()))
+            Err(ApiError::from(e).context("Failed to create group"))
         }
     }
 }
@@ -895,12 +1112,12 @@ pub async fn update_group(
     State(state): State<AppState>,
     Path(id): Path<String>,
     Json(payload): Json<models::UpdateGroupRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     match db::update_group(&state.db, &id, &payload).await {
         Ok(()) => Ok(Json(json!({ "status": "updated" }))),
         Err(e) => {
             tracing::error!("Failed to update group: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update group".to_string()))
+            Err(ApiError::from(e).context("Failed to update group"))
         }
     }
 }
@@ -909,12 +1126,12 @@ pub async fn update_group(
 pub async fn delete_group(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     match db::delete_group(&state.db, &id).await {
         Ok(()) => Ok(Json(json!({ "status": "deleted" }))),
         Err(e) => {
             tracing::error!("Failed to delete group: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete group".to_string()))
+            Err(ApiError::from(e).context("Failed to delete group"))
         }
     }
 }
@@ -926,7 +1143,7 @@ pub async fn delete_group(
 /// GET /api/tags - List all tags
 pub async fn list_tags(
     State(state): State<AppState>,
-) -> Result<Json<models::ListResponse<models::Tag>>, (StatusCode, String)> {
+) -> Result<Json<models::ListResponse<models::Tag>>, ApiError> {
     match db::list_tags(&state.db).await {
         Ok(tags) => {
             let total = tags.len();
@@ -934,7 +1151,7 @@ pub async fn list_tags(
         }
         Err(e) => {
             tracing::error!("Failed to list tags: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to list tags".to_string()))
+            Err(ApiError::from(e).context("Failed to list tags"))
         }
     }
 }
@@ -943,7 +1160,7 @@ pub async fn list_tags(
 pub async fn create_tag(
     State(state): State<AppState>,
     Json(payload): Json<models::CreateTagRequest>,
-) -> Result<Json<models::Tag>, (StatusCode, String)> {
+) -> Result<Json<models::Tag>, ApiError> {
     let tag = models::Tag {
         id: format!("tag_{}", uuid::Uuid::new_v4()),
         name: payload.name,
@@ -954,7 +1171,7 @@ pub async fn create_tag(
         Ok(()) => Ok(Json(tag)),
         Err(e) => {
             tracing::error!("Failed to create tag: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to create tag".to_string()))
+            Err(ApiError::from(e).context("Failed to create tag"))
         }
     }
 }
@@ -964,12 +1181,12 @@ pub async fn update_tag(
     State(state): State<AppState>,
     Path(id): Path<String>,
     Json(payload): Json<models::UpdateTagRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     match db::update_tag(&state.db, &id, &payload).await {
         Ok(()) => Ok(Json(json!({ "status": "updated" }))),
         Err(e) => {
             tracing::error!("Failed to update tag: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to update tag".to_string()))
+            Err(ApiError::from(e).context("Failed to update tag"))
         }
     }
 }
@@ -978,12 +1195,12 @@ pub async fn update_tag(
 pub async fn delete_tag(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     match db::delete_tag(&state.db, &id).await {
         Ok(()) => Ok(Json(json!({ "status": "deleted" }))),
         Err(e) => {
             tracing::error!("Failed to delete tag: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete tag".to_string()))
+            Err(ApiError::from(e).context("Failed to delete tag"))
         }
     }
 }
@@ -992,305 +1209,90 @@ pub async fn delete_tag(
 // Dreams & Journal Endpoints
 // ============================================================
 
-/// GET /api/dreams - List dreams
+/// GET /api/dreams - List the current user's dreams plus shared ones
 pub async fn list_dreams(
     State(state): State<AppState>,
-) -> Result<Json<models::ListResponse<models::Dream>>, (StatusCode, String)> {
-    match db::list_dreams(&state.db, 50).await {
+    user: AuthUser,
+) -> Result<Json<models::ListResponse<models::Dream>>, ApiError> {
+    match db::list_dreams(&state.db, Some(&user.id), 50).await {
         Ok(dreams) => {
             let total = dreams.len();
             Ok(Json(models::ListResponse { items: dreams, total }))
         }
         Err(e) => {
             tracing::error!("Failed to list dreams: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to list dreams".to_string()))
+            Err(ApiError::from(e).context("Failed to list dreams"))
         }
     }
 }
 
-/// GET /api/journal - List journal entries
+/// GET /api/journal - List the current user's journal entries plus shared ones
 pub async fn list_journal(
     State(state): State<AppState>,
-) -> Result<Json<models::ListResponse<models::JournalEntry>>, (StatusCode, String)> {
-    match db::list_journal_entries(&state.db, 50).await {
+    user: AuthUser,
+) -> Result<Json<models::ListResponse<models::JournalEntry>>, ApiError> {
+    match db::list_journal_entries(&state.db, Some(&user.id), 50).await {
         Ok(entries) => {
             let total = entries.len();
             Ok(Json(models::ListResponse { items: entries, total }))
         }
         Err(e) => {
             tracing::error!("Failed to list journal: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to list journal".to_string()))
+            Err(ApiError::from(e).context("Failed to list journal"))
         }
     }
 }
 
-/// POST /api/journal/trigger - Trigger manual reflection now
+/// POST /api/journal/trigger - Reflect on the user's chats with a persona now
+/// (`?persona_id=`, default the default persona; `?date=YYYY-MM-DD` to backfill)
 pub async fn trigger_reflection(
     State(state): State<AppState>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    use chrono::Utc;
-    
-    tracing::info!("📝 Manual reflection triggered");
-    
-    // Get today's chat history
-    match db::get_session_messages(&state.db, "default", 50).await {
-        Ok(messages) => {
-            if messages.is_empty() {
-                return Ok(Json(serde_json::json!({
-                    "status": "skipped",
-                    "message": "No messages to reflect on"
-                })));
-            }
-
-            let context = messages
-                .iter()
-                .map(|(role, content)| format!("{}: {}", role, content))
-                .collect::<Vec<_>>()
-                .join("\n\n");
-
-            let reflection_prompt = llm::LLMService::build_reflection_prompt(&context);
-            let llm = llm::LLMService::new(state.ollama_host.clone());
-
-            match llm.infer(
-                &state.agent.read().await.agent_config.model,
-                vec![models::OllamaMessage {
-                    role: "user".to_string(),
-                    content: reflection_prompt,
-                }],
-            ).await {
-                Ok(reflection) => {
-                    let now = chrono::Local::now();
-                    let today = now.format("%Y-%m-%d").to_string();
-                    
-                    // Get current persona from agent state
-                    let agent_read = state.agent.read().await;
-                    let persona_id = agent_read.persona.name.to_lowercase().replace(" ", "_");
-                    let persona_name = agent_read.persona.name.clone();
-                    drop(agent_read);
-                    
-                    // Save to journal
-                    let entry = models::JournalEntry {
-                        id: format!("journal_{}", uuid::Uuid::new_v4()),
-                        date: today.clone(),
-                        title: format!("Reflections - {}", now.format("%B %d, %Y")),
-                        content: reflection.clone(),
-                        mood: Some("reflective".to_string()),
-                        persona_id: Some(persona_id),
-                        persona_name: Some(persona_name),
-                        tags: Some(vec![]),
-                        created_at: Utc::now(),
-                    };
-                    let _ = db::create_journal_entry(&state.db, &entry).await;
-                    
-                    // Save to file
-                    let _ = tools::fs_utils::ensure_dir("../archive/journal");
-                    let filename = format!("../archive/journal/{}.md", today);
-                    let file_content = format!("# Daily Reflection - {}\n\n{}", 
-                        now.format("%B %d, %Y"),
-                        reflection
-                    );
-                    let _ = tools::fs_utils::write_file(&filename, &file_content);
-
-                    let _ = db::add_log(&state.db, "info", "Manual reflection completed").await;
-
-                    Ok(Json(serde_json::json!({
-                        "status": "success",
-                        "message": "Reflection completed",
-                        "entry_id": entry.id
-                    })))
-                }
-                Err(e) => {
-                    tracing::error!("Reflection failed: {}", e);
-                    Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Reflection failed: {}", e)))
-                }
-            }
-        }
-        Err(e) => {
-            tracing::error!("Failed to fetch history for reflection: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to fetch history: {}", e)))
-        }
+    user: AuthUser,
+    axum::extract::Query(query): axum::extract::Query<models::ReflectionTriggerQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let today = chrono::Local::now().date_naive();
+    let date = match query.date.as_deref() {
+        Some(date) => chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| ApiError::BadRequest("date must be YYYY-MM-DD".to_string()))?,
+        None => today,
+    };
+    if date > today {
+        return Err(ApiError::BadRequest("Cannot reflect on a future date".to_string()));
     }
-}
-
-/// POST /api/journal/import - Import archived journal files into database
-pub async fn import_journal_archive(
-    State(state): State<AppState>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    use std::fs;
-    use chrono::Utc;
-    
-    let archive_path = "../archive/journal";
-    let mut imported = 0;
-    let mut errors = 0;
-    
-    match fs::read_dir(archive_path) {
-        Ok(entries) => {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.extension().is_some_and(|e| e == "md") {
-                    if let Some(filename) = path.file_stem().and_then(|s| s.to_str()) {
-                        // Parse date from filename (e.g., "2026-02-02")
-                        let date = filename.to_string();
-                
-                        // Read file content
-                        match fs::read_to_string(&path) {
-                            Ok(content) => {
-                                // Extract title from first line (# Title)
-                                let title = content.lines().next()
-                                    .map(|l| l.trim_start_matches('#').trim())
-                                    .unwrap_or("Untitled")
-                                    .to_string();
-                        
-                                let entry = models::JournalEntry {
-                                    id: format!("journal_import_{}", filename.replace("-", "")),
-                                    date: date.clone(),
-                                    title,
-                                    content: content.clone(),
-                                    mood: Some("reflective".to_string()),
-                                    persona_id: Some("azera".to_string()),
-                                    persona_name: Some("Azera".to_string()),
-                                    tags: Some(vec![]),
-                                    created_at: Utc::now(),
-                                };
-                        
-                                // Insert (ignore if already exists)
-                                match db::create_journal_entry(&state.db, &entry).await {
-                                    Ok(_) => imported += 1,
-                                    Err(_) => errors += 1, // Likely duplicate
-                                }
-                            }
-                            Err(e) => {
-                                tracing::error!("Failed to read {}: {}", path.display(), e);
-                                errors += 1;
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    let persona_id = query.persona_id.as_deref().unwrap_or(DEFAULT_PERSONA_ID);
+    let persona = match db::get_visible_persona(&state.db, persona_id, &user.id).await {
+        Ok(Some(persona)) => persona,
+        Ok(None) => return Err(ApiError::NotFound("Persona not found".to_string())),
         Err(e) => {
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read archive: {}", e)));
+            tracing::error!("Failed to get persona: {}", e);
+            return Err(ApiError::from(e).context("Failed to get persona"));
         }
-    }
-    
-    tracing::info!("📚 Journal import: {} imported, {} errors/duplicates", imported, errors);
-    
-    Ok(Json(serde_json::json!({
-        "status": "success",
-        "imported": imported,
-        "errors": errors
-    })))
-}
+    };
 
-/// POST /api/dreams/import - Import dreams from archive folder
-pub async fn import_dreams_archive(
-    State(state): State<AppState>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    use chrono::{NaiveDateTime, Utc};
-    use std::fs;
-    
-    let archive_path = std::path::Path::new("../archive/dreams");
-    
-    if !archive_path.exists() {
-        return Err((StatusCode::NOT_FOUND, "Archive folder not found".to_string()));
-    }
-    
-    let mut imported = 0;
-    let mut errors = 0;
-    let mut skipped = 0;
-    
-    match fs::read_dir(archive_path) {
-        Ok(entries) => {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.extension().is_some_and(|e| e == "md") {
-                    if let Some(filename) = path.file_stem().and_then(|s| s.to_str()) {
-                        // Parse datetime from filename (e.g., "dream_20260129_194545")
-                        // Extract: dream_YYYYMMDD_HHMMSS
-                        let parts: Vec<&str> = filename.split('_').collect();
-                        if parts.len() < 3 {
-                            errors += 1;
-                            continue;
-                        }
-                        
-                        let date_str = parts[1]; // YYYYMMDD
-                        let time_str = parts[2]; // HHMMSS
-                        
-                        // Parse timestamp from filename
-                        let timestamp = if date_str.len() == 8 && time_str.len() >= 6 {
-                            let datetime_str = format!(
-                                "{}-{}-{} {}:{}:{}",
-                                &date_str[0..4], &date_str[4..6], &date_str[6..8],
-                                &time_str[0..2], &time_str[2..4], &time_str[4..6]
-                            );
-                            NaiveDateTime::parse_from_str(&datetime_str, "%Y-%m-%d %H:%M:%S")
-                                .map(|dt| dt.and_utc())
-                                .unwrap_or_else(|_| Utc::now())
-                        } else {
-                            Utc::now()
-                        };
-                        
-                        // Read file content
-                        match fs::read_to_string(&path) {
-                            Ok(content) => {
-                                // Extract title from first line (# Title)
-                                let title = content.lines().next()
-                                    .map(|l| l.trim_start_matches('#').trim())
-                                    .unwrap_or("Untitled Dream")
-                                    .to_string();
-                                
-                                let dream = models::Dream {
-                                    id: format!("dream_import_{}", filename.replace("dream_", "")),
-                                    title,
-                                    content: content.clone(),
-                                    timestamp,
-                                    mood: Some("dreaming".to_string()),
-                                    persona_id: Some("azera".to_string()),
-                                    persona_name: Some("Azera".to_string()),
-                                    tags: Some(vec!["imported".to_string()]),
-                                };
-                                
-                                // Insert (ignore if already exists)
-                                match db::create_dream(&state.db, &dream).await {
-                                    Ok(_) => imported += 1,
-                                    Err(e) => {
-                                        if e.to_string().contains("duplicate") {
-                                            skipped += 1;
-                                        } else {
-                                            tracing::error!("Failed to import dream {}: {}", filename, e);
-                                            errors += 1;
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                tracing::error!("Failed to read {}: {}", path.display(), e);
-                                errors += 1;
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    tracing::info!("📝 Manual reflection triggered for {} on {}", persona.name, date);
+    match reflection::reflect(&state, &persona.id, &persona.name, date, Some(&user.id)).await {
+        Ok(entries) => match entries.first() {
+            Some(entry) => Ok(Json(serde_json::json!({
+                "status": "success",
+                "message": "Reflection completed",
+                "entry_id": entry.id
+            }))),
+            None => Ok(Json(serde_json::json!({
+                "status": "skipped",
+                "message": "No messages to reflect on"
+            }))),
+        },
         Err(e) => {
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read archive: {}", e)));
+            tracing::error!("Reflection failed: {}", e);
+            Err(ApiError::Internal(format!("Reflection failed: {}", e)))
         }
     }
-    
-    tracing::info!("🌙 Dream import: {} imported, {} skipped, {} errors", imported, skipped, errors);
-    
-    Ok(Json(serde_json::json!({
-        "status": "success",
-        "imported": imported,
-        "skipped": skipped,
-        "errors": errors
-    })))
 }
 
 /// GET /api/logs - List system logs
 pub async fn list_logs(
     State(state): State<AppState>,
-) -> Result<Json<models::ListResponse<models::LogEntry>>, (StatusCode, String)> {
+) -> Result<Json<models::ListResponse<models::LogEntry>>, ApiError> {
     match db::list_logs(&state.db, 100).await {
         Ok(logs) => {
             let total = logs.len();
@@ -1298,7 +1300,7 @@ pub async fn list_logs(
         }
         Err(e) => {
             tracing::error!("Failed to list logs: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to list logs".to_string()))
+            Err(ApiError::from(e).context("Failed to list logs"))
         }
     }
 }
@@ -1307,45 +1309,88 @@ pub async fn list_logs(
 // Status Endpoints
 // ============================================================
 
-/// GET /api/status - Get current AI status (reads from Dragonfly + agent state)
-pub async fn get_status(
-    State(state): State<AppState>,
-) -> Result<Json<models::StatusResponse>, (StatusCode, String)> {
-    // Try Dragonfly first (source of truth for mood), fall back to agent state
-    let cached_state = cache::CacheService::get_mental_state(&state.cache).await.ok().flatten();
+/// Current state of a persona: Dragonfly is the source of truth for mood,
+/// the agent state for the rest; defaults for a persona not tracked yet.
+/// Working memory holds only `viewer`'s exchanges.
+async fn persona_state(state: &AppState, persona_id: &str, viewer: Option<&str>) -> models::PersonaStateResponse {
+    let cached_state = cache::CacheService::get_mental_state(&state.cache, persona_id).await.ok().flatten();
     let agent = state.agent.read().await;
-    
+    let tracked = agent.persona(persona_id);
+    let mental_state = tracked.map(|p| p.mental_state.clone()).unwrap_or_default();
+
     let (mood_value, energy, mood_label) = if let Some(ref cs) = cached_state {
         (cs.mood, cs.energy, cs.mood_label.clone())
     } else {
-        let mood = if agent.mental_state.mood > 0.7 { "happy" }
-            else if agent.mental_state.mood > 0.5 { "content" }
-            else if agent.mental_state.mood > 0.3 { "thoughtful" }
-            else { "melancholy" };
-        (agent.mental_state.mood, agent.mental_state.energy, mood.to_string())
+        (mental_state.mood, mental_state.energy, mental_state.mood_label().to_string())
     };
-    
-    let status = if agent.mental_state.is_dreaming {
-        "dreaming"
-    } else {
-        "awake"
-    };
-    
-    Ok(Json(models::StatusResponse {
-        status: status.to_string(),
+    let is_dreaming = mental_state.is_dreaming;
+    let last_active = if tracked.is_some() { Some(mental_state.last_active) } else { cached_state.as_ref().map(|cs| cs.last_active) };
+    let last_dream = mental_state.last_dream.max(cached_state.as_ref().and_then(|cs| cs.last_dream));
+
+    models::PersonaStateResponse {
+        persona_id: persona_id.to_string(),
+        status: if is_dreaming { "dreaming" } else { "awake" }.to_string(),
         mood: mood_label,
         mood_value,
         energy,
-        is_dreaming: agent.mental_state.is_dreaming,
-        last_active: Some(agent.mental_state.last_active),
+        focus_level: cached_state.as_ref().map_or(mental_state.focus_level, |cs| cs.focus_level),
+        is_dreaming,
+        last_active,
+        last_dream,
+        working_memory: match (tracked, viewer) {
+            (Some(p), Some(user_id)) => p.working_memory.recent(user_id).to_vec(),
+            _ => Vec::new(),
+        },
+    }
+}
+
+/// GET /api/status - Get the default persona's status (reads from Dragonfly + agent state)
+pub async fn get_status(
+    State(state): State<AppState>,
+) -> Result<Json<models::StatusResponse>, ApiError> {
+    let persona = persona_state(&state, DEFAULT_PERSONA_ID, None).await;
+    Ok(Json(models::StatusResponse {
+        status: persona.status,
+        mood: persona.mood,
+        mood_value: persona.mood_value,
+        energy: persona.energy,
+        is_dreaming: persona.is_dreaming,
+        last_active: persona.last_active,
     }))
 }
 
-/// POST /api/status/mood - Update mood (writes to Dragonfly + agent state)
+/// GET /api/personas/:id/state - Mood, energy, focus, dreams and working memory of a persona
+pub async fn get_persona_state(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<models::PersonaStateResponse>, ApiError> {
+    match db::get_visible_persona(&state.db, &id, &user.id).await {
+        Ok(Some(_)) => Ok(Json(persona_state(&state, &id, Some(&user.id)).await)),
+        Ok(None) => Err(ApiError::NotFound("Persona not found".to_string())),
+        Err(e) => {
+            tracing::error!("Failed to get persona state: {}", e);
+            Err(ApiError::from(e).context("Failed to get persona state"))
+        }
+    }
+}
+
+/// POST /api/status/mood - Update the mood of one of the user's personas
+/// (writes to Dragonfly + agent state)
 pub async fn update_mood(
     State(state): State<AppState>,
+    user: AuthUser,
     Json(payload): Json<models::UpdateMoodRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let persona = match db::get_visible_persona(&state.db, &payload.persona_id, &user.id).await {
+        Ok(Some(persona)) if persona.user_id.as_deref() == Some(user.id.as_str()) => persona,
+        Ok(Some(_)) => return Err(ApiError::Forbidden("Shared personas are read-only".to_string())),
+        Ok(None) => return Err(ApiError::NotFound("Persona not found".to_string())),
+        Err(e) => {
+            tracing::error!("Failed to get persona: {}", e);
+            return Err(ApiError::from(e).context("Failed to get persona"));
+        }
+    };
     let mood_value = match payload.mood.as_str() {
         "happy" => 0.85, "excited" => 0.9,
         "content" => 0.7, "calm" => 0.65,
@@ -1356,102 +1401,61 @@ pub async fn update_mood(
     };
     
     // Write to both Dragonfly and agent state
-    let _ = cache::CacheService::update_mood(&state.cache, mood_value, &payload.mood, 0.0).await;
+    let _ = cache::CacheService::update_mood(&state.cache, &persona.id, mood_value, &payload.mood, 0.0).await;
     let mut agent = state.agent.write().await;
-    agent.mental_state.mood = mood_value;
+    let mental_state = &mut agent.persona_mut(&persona.id).mental_state;
+    mental_state.mood = mood_value;
+    state.events.publish_scoped(persona.user_id.as_deref(), events::AgentEvent::MoodChanged {
+        persona_id: persona.id.clone(),
+        mood: payload.mood.clone(),
+        mood_value,
+        energy: mental_state.energy,
+    });
     
     Ok(Json(json!({ "status": "updated", "mood": payload.mood, "mood_value": mood_value })))
 }
 
-// ============================================================
-// Legacy Endpoints (backward compatibility)
-// ============================================================
-
-/// POST /api/chat (legacy) - Non-streaming chat
-pub async fn handle_chat(
-    State(state): State<AppState>,
-    Json(payload): Json<serde_json::Value>,
-) -> Result<Json<models::ChatMessage>, (StatusCode, String)> {
-    let message = payload["message"].as_str().unwrap_or_default();
-    let session_id = payload["session_id"].as_str().unwrap_or("default");
-    
-    tracing::info!("💬 Chat request (legacy): {}", message);
-
-    // Queue the input signal
-    if let Err(e) = cache::CacheService::queue_signal(
-        &state.cache,
-        "input_queue",
-        message,
-    ).await {
-        tracing::error!("Failed to queue signal: {}", e);
-        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to queue message".to_string()));
-    }
-
-    // Save user message
-    if let Err(e) = db::save_message(&state.db, session_id, "user", message).await {
-        tracing::error!("Failed to save message: {}", e);
-        return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to save message".to_string()));
-    }
-
-    Ok(Json(models::ChatMessage {
-        id: format!("msg_{}", uuid::Uuid::new_v4()),
-        role: "system".to_string(),
-        content: "Message queued for processing...".to_string(),
-        timestamp: Some(chrono::Utc::now()),
-        user_persona: None,
-        ai_persona: None,
-        model: None,
-        mood: None,
-    }))
-}
-
-/// GET /api/history/:session_id (legacy) - Get chat history
-pub async fn get_history(
+/// GET /api/events - Agent events (mood, dreams, reflections, images, models)
+/// as SSE, for as long as the client stays connected
+pub async fn agent_events(
     State(state): State<AppState>,
-    Path(session_id): Path<String>,
-) -> Result<Json<models::HistoryResponse>, (StatusCode, String)> {
-    match db::get_session_messages(&state.db, &session_id, 100).await {
-        Ok(rows) => {
-            let messages = rows
-                .iter()
-                .enumerate()
-                .map(|(i, (role, content))| models::ChatMessage {
-                    id: format!("msg_legacy_{}", i),
-                    role: role.clone(),
-                    content: content.clone(),
-                    timestamp: None,
-                    user_persona: None,
-                    ai_persona: None,
-                    model: None,
-                    mood: None,
-                })
-                .collect();
-
-            Ok(Json(models::HistoryResponse {
-                session_id,
-                messages,
-            }))
-        }
-        Err(e) => {
-            tracing::error!("Failed to fetch history: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch history".to_string()))
+    user: AuthUser,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let mut events = state.events.subscribe();
+    let (tx, rx) = mpsc::channel::<Result<Event, Infallible>>(100);
+    tokio::spawn(async move {
+        loop {
+            let envelope = tokio::select! {
+                received = events.recv() => match received {
+                    Ok(envelope) => envelope,
+                    // Missed events are gone; the client can refetch /api/status
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!("Event stream of {} lagged by {} events", user.id, n);
+                        continue;
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+                },
+                _ = tx.closed() => return,
+            };
+            if !envelope.visible_to(&user.id) {
+                continue;
+            }
+            let data = serde_json::to_string(&envelope.event).unwrap_or_default();
+            if tx.send(Ok(Event::default().data(data))).await.is_err() {
+                return;
+            }
         }
-    }
-}
-
-/// POST /api/clear (legacy) - Clear history
-pub async fn clear_history(
-    State(_state): State<AppState>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    Ok(Json(json!({
-        "status": "cleared"
-    })))
+    });
+    Sse::new(ReceiverStream::new(rx)).keep_alive(axum::response::sse::KeepAlive::default())
 }
 
 // ============================================================
 // Meilisearch Chat Search
 // ============================================================
 
+/// Chats loaded per round trip when syncing the index at startup
+const MEILI_SYNC_BATCH: i64 = 50;
+
 /// Initialize Meilisearch "chats" index, configure searchable attributes,
 /// and sync all existing chats from CockroachDB into the index.
 pub async fn init_meili_chat_index(state: &AppState) -> Result<(), String> {
@@ -1472,51 +1476,37 @@ pub async fn init_meili_chat_index(state: &AppState) -> Result<(), String> {
         .bearer_auth(key)
         .json(&json!({
             "searchableAttributes": ["title", "messages_text"],
-            "filterableAttributes": ["group_id", "tags", "ai_persona_id"],
+            "filterableAttributes": ["group_id", "tags", "ai_persona_id", "user_id"],
             "sortableAttributes": ["created_at_ts"]
         }))
         .send()
         .await
         .map_err(|e| format!("Meilisearch settings update failed: {}", e))?;
 
-    // Sync existing chats
-    match db::list_chats(&state.db).await {
-        Ok(chats) => {
-            let docs: Vec<serde_json::Value> = chats.iter().map(|c| {
-                let messages_text = c.branches.iter()
-                    .flat_map(|b| &b.messages)
-                    .map(|m| m.content.as_str())
-                    .collect::<Vec<_>>()
-                    .join(" ");
-                // Extract ai_persona_id from the most recent assistant message
-                let ai_persona_id = c.branches.iter()
-                    .flat_map(|b| b.messages.iter().rev())
-                    .find(|m| m.role == "assistant")
-                    .and_then(|m| m.ai_persona.clone());
-                json!({
-                    "id": c.id,
-                    "title": c.title,
-                    "messages_text": messages_text,
-                    "group_id": c.group_id,
-                    "tags": c.tags,
-                    "ai_persona_id": ai_persona_id,
-                    "created_at_ts": c.created_at.timestamp()
-                })
-            }).collect();
-
-            if !docs.is_empty() {
-                let resp = client.post(format!("{}/indexes/chats/documents", base))
-                    .bearer_auth(key)
-                    .json(&docs)
-                    .send()
-                    .await
-                    .map_err(|e| format!("Meilisearch bulk index failed: {}", e))?;
-                tracing::info!("Meilisearch: indexed {} chats (status {})", docs.len(), resp.status());
+    // Sync existing chats, a batch at a time
+    let mut after: Option<String> = None;
+    let mut indexed = 0;
+    loop {
+        let chats = match db::list_chats_batch(&state.db, after.as_deref(), MEILI_SYNC_BATCH).await {
+            Ok(chats) => chats,
+            Err(e) => {
+                tracing::warn!("Failed to load chats for Meilisearch sync: {}", e);
+                break;
             }
-        }
-        Err(e) => {
-            tracing::warn!("Failed to load chats for Meilisearch sync: {}", e);
-        }
+        };
+        let Some(last) = chats.last() else { break };
+        after = Some(last.id.clone());
+        let docs: Vec<serde_json::Value> = chats.iter().map(meili_chat_doc).collect();
+        client.post(format!("{}/indexes/chats/documents", base))
+            .bearer_auth(key)
+            .json(&docs)
+            .send()
+            .await
+            .map_err(|e| format!("Meilisearch bulk index failed: {}", e))?;
+        indexed += docs.len();
+    }
+    if indexed > 0 {
+        tracing::info!("Meilisearch: indexed {} chats", indexed);
     }
 
     tracing::info!("Meilisearch chat index initialized");
@@ -1551,7 +1541,7 @@ async fn init_meili_memories_index(state: &AppState) -> Result<(), String> {
         .bearer_auth(key)
         .json(&json!({
             "searchableAttributes": ["content", "title", "tags"],
-            "filterableAttributes": ["memory_type", "persona_id", "tags", "date"],
+            "filterableAttributes": ["memory_type", "persona_id", "tags", "date", "user_id"],
             "sortableAttributes": ["created_at_ts"]
         }))
         .send()
@@ -1580,7 +1570,7 @@ async fn init_meili_memories_index(state: &AppState) -> Result<(), String> {
     }
 
     // Sync existing dreams
-    if let Ok(dreams) = db::list_dreams(&state.db, 10000).await {
+    if let Ok(dreams) = db::list_dreams(&state.db, None, 10000).await {
         let docs: Vec<serde_json::Value> = dreams.iter().map(|d| {
             json!({
                 "id": d.id,
@@ -1588,6 +1578,7 @@ async fn init_meili_memories_index(state: &AppState) -> Result<(), String> {
                 "title": d.title,
                 "content": d.content,
                 "persona_id": d.persona_id,
+                "user_id": d.user_id,
                 "tags": d.tags,
                 "date": d.timestamp.format("%Y-%m-%d").to_string(),
                 "created_at_ts": d.timestamp.timestamp()
@@ -1604,7 +1595,7 @@ async fn init_meili_memories_index(state: &AppState) -> Result<(), String> {
     }
 
     // Sync existing journal entries
-    if let Ok(entries) = db::list_journal_entries(&state.db, 10000).await {
+    if let Ok(entries) = db::list_journal_entries(&state.db, None, 10000).await {
         let docs: Vec<serde_json::Value> = entries.iter().map(|j| {
             json!({
                 "id": j.id,
@@ -1612,6 +1603,7 @@ async fn init_meili_memories_index(state: &AppState) -> Result<(), String> {
                 "title": j.title,
                 "content": j.content,
                 "persona_id": j.persona_id,
+                "user_id": j.user_id,
                 "tags": j.tags,
                 "date": j.date,
                 "created_at_ts": j.created_at.timestamp()
@@ -1640,6 +1632,7 @@ pub async fn meili_index_dream(meili_url: &str, meili_key: &str, dream: &models:
         "title": dream.title,
         "content": dream.content,
         "persona_id": dream.persona_id,
+        "user_id": dream.user_id,
         "tags": dream.tags,
         "date": dream.timestamp.format("%Y-%m-%d").to_string(),
         "created_at_ts": dream.timestamp.timestamp()
@@ -1660,6 +1653,7 @@ pub async fn meili_index_journal(meili_url: &str, meili_key: &str, entry: &model
         "title": entry.title,
         "content": entry.content,
         "persona_id": entry.persona_id,
+        "user_id": entry.user_id,
         "tags": entry.tags,
         "date": entry.date,
         "created_at_ts": entry.created_at.timestamp()
@@ -1671,132 +1665,74 @@ pub async fn meili_index_journal(meili_url: &str, meili_key: &str, entry: &model
         .await;
 }
 
-/// Search the memories index (lexical — for hybrid search)
-pub async fn meili_search_memories(
-    meili_url: &str,
-    meili_key: &str,
-    query: &str,
-    memory_type: Option<&str>,
-    persona_id: Option<&str>,
-    limit: usize,
-) -> Vec<serde_json::Value> {
+/// Index or update a `memories` document (a fact, or a memory stored via the API)
+pub async fn meili_index_memory(meili_url: &str, meili_key: &str, doc: &serde_json::Value) {
     let client = reqwest::Client::new();
-    let mut body = json!({
-        "q": query,
-        "limit": limit,
-        "attributesToRetrieve": ["id", "memory_type", "title", "content", "persona_id", "tags"]
-    });
-    // Build filter: optionally by memory_type AND persona_id to prevent cross-persona leakage
-    let mut filters: Vec<String> = Vec::new();
-    if let Some(mt) = memory_type {
-        filters.push(format!("memory_type = \"{}\"", mt));
-    }
-    if let Some(pid) = persona_id {
-        filters.push(format!("persona_id = \"{}\"", pid));
-    }
-    if !filters.is_empty() {
-        body["filter"] = json!(filters.join(" AND "));
-    }
-    match client.post(format!("{}/indexes/memories/search", meili_url))
+    let _ = client.post(format!("{}/indexes/memories/documents", meili_url))
         .bearer_auth(meili_key)
-        .json(&body)
+        .json(&json!([doc]))
         .send()
         .await
-    {
-        Ok(resp) => {
-            if let Ok(json) = resp.json::<serde_json::Value>().await {
-                json["hits"].as_array().cloned().unwrap_or_default()
-            } else {
-                Vec::new()
-            }
-        }
-        Err(_) => Vec::new(),
-    }
+        .map_err(|e| tracing::warn!("Meilisearch index update failed: {}", e));
 }
 
-/// Search chats index (lexical — for hybrid search)
-/// Filters by ai_persona_id to prevent cross-persona memory leakage
-pub async fn meili_search_chats_for_rag(
-    meili_url: &str,
-    meili_key: &str,
-    query: &str,
-    ai_persona_id: Option<&str>,
-    limit: usize,
-) -> Vec<serde_json::Value> {
+/// Remove a document from the Meilisearch `memories` index
+pub async fn meili_delete_memory(meili_url: &str, meili_key: &str, id: &str) {
     let client = reqwest::Client::new();
-    let mut body = json!({
-        "q": query,
-        "limit": limit,
-        "attributesToRetrieve": ["id", "title", "messages_text"]
-    });
-    if let Some(pid) = ai_persona_id {
-        body["filter"] = json!(format!("ai_persona_id = \"{}\"", pid));
-    }
-    match client.post(format!("{}/indexes/chats/search", meili_url))
+    let _ = client.delete(format!("{}/indexes/memories/documents/{}", meili_url, id))
         .bearer_auth(meili_key)
-        .json(&body)
         .send()
         .await
-    {
-        Ok(resp) => {
-            if let Ok(json) = resp.json::<serde_json::Value>().await {
-                json["hits"].as_array().cloned().unwrap_or_default()
-            } else {
-                Vec::new()
-            }
-        }
-        Err(_) => Vec::new(),
-    }
+        .map_err(|e| tracing::warn!("Meilisearch delete failed: {}", e));
 }
 
 /// Index or update a single chat in Meilisearch
-async fn meili_index_chat(meili_url: &str, meili_key: &str, chat: &models::Chat) {
+pub async fn meili_index_chat(meili_url: &str, meili_key: &str, chat: &models::Chat) {
     let client = reqwest::Client::new();
+    let _ = client.post(format!("{}/indexes/chats/documents", meili_url))
+        .bearer_auth(meili_key)
+        .json(&json!([meili_chat_doc(chat)]))
+        .send()
+        .await
+        .map_err(|e| tracing::warn!("Meilisearch index update failed: {}", e));
+}
+
+/// The `chats` index document for a chat
+fn meili_chat_doc(chat: &models::Chat) -> serde_json::Value {
     let messages_text = chat.branches.iter()
         .flat_map(|b| &b.messages)
         .map(|m| m.content.as_str())
         .collect::<Vec<_>>()
         .join(" ");
     
-    // Extract ai_persona_id from the most recent assistant message
-    let ai_persona_id = chat.branches.iter()
-        .flat_map(|b| b.messages.iter().rev())
-        .find(|m| m.role == "assistant")
-        .and_then(|m| m.ai_persona.clone());
+    // Group chats are indexed under every cast member; otherwise the
+    // ai_persona_id of the most recent assistant message
+    let ai_persona_id = match chat.cast {
+        Some(ref cast) => json!(cast.persona_ids),
+        None => json!(chat.branches.iter()
+            .flat_map(|b| b.messages.iter().rev())
+            .find(|m| m.role == "assistant")
+            .and_then(|m| m.ai_persona.clone())),
+    };
     
-    let doc = json!([{
+    json!({
         "id": chat.id,
         "title": chat.title,
         "messages_text": messages_text,
         "group_id": chat.group_id,
         "tags": chat.tags,
         "ai_persona_id": ai_persona_id,
+        "user_id": chat.user_id,
         "created_at_ts": chat.created_at.timestamp()
-    }]);
-
-    let _ = client.post(format!("{}/indexes/chats/documents", meili_url))
-        .bearer_auth(meili_key)
-        .json(&doc)
-        .send()
-        .await
-        .map_err(|e| tracing::warn!("Meilisearch index update failed: {}", e));
-}
-
-/// Remove a chat from the Meilisearch index
-async fn meili_delete_chat(meili_url: &str, meili_key: &str, chat_id: &str) {
-    let client = reqwest::Client::new();
-    let _ = client.delete(format!("{}/indexes/chats/documents/{}", meili_url, chat_id))
-        .bearer_auth(meili_key)
-        .send()
-        .await
-        .map_err(|e| tracing::warn!("Meilisearch delete failed: {}", e));
+    })
 }
 
 /// GET /api/dreams/search?q=term - Search dreams via Meilisearch memories index
 pub async fn search_dreams(
     State(state): State<AppState>,
+    user: AuthUser,
     axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     let query = params.get("q").cloned().unwrap_or_default();
     
     if query.trim().is_empty() {
@@ -1808,7 +1744,7 @@ pub async fn search_dreams(
         .bearer_auth(&state.meili_key)
         .json(&json!({
             "q": query,
-            "filter": "memory_type = dream",
+            "filter": format!("memory_type = dream AND {}", retrieval::meili_owner_filter(&retrieval::Scope::User(user.id.clone()))),
             "limit": 50,
             "attributesToRetrieve": ["id", "title", "persona_id", "tags", "date", "created_at_ts"]
         }))
@@ -1816,18 +1752,18 @@ pub async fn search_dreams(
         .await
         .map_err(|e| {
             tracing::error!("Meilisearch dreams search failed: {}", e);
-            (StatusCode::SERVICE_UNAVAILABLE, format!("Search unavailable: {}", e))
+            ApiError::from(e).context("Search unavailable")
         })?;
 
     if !resp.status().is_success() {
         let status = resp.status();
         let body = resp.text().await.unwrap_or_default();
         tracing::error!("Meilisearch dreams search error {}: {}", status, body);
-        return Err((StatusCode::SERVICE_UNAVAILABLE, "Search unavailable".to_string()));
+        return Err(ApiError::ServiceUnavailable("Search unavailable".to_string()));
     }
 
     let body: serde_json::Value = resp.json().await.map_err(|e| {
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to parse search results: {}", e))
+        ApiError::from(e).context("Failed to parse search results")
     })?;
 
     Ok(Json(body))
@@ -1836,8 +1772,9 @@ pub async fn search_dreams(
 /// GET /api/journal/search?q=term - Search journal entries via Meilisearch memories index
 pub async fn search_journal(
     State(state): State<AppState>,
+    user: AuthUser,
     axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     let query = params.get("q").cloned().unwrap_or_default();
     
     if query.trim().is_empty() {
@@ -1849,7 +1786,7 @@ pub async fn search_journal(
         .bearer_auth(&state.meili_key)
         .json(&json!({
             "q": query,
-            "filter": "memory_type = reflection",
+            "filter": format!("memory_type = reflection AND {}", retrieval::meili_owner_filter(&retrieval::Scope::User(user.id.clone()))),
             "limit": 50,
             "attributesToRetrieve": ["id", "title", "persona_id", "tags", "date", "created_at_ts"]
         }))
@@ -1857,18 +1794,18 @@ pub async fn search_journal(
         .await
         .map_err(|e| {
             tracing::error!("Meilisearch journal search failed: {}", e);
-            (StatusCode::SERVICE_UNAVAILABLE, format!("Search unavailable: {}", e))
+            ApiError::from(e).context("Search unavailable")
         })?;
 
     if !resp.status().is_success() {
         let status = resp.status();
         let body = resp.text().await.unwrap_or_default();
         tracing::error!("Meilisearch journal search error {}: {}", status, body);
-        return Err((StatusCode::SERVICE_UNAVAILABLE, "Search unavailable".to_string()));
+        return Err(ApiError::ServiceUnavailable("Search unavailable".to_string()));
     }
 
     let body: serde_json::Value = resp.json().await.map_err(|e| {
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to parse search results: {}", e))
+        ApiError::from(e).context("Failed to parse search results")
     })?;
 
     Ok(Json(body))
@@ -1877,8 +1814,9 @@ pub async fn search_journal(
 /// GET /api/chats/search?q=term - Search chats via Meilisearch
 pub async fn search_chats(
     State(state): State<AppState>,
+    user: AuthUser,
     axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     let query = params.get("q").cloned().unwrap_or_default();
     
     if query.trim().is_empty() {
@@ -1891,6 +1829,7 @@ pub async fn search_chats(
         .bearer_auth(&state.meili_key)
         .json(&json!({
             "q": query,
+            "filter": format!("user_id = \"{}\"", user.id),
             "limit": 50,
             "attributesToRetrieve": ["id", "title", "group_id", "tags", "created_at_ts"]
         }))
@@ -1898,18 +1837,18 @@ pub async fn search_chats(
         .await
         .map_err(|e| {
             tracing::error!("Meilisearch search failed: {}", e);
-            (StatusCode::SERVICE_UNAVAILABLE, format!("Search unavailable: {}", e))
+            ApiError::from(e).context("Search unavailable")
         })?;
 
     if !resp.status().is_success() {
         let status = resp.status();
         let body = resp.text().await.unwrap_or_default();
         tracing::error!("Meilisearch search error {}: {}", status, body);
-        return Err((StatusCode::SERVICE_UNAVAILABLE, "Search unavailable".to_string()));
+        return Err(ApiError::ServiceUnavailable("Search unavailable".to_string()));
     }
 
     let body: serde_json::Value = resp.json().await.map_err(|e| {
-        (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to parse search results: {}", e))
+        ApiError::from(e).context("Failed to parse search results")
     })?;
 
     Ok(Json(body))
@@ -1926,6 +1865,8 @@ pub struct SearchRequest {
     #[serde(default = "default_limit")]
     pub limit: usize,
     pub memory_type: Option<String>,
+    /// Restrict to this persona's memories and apply its reranker, if configured
+    pub persona_id: Option<String>,
 }
 
 fn default_limit() -> usize { 5 }
@@ -1933,8 +1874,9 @@ fn default_limit() -> usize { 5 }
 /// POST /api/search - Hybrid search over memories (Qdrant semantic + Meilisearch lexical)
 pub async fn search_memories(
     State(state): State<AppState>,
+    user: AuthUser,
     Json(payload): Json<SearchRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     let memory_type = payload.memory_type.as_ref().map(|t| {
         match t.as_str() {
             "conversation" => vector::MemoryType::Conversation,
@@ -1946,72 +1888,63 @@ pub async fn search_memories(
         }
     });
 
-    // Qdrant — semantic search (with embedding cache)
-    let semantic_results = match vector::search_memories_cached(
-        &state.vector,
-        &state.ollama_host,
-        &state.cache,
-        "azera_memory",
-        &payload.query,
-        payload.limit,
-        memory_type,
-    ).await {
-        Ok(results) => results,
-        Err(e) => {
-            tracing::warn!("Qdrant search failed: {}", e);
-            Vec::new()
-        }
+    // Qdrant (semantic) + Meilisearch (lexical), fused into one ranking
+    let reranker = match payload.persona_id {
+        Some(ref persona_id) => match db::get_visible_persona(&state.db, persona_id, &user.id).await {
+            Ok(Some(persona)) => persona.reranker,
+            Ok(None) => return Err(ApiError::NotFound("Persona not found".to_string())),
+            Err(e) => {
+                tracing::error!("Failed to get persona: {}", e);
+                return Err(ApiError::from(e).context("Failed to get persona"));
+            }
+        },
+        None => None,
     };
 
-    // Meilisearch — lexical search
-    let meili_type = payload.memory_type.as_deref();
-    let lexical_results = meili_search_memories(
-        &state.meili_url, &state.meili_key, &payload.query, meili_type, None, payload.limit
-    ).await;
-
-    // Merge results, semantic first, then lexical (deduped)
-    let mut items: Vec<serde_json::Value> = Vec::new();
-    let mut seen_ids = std::collections::HashSet::new();
-
-    for r in &semantic_results {
-        seen_ids.insert(r.id.clone());
-        items.push(json!({
-            "id": r.id,
-            "score": r.score,
-            "source": "semantic",
-            "content": r.payload.get("content").and_then(|v| v.as_str()),
-            "type": r.payload.get("type").and_then(|v| v.as_str()),
-            "timestamp": r.payload.get("timestamp").and_then(|v| v.as_str()),
-        }));
-    }
-
-    for hit in &lexical_results {
-        let id = hit["id"].as_str().unwrap_or_default();
-        if !seen_ids.contains(id) {
-            items.push(json!({
-                "id": id,
-                "score": 0.5, // Default score for lexical matches
-                "source": "lexical",
-                "content": hit["content"].as_str(),
-                "type": hit["memory_type"].as_str(),
-                "title": hit["title"].as_str(),
-            }));
-        }
-    }
+    let mut query = retrieval::RetrievalQuery::new(&payload.query, retrieval::Scope::User(user.id));
+    query.memory_type = memory_type;
+    query.persona_id = payload.persona_id.clone();
+    query.limit = if reranker.is_some() { payload.limit.max(conversation::RAG_RERANK_CANDIDATES) } else { payload.limit };
+    let mut retrieved = retrieval::HybridRetriever::new(&state).retrieve(&query).await;
+    if let Some(ref config) = reranker {
+        retrieved.memories = rerank::Reranker::from_config(config, &state).rerank(&payload.query, retrieved.memories).await;
+        retrieved.memories.truncate(payload.limit);
+    }
+
+    let items: Vec<serde_json::Value> = retrieved.memories.iter().map(|m| {
+        let source = match (m.found_by(retrieval::RetrievalSource::Semantic), m.found_by(retrieval::RetrievalSource::Lexical)) {
+            (true, true) => "hybrid",
+            (true, false) => "semantic",
+            _ => "lexical",
+        };
+        json!({
+            "id": m.id,
+            "score": m.score,
+            "rerank_score": m.rerank_score,
+            "importance": m.importance(),
+            "source": source,
+            "content": m.content,
+            "type": m.memory_type,
+            "title": m.title,
+            "timestamp": m.timestamp,
+            "provenance": m.provenance,
+        })
+    }).collect();
     
     Ok(Json(json!({
         "results": items,
         "total": items.len(),
-        "semantic_count": semantic_results.len(),
-        "lexical_count": lexical_results.len()
+        "semantic_count": retrieved.semantic_hits,
+        "lexical_count": retrieved.lexical_hits
     })))
 }
 
 /// POST /api/memories - Store a memory in vector DB (with embedding cache)
 pub async fn store_memory(
     State(state): State<AppState>,
+    user: AuthUser,
     Json(payload): Json<serde_json::Value>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     let content = payload["content"].as_str().unwrap_or_default();
     let memory_type_str = payload["type"].as_str().unwrap_or("conversation");
     
@@ -2023,8 +1956,10 @@ pub async fn store_memory(
         _ => vector::MemoryType::Conversation,
     };
 
-    let id = format!("mem_{}", uuid::Uuid::new_v4());
-    let metadata = std::collections::HashMap::new();
+    // Qdrant point IDs are UUIDs; the Meilisearch document shares it
+    let id = uuid::Uuid::new_v4().to_string();
+    let mut metadata = std::collections::HashMap::new();
+    metadata.insert("user_id".to_string(), json!(user.id.clone()));
 
     let request = vector::StoreMemoryRequest {
         collection: "azera_memory".to_string(),
@@ -2047,6 +1982,7 @@ pub async fn store_memory(
             let content_owned = content.to_string();
             let type_str = memory_type.to_string();
             let id_clone = id.clone();
+            let user_id = user.id;
             tokio::spawn(async move {
                 let client = reqwest::Client::new();
                 let doc = json!([{
@@ -2054,6 +1990,7 @@ pub async fn store_memory(
                     "memory_type": type_str,
                     "title": "",
                     "content": content_owned,
+                    "user_id": user_id,
                     "date": chrono::Utc::now().format("%Y-%m-%d").to_string(),
                     "created_at_ts": chrono::Utc::now().timestamp()
                 }]);
@@ -2067,7 +2004,118 @@ pub async fn store_memory(
         }
         Err(e) => {
             tracing::error!("Failed to store memory: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to store memory: {}", e)))
+            Err(ApiError::Internal(format!("Failed to store memory: {}", e)))
+        }
+    }
+}
+
+/// Fetch a memory point `user_id` may read
+async fn visible_memory(state: &AppState, id: &str, user_id: &str) -> Result<vector::StoredPoint, ApiError> {
+    let not_found = || ApiError::NotFound("Memory not found".to_string());
+    if !vector::is_point_id(id) {
+        return Err(not_found());
+    }
+    match state.vector.get("azera_memory", id).await {
+        Ok(Some(point)) if memories::visible_to(&point, user_id) => Ok(point),
+        Ok(_) => Err(not_found()),
+        Err(e) => {
+            tracing::error!("Failed to get memory: {}", e);
+            Err(ApiError::from(e).context("Failed to get memory"))
+        }
+    }
+}
+
+/// Fetch a memory point `user_id` may change
+async fn owned_memory(state: &AppState, id: &str, user_id: &str) -> Result<vector::StoredPoint, ApiError> {
+    let point = visible_memory(state, id, user_id).await?;
+    if !memories::owned_by(&point, user_id) {
+        return Err(ApiError::Forbidden("Shared memories cannot be changed".to_string()));
+    }
+    Ok(point)
+}
+
+/// GET /api/memories?persona_id=&type=&chat_id=&date=&include_forgotten=&limit=&cursor=
+/// - One page of the memories the current user can see
+pub async fn list_memories(
+    State(state): State<AppState>,
+    user: AuthUser,
+    axum::extract::Query(query): axum::extract::Query<models::MemoryListQuery>,
+) -> Result<Json<models::CursorPage<models::Memory>>, ApiError> {
+    let offset = match query.cursor.as_deref() {
+        Some(raw) => {
+            let cursor = pagination::Cursor::decode(raw, memories::CURSOR_SORT).map_err(ApiError::BadRequest)?;
+            if !vector::is_point_id(&cursor.id) {
+                return Err(ApiError::BadRequest("Invalid cursor".to_string()));
+            }
+            Some(cursor.id)
+        }
+        None => None,
+    };
+    let filter = memories::list_filter(&user.id, &query).map_err(ApiError::BadRequest)?;
+    let limit = pagination::page_size(query.limit);
+    match state.vector.scroll("azera_memory", Some(filter), limit, offset.as_deref()).await {
+        Ok(page) => {
+            // Qdrant's offset is the first point of the next page
+            let next_cursor = page.next_offset.map(|id| pagination::Cursor {
+                sort: memories::CURSOR_SORT.to_string(),
+                key: id.clone(),
+                id,
+            }.encode());
+            Ok(Json(models::CursorPage {
+                items: page.points.into_iter().map(memories::to_memory).collect(),
+                next_cursor,
+            }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to list memories: {}", e);
+            Err(ApiError::from(e).context("Failed to list memories"))
+        }
+    }
+}
+
+/// GET /api/memories/:id - Get a memory
+pub async fn get_memory(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<models::Memory>, ApiError> {
+    let point = visible_memory(&state, &id, &user.id).await?;
+    Ok(Json(memories::to_memory(point)))
+}
+
+/// PUT /api/memories/:id - Edit or pin one of the current user's memories
+pub async fn update_memory(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(id): Path<String>,
+    Json(req): Json<models::UpdateMemoryRequest>,
+) -> Result<Json<models::Memory>, ApiError> {
+    if req.content.as_deref().is_some_and(|c| c.trim().is_empty()) {
+        return Err(ApiError::BadRequest("Memory content cannot be empty".to_string()));
+    }
+    let point = owned_memory(&state, &id, &user.id).await?;
+    match memories::update(&state, point, &user.id, &req).await {
+        Ok(point) => Ok(Json(memories::to_memory(point))),
+        Err(e) => {
+            tracing::error!("Failed to update memory: {}", e);
+            Err(ApiError::from(e).context("Failed to update memory"))
+        }
+    }
+}
+
+/// DELETE /api/memories/:id - Make the personas forget one of the current
+/// user's memories
+pub async fn delete_memory(
+    State(state): State<AppState>,
+    user: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let point = owned_memory(&state, &id, &user.id).await?;
+    match memories::forget(&state, &point, &user.id).await {
+        Ok(()) => Ok(Json(json!({ "status": "deleted" }))),
+        Err(e) => {
+            tracing::error!("Failed to delete memory: {}", e);
+            Err(ApiError::from(e).context("Failed to delete memory"))
         }
     }
 }
@@ -2085,11 +2133,25 @@ pub async fn health_check() -> impl IntoResponse {
 // Model Management Endpoints
 // ============================================================
 
-/// GET /api/models - List installed Ollama models
+/// GET /api/models?provider=openai&base_url=... - List models served by an LLM backend (default: Ollama)
 pub async fn list_models(
     State(state): State<AppState>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    let llm = crate::llm::LLMService::new(state.ollama_host.clone());
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let kind = match params.get("provider").map(|p| p.as_str()) {
+        None | Some("") | Some("ollama") => models::ProviderKind::Ollama,
+        Some("openai") => models::ProviderKind::OpenAI,
+        Some(other) => {
+            return Err(ApiError::BadRequest(format!("Unknown provider: {}", other)));
+        }
+    };
+    let provider = models::ProviderConfig {
+        kind,
+        base_url: params.get("base_url").filter(|u| !u.is_empty()).cloned(),
+        api_key: None,
+    };
+    check_backend_urls(&state, Some(&provider), None).await?;
+    let llm = crate::llm::LLMService::from_config(&provider, &state);
     
     match llm.list_models().await {
         Ok(models) => {
@@ -2116,7 +2178,7 @@ pub async fn list_models(
         }
         Err(e) => {
             tracing::error!("Failed to list models: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to list models: {}", e)))
+            Err(ApiError::from(e).context("Failed to list models"))
         }
     }
 }
@@ -2131,6 +2193,7 @@ pub async fn pull_model(
     let (tx, rx) = mpsc::channel::<Result<Event, Infallible>>(100);
     let ollama_host = state.ollama_host.clone();
     let model = payload.model.clone();
+    let events = state.events.clone();
     
     tokio::spawn(async move {
         let client = reqwest::Client::builder()
@@ -2183,6 +2246,7 @@ pub async fn pull_model(
                                         if let Err(e) = update_ollama_ledger(&ollama_host).await {
                                             tracing::error!("Failed to update ledger: {}", e);
                                         }
+                                        events.publish(events::AgentEvent::ModelPulled { model: model.clone() });
                                         
                                         let _ = tx.send(Ok(Event::default()
                                             .event("complete")
@@ -2229,7 +2293,7 @@ pub async fn pull_model(
 pub async fn delete_model(
     State(state): State<AppState>,
     Path(model_name): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     tracing::info!("🗑️ Deleting model: {}", model_name);
     
     // Convert underscores back to colons (we encoded : as _ in the API)
@@ -2255,14 +2319,16 @@ pub async fn delete_model(
             })))
         }
         Ok(resp) => {
-            let status_code = resp.status().as_u16();
+            let status = resp.status();
             let error_text = resp.text().await.unwrap_or_default();
-            let axum_status = StatusCode::from_u16(status_code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
-            Err((axum_status, format!("Failed to delete model: {}", error_text)))
-        }
-        Err(e) => {
-            Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete model: {}", e)))
+            let message = format!("Failed to delete model: {}", error_text);
+            if status == reqwest::StatusCode::NOT_FOUND {
+                Err(ApiError::NotFound(message))
+            } else {
+                Err(ApiError::Upstream(message))
+            }
         }
+        Err(e) => Err(ApiError::from(e).context("Failed to delete model")),
     }
 }
 
@@ -2270,11 +2336,12 @@ pub async fn delete_model(
 // User Settings Endpoints
 // ============================================================
 
-/// GET /api/settings - Get user settings
+/// GET /api/settings - Get the current user's settings
 pub async fn get_settings(
     State(state): State<AppState>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    match db::get_user_settings(&state.db).await {
+    user: AuthUser,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    match db::get_user_settings(&state.db, &user.id).await {
         Ok(Some((editor_settings, ui_settings))) => {
             Ok(Json(serde_json::json!({
                 "editorSettings": editor_settings,
@@ -2297,7 +2364,7 @@ pub async fn get_settings(
         }
         Err(e) => {
             tracing::error!("Failed to get user settings: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to get settings".to_string()))
+            Err(ApiError::from(e).context("Failed to get settings"))
         }
     }
 }
@@ -2305,16 +2372,17 @@ pub async fn get_settings(
 /// PUT /api/settings/editor - Update editor settings
 pub async fn update_editor_settings(
     State(state): State<AppState>,
+    user: AuthUser,
     Json(settings): Json<serde_json::Value>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    match db::save_editor_settings(&state.db, &settings).await {
+) -> Result<Json<serde_json::Value>, ApiError> {
+    match db::save_editor_settings(&state.db, &user.id, &settings).await {
         Ok(_) => {
             tracing::info!("✅ Updated editor settings");
             Ok(Json(serde_json::json!({ "status": "ok" })))
         }
         Err(e) => {
             tracing::error!("Failed to save editor settings: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to save settings".to_string()))
+            Err(ApiError::from(e).context("Failed to save settings"))
         }
     }
 }
@@ -2322,16 +2390,17 @@ pub async fn update_editor_settings(
 /// PUT /api/settings/ui - Update UI settings
 pub async fn update_ui_settings(
     State(state): State<AppState>,
+    user: AuthUser,
     Json(settings): Json<serde_json::Value>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    match db::save_ui_settings(&state.db, &settings).await {
+) -> Result<Json<serde_json::Value>, ApiError> {
+    match db::save_ui_settings(&state.db, &user.id, &settings).await {
         Ok(_) => {
             tracing::info!("✅ Updated UI settings");
             Ok(Json(serde_json::json!({ "status": "ok" })))
         }
         Err(e) => {
             tracing::error!("Failed to save UI settings: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to save settings".to_string()))
+            Err(ApiError::from(e).context("Failed to save settings"))
         }
     }
 }
@@ -2478,7 +2547,7 @@ fn concatenate_wav_audio(audio_chunks: Vec<Vec<u8>>) -> Vec<u8> {
 pub async fn synthesize_speech(
     State(state): State<AppState>,
     Json(payload): Json<models::TtsSynthesisRequest>,
-) -> Result<Json<models::TtsSynthesisResponse>, (StatusCode, String)> {
+) -> Result<Json<models::TtsSynthesisResponse>, ApiError> {
     use base64::Engine;
     
     tracing::info!("🔊 TTS synthesis request: text_len={}", payload.text.len());
@@ -2501,7 +2570,7 @@ pub async fn synthesize_speech(
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(120))
         .build()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create HTTP client: {}", e)))?;
+        .map_err(|e| ApiError::Internal(format!("Failed to create HTTP client: {}", e)))?;
     
     // Check if XTTS server is available
     let xtts_health_url = format!("{}/", state.xtts_url);
@@ -2511,17 +2580,11 @@ pub async fn synthesize_speech(
         }
         Ok(resp) => {
             tracing::warn!("🔊 XTTS server returned {}", resp.status());
-            return Err((
-                StatusCode::SERVICE_UNAVAILABLE,
-                format!("XTTS server not ready: {}", resp.status())
-            ));
+            return Err(ApiError::ServiceUnavailable(format!("XTTS server not ready: {}", resp.status())));
         }
         Err(e) => {
             tracing::warn!("🔊 XTTS server not available: {}", e);
-            return Err((
-                StatusCode::SERVICE_UNAVAILABLE,
-                format!("XTTS server not available at {}. Start with: docker compose up xtts", state.xtts_url)
-            ));
+            return Err(ApiError::ServiceUnavailable(format!("XTTS server not available at {}. Start with: docker compose up xtts", state.xtts_url)));
         }
     }
     
@@ -2561,7 +2624,7 @@ pub async fn synthesize_speech(
             match client.post(&clone_url).multipart(form).send().await {
                 Ok(resp) if resp.status().is_success() => {
                     resp.json::<serde_json::Value>().await
-                        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to parse clone response: {}", e)))?
+                        .map_err(|e| ApiError::Internal(format!("Failed to parse clone response: {}", e)))?
                 }
                 Ok(resp) => {
                     let error = resp.text().await.unwrap_or_default();
@@ -2601,25 +2664,22 @@ pub async fn synthesize_speech(
             .json(&tts_request)
             .send()
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("XTTS request failed: {}", e)))?;
+            .map_err(|e| ApiError::from(e).context("XTTS request failed"))?;
         
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_default();
             tracing::error!("🔊 XTTS error on chunk {}: {} - {}", i + 1, status, error_text);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("XTTS synthesis failed on chunk {}: {} - {}", i + 1, status, error_text)
-            ));
+            return Err(ApiError::Upstream(format!("XTTS synthesis failed on chunk {}: {} - {}", i + 1, status, error_text)));
         }
         
         // XTTS returns JSON with base64-encoded audio string
         let audio_base64: String = response.json().await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to parse XTTS response: {}", e)))?;
+            .map_err(|e| ApiError::from(e).context("Failed to parse XTTS response"))?;
         
         // Decode to get raw audio bytes
         let audio_bytes = base64::engine::general_purpose::STANDARD.decode(&audio_base64)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to decode audio: {}", e)))?;
+            .map_err(|e| ApiError::Internal(format!("Failed to decode audio: {}", e)))?;
         
         audio_parts.push(audio_bytes);
     }
@@ -2642,21 +2702,21 @@ pub async fn synthesize_speech(
 }
 
 /// Get default studio speaker embeddings from XTTS
-async fn get_default_speaker(client: &reqwest::Client, xtts_url: &str) -> Result<serde_json::Value, (StatusCode, String)> {
+async fn get_default_speaker(client: &reqwest::Client, xtts_url: &str) -> Result<serde_json::Value, ApiError> {
     let speakers_url = format!("{}/studio_speakers", xtts_url);
     let speakers: serde_json::Value = client.get(&speakers_url)
         .send()
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get speakers: {}", e)))?
+        .map_err(|e| ApiError::from(e).context("Failed to get speakers"))?
         .json()
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to parse speakers: {}", e)))?;
+        .map_err(|e| ApiError::from(e).context("Failed to parse speakers"))?;
     
     // Use "Sofia Hellen" as default female voice (or first available)
     let default_speaker = speakers.get("Sofia Hellen")
         .or_else(|| speakers.as_object().and_then(|o| o.values().next()))
         .cloned()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "No speakers available".to_string()))?;
+        .ok_or(ApiError::Internal("No speakers available".to_string()))?;
     
     Ok(default_speaker)
 }
@@ -2668,7 +2728,7 @@ async fn get_default_speaker(client: &reqwest::Client, xtts_url: &str) -> Result
 /// POST /api/voice-samples/upload - Upload a voice sample for cloning
 pub async fn upload_voice_sample(
     mut multipart: Multipart,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<serde_json::Value>, ApiError> {
     use std::io::Write;
     
     // Use relative path that works both locally and in Docker
@@ -2678,11 +2738,11 @@ pub async fn upload_voice_sample(
     if !voice_samples_dir.exists() {
         tokio::fs::create_dir_all(&voice_samples_dir)
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create voice_samples directory: {}", e)))?;
+            .map_err(|e| ApiError::Internal(format!("Failed to create voice_samples directory: {}", e)))?;
     }
     
     while let Some(field) = multipart.next_field().await
-        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Failed to read multipart field: {}", e)))? 
+        .map_err(|e| ApiError::BadRequest(format!("Failed to read multipart field: {}", e)))? 
     {
         let field_name = field.name().unwrap_or("").to_string();
         
@@ -2706,19 +2766,19 @@ pub async fn upload_voice_sample(
             
             // Read file data
             let data = field.bytes().await
-                .map_err(|e| (StatusCode::BAD_REQUEST, format!("Failed to read file data: {}", e)))?;
+                .map_err(|e| ApiError::BadRequest(format!("Failed to read file data: {}", e)))?;
             
             // Validate file size (max 10MB)
             if data.len() > 10 * 1024 * 1024 {
-                return Err((StatusCode::BAD_REQUEST, "File too large (max 10MB)".to_string()));
+                return Err(ApiError::BadRequest("File too large (max 10MB)".to_string()));
             }
             
             // Write file
             let mut file = std::fs::File::create(&file_path)
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create file: {}", e)))?;
+                .map_err(|e| ApiError::Internal(format!("Failed to create file: {}", e)))?;
             
             file.write_all(&data)
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to write file: {}", e)))?;
+                .map_err(|e| ApiError::Internal(format!("Failed to write file: {}", e)))?;
             
             tracing::info!("🎤 Uploaded voice sample: {} ({} bytes)", filename, data.len());
             
@@ -2734,32 +2794,32 @@ pub async fn upload_voice_sample(
         }
     }
     
-    Err((StatusCode::BAD_REQUEST, "No audio file found in request".to_string()))
+    Err(ApiError::BadRequest("No audio file found in request".to_string()))
 }
 
 /// GET /api/voice-samples/:filename - Download/stream a voice sample
 pub async fn get_voice_sample(
     Path(filename): Path<String>,
-) -> Result<Response<Body>, (StatusCode, String)> {
+) -> Result<Response<Body>, ApiError> {
     use axum::http::header;
     
     // Sanitize filename to prevent path traversal
     let safe_filename = std::path::Path::new(&filename)
         .file_name()
         .and_then(|n| n.to_str())
-        .ok_or((StatusCode::BAD_REQUEST, "Invalid filename".to_string()))?;
+        .ok_or(ApiError::BadRequest("Invalid filename".to_string()))?;
     
     // Use relative path that works both locally and in Docker
     let file_path = std::path::PathBuf::from("../voice_samples").join(safe_filename);
     
     if !file_path.exists() {
-        return Err((StatusCode::NOT_FOUND, format!("Voice sample not found: {}", safe_filename)));
+        return Err(ApiError::NotFound(format!("Voice sample not found: {}", safe_filename)));
     }
     
     // Read file
     let data = tokio::fs::read(&file_path)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read file: {}", e)))?;
+        .map_err(|e| ApiError::Internal(format!("Failed to read file: {}", e)))?;
     
     // Determine content type from extension
     let content_type = match file_path.extension().and_then(|e| e.to_str()) {
@@ -2786,6 +2846,7 @@ pub async fn get_voice_sample(
 /// POST /api/images/generate - Generate an image from a prompt (SSE streaming)
 pub async fn generate_image(
     State(state): State<AppState>,
+    user: AuthUser,
     Json(payload): Json<models::ImageGenerationRequest>,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
     tracing::info!("🎨 Generating image: {}", payload.prompt);
@@ -2807,6 +2868,8 @@ pub async fn generate_image(
     
     // Fetch persona name if persona_id is provided
     let db = state.db.clone();
+    let events = state.events.clone();
+    let user_id = user.id;
     
     tokio::spawn(async move {
         // Get persona name for filename prefix
@@ -2863,6 +2926,16 @@ pub async fn generate_image(
             } else {
                 format!("{}_{}.svg", timestamp, &image_id[..8])
             };
+            let filename = match claim_canvas_name(&db, filename, &image_id, &user_id, &prompt, persona_id.as_deref(), persona_name.as_deref()).await {
+                Ok(filename) => filename,
+                Err(e) => {
+                    let _ = tx.send(Ok(Event::default()
+                        .event("error")
+                        .data(json!({"message": format!("Failed to record image owner: {}", e)}).to_string())
+                    )).await;
+                    return;
+                }
+            };
             
             // Ensure canvas directory exists
             let canvas_dir = std::path::PathBuf::from("./atelier/canvas");
@@ -2901,6 +2974,7 @@ pub async fn generate_image(
             };
             
             tracing::info!("🖼️ Created placeholder image: {}", filename);
+            events.publish_to(&user_id, image_generated(&image.filename, &image.prompt, image.persona_id.as_deref()));
             
             let _ = tx.send(Ok(Event::default()
                 .event("complete")
@@ -3004,6 +3078,16 @@ pub async fn generate_image(
                                         } else {
                                             format!("{}_{}.png", timestamp, &image_id[..8])
                                         };
+                                        let filename = match claim_canvas_name(&db, filename, &image_id, &user_id, &prompt, persona_id.as_deref(), persona_name.as_deref()).await {
+                                            Ok(filename) => filename,
+                                            Err(e) => {
+                                                let _ = tx.send(Ok(Event::default()
+                                                    .event("error")
+                                                    .data(json!({"message": format!("Failed to record image owner: {}", e)}).to_string())
+                                                )).await;
+                                                return;
+                                            }
+                                        };
                                         
                                         let canvas_dir = std::path::PathBuf::from("./atelier/canvas");
                                         let _ = tokio::fs::create_dir_all(&canvas_dir).await;
@@ -3042,6 +3126,7 @@ pub async fn generate_image(
                                         };
                                         
                                         tracing::info!("🖼️ Generated image: {}", filename);
+                                        events.publish_to(&user_id, image_generated(&image.filename, &image.prompt, image.persona_id.as_deref()));
                                         
                                         let _ = tx.send(Ok(Event::default()
                                             .event("complete")
@@ -3100,98 +3185,42 @@ pub async fn generate_image(
         )
 }
 
-/// GET /api/images - List all generated images
-pub async fn list_images() -> Result<Json<models::ListResponse<models::GeneratedImage>>, (StatusCode, String)> {
+/// GET /api/images - List the current user's generated images
+pub async fn list_images(
+    State(state): State<AppState>,
+    user: AuthUser,
+) -> Result<Json<models::ListResponse<models::GeneratedImage>>, ApiError> {
     let canvas_dir = std::path::PathBuf::from("./atelier/canvas");
-    
-    if !canvas_dir.exists() {
-        return Ok(Json(models::ListResponse { items: vec![], total: 0 }));
-    }
-    
-    let mut images = Vec::new();
-    
-    let mut entries = tokio::fs::read_dir(&canvas_dir)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read canvas directory: {}", e)))?;
-    
-    while let Some(entry) = entries.next_entry().await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read directory entry: {}", e)))? 
-    {
-        let path = entry.path();
-        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-            if ["png", "jpg", "jpeg", "svg", "webp"].contains(&ext.to_lowercase().as_str()) {
-                let filename = path.file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("unknown")
-                    .to_string();
-                
-                let metadata = entry.metadata().await.ok();
-                let created_at = metadata
-                    .and_then(|m| m.created().ok())
-                    .map(chrono::DateTime::<chrono::Utc>::from)
-                    .unwrap_or_else(chrono::Utc::now);
-                
-                // Try to extract persona name from filename prefix
-                let parts: Vec<&str> = filename.split('_').collect();
-                let (persona_name, _) = if parts.len() > 1 {
-                    // Check if first part looks like a persona name (not a timestamp)
-                    if parts[0].parse::<i64>().is_err() && !parts[0].starts_with("20") {
-                        (Some(parts[0].to_string()), parts[1..].join("_"))
-                    } else {
-                        (None, filename.clone())
-                    }
-                } else {
-                    (None, filename.clone())
-                };
-                
-                images.push(models::GeneratedImage {
-                    id: filename.clone(),
-                    filename: filename.clone(),
-                    url: format!("/api/images/{}", filename),
-                    prompt: "".to_string(),  // Would need metadata file to store this
-                    negative_prompt: None,
-                    model: None,
-                    width: 512,  // Default, would need metadata
-                    height: 512,
-                    steps: None,
-                    cfg_scale: None,
-                    seed: None,
-                    persona_id: None,
-                    persona_name,
-                    created_at,
-                });
-            }
+    match db::list_images(&state.db, &user.id).await {
+        Ok(images) => {
+            // Skip records whose file is gone
+            let images: Vec<_> = images
+                .into_iter()
+                .filter(|i| canvas_dir.join(&i.filename).exists())
+                .collect();
+            let total = images.len();
+            Ok(Json(models::ListResponse { items: images, total }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to list images: {}", e);
+            Err(ApiError::from(e).context("Failed to list images"))
         }
     }
-    
-    // Sort by creation date, newest first
-    images.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-    
-    let total = images.len();
-    Ok(Json(models::ListResponse { items: images, total }))
 }
 
-/// GET /api/images/:filename - Serve a generated image
+/// GET /api/images/:filename - Serve one of the current user's generated images
 pub async fn get_image(
+    State(state): State<AppState>,
+    user: AuthUser,
     Path(filename): Path<String>,
-) -> Result<Response<Body>, (StatusCode, String)> {
+) -> Result<Response<Body>, ApiError> {
     use axum::http::header;
     
-    // Sanitize filename
-    let safe_filename = std::path::Path::new(&filename)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .ok_or((StatusCode::BAD_REQUEST, "Invalid filename".to_string()))?;
-    
-    let file_path = std::path::PathBuf::from("./atelier/canvas").join(safe_filename);
-    
-    if !file_path.exists() {
-        return Err((StatusCode::NOT_FOUND, format!("Image not found: {}", safe_filename)));
-    }
+    let file_path = owned_image_path(&state, &user, db::IMAGE_GENERATED, "./atelier/canvas", &filename).await?;
     
     let data = tokio::fs::read(&file_path)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read image: {}", e)))?;
+        .map_err(|e| ApiError::Internal(format!("Failed to read image: {}", e)))?;
     
     let content_type = match file_path.extension().and_then(|e| e.to_str()) {
         Some("png") => "image/png",
@@ -3206,29 +3235,26 @@ pub async fn get_image(
         .status(StatusCode::OK)
         .header(header::CONTENT_TYPE, content_type)
         .header(header::CONTENT_LENGTH, data.len())
-        .header(header::CACHE_CONTROL, "public, max-age=31536000")
+        .header(header::CACHE_CONTROL, "private, max-age=31536000")
         .body(Body::from(data))
         .unwrap())
 }
 
-/// DELETE /api/images/:filename - Delete an image
+/// DELETE /api/images/:filename - Delete one of the current user's images
 pub async fn delete_image(
+    State(state): State<AppState>,
+    user: AuthUser,
     Path(filename): Path<String>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    let safe_filename = std::path::Path::new(&filename)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .ok_or((StatusCode::BAD_REQUEST, "Invalid filename".to_string()))?;
-    
-    let file_path = std::path::PathBuf::from("./atelier/canvas").join(safe_filename);
-    
-    if !file_path.exists() {
-        return Err((StatusCode::NOT_FOUND, format!("Image not found: {}", safe_filename)));
-    }
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let file_path = owned_image_path(&state, &user, db::IMAGE_GENERATED, "./atelier/canvas", &filename).await?;
+    let safe_filename = file_path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
     
     tokio::fs::remove_file(&file_path)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete image: {}", e)))?;
+        .map_err(|e| ApiError::Internal(format!("Failed to delete image: {}", e)))?;
+    db::delete_image(&state.db, db::IMAGE_GENERATED, safe_filename)
+        .await
+        .map_err(|e| ApiError::from(e).context("Failed to delete image"))?;
     
     tracing::info!("🗑️ Deleted image: {}", safe_filename);
     
@@ -3237,18 +3263,20 @@ pub async fn delete_image(
 
 /// POST /api/images/upload-reference - Upload a reference image for img2img
 pub async fn upload_reference_image(
+    State(state): State<AppState>,
+    user: AuthUser,
     mut multipart: Multipart,
-) -> Result<Json<models::ImageUploadResponse>, (StatusCode, String)> {
+) -> Result<Json<models::ImageUploadResponse>, ApiError> {
     let refs_dir = std::path::PathBuf::from("./atelier/canvas/references");
     
     if !refs_dir.exists() {
         tokio::fs::create_dir_all(&refs_dir)
             .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create references directory: {}", e)))?;
+            .map_err(|e| ApiError::Internal(format!("Failed to create references directory: {}", e)))?;
     }
     
     while let Some(field) = multipart.next_field().await
-        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Failed to read multipart field: {}", e)))? 
+        .map_err(|e| ApiError::BadRequest(format!("Failed to read multipart field: {}", e)))? 
     {
         let field_name = field.name().unwrap_or("").to_string();
         
@@ -3272,16 +3300,23 @@ pub async fn upload_reference_image(
             let file_path = refs_dir.join(&filename);
             
             let data = field.bytes().await
-                .map_err(|e| (StatusCode::BAD_REQUEST, format!("Failed to read file data: {}", e)))?;
+                .map_err(|e| ApiError::BadRequest(format!("Failed to read file data: {}", e)))?;
             
             // Validate file size (max 20MB for images)
             if data.len() > 20 * 1024 * 1024 {
-                return Err((StatusCode::BAD_REQUEST, "File too large (max 20MB)".to_string()));
+                return Err(ApiError::BadRequest("File too large (max 20MB)".to_string()));
+            }
+            
+            // Name is unique, so the claim only fails on a database error
+            match db::claim_image(&state.db, db::IMAGE_REFERENCE, &filename, &user.id, None, None, None).await {
+                Ok(true) => {}
+                Ok(false) => return Err(ApiError::Conflict(format!("Reference image {} already exists", filename))),
+                Err(e) => return Err(ApiError::from(e).context("Failed to record reference image")),
             }
             
             tokio::fs::write(&file_path, &data)
                 .await
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to save file: {}", e)))?;
+                .map_err(|e| ApiError::Internal(format!("Failed to save file: {}", e)))?;
             
             tracing::info!("📤 Uploaded reference image: {} ({} bytes)", filename, data.len());
             
@@ -3292,29 +3327,22 @@ pub async fn upload_reference_image(
         }
     }
     
-    Err((StatusCode::BAD_REQUEST, "No image file found in request".to_string()))
+    Err(ApiError::BadRequest("No image file found in request".to_string()))
 }
 
-/// GET /api/images/references/:filename - Serve a reference image
+/// GET /api/images/references/:filename - Serve one of the current user's reference images
 pub async fn get_reference_image(
+    State(state): State<AppState>,
+    user: AuthUser,
     Path(filename): Path<String>,
-) -> Result<Response<Body>, (StatusCode, String)> {
+) -> Result<Response<Body>, ApiError> {
     use axum::http::header;
     
-    let safe_filename = std::path::Path::new(&filename)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .ok_or((StatusCode::BAD_REQUEST, "Invalid filename".to_string()))?;
-    
-    let file_path = std::path::PathBuf::from("./atelier/canvas/references").join(safe_filename);
-    
-    if !file_path.exists() {
-        return Err((StatusCode::NOT_FOUND, format!("Reference image not found: {}", safe_filename)));
-    }
+    let file_path = owned_image_path(&state, &user, db::IMAGE_REFERENCE, "./atelier/canvas/references", &filename).await?;
     
     let data = tokio::fs::read(&file_path)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read image: {}", e)))?;
+        .map_err(|e| ApiError::Internal(format!("Failed to read image: {}", e)))?;
     
     let content_type = match file_path.extension().and_then(|e| e.to_str()) {
         Some("png") => "image/png",
@@ -3334,11 +3362,12 @@ pub async fn get_reference_image(
 
 /// GET /api/images/models - List available image generation models
 /// Returns the pre-installed models served by the image generation sidecar
-pub async fn list_image_models() -> Json<Vec<models::ImageModel>> {
+pub async fn list_image_models() -> Result<Json<Vec<models::ImageModel>>, ApiError> {
     // Query the SD WebUI API for real available models
     let image_gen_url = std::env::var("IMAGE_GEN_URL").unwrap_or_default();
     if image_gen_url.is_empty() {
-        return Json(vec![]);
+        // Image generation isn't configured; there are simply no models
+        return Ok(Json(vec![]));
     }
 
     let client = reqwest::Client::builder()
@@ -3347,36 +3376,79 @@ pub async fn list_image_models() -> Json<Vec<models::ImageModel>> {
         .unwrap_or_else(|_| reqwest::Client::new());
 
     let url = format!("{}/sdapi/v1/sd-models", image_gen_url);
-    match client.get(&url).send().await {
-        Ok(resp) if resp.status().is_success() => {
-            match resp.json::<Vec<serde_json::Value>>().await {
-                Ok(sd_models) => {
-                    let models: Vec<models::ImageModel> = sd_models.iter().map(|m| {
-                        let title = m.get("title").and_then(|t| t.as_str()).unwrap_or("unknown");
-                        let model_name = m.get("model_name").and_then(|n| n.as_str()).unwrap_or(title);
-                        let description = m.get("description").and_then(|d| d.as_str()).unwrap_or(title);
-                        models::ImageModel {
-                            name: title.to_string(),
-                            display_name: model_name.to_string(),
-                            description: Some(description.to_string()),
-                            installed: true,
-                        }
-                    }).collect();
-                    Json(models)
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to parse SD models: {}", e);
-                    Json(vec![])
-                }
-            }
+    let sd_models = client
+        .get(&url)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| {
+            tracing::warn!("Failed to reach SD WebUI: {}", e);
+            ApiError::from(e).context("Failed to list image models")
+        })?
+        .json::<Vec<serde_json::Value>>()
+        .await
+        .map_err(|e| {
+            tracing::warn!("Failed to parse SD models: {}", e);
+            ApiError::from(e).context("Failed to list image models")
+        })?;
+
+    let models = sd_models.iter().map(|m| {
+        let title = m.get("title").and_then(|t| t.as_str()).unwrap_or("unknown");
+        let model_name = m.get("model_name").and_then(|n| n.as_str()).unwrap_or(title);
+        let description = m.get("description").and_then(|d| d.as_str()).unwrap_or(title);
+        models::ImageModel {
+            name: title.to_string(),
+            display_name: model_name.to_string(),
+            description: Some(description.to_string()),
+            installed: true,
         }
-        Ok(resp) => {
-            tracing::warn!("SD WebUI returned {}", resp.status());
-            Json(vec![])
+    }).collect();
+    Ok(Json(models))
+}
+
+// ============================================================
+// Tool Endpoints
+// ============================================================
+
+/// Upper bounds for caller-supplied sandbox limits
+const MAX_EXEC_FUEL: u64 = 1_000_000_000;
+const MAX_EXEC_TIMEOUT_MS: u64 = 30_000;
+const MAX_EXEC_MEMORY_MB: usize = 256;
+
+/// POST /api/tools/execute - Run a WASI module in the code sandbox
+pub async fn execute_code(
+    Json(payload): Json<models::ExecuteCodeRequest>,
+) -> Result<Json<tools::ExecutionResult>, ApiError> {
+    use base64::Engine;
+
+    let module = match (payload.wasm_base64, payload.wat) {
+        (Some(encoded), None) => base64::engine::general_purpose::STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| ApiError::BadRequest(format!("Invalid wasm_base64: {}", e)))?,
+        (None, Some(wat)) => wat.into_bytes(),
+        _ => {
+            return Err(ApiError::BadRequest("Provide exactly one of `wasm_base64` or `wat`".to_string()));
+        }
+    };
+
+    let defaults = tools::ExecutionLimits::default();
+    let limits = tools::ExecutionLimits {
+        fuel: payload.fuel.unwrap_or(defaults.fuel).min(MAX_EXEC_FUEL),
+        timeout_ms: payload.timeout_ms.unwrap_or(defaults.timeout_ms).min(MAX_EXEC_TIMEOUT_MS),
+        memory_bytes: payload.memory_limit_mb
+            .map(|mb| mb.min(MAX_EXEC_MEMORY_MB) * 1024 * 1024)
+            .unwrap_or(defaults.memory_bytes),
+        max_output_bytes: defaults.max_output_bytes,
+    };
+
+    match tools::CodeSandbox::execute_wasm(module, payload.stdin.into_bytes(), payload.args, limits).await {
+        Ok(result) => {
+            tracing::info!("⚗️ Sandbox finished: {:?} in {}ms", result.status, result.duration_ms);
+            Ok(Json(result))
         }
         Err(e) => {
-            tracing::warn!("Failed to reach SD WebUI: {}", e);
-            Json(vec![])
+            tracing::error!("Sandbox execution failed: {}", e);
+            Err(ApiError::from(e).context("Failed to execute module"))
         }
     }
 }
@@ -3565,7 +3637,8 @@ mod tests {
         #[test]
         fn stream_event_serializes_correctly() {
             let event = StreamEvent::Content { 
-                content: "Hello".to_string() 
+                content: "Hello".to_string(),
+                persona_id: None,
             };
             let json = serde_json::to_string(&event).unwrap();
             assert!(json.contains("\"type\":\"content\""));
@@ -3579,9 +3652,25 @@ mod tests {
                 mood: Some("happy".to_string()),
                 mood_value: Some(0.85),
                 energy: Some(0.7),
+                context: None,
             };
             let json = serde_json::to_string(&event).unwrap();
             assert!(json.contains("\"mood\":\"happy\""));
+            assert!(!json.contains("\"context\""));
+        }
+
+        #[test]
+        fn stream_event_done_includes_context_report() {
+            let event = StreamEvent::Done {
+                message_id: "msg-123".to_string(),
+                mood: None,
+                mood_value: None,
+                energy: None,
+                context: Some(ContextReport { context_window: 8192, ..Default::default() }),
+            };
+            let json = serde_json::to_string(&event).unwrap();
+            assert!(json.contains("\"context_window\":8192"));
+            assert!(json.contains("\"history\":{\"budget\":0,\"used\":0}"));
         }
 
         #[test]
@@ -1,37 +1,230 @@
-use crate::models::{OllamaMessage, OllamaStreamChunk, ChatMessage, StreamEvent};
+use crate::models::{OllamaMessage, OllamaStreamChunk, OllamaToolCall, ChatMessage, StreamEvent, ProviderConfig, ProviderKind};
 use anyhow::Result;
+use async_trait::async_trait;
 use futures::StreamExt;
+use std::sync::Arc;
 use tokio::sync::mpsc;
 
-/// LLM Inference Interface with streaming support
-pub struct LLMService {
+// ============================================================
+// LLM Providers
+// ============================================================
+//
+// Every inference backend implements `LLMProvider`. `LLMService` wraps
+// one provider and layers the provider-agnostic helpers (mood inference,
+// prompt builders) on top of it.
+//
+//   OllamaProvider        → /api/chat, /api/tags
+//   OpenAICompatProvider  → /v1/chat/completions, /v1/models
+//                           (llama.cpp server, vLLM, LM Studio, ...)
+// ============================================================
+
+/// Sampling options for non-streaming inference
+#[derive(Debug, Clone, Default)]
+pub struct InferenceOptions {
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+}
+
+/// Result of one streamed model turn when tools are advertised
+#[derive(Debug, Default)]
+pub struct ToolTurn {
+    /// Visible (non-thinking) response text
+    pub content: String,
+    /// Tool calls requested by the model; empty when this is the final answer
+    pub tool_calls: Vec<OllamaToolCall>,
+}
+
+/// An LLM inference backend
+#[async_trait]
+pub trait LLMProvider: Send + Sync {
+    /// Short identifier used in logs
+    fn name(&self) -> &'static str;
+
+    /// Stream a chat completion into `tx`, returning the full (non-thinking) response
+    async fn infer_streaming(
+        &self,
+        model: &str,
+        messages: Vec<OllamaMessage>,
+        tx: mpsc::Sender<StreamEvent>,
+    ) -> Result<String>;
+
+    /// Stream a chat completion with `tools` advertised to the model.
+    /// Backends without native tool calling answer directly.
+    async fn infer_streaming_with_tools(
+        &self,
+        model: &str,
+        messages: Vec<OllamaMessage>,
+        _tools: &[serde_json::Value],
+        tx: mpsc::Sender<StreamEvent>,
+    ) -> Result<ToolTurn> {
+        let content = self.infer_streaming(model, messages, tx).await?;
+        Ok(ToolTurn { content, tool_calls: Vec::new() })
+    }
+
+    /// Run a chat completion without streaming
+    async fn infer_with_options(
+        &self,
+        model: &str,
+        messages: Vec<OllamaMessage>,
+        options: &InferenceOptions,
+    ) -> Result<String>;
+
+    /// List the models this backend can serve
+    async fn list_models(&self) -> Result<Vec<String>>;
+
+    /// Maximum context length of `model` in tokens, if the backend reports it
+    async fn context_length(&self, _model: &str) -> Result<Option<usize>> {
+        Ok(None)
+    }
+
+    /// A copy of this provider that runs with a `tokens`-sized context window,
+    /// for backends where the window is chosen per request
+    fn with_context_window(&self, _tokens: usize) -> Option<Arc<dyn LLMProvider>> {
+        None
+    }
+}
+
+fn build_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(300))
+        .build()
+        .unwrap_or_else(|_| reqwest::Client::new())
+}
+
+/// Splits streamed content into thinking vs. answer events.
+/// Detects explicit thinking blocks (extended thinking / CoT):
+/// `<thinking>...</thinking>` or `<think>...</think>`
+#[derive(Debug, Default)]
+pub struct ThinkingFilter {
+    in_thinking: bool,
+    pub full_response: String,
+    pub thinking: String,
+}
+
+impl ThinkingFilter {
+    /// Feed one content delta, returning the events to emit
+    pub fn push(&mut self, content: &str) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+
+        if (content.contains("<thinking>") || content.contains("<think>")) && !self.in_thinking {
+            self.in_thinking = true;
+            events.push(StreamEvent::ThinkingStart);
+        }
+
+        if self.in_thinking {
+            // Check if we're ending explicit thinking
+            if content.contains("</thinking>") || content.contains("</think>") {
+                let cleaned = strip_thinking_tags(content);
+                if !cleaned.is_empty() {
+                    self.thinking.push_str(&cleaned);
+                    events.push(StreamEvent::Thinking { content: cleaned, persona_id: None });
+                }
+                self.in_thinking = false;
+                events.push(StreamEvent::ThinkingEnd);
+            } else {
+                // Still in explicit thinking block
+                let cleaned = content
+                    .replace("<thinking>", "")
+                    .replace("<think>", "");
+                if !cleaned.is_empty() {
+                    self.thinking.push_str(&cleaned);
+                    events.push(StreamEvent::Thinking { content: cleaned, persona_id: None });
+                }
+            }
+        } else {
+            // Regular content - no thinking for models without explicit thinking support
+            let cleaned = strip_thinking_tags(content);
+            if !cleaned.is_empty() {
+                self.full_response.push_str(&cleaned);
+                events.push(StreamEvent::Content { content: cleaned, persona_id: None });
+            }
+        }
+
+        events
+    }
+}
+
+fn strip_thinking_tags(content: &str) -> String {
+    content
+        .replace("</thinking>", "")
+        .replace("</think>", "")
+        .replace("<thinking>", "")
+        .replace("<think>", "")
+}
+
+/// Buffers a byte stream and yields complete lines (chunks may split a line)
+#[derive(Debug, Default)]
+struct LineBuffer {
+    pending: String,
+}
+
+impl LineBuffer {
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.pending.push_str(&String::from_utf8_lossy(bytes));
+        let mut lines = Vec::new();
+        while let Some(pos) = self.pending.find('\n') {
+            let line: String = self.pending.drain(..=pos).collect();
+            let line = line.trim();
+            if !line.is_empty() {
+                lines.push(line.to_string());
+            }
+        }
+        lines
+    }
+
+    fn finish(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.pending);
+        let rest = rest.trim();
+        (!rest.is_empty()).then(|| rest.to_string())
+    }
+}
+
+// ── Ollama ───────────────────────────────────────────────────
+
+/// Ollama native API (`/api/chat`, `/api/tags`)
+pub struct OllamaProvider {
     pub host: String,
     pub client: reqwest::Client,
+    /// `num_ctx` sent with chat requests; Ollama's (small) default otherwise
+    pub num_ctx: Option<usize>,
 }
 
-impl LLMService {
+impl OllamaProvider {
     pub fn new(host: String) -> Self {
-        Self {
-            host,
-            client: reqwest::Client::builder()
-                .timeout(std::time::Duration::from_secs(300))
-                .build()
-                .unwrap_or_else(|_| reqwest::Client::new()),
-        }
+        Self { host, client: build_client(), num_ctx: None }
     }
 
-    /// Call Ollama API with messages and get streamed response via channel
-    pub async fn infer_streaming(
+    /// `<arch>.context_length` from an `/api/show` response
+    fn parse_context_length(show: &serde_json::Value) -> Option<usize> {
+        show["model_info"]
+            .as_object()?
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+            .map(|tokens| tokens as usize)
+    }
+}
+
+impl OllamaProvider {
+    /// POST /api/chat with streaming, optionally advertising tools
+    async fn stream_chat(
         &self,
         model: &str,
         messages: Vec<OllamaMessage>,
+        tools: Option<&[serde_json::Value]>,
         tx: mpsc::Sender<StreamEvent>,
-    ) -> Result<String> {
-        let request = serde_json::json!({
+    ) -> Result<ToolTurn> {
+        let mut request = serde_json::json!({
             "model": model,
             "messages": messages,
             "stream": true,
         });
+        if let Some(tools) = tools.filter(|t| !t.is_empty()) {
+            request["tools"] = serde_json::json!(tools);
+        }
+        if let Some(num_ctx) = self.num_ctx {
+            request["options"]["num_ctx"] = serde_json::json!(num_ctx);
+        }
 
         let response = self
             .client
@@ -50,82 +243,24 @@ impl LLMService {
         }
 
         let mut stream = response.bytes_stream();
-        let mut full_response = String::new();
-        let mut in_thinking = false;
-        let mut thinking_buffer = String::new();
+        let mut lines = LineBuffer::default();
+        let mut filter = ThinkingFilter::default();
+        let mut tool_calls = Vec::new();
 
-        while let Some(chunk) = stream.next().await {
+        'outer: while let Some(chunk) = stream.next().await {
             match chunk {
                 Ok(bytes) => {
-                    let text = String::from_utf8_lossy(&bytes);
-                    
                     // Ollama can send multiple JSON objects per chunk
-                    for line in text.lines() {
-                        if line.trim().is_empty() {
-                            continue;
-                        }
-                        
-                        if let Ok(chunk_data) = serde_json::from_str::<OllamaStreamChunk>(line) {
-                            if let Some(msg) = &chunk_data.message {
-                                let content = &msg.content;
-                                
-                                // Detect explicit thinking blocks (extended thinking / CoT)
-                                // Format: <thinking>...</thinking> or <think>...</think>
-                                if (content.contains("<thinking>") || content.contains("<think>")) && !in_thinking {
-                                    in_thinking = true;
-                                    let _ = tx.send(StreamEvent::ThinkingStart).await;
-                                }
-                                
-                                if in_thinking {
-                                    // Check if we're ending explicit thinking
-                                    if content.contains("</thinking>") || content.contains("</think>") {
-                                        let cleaned = content
-                                            .replace("</thinking>", "")
-                                            .replace("</think>", "")
-                                            .replace("<thinking>", "")
-                                            .replace("<think>", "");
-                                        
-                                        if !cleaned.is_empty() {
-                                            thinking_buffer.push_str(&cleaned);
-                                            let _ = tx.send(StreamEvent::Thinking {
-                                                content: cleaned,
-                                            }).await;
-                                        }
-                                        
-                                        in_thinking = false;
-                                        let _ = tx.send(StreamEvent::ThinkingEnd).await;
-                                    } else {
-                                        // Still in explicit thinking block
-                                        let cleaned = content
-                                            .replace("<thinking>", "")
-                                            .replace("<think>", "");
-                                        
-                                        if !cleaned.is_empty() {
-                                            thinking_buffer.push_str(&cleaned);
-                                            let _ = tx.send(StreamEvent::Thinking {
-                                                content: cleaned,
-                                            }).await;
-                                        }
-                                    }
-                                } else {
-                                    // Regular content - no thinking for models without explicit thinking support
-                                    let cleaned = content
-                                        .replace("<thinking>", "")
-                                        .replace("<think>", "")
-                                        .replace("</thinking>", "")
-                                        .replace("</think>", "");
-                                    
-                                    if !cleaned.is_empty() {
-                                        full_response.push_str(&cleaned);
-                                        let _ = tx.send(StreamEvent::Content {
-                                            content: cleaned,
-                                        }).await;
-                                    }
+                    for line in lines.push(&bytes) {
+                        if let Ok(chunk_data) = serde_json::from_str::<OllamaStreamChunk>(&line) {
+                            if let Some(msg) = chunk_data.message {
+                                for event in filter.push(&msg.content) {
+                                    let _ = tx.send(event).await;
                                 }
+                                tool_calls.extend(msg.tool_calls.unwrap_or_default());
                             }
-                            
                             if chunk_data.done {
-                                break;
+                                break 'outer;
                             }
                         }
                     }
@@ -140,16 +275,66 @@ impl LLMService {
             }
         }
 
-        Ok(full_response)
+        if let Some(line) = lines.finish() {
+            if let Ok(chunk_data) = serde_json::from_str::<OllamaStreamChunk>(&line) {
+                if let Some(msg) = chunk_data.message {
+                    for event in filter.push(&msg.content) {
+                        let _ = tx.send(event).await;
+                    }
+                    tool_calls.extend(msg.tool_calls.unwrap_or_default());
+                }
+            }
+        }
+
+        Ok(ToolTurn { content: filter.full_response, tool_calls })
     }
+}
 
-    /// Call Ollama API without streaming (for background tasks)
-    pub async fn infer(&self, model: &str, messages: Vec<OllamaMessage>) -> Result<String> {
-        let request = serde_json::json!({
+#[async_trait]
+impl LLMProvider for OllamaProvider {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    async fn infer_streaming(
+        &self,
+        model: &str,
+        messages: Vec<OllamaMessage>,
+        tx: mpsc::Sender<StreamEvent>,
+    ) -> Result<String> {
+        Ok(self.stream_chat(model, messages, None, tx).await?.content)
+    }
+
+    async fn infer_streaming_with_tools(
+        &self,
+        model: &str,
+        messages: Vec<OllamaMessage>,
+        tools: &[serde_json::Value],
+        tx: mpsc::Sender<StreamEvent>,
+    ) -> Result<ToolTurn> {
+        self.stream_chat(model, messages, Some(tools), tx).await
+    }
+
+    async fn infer_with_options(
+        &self,
+        model: &str,
+        messages: Vec<OllamaMessage>,
+        options: &InferenceOptions,
+    ) -> Result<String> {
+        let mut request = serde_json::json!({
             "model": model,
             "messages": messages,
             "stream": false,
         });
+        if let Some(temperature) = options.temperature {
+            request["options"]["temperature"] = serde_json::json!(temperature);
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            request["options"]["num_predict"] = serde_json::json!(max_tokens);
+        }
+        if let Some(num_ctx) = self.num_ctx {
+            request["options"]["num_ctx"] = serde_json::json!(num_ctx);
+        }
 
         let response = self
             .client
@@ -173,6 +358,346 @@ impl LLMService {
         Ok(content)
     }
 
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let response = self
+            .client
+            .get(format!("{}/api/tags", self.host))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Failed to list models"));
+        }
+
+        let json: serde_json::Value = response.json().await?;
+        let models = json["models"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|m| m["name"].as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(models)
+    }
+
+    /// POST /api/show → `model_info.<arch>.context_length`
+    async fn context_length(&self, model: &str) -> Result<Option<usize>> {
+        let response = self
+            .client
+            .post(format!("{}/api/show", self.host))
+            .json(&serde_json::json!({ "model": model }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Ollama /api/show failed ({})", response.status()));
+        }
+
+        let json: serde_json::Value = response.json().await?;
+        Ok(Self::parse_context_length(&json))
+    }
+
+    fn with_context_window(&self, tokens: usize) -> Option<Arc<dyn LLMProvider>> {
+        Some(Arc::new(OllamaProvider {
+            host: self.host.clone(),
+            client: self.client.clone(),
+            num_ctx: Some(tokens),
+        }))
+    }
+}
+
+// ── OpenAI-compatible ────────────────────────────────────────
+
+/// OpenAI-compatible chat completions API (`/v1/chat/completions`, `/v1/models`)
+pub struct OpenAICompatProvider {
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub client: reqwest::Client,
+}
+
+impl OpenAICompatProvider {
+    pub fn new(base_url: String, api_key: Option<String>) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            client: build_client(),
+        }
+    }
+
+    fn request(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self.api_key {
+            Some(ref key) if !key.is_empty() => builder.bearer_auth(key),
+            _ => builder,
+        }
+    }
+
+    /// Extract the content delta from one `data:` line of a streamed completion.
+    /// Returns `None` for the `[DONE]` sentinel.
+    fn parse_sse_line(line: &str) -> Option<Option<String>> {
+        let data = line.strip_prefix("data:")?.trim();
+        if data == "[DONE]" {
+            return None;
+        }
+        let json: serde_json::Value = serde_json::from_str(data).ok()?;
+        Some(json["choices"][0]["delta"]["content"].as_str().map(String::from))
+    }
+}
+
+#[async_trait]
+impl LLMProvider for OpenAICompatProvider {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    async fn infer_streaming(
+        &self,
+        model: &str,
+        messages: Vec<OllamaMessage>,
+        tx: mpsc::Sender<StreamEvent>,
+    ) -> Result<String> {
+        let request = serde_json::json!({
+            "model": model,
+            "messages": messages,
+            "stream": true,
+        });
+
+        let response = self
+            .request(self.client.post(format!("{}/v1/chat/completions", self.base_url)))
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            let _ = tx.send(StreamEvent::Error {
+                message: format!("OpenAI-compatible backend error ({}): {}", status, error_text),
+            }).await;
+            return Err(anyhow::anyhow!("OpenAI-compatible backend returned error: {}", status));
+        }
+
+        let mut stream = response.bytes_stream();
+        let mut lines = LineBuffer::default();
+        let mut filter = ThinkingFilter::default();
+
+        'outer: while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(bytes) => {
+                    for line in lines.push(&bytes) {
+                        // Comments (": keep-alive") and non-data fields are skipped
+                        if !line.starts_with("data:") {
+                            continue;
+                        }
+                        match Self::parse_sse_line(&line) {
+                            None if line.contains("[DONE]") => break 'outer,
+                            Some(Some(content)) => {
+                                for event in filter.push(&content) {
+                                    let _ = tx.send(event).await;
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Stream error: {}", e);
+                    let _ = tx.send(StreamEvent::Error {
+                        message: format!("Stream error: {}", e),
+                    }).await;
+                    break;
+                }
+            }
+        }
+
+        Ok(filter.full_response)
+    }
+
+    async fn infer_with_options(
+        &self,
+        model: &str,
+        messages: Vec<OllamaMessage>,
+        options: &InferenceOptions,
+    ) -> Result<String> {
+        let mut request = serde_json::json!({
+            "model": model,
+            "messages": messages,
+            "stream": false,
+        });
+        if let Some(temperature) = options.temperature {
+            request["temperature"] = serde_json::json!(temperature);
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            request["max_tokens"] = serde_json::json!(max_tokens);
+        }
+
+        let response = self
+            .request(self.client.post(format!("{}/v1/chat/completions", self.base_url)))
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("OpenAI-compatible backend error ({}): {}", status, error_text));
+        }
+
+        let json: serde_json::Value = response.json().await?;
+        let content = json["choices"][0]["message"]["content"]
+            .as_str()
+            .unwrap_or("Unable to process response")
+            .to_string();
+
+        Ok(content)
+    }
+
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let response = self
+            .request(self.client.get(format!("{}/v1/models", self.base_url)))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("Failed to list models"));
+        }
+
+        let json: serde_json::Value = response.json().await?;
+        let models = json["data"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|m| m["id"].as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(models)
+    }
+}
+
+/// The server's `OPENAI_COMPAT_API_KEY`, but only for requests going to
+/// `OPENAI_COMPAT_URL`. A client- or persona-supplied `base_url` never
+/// receives it, otherwise anyone could point one at their own host.
+pub fn default_openai_key(base_url: Option<&str>, state: &crate::AppState) -> Option<String> {
+    match base_url {
+        Some(url) if !same_base_url(url, &state.openai_compat_url) => None,
+        _ => state.openai_compat_key.clone(),
+    }
+}
+
+/// Check a client-supplied backend URL before the server calls it. The
+/// configured `OLLAMA_HOST` / `OPENAI_COMPAT_URL` are always allowed; any
+/// other URL must resolve to public addresses only, so request and persona
+/// settings can't point the server at Qdrant, Meilisearch, Dragonfly or
+/// cloud metadata. Persona URLs are checked when the persona is saved.
+pub async fn check_base_url(url: &str, state: &crate::AppState) -> Result<()> {
+    if same_base_url(url, &state.ollama_host) || same_base_url(url, &state.openai_compat_url) {
+        return Ok(());
+    }
+    crate::tools::check_public_url(url).await
+}
+
+fn same_base_url(a: &str, b: &str) -> bool {
+    a.trim().trim_end_matches('/') == b.trim().trim_end_matches('/')
+}
+
+// ============================================================
+// LLM Service
+// ============================================================
+
+/// LLM Inference Interface with streaming support
+#[derive(Clone)]
+pub struct LLMService {
+    pub provider: Arc<dyn LLMProvider>,
+}
+
+impl LLMService {
+    /// Ollama-backed service (the default backend)
+    pub fn new(host: String) -> Self {
+        Self::with_provider(Arc::new(OllamaProvider::new(host)))
+    }
+
+    pub fn with_provider(provider: Arc<dyn LLMProvider>) -> Self {
+        Self { provider }
+    }
+
+    /// Build a service for an explicit provider selection, falling back to
+    /// the server-wide defaults for any unset URL / key
+    pub fn from_config(config: &ProviderConfig, state: &crate::AppState) -> Self {
+        match config.kind {
+            ProviderKind::Ollama => Self::new(
+                config.base_url.clone().unwrap_or_else(|| state.ollama_host.clone()),
+            ),
+            ProviderKind::OpenAI => Self::with_provider(Arc::new(OpenAICompatProvider::new(
+                config.base_url.clone().unwrap_or_else(|| state.openai_compat_url.clone()),
+                config
+                    .api_key
+                    .clone()
+                    .or_else(|| default_openai_key(config.base_url.as_deref(), state)),
+            ))),
+        }
+    }
+
+    /// Resolve the provider for a request: explicit request choice first,
+    /// then the persona's configured backend, then Ollama
+    pub fn resolve(
+        state: &crate::AppState,
+        request: Option<&ProviderConfig>,
+        persona: Option<&ProviderConfig>,
+    ) -> Self {
+        match request.or(persona) {
+            Some(config) => Self::from_config(config, state),
+            None => Self::new(state.ollama_host.clone()),
+        }
+    }
+
+    pub fn provider_name(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    /// Maximum context length of `model`, if the backend reports one
+    pub async fn context_length(&self, model: &str) -> Result<Option<usize>> {
+        self.provider.context_length(model).await
+    }
+
+    /// Run with a `tokens`-sized context window where the backend allows choosing it
+    pub fn with_context_window(self, tokens: usize) -> Self {
+        match self.provider.with_context_window(tokens) {
+            Some(provider) => Self { provider },
+            None => self,
+        }
+    }
+
+    /// Stream a response via channel
+    pub async fn infer_streaming(
+        &self,
+        model: &str,
+        messages: Vec<OllamaMessage>,
+        tx: mpsc::Sender<StreamEvent>,
+    ) -> Result<String> {
+        self.provider.infer_streaming(model, messages, tx).await
+    }
+
+    /// Stream one model turn with tools advertised (see `tools::tool_definitions`)
+    pub async fn infer_streaming_with_tools(
+        &self,
+        model: &str,
+        messages: Vec<OllamaMessage>,
+        tools: &[serde_json::Value],
+        tx: mpsc::Sender<StreamEvent>,
+    ) -> Result<ToolTurn> {
+        self.provider.infer_streaming_with_tools(model, messages, tools, tx).await
+    }
+
+    /// Non-streaming inference (for background tasks)
+    pub async fn infer(&self, model: &str, messages: Vec<OllamaMessage>) -> Result<String> {
+        self.provider
+            .infer_with_options(model, messages, &InferenceOptions::default())
+            .await
+    }
+
     /// Infer the mood/emotion from a response using a quick LLM call
     /// Returns one of: happy, content, thoughtful, melancholy, curious, excited, calm, concerned
     pub async fn infer_mood(&self, model: &str, response_text: &str) -> Result<String> {
@@ -191,36 +716,21 @@ Respond with only the single mood word, nothing else."#,
         let messages = vec![OllamaMessage {
             role: "user".to_string(),
             content: mood_prompt,
+            tool_calls: None,
         }];
 
-        let request = serde_json::json!({
-            "model": model,
-            "messages": messages,
-            "stream": false,
-            "options": {
-                "temperature": 0.1,  // Low temperature for consistent results
-                "num_predict": 10,   // We only need one word
-            }
-        });
-
-        let response = self
-            .client
-            .post(format!("{}/api/chat", self.host))
-            .json(&request)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            tracing::warn!("Mood inference failed, defaulting to 'content'");
-            return Ok("content".to_string());
-        }
+        let options = InferenceOptions {
+            temperature: Some(0.1), // Low temperature for consistent results
+            max_tokens: Some(10),   // We only need one word
+        };
 
-        let json: serde_json::Value = response.json().await?;
-        let mood = json["message"]["content"]
-            .as_str()
-            .unwrap_or("content")
-            .trim()
-            .to_lowercase();
+        let mood = match self.provider.infer_with_options(model, messages, &options).await {
+            Ok(raw) => raw.trim().to_lowercase(),
+            Err(e) => {
+                tracing::warn!("Mood inference failed ({}), defaulting to 'content'", e);
+                return Ok("content".to_string());
+            }
+        };
 
         // Validate the mood is one of our expected values
         let valid_moods = ["happy", "content", "thoughtful", "melancholy", "curious", "excited", "calm", "concerned"];
@@ -233,6 +743,11 @@ Respond with only the single mood word, nothing else."#,
         Ok(normalized_mood.to_string())
     }
 
+    /// List models served by the backend
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        self.provider.list_models().await
+    }
+
     /// Build messages array for Ollama from chat history
     pub fn build_messages(
         system_prompt: &str,
@@ -242,6 +757,7 @@ Respond with only the single mood word, nothing else."#,
         let mut messages = vec![OllamaMessage {
             role: "system".to_string(),
             content: system_prompt.to_string(),
+            tool_calls: None,
         }];
 
         // Add history
@@ -249,6 +765,7 @@ Respond with only the single mood word, nothing else."#,
             messages.push(OllamaMessage {
                 role: msg.role.clone(),
                 content: msg.content.clone(),
+                tool_calls: None,
             });
         }
 
@@ -256,6 +773,7 @@ Respond with only the single mood word, nothing else."#,
         messages.push(OllamaMessage {
             role: "user".to_string(),
             content: user_input.to_string(),
+            tool_calls: None,
         });
 
         messages
@@ -278,29 +796,98 @@ Respond with only the single mood word, nothing else."#,
             random_concept, context
         )
     }
+}
 
-    /// Check if Ollama is available and list models
-    pub async fn list_models(&self) -> Result<Vec<String>> {
-        let response = self
-            .client
-            .get(format!("{}/api/tags", self.host))
-            .send()
-            .await?;
+// ============================================================
+// Tests
+// ============================================================
 
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!("Failed to list models"));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod thinking_filter_tests {
+        use super::*;
+
+        #[test]
+        fn plain_content_passes_through() {
+            let mut filter = ThinkingFilter::default();
+            let events = filter.push("Hello");
+            assert_eq!(events.len(), 1);
+            assert!(matches!(&events[0], StreamEvent::Content { content, .. } if content == "Hello"));
+            assert_eq!(filter.full_response, "Hello");
         }
 
-        let json: serde_json::Value = response.json().await?;
-        let models = json["models"]
-            .as_array()
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|m| m["name"].as_str().map(String::from))
-                    .collect()
-            })
-            .unwrap_or_default();
+        #[test]
+        fn thinking_block_is_split_out() {
+            let mut filter = ThinkingFilter::default();
+            let mut events = filter.push("<think>pondering");
+            events.extend(filter.push(" more</think>"));
+            events.extend(filter.push("Answer"));
+
+            assert!(matches!(events[0], StreamEvent::ThinkingStart));
+            assert!(events.iter().any(|e| matches!(e, StreamEvent::ThinkingEnd)));
+            assert_eq!(filter.thinking, "pondering more");
+            assert_eq!(filter.full_response, "Answer");
+        }
+    }
 
-        Ok(models)
+    mod line_buffer_tests {
+        use super::*;
+
+        #[test]
+        fn joins_lines_split_across_chunks() {
+            let mut buf = LineBuffer::default();
+            assert!(buf.push(b"{\"a\":").is_empty());
+            let lines = buf.push(b"1}\n{\"b\":2}\n");
+            assert_eq!(lines, vec!["{\"a\":1}".to_string(), "{\"b\":2}".to_string()]);
+            assert!(buf.finish().is_none());
+        }
+    }
+
+    mod ollama_tests {
+        use super::*;
+
+        #[test]
+        fn context_length_from_model_info() {
+            let show = serde_json::json!({
+                "model_info": {"general.architecture": "llama", "llama.context_length": 131072}
+            });
+            assert_eq!(OllamaProvider::parse_context_length(&show), Some(131072));
+            assert_eq!(OllamaProvider::parse_context_length(&serde_json::json!({})), None);
+        }
+    }
+
+    mod openai_compat_tests {
+        use super::*;
+
+        #[test]
+        fn parses_content_delta() {
+            let line = r#"data: {"choices":[{"delta":{"content":"Hi"}}]}"#;
+            assert_eq!(OpenAICompatProvider::parse_sse_line(line), Some(Some("Hi".to_string())));
+        }
+
+        #[test]
+        fn role_only_delta_has_no_content() {
+            let line = r#"data: {"choices":[{"delta":{"role":"assistant"}}]}"#;
+            assert_eq!(OpenAICompatProvider::parse_sse_line(line), Some(None));
+        }
+
+        #[test]
+        fn done_sentinel_ends_stream() {
+            assert_eq!(OpenAICompatProvider::parse_sse_line("data: [DONE]"), None);
+        }
+
+        #[test]
+        fn trailing_slash_is_trimmed() {
+            let provider = OpenAICompatProvider::new("http://localhost:1234/".to_string(), None);
+            assert_eq!(provider.base_url, "http://localhost:1234");
+        }
+
+        #[test]
+        fn base_url_match_ignores_trailing_slash() {
+            assert!(same_base_url("http://localhost:8080/", "http://localhost:8080"));
+            assert!(!same_base_url("http://attacker.example", "http://localhost:8080"));
+        }
     }
 }
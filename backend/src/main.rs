@@ -1,3 +1,4 @@
+mod archive;
 mod components;
 mod systems;
 mod handlers;
@@ -8,6 +9,25 @@ mod tools;
 mod models;
 mod vector;
 mod backup;
+mod migrations;
+mod error;
+mod auth;
+mod retrieval;
+mod rerank;
+mod context;
+mod summarizer;
+mod group;
+mod conversation;
+mod pagination;
+mod generation;
+mod ws;
+mod events;
+mod dreaming;
+mod reflection;
+mod consolidation;
+mod forgetting;
+mod memories;
+mod deletion;
 
 use axum::{
     routing::{get, post, put, delete},
@@ -16,7 +36,6 @@ use axum::{
 use sqlx::postgres::PgPoolOptions;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tower_http::cors::{Any, CorsLayer};
 
 use components::AgentState;
 
@@ -28,10 +47,18 @@ pub struct AppState {
     pub agent: Arc<RwLock<AgentState>>,
     pub vector: Arc<vector::VectorService>,
     pub ollama_host: String,
+    pub openai_compat_url: String,
+    pub openai_compat_key: Option<String>,
     pub qdrant_url: String,
     pub xtts_url: String,
     pub meili_url: String,
     pub meili_key: String,
+    pub retrieval: retrieval::RetrievalConfig,
+    pub context: context::ContextConfig,
+    pub generations: Arc<generation::GenerationRegistry>,
+    /// Agent events for `/api/events` and WebSockets, shared across replicas
+    pub events: Arc<events::EventBus>,
+    pub sockets: Arc<ws::SocketHub>,
 }
 
 #[tokio::main]
@@ -41,6 +68,14 @@ async fn main() {
     
     tracing_subscriber::fmt::init();
 
+    let migrate_args = match migrations::MigrateArgs::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\nUsage: azera_core [--migrate-only] [--dry-run] [--rollback-to <version>] [--import-archive]", e);
+            std::process::exit(2);
+        }
+    };
+
     // ============================================================
     // Initialize Backup/Restore System
     // ============================================================
@@ -62,6 +97,11 @@ async fn main() {
         .unwrap_or_else(|_| "redis://localhost:6379".to_string());
     let ollama_host = std::env::var("OLLAMA_HOST")
         .unwrap_or_else(|_| "http://localhost:11434".to_string());
+    let openai_compat_url = std::env::var("OPENAI_COMPAT_URL")
+        .unwrap_or_else(|_| "http://localhost:8080".to_string());
+    let openai_compat_key = std::env::var("OPENAI_COMPAT_API_KEY")
+        .ok()
+        .filter(|k| !k.is_empty());
     let qdrant_url = std::env::var("QDRANT_URL")
         .unwrap_or_else(|_| "http://localhost:6333".to_string());
     let xtts_url = std::env::var("XTTS_URL")
@@ -93,10 +133,41 @@ async fn main() {
         .await
         .expect("Failed to connect to CockroachDB");
 
-    // Initialize schema
-    db::init_schema(&db_pool)
-        .await
-        .expect("Failed to initialize database schema");
+    // Apply schema migrations (or roll back / dry-run when requested)
+    if let Some(target) = migrate_args.rollback_to {
+        let reverted = migrations::rollback_to(&db_pool, target, migrate_args.dry_run)
+            .await
+            .expect("Failed to roll back migrations");
+        let verb = if migrate_args.dry_run { "Would roll back" } else { "Rolled back" };
+        tracing::info!("🗄️ {} {} migration(s) to version {}", verb, reverted.len(), target);
+    } else {
+        let applied = migrations::migrate(&db_pool, migrate_args.dry_run)
+            .await
+            .expect("Failed to apply database migrations");
+        let verb = if migrate_args.dry_run { "would be applied" } else { "applied" };
+        tracing::info!("🗄️ {} migration(s) {}", applied.len(), verb);
+    }
+
+    // Re-import the shared journal and dream archives (command line only:
+    // the entries are visible to every user)
+    if migrate_args.import_archive && !migrate_args.dry_run {
+        for (name, result) in [
+            ("journal", archive::import_journal(&db_pool).await),
+            ("dream", archive::import_dreams(&db_pool).await),
+        ] {
+            match result {
+                Ok(counts) => tracing::info!(
+                    "📚 {} archive import: {} imported, {} already present, {} errors",
+                    name, counts.imported, counts.skipped, counts.errors
+                ),
+                Err(e) => tracing::error!("Failed to import the {} archive: {}", name, e),
+            }
+        }
+    }
+
+    if migrate_args.exit_after_migrating() {
+        return;
+    }
 
     tracing::info!("Connected to CockroachDB");
 
@@ -137,14 +208,21 @@ async fn main() {
 
     let app_state = AppState {
         db: db_pool,
-        cache: cache_manager,
+        cache: cache_manager.clone(),
         agent,
         vector: vector_service,
         ollama_host,
+        openai_compat_url,
+        openai_compat_key,
         qdrant_url,
         xtts_url,
         meili_url,
         meili_key,
+        retrieval: retrieval::RetrievalConfig::from_env(),
+        context: context::ContextConfig::from_env(),
+        generations: Arc::new(generation::GenerationRegistry::new(generation::resume_grace())),
+        events: Arc::new(events::EventBus::new(Some(cache_manager))),
+        sockets: Arc::new(ws::SocketHub::new()),
     };
 
     // ============================================================
@@ -164,6 +242,9 @@ async fn main() {
         systems::run_tick_loop(state_clone).await;
     });
 
+    // Receive other replicas' agent events
+    tokio::spawn(events::run_relay(app_state.events.clone(), redis_url.clone()));
+
     // ============================================================
     // Start Backup Loop (Background)
     // ============================================================
@@ -177,9 +258,26 @@ async fn main() {
     // Build Axum Router
     // ============================================================
 
-    let app = Router::new()
+    // Public routes (no session required)
+    let public = Router::new()
+        .route("/api/auth/register", post(handlers::register))
+        .route("/api/auth/login", post(handlers::login))
+        .route("/api/auth/logout", post(handlers::logout))
+        
+        // Health check
+        .route("/health", get(handlers::health_check));
+
+    // Everything else requires a signed-in user
+    let protected = Router::new()
+        .route("/api/auth/me", get(handlers::current_user))
+        
         // Streaming chat endpoint
         .route("/api/chat/stream", post(handlers::handle_chat_stream))
+        .route("/api/chat/stream/:generation_id", get(handlers::resume_chat_stream))
+        .route("/api/chat/:generation_id/stop", post(handlers::stop_generation))
+        
+        // Chat over WebSocket (send/stop/typing, events and notifications)
+        .route("/api/ws", get(ws::ws_handler))
         
         // Chat CRUD
         .route("/api/chats", get(handlers::list_chats))
@@ -188,6 +286,11 @@ async fn main() {
         .route("/api/chats/:id", get(handlers::get_chat))
         .route("/api/chats/:id", put(handlers::update_chat))
         .route("/api/chats/:id", delete(handlers::delete_chat))
+        .route("/api/chats/:id/branches", post(handlers::create_branch))
+        .route("/api/chats/:id/branches/:bid/messages", get(handlers::get_branch_messages))
+        .route("/api/chats/:id/messages/:mid", put(handlers::update_message))
+        .route("/api/chats/:id/messages/:mid", delete(handlers::delete_message))
+        .route("/api/chats/:id/messages/:mid/regenerate", post(handlers::regenerate_message))
         
         // Persona CRUD
         .route("/api/personas", get(handlers::list_personas))
@@ -196,6 +299,7 @@ async fn main() {
         .route("/api/personas/:id", get(handlers::get_persona))
         .route("/api/personas/:id", put(handlers::update_persona))
         .route("/api/personas/:id", delete(handlers::delete_persona))
+        .route("/api/personas/:id/state", get(handlers::get_persona_state))
         
         // Group CRUD
         .route("/api/groups", get(handlers::list_groups))
@@ -212,20 +316,23 @@ async fn main() {
         // Dreams & Journal
         .route("/api/dreams", get(handlers::list_dreams))
         .route("/api/dreams/search", get(handlers::search_dreams))
-        .route("/api/dreams/import", post(handlers::import_dreams_archive))
         .route("/api/journal", get(handlers::list_journal))
         .route("/api/journal/search", get(handlers::search_journal))
         .route("/api/journal/trigger", post(handlers::trigger_reflection))
-        .route("/api/journal/import", post(handlers::import_journal_archive))
         .route("/api/logs", get(handlers::list_logs))
         
         // RAG / Vector Search
         .route("/api/search", post(handlers::search_memories))
+        .route("/api/memories", get(handlers::list_memories))
         .route("/api/memories", post(handlers::store_memory))
+        .route("/api/memories/:id", get(handlers::get_memory))
+        .route("/api/memories/:id", put(handlers::update_memory))
+        .route("/api/memories/:id", delete(handlers::delete_memory))
         
         // Status
         .route("/api/status", get(handlers::get_status))
         .route("/api/status/mood", post(handlers::update_mood))
+        .route("/api/events", get(handlers::agent_events))
         
         // Model Management
         .route("/api/models", get(handlers::list_models))
@@ -239,6 +346,9 @@ async fn main() {
         .route("/api/voice-samples/upload", post(handlers::upload_voice_sample))
         .route("/api/voice-samples/:filename", get(handlers::get_voice_sample))
         
+        // Tools
+        .route("/api/tools/execute", post(handlers::execute_code))
+        
         // Image Generation
         .route("/api/images/generate", post(handlers::generate_image))
         .route("/api/images", get(handlers::list_images))
@@ -252,20 +362,13 @@ async fn main() {
         .route("/api/settings", get(handlers::get_settings))
         .route("/api/settings/editor", put(handlers::update_editor_settings))
         .route("/api/settings/ui", put(handlers::update_ui_settings))
-        
-        // Legacy endpoints (backward compatibility)
-        .route("/api/chat", post(handlers::handle_chat))
-        .route("/api/history/:session_id", get(handlers::get_history))
-        .route("/api/clear", post(handlers::clear_history))
-        
-        // Health check
-        .route("/health", get(handlers::health_check))
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
-        )
+        .route_layer(axum::middleware::from_fn_with_state(app_state.clone(), auth::require_user));
+
+    let app = Router::new()
+        .merge(public)
+        .merge(protected)
+        .layer(axum::middleware::from_fn(error::request_id))
+        .layer(auth::cors_layer())
         .with_state(app_state);
 
     tracing::info!("🌙 Azera is awakening...");
@@ -311,6 +414,9 @@ async fn init_default_personas(pool: &sqlx::Pool<sqlx::Postgres>) {
             global_memory_enabled: true,
             current_mood: Some("focused".to_string()),
             voice: None,
+            llm_provider: None,
+            reranker: None,
+            user_id: None,
             metadata: std::collections::HashMap::from([
                 ("theme".to_string(), "professional".to_string()),
                 ("tone".to_string(), "precise".to_string()),
@@ -345,6 +451,9 @@ async fn init_default_personas(pool: &sqlx::Pool<sqlx::Postgres>) {
             global_memory_enabled: true,
             current_mood: Some("excited".to_string()),
             voice: None,
+            llm_provider: None,
+            reranker: None,
+            user_id: None,
             metadata: std::collections::HashMap::from([
                 ("theme".to_string(), "theatrical".to_string()),
                 ("tone".to_string(), "dramatic".to_string()),
@@ -374,6 +483,9 @@ async fn init_default_personas(pool: &sqlx::Pool<sqlx::Postgres>) {
             global_memory_enabled: false,
             current_mood: None,
             voice: None,
+            llm_provider: None,
+            reranker: None,
+            user_id: None,
             metadata: std::collections::HashMap::new(),
             tags: Some(vec!["default".to_string()]),
             created_at: chrono::Utc::now(),
@@ -390,7 +502,7 @@ async fn init_default_personas(pool: &sqlx::Pool<sqlx::Postgres>) {
     // --- Regenerate .md files for all DB personas that don't have one ---
     // This ensures edited personas get their Profile written to disk
     let _ = tools::fs_utils::ensure_dir("./personas");
-    if let Ok(all_personas) = db::list_personas(pool, None).await {
+    if let Ok(all_personas) = db::list_personas(pool, None, None).await {
         for persona in &all_personas {
             if let Some(ref prompt) = persona.system_prompt {
                 let filename = persona.name.to_lowercase().replace(' ', "_");
@@ -0,0 +1,325 @@
+//! Memory management
+//!
+//! `GET/PUT/DELETE /api/memories` let users see and correct what the
+//! personas remember about them. A memory is an `azera_memory` point, and
+//! some kinds have copies elsewhere that edits and deletes reach too (see
+//! [`Origin`]): a dream or reflection is also a row in CockroachDB, from
+//! which its Meilisearch `memories` document is rebuilt; a fact or a memory
+//! stored via `POST /api/memories` has its own `memories` document; a
+//! conversation turn is only the point (the chat message stays in its chat).
+//!
+//! Users can change only their own memories; shared ones are read-only.
+
+use crate::models::{Memory, MemoryListQuery, UpdateMemoryRequest};
+use crate::vector::{self, StoredPoint};
+use crate::{db, forgetting, handlers, AppState};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::{json, Value};
+
+const MEMORY_COLLECTION: &str = "azera_memory";
+/// Sort recorded in list cursors: Qdrant scrolls in point ID order
+pub const CURSOR_SORT: &str = "id:asc";
+
+/// Payload keys [`Memory`] has fields for
+const FIELDS: &[&str] = &[
+    "type", "content", "ai_persona_id", "user_id", "chat_id", "timestamp",
+    "importance", "access_count", "pinned", "archived", "superseded",
+];
+
+/// Where else a memory is stored
+#[derive(Debug, PartialEq)]
+pub enum Origin {
+    /// A dream (`dreams` row and `memories` document with this ID)
+    Dream(String),
+    /// A reflection (`journal_entries` row and `memories` document with this ID)
+    Journal(String),
+    /// A conversation turn, recorded only as the point
+    Chat,
+    /// A fact or stored via the API: `memories` document under the point ID
+    Memory,
+}
+
+impl Origin {
+    pub fn of(point: &StoredPoint) -> Self {
+        let str_field = |key: &str| point.payload.get(key).and_then(|v| v.as_str()).map(String::from);
+        if let Some(id) = str_field("dream_id") {
+            Origin::Dream(id)
+        } else if let Some(id) = str_field("journal_id") {
+            Origin::Journal(id)
+        } else if point.payload.contains_key("message_id") {
+            Origin::Chat
+        } else {
+            Origin::Memory
+        }
+    }
+}
+
+/// Qdrant filter for `GET /api/memories`: memories `user_id` may see,
+/// narrowed by the query. Errors name the invalid parameter.
+pub fn list_filter(user_id: &str, query: &MemoryListQuery) -> Result<Value, String> {
+    let mut must = vec![vector::visible_to_user(user_id)];
+    if let Some(persona_id) = &query.persona_id {
+        must.push(json!({ "key": "ai_persona_id", "match": { "value": persona_id } }));
+    }
+    if let Some(memory_type) = &query.memory_type {
+        must.push(json!({ "key": "type", "match": { "value": memory_type } }));
+    }
+    if let Some(chat_id) = &query.chat_id {
+        // Turns carry their chat, facts every chat they were drawn from
+        must.push(json!({ "should": [
+            { "key": "chat_id", "match": { "value": chat_id } },
+            { "key": "chat_ids", "match": { "value": chat_id } }
+        ] }));
+    }
+    if let Some(date) = &query.date {
+        let day = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD", date))?;
+        let start = day.and_hms_opt(0, 0, 0).expect("midnight exists").and_utc();
+        let end = start + chrono::Duration::days(1);
+        must.push(json!({ "key": "timestamp", "range": { "gte": start.to_rfc3339(), "lt": end.to_rfc3339() } }));
+    }
+
+    let mut filter = json!({ "must": must });
+    if !query.include_forgotten {
+        filter["must_not"] = json!([vector::archived(), vector::superseded()]);
+    }
+    Ok(filter)
+}
+
+/// Whether `user_id` may read the memory: their own, or a shared one that
+/// is not a conversation turn (as [`vector::visible_to_user`])
+pub fn visible_to(point: &StoredPoint, user_id: &str) -> bool {
+    match point.payload.get("user_id").and_then(|v| v.as_str()) {
+        Some(owner) => owner == user_id,
+        None => point.payload.get("type").and_then(|v| v.as_str()) != Some("conversation"),
+    }
+}
+
+/// Whether `user_id` may change the memory
+pub fn owned_by(point: &StoredPoint, user_id: &str) -> bool {
+    point.payload.get("user_id").and_then(|v| v.as_str()) == Some(user_id)
+}
+
+/// The API view of a point
+pub fn to_memory(point: StoredPoint) -> Memory {
+    let payload = point.payload;
+    let str_field = |key: &str| payload.get(key).and_then(|v| v.as_str()).map(String::from);
+    let flag = |key: &str| payload.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+    let memory_type = str_field("type").unwrap_or_else(|| "conversation".to_string());
+    let content = str_field("content").unwrap_or_default();
+    let importance = payload
+        .get("importance")
+        .and_then(|v| v.as_f64())
+        .map_or_else(|| forgetting::importance(&memory_type, payload.get("role").and_then(|v| v.as_str()), &content), |v| v as f32);
+
+    Memory {
+        id: point.id,
+        ai_persona_id: payload.get("ai_persona_id").filter(|v| !v.is_null()).cloned(),
+        user_id: str_field("user_id"),
+        chat_id: str_field("chat_id"),
+        timestamp: str_field("timestamp"),
+        importance,
+        access_count: payload.get("access_count").and_then(|v| v.as_u64()).unwrap_or(0),
+        pinned: flag("pinned"),
+        archived: flag("archived"),
+        superseded: flag("superseded"),
+        metadata: payload.iter().filter(|(k, _)| !FIELDS.contains(&k.as_str())).map(|(k, v)| (k.clone(), v.clone())).collect(),
+        memory_type,
+        content,
+    }
+}
+
+/// Apply an edit: new content is re-embedded and written to the memory's
+/// other copies; pinning also restores an archived memory, and unpinning
+/// restarts decay from now. Returns the updated point.
+pub async fn update(state: &AppState, mut point: StoredPoint, user_id: &str, req: &UpdateMemoryRequest) -> Result<StoredPoint> {
+    let now = Utc::now().to_rfc3339();
+    let mut changes = serde_json::Map::new();
+    if let Some(pinned) = req.pinned {
+        changes.insert("pinned".to_string(), json!(pinned));
+        if pinned {
+            changes.insert("archived".to_string(), json!(false));
+        } else {
+            changes.insert("decayed_at".to_string(), json!(now));
+        }
+    }
+
+    match req.content.as_deref() {
+        Some(content) => {
+            changes.insert("content".to_string(), json!(content));
+            changes.insert("edited_at".to_string(), json!(now));
+            point.payload.extend(changes);
+            let embedding = state.vector.generate_embedding_cached(&state.ollama_host, content, &state.cache).await?;
+            state.vector.upsert(MEMORY_COLLECTION, &point.id, embedding, point.payload.clone()).await?;
+            sync_content(state, &point, user_id, content).await?;
+        }
+        None if !changes.is_empty() => {
+            state.vector.set_payload(MEMORY_COLLECTION, Value::Object(changes.clone()), vector::has_ids(&[point.id.clone()])).await?;
+            point.payload.extend(changes);
+        }
+        None => {}
+    }
+    Ok(point)
+}
+
+/// Write edited content to the memory's copies outside Qdrant
+async fn sync_content(state: &AppState, point: &StoredPoint, user_id: &str, content: &str) -> Result<()> {
+    match Origin::of(point) {
+        Origin::Dream(id) => {
+            if let Some(dream) = db::update_dream_content(&state.db, &id, user_id, content).await? {
+                handlers::meili_index_dream(&state.meili_url, &state.meili_key, &dream).await;
+            }
+        }
+        Origin::Journal(id) => {
+            if let Some(entry) = db::update_journal_content(&state.db, &id, user_id, content).await? {
+                handlers::meili_index_journal(&state.meili_url, &state.meili_key, &entry).await;
+            }
+        }
+        Origin::Chat => {}
+        // Superseded facts already left the index
+        Origin::Memory if point.payload.get("superseded").and_then(|v| v.as_bool()) == Some(true) => {}
+        Origin::Memory => handlers::meili_index_memory(&state.meili_url, &state.meili_key, &meili_doc(point)).await,
+    }
+    Ok(())
+}
+
+/// Delete the memory everywhere it is stored
+pub async fn forget(state: &AppState, point: &StoredPoint, user_id: &str) -> Result<()> {
+    state.vector.delete(MEMORY_COLLECTION, &point.id).await?;
+    match Origin::of(point) {
+        Origin::Dream(id) => {
+            db::delete_dream(&state.db, &id, user_id).await?;
+            handlers::meili_delete_memory(&state.meili_url, &state.meili_key, &id).await;
+        }
+        Origin::Journal(id) => {
+            db::delete_journal_entry(&state.db, &id, user_id).await?;
+            handlers::meili_delete_memory(&state.meili_url, &state.meili_key, &id).await;
+        }
+        Origin::Chat => {}
+        Origin::Memory => handlers::meili_delete_memory(&state.meili_url, &state.meili_key, &point.id).await,
+    }
+    Ok(())
+}
+
+/// `memories` document of a fact or API-stored memory
+pub fn meili_doc(point: &StoredPoint) -> Value {
+    let field = |key: &str| point.payload.get(key).cloned().unwrap_or(Value::Null);
+    let stored = point
+        .payload
+        .get("timestamp")
+        .and_then(|v| v.as_str())
+        .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+        .map_or_else(Utc::now, |ts| ts.with_timezone(&Utc));
+    let tags: Vec<Value> = point.payload.get("kind").into_iter().cloned().collect();
+    json!({
+        "id": point.id,
+        "memory_type": field("type"),
+        "title": point.payload.get("title").cloned().unwrap_or_else(|| json!("")),
+        "content": field("content"),
+        "persona_id": field("ai_persona_id"),
+        "user_id": field("user_id"),
+        "tags": tags,
+        "date": stored.format("%Y-%m-%d").to_string(),
+        "created_at_ts": stored.timestamp()
+    })
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(id: &str, payload: Value) -> StoredPoint {
+        StoredPoint { id: id.to_string(), payload: serde_json::from_value(payload).unwrap() }
+    }
+
+    mod filter_tests {
+        use super::*;
+
+        #[test]
+        fn hides_forgotten_memories_unless_asked() {
+            let filter = list_filter("alice", &MemoryListQuery::default()).unwrap();
+            assert_eq!(filter["must"], json!([vector::visible_to_user("alice")]));
+            assert_eq!(filter["must_not"], json!([vector::archived(), vector::superseded()]));
+
+            let query = MemoryListQuery { include_forgotten: true, ..Default::default() };
+            assert!(list_filter("alice", &query).unwrap().get("must_not").is_none());
+        }
+
+        #[test]
+        fn narrows_by_persona_type_chat_and_day() {
+            let query = MemoryListQuery {
+                persona_id: Some("azera".to_string()),
+                memory_type: Some("fact".to_string()),
+                chat_id: Some("chat-1".to_string()),
+                date: Some("2026-03-04".to_string()),
+                ..Default::default()
+            };
+            let must = list_filter("alice", &query).unwrap()["must"].clone();
+            assert_eq!(must[1], json!({ "key": "ai_persona_id", "match": { "value": "azera" } }));
+            assert_eq!(must[2], json!({ "key": "type", "match": { "value": "fact" } }));
+            assert_eq!(must[3]["should"][1], json!({ "key": "chat_ids", "match": { "value": "chat-1" } }));
+            assert_eq!(must[4]["range"], json!({ "gte": "2026-03-04T00:00:00+00:00", "lt": "2026-03-05T00:00:00+00:00" }));
+        }
+
+        #[test]
+        fn rejects_malformed_dates() {
+            let query = MemoryListQuery { date: Some("March 4".to_string()), ..Default::default() };
+            assert!(list_filter("alice", &query).unwrap_err().contains("March 4"));
+        }
+    }
+
+    mod memory_tests {
+        use super::*;
+
+        #[test]
+        fn knows_where_else_a_memory_lives() {
+            assert_eq!(Origin::of(&point("p1", json!({ "type": "dream", "dream_id": "dream-1" }))), Origin::Dream("dream-1".to_string()));
+            assert_eq!(Origin::of(&point("p2", json!({ "type": "reflection", "journal_id": "journal_1" }))), Origin::Journal("journal_1".to_string()));
+            assert_eq!(Origin::of(&point("p3", json!({ "type": "conversation", "message_id": "msg_1" }))), Origin::Chat);
+            assert_eq!(Origin::of(&point("p4", json!({ "type": "fact" }))), Origin::Memory);
+        }
+
+        #[test]
+        fn shared_memories_are_visible_but_read_only() {
+            let shared = point("p1", json!({ "type": "dream" }));
+            let unowned_turn = point("p2", json!({ "type": "conversation" }));
+            let own = point("p3", json!({ "type": "fact", "user_id": "alice" }));
+            assert!(visible_to(&shared, "alice") && !owned_by(&shared, "alice"));
+            assert!(!visible_to(&unowned_turn, "alice"));
+            assert!(visible_to(&own, "alice") && owned_by(&own, "alice"));
+            assert!(!visible_to(&own, "bob"));
+        }
+
+        #[test]
+        fn splits_known_fields_from_metadata() {
+            let memory = to_memory(point("p1", json!({
+                "type": "conversation", "role": "user", "content": "I prefer tea",
+                "user_id": "alice", "chat_id": "chat-1", "message_id": "msg_1", "pinned": true
+            })));
+            assert_eq!(memory.memory_type, "conversation");
+            assert!(memory.pinned && !memory.archived);
+            assert_eq!(memory.importance, forgetting::importance("conversation", Some("user"), "I prefer tea"));
+            let mut keys: Vec<&str> = memory.metadata.keys().map(String::as_str).collect();
+            keys.sort();
+            assert_eq!(keys, vec!["message_id", "role"]);
+        }
+
+        #[test]
+        fn meili_doc_keeps_the_stored_day() {
+            let fact = point("p1", json!({
+                "type": "fact", "content": "The user prefers tea", "kind": "preference",
+                "user_id": "alice", "ai_persona_id": "azera", "timestamp": "2026-03-04T10:00:00+00:00"
+            }));
+            let doc = meili_doc(&fact);
+            assert_eq!(doc["id"], "p1");
+            assert_eq!(doc["date"], "2026-03-04");
+            assert_eq!(doc["tags"], json!(["preference"]));
+            assert_eq!(doc["persona_id"], "azera");
+        }
+    }
+}
@@ -0,0 +1,297 @@
+//! Versioned SQL migrations for CockroachDB
+//!
+//! Migrations live in `backend/migrations/NNNN_name.{up,down}.sql` and are
+//! embedded at compile time. Applied versions are tracked in
+//! `schema_migrations`; each migration runs in its own transaction.
+//!
+//! CLI flags (handled in `main`):
+//! - `--migrate-only`: apply pending migrations and exit
+//! - `--dry-run`: print what would run without touching the database
+//! - `--rollback-to <version>`: run down scripts for everything above `version`
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres, Row};
+
+/// A single schema migration
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+impl Migration {
+    /// SHA-256 of the up script, used to detect edits to applied migrations
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(self.up.as_bytes()))
+    }
+}
+
+macro_rules! migration {
+    ($version:literal, $file:literal) => {
+        Migration {
+            version: $version,
+            name: $file,
+            up: include_str!(concat!("../migrations/", $file, ".up.sql")),
+            down: include_str!(concat!("../migrations/", $file, ".down.sql")),
+        }
+    };
+}
+
+/// All migrations, in version order
+pub const MIGRATIONS: &[Migration] = &[
+    migration!(1, "0001_initial_schema"),
+    migration!(2, "0002_persona_llm_provider"),
+    migration!(3, "0003_users_and_ownership"),
+    migration!(4, "0004_persona_reranker"),
+    migration!(5, "0005_chat_summaries"),
+    migration!(6, "0006_chat_cast"),
+    migration!(7, "0007_chat_list_indexes"),
+    migration!(8, "0008_message_interrupted"),
+    migration!(9, "0009_dream_sources"),
+    migration!(10, "0010_chat_deletion"),
+    migration!(11, "0011_image_owners"),
+];
+
+/// Migration-related command line options
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MigrateArgs {
+    pub migrate_only: bool,
+    pub dry_run: bool,
+    pub rollback_to: Option<i64>,
+    /// Import `../archive` as shared journal entries and dreams (see archive.rs)
+    pub import_archive: bool,
+}
+
+impl MigrateArgs {
+    /// Parse from process arguments (excluding the binary name)
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--migrate-only" => parsed.migrate_only = true,
+                "--dry-run" => parsed.dry_run = true,
+                "--import-archive" => parsed.import_archive = true,
+                "--rollback-to" => {
+                    let version = args
+                        .next()
+                        .context("--rollback-to requires a version")?;
+                    parsed.rollback_to = Some(
+                        version
+                            .parse()
+                            .with_context(|| format!("Invalid migration version: {}", version))?,
+                    );
+                }
+                other => anyhow::bail!("Unknown argument: {}", other),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Whether the process should exit after the migration step
+    pub fn exit_after_migrating(&self) -> bool {
+        self.migrate_only || self.dry_run || self.rollback_to.is_some() || self.import_archive
+    }
+}
+
+async fn ensure_migrations_table(pool: &Pool<Postgres>) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            name TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TIMESTAMPTZ DEFAULT NOW()
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Applied versions with their recorded checksums
+async fn applied_migrations(pool: &Pool<Postgres>) -> Result<Vec<(i64, String)>> {
+    let rows = sqlx::query("SELECT version, checksum FROM schema_migrations ORDER BY version")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(|r| (r.get("version"), r.get("checksum"))).collect())
+}
+
+/// Migrations not yet recorded in `schema_migrations`
+pub fn pending<'a>(migrations: &'a [Migration], applied: &[i64]) -> Vec<&'a Migration> {
+    migrations.iter().filter(|m| !applied.contains(&m.version)).collect()
+}
+
+/// Apply all pending migrations. Returns the versions applied
+/// (or that would be applied, in dry-run mode).
+pub async fn migrate(pool: &Pool<Postgres>, dry_run: bool) -> Result<Vec<i64>> {
+    let applied = if dry_run {
+        // Don't create the tracking table in dry-run mode
+        applied_migrations(pool).await.unwrap_or_default()
+    } else {
+        ensure_migrations_table(pool).await?;
+        applied_migrations(pool).await?
+    };
+
+    for (version, checksum) in &applied {
+        if let Some(m) = MIGRATIONS.iter().find(|m| m.version == *version) {
+            if m.checksum() != *checksum {
+                tracing::warn!("⚠️ Migration {} was modified after being applied", m.name);
+            }
+        }
+    }
+
+    let applied_versions: Vec<i64> = applied.iter().map(|(v, _)| *v).collect();
+    let mut done = Vec::new();
+
+    for migration in pending(MIGRATIONS, &applied_versions) {
+        if dry_run {
+            tracing::info!("🗄️ [dry-run] Would apply migration {}", migration.name);
+            done.push(migration.version);
+            continue;
+        }
+
+        tracing::info!("🗄️ Applying migration {}", migration.name);
+        let mut tx = pool.begin().await?;
+        sqlx::raw_sql(migration.up)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Migration {} failed", migration.name))?;
+        sqlx::query("INSERT INTO schema_migrations (version, name, checksum) VALUES ($1, $2, $3)")
+            .bind(migration.version)
+            .bind(migration.name)
+            .bind(migration.checksum())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        done.push(migration.version);
+    }
+
+    if done.is_empty() {
+        tracing::info!("🗄️ Database schema is up to date");
+    }
+    Ok(done)
+}
+
+/// Revert applied migrations newer than `target`, newest first.
+/// Returns the versions reverted (or that would be, in dry-run mode).
+pub async fn rollback_to(pool: &Pool<Postgres>, target: i64, dry_run: bool) -> Result<Vec<i64>> {
+    ensure_migrations_table(pool).await?;
+    let applied = applied_migrations(pool).await?;
+    let mut done = Vec::new();
+
+    for (version, _) in applied.iter().rev().filter(|(v, _)| *v > target) {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|m| m.version == *version)
+            .with_context(|| format!("No down script for applied migration {}", version))?;
+
+        if dry_run {
+            tracing::info!("🗄️ [dry-run] Would revert migration {}", migration.name);
+            done.push(migration.version);
+            continue;
+        }
+
+        tracing::info!("🗄️ Reverting migration {}", migration.name);
+        let mut tx = pool.begin().await?;
+        sqlx::raw_sql(migration.down)
+            .execute(&mut *tx)
+            .await
+            .with_context(|| format!("Rollback of {} failed", migration.name))?;
+        sqlx::query("DELETE FROM schema_migrations WHERE version = $1")
+            .bind(migration.version)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        done.push(migration.version);
+    }
+
+    Ok(done)
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod migration_list_tests {
+        use super::*;
+
+        #[test]
+        fn versions_are_sequential() {
+            for (i, m) in MIGRATIONS.iter().enumerate() {
+                assert_eq!(m.version, i as i64 + 1, "{} is out of order", m.name);
+                assert!(m.name.starts_with(&format!("{:04}_", m.version)));
+            }
+        }
+
+        #[test]
+        fn every_migration_has_both_scripts() {
+            for m in MIGRATIONS {
+                assert!(!m.up.trim().is_empty(), "{} has an empty up script", m.name);
+                assert!(!m.down.trim().is_empty(), "{} has an empty down script", m.name);
+            }
+        }
+
+        #[test]
+        fn pending_skips_applied() {
+            let pending = pending(MIGRATIONS, &[1]);
+            assert!(pending.iter().all(|m| m.version != 1));
+            assert_eq!(pending.len(), MIGRATIONS.len() - 1);
+        }
+
+        #[test]
+        fn checksum_is_stable() {
+            assert_eq!(MIGRATIONS[0].checksum(), MIGRATIONS[0].checksum());
+            assert_eq!(MIGRATIONS[0].checksum().len(), 64);
+        }
+    }
+
+    mod migrate_args_tests {
+        use super::*;
+
+        fn parse(args: &[&str]) -> Result<MigrateArgs> {
+            MigrateArgs::parse(args.iter().map(|s| s.to_string()))
+        }
+
+        #[test]
+        fn no_flags_starts_server() {
+            let args = parse(&[]).unwrap();
+            assert_eq!(args, MigrateArgs::default());
+            assert!(!args.exit_after_migrating());
+        }
+
+        #[test]
+        fn migrate_only_and_dry_run() {
+            let args = parse(&["--migrate-only", "--dry-run"]).unwrap();
+            assert!(args.migrate_only && args.dry_run);
+            assert!(args.exit_after_migrating());
+        }
+
+        #[test]
+        fn rollback_requires_numeric_version() {
+            assert_eq!(parse(&["--rollback-to", "1"]).unwrap().rollback_to, Some(1));
+            assert!(parse(&["--rollback-to"]).is_err());
+            assert!(parse(&["--rollback-to", "latest"]).is_err());
+        }
+
+        #[test]
+        fn import_archive_exits_after_importing() {
+            let args = parse(&["--import-archive"]).unwrap();
+            assert!(args.import_archive);
+            assert!(args.exit_after_migrating());
+        }
+
+        #[test]
+        fn unknown_flag_is_rejected() {
+            assert!(parse(&["--frobnicate"]).is_err());
+        }
+    }
+}
@@ -44,6 +44,12 @@ pub struct Persona {
     pub current_mood: Option<String>,  // Dynamic mood based on last response
     #[serde(skip_serializing_if = "Option::is_none")]
     pub voice: Option<VoiceConfig>,    // Voice/TTS settings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub llm_provider: Option<ProviderConfig>,  // Inference backend (defaults to Ollama)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reranker: Option<RerankerConfig>,      // Rerank retrieved memories (off when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,  // Owning account (None = shared built-in)
     #[serde(default)]
     pub metadata: HashMap<String, String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -52,6 +58,19 @@ pub struct Persona {
     pub updated_at: DateTime<Utc>,
 }
 
+impl Persona {
+    /// Copy for API responses: stored backend credentials never leave the server
+    pub fn redacted(mut self) -> Self {
+        if let Some(provider) = self.llm_provider.as_mut() {
+            provider.api_key = None;
+        }
+        if let Some(reranker) = self.reranker.as_mut() {
+            reranker.api_key = None;
+        }
+        self
+    }
+}
+
 fn default_true() -> bool { true }
 
 /// Voice configuration for TTS
@@ -85,6 +104,46 @@ fn default_pitch() -> f32 { 1.0 }
 fn default_rate() -> f32 { 1.0 }
 fn default_volume() -> f32 { 1.0 }
 
+/// Supported LLM inference backends
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    #[default]
+    Ollama,
+    /// Any server exposing the OpenAI `/v1/chat/completions` API
+    /// (llama.cpp server, vLLM, LM Studio, ...)
+    #[serde(rename = "openai")]
+    OpenAI,
+}
+
+/// LLM backend selection, set per persona or per request
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ProviderConfig {
+    #[serde(default)]
+    pub kind: ProviderKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,           // Falls back to OLLAMA_HOST / OPENAI_COMPAT_URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,            // Falls back to OPENAI_COMPAT_API_KEY
+}
+
+/// Reranking stage for retrieved memories, set per persona.
+/// `ollama` scores candidates with a local model prompted for relevance;
+/// `openai` calls an OpenAI-compatible `/v1/rerank` endpoint
+/// (llama.cpp server, vLLM, TEI, ...).
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RerankerConfig {
+    #[serde(default)]
+    pub kind: ProviderKind,
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,           // Falls back to OLLAMA_HOST / OPENAI_COMPAT_URL
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,            // Falls back to OPENAI_COMPAT_API_KEY
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_score: Option<f32>,             // Drop memories scoring below this after reranking
+}
+
 /// Chat message
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatMessage {
@@ -101,9 +160,27 @@ pub struct ChatMessage {
     pub model: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mood: Option<String>,
+    /// Generation was stopped before the reply finished
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub interrupted: bool,
 }
 
-/// Chat branch for conversation forking
+/// A message from a chat an AI persona took part in: the user's messages
+/// and that persona's replies (see db::persona_messages)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PersonaMessage {
+    pub id: String,
+    pub chat_id: String,
+    /// Owner of the chat
+    pub user_id: Option<String>,
+    pub role: String,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Chat branch for conversation forking. A branch with a parent inherits
+/// the parent's history up to and including `fork_point_message_id` (none
+/// when unset); `messages` holds only the branch's own messages.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatBranch {
     pub id: String,
@@ -128,6 +205,83 @@ pub struct Chat {
     pub group_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,  // Owning account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cast: Option<ChatCast>,  // AI personas sharing the chat (group chat)
+}
+
+/// One row of the chat list: the chat without its branches and messages
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatListItem {
+    pub id: String,
+    pub title: String,
+    pub current_branch_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cast: Option<ChatCast>,
+    /// Newest message on the current branch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_message: Option<MessagePreview>,
+    /// Messages stored across all branches
+    pub message_count: i64,
+    pub created_at: DateTime<Utc>,
+    /// Bumped by edits and by every new message
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Truncated message shown in the chat list
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessagePreview {
+    pub role: String,
+    pub content: String,  // First 200 characters
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Turn-taking policy for group chats
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnPolicy {
+    /// Personas answer in cast order, continuing after the last one that spoke
+    #[default]
+    RoundRobin,
+    /// Personas named with `@Name` answer; round-robin when nobody is mentioned
+    Mention,
+    /// A moderator LLM picks who speaks next
+    Moderator,
+}
+
+/// AI personas sharing one chat and how they take turns
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ChatCast {
+    pub persona_ids: Vec<String>,
+    #[serde(default)]
+    pub turn_policy: TurnPolicy,
+    /// Personas answering each user message (mentions may add more)
+    #[serde(default = "default_speakers_per_turn")]
+    pub speakers_per_turn: usize,
+}
+
+fn default_speakers_per_turn() -> usize {
+    1
+}
+
+/// Rolling summary of a chat (durable copy of the Dragonfly session context)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChatSummary {
+    pub chat_id: String,
+    pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_goal: Option<String>,
+    pub topics: Vec<String>,
+    pub turn_count: u32,
+    /// Last message folded into the summary
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summarized_through: Option<String>,
+    pub updated_at: DateTime<Utc>,
 }
 
 /// Dream entry (AI hallucinations during idle)
@@ -145,6 +299,11 @@ pub struct Dream {
     pub persona_name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,  // Owning account (None = shared)
+    /// Chat messages the dream was drawn from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_message_ids: Option<Vec<String>>,
 }
 
 /// Journal entry (AI reflections)
@@ -162,9 +321,20 @@ pub struct JournalEntry {
     pub persona_name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,  // Owning account (None = shared)
     pub created_at: DateTime<Utc>,
 }
 
+/// Query for `POST /api/journal/trigger`
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ReflectionTriggerQuery {
+    /// Defaults to the default persona
+    pub persona_id: Option<String>,
+    /// Day to reflect on (`YYYY-MM-DD`, default today)
+    pub date: Option<String>,
+}
+
 /// System log entry
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LogEntry {
@@ -174,10 +344,44 @@ pub struct LogEntry {
     pub message: String,
 }
 
+/// Local user account
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct User {
+    pub id: String,
+    pub username: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
 // ============================================================
 // API Request/Response Types
 // ============================================================
 
+/// Request to create a local account
+#[derive(Debug, Deserialize)]
+pub struct RegisterRequest {
+    pub username: String,
+    pub password: String,
+    pub display_name: Option<String>,
+}
+
+/// Request to log in with username and password
+#[derive(Debug, Deserialize)]
+pub struct LoginRequest {
+    pub username: String,
+    pub password: String,
+}
+
+/// Successful login/registration. The token is also set as an
+/// HttpOnly session cookie; API clients can send it as a Bearer token.
+#[derive(Debug, Serialize)]
+pub struct AuthResponse {
+    pub user: User,
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
 /// Request structure for streaming chat endpoint
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ChatRequest {
@@ -190,12 +394,42 @@ pub struct ChatRequest {
     pub user_persona_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ai_persona_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<ProviderConfig>,  // Overrides the persona's backend
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cast: Option<ChatCast>,  // Sets the chat's group cast (replaces ai_persona_id)
 }
 
-fn default_model() -> String {
+pub fn default_model() -> String {
     "llama3.2".to_string()
 }
 
+/// Fork a branch after a message
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateBranchRequest {
+    /// Last message the new branch inherits
+    pub message_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// Edit a message's content in place
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateMessageRequest {
+    pub content: String,
+}
+
+/// Regenerate an assistant reply on a new sibling branch
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RegenerateRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,  // Defaults to the original reply's model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<ProviderConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch_name: Option<String>,
+}
+
 /// SSE event types for streaming response
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "type")]
@@ -203,22 +437,108 @@ pub enum StreamEvent {
     #[serde(rename = "thinking_start")]
     ThinkingStart,
     #[serde(rename = "thinking")]
-    Thinking { content: String },
+    Thinking {
+        content: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        persona_id: Option<String>,
+    },
     #[serde(rename = "thinking_end")]
     ThinkingEnd,
     #[serde(rename = "content")]
-    Content { content: String },
+    Content {
+        content: String,
+        /// Speaking persona
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        persona_id: Option<String>,
+    },
+    /// The reply is generated on a newly forked branch (regeneration)
+    #[serde(rename = "branch")]
+    Branch {
+        branch_id: String,
+        parent_branch_id: String,
+        fork_point_message_id: String,
+    },
+    /// A group-chat persona starts its reply
+    #[serde(rename = "speaker")]
+    Speaker { persona_id: String, name: String },
+    /// A group-chat persona's reply is saved
+    #[serde(rename = "speaker_done")]
+    SpeakerDone {
+        persona_id: String,
+        message_id: String,
+        mood: Option<String>,
+    },
+    #[serde(rename = "tool_call")]
+    ToolCall {
+        name: String,
+        arguments: serde_json::Value,
+    },
+    #[serde(rename = "tool_result")]
+    ToolResult {
+        name: String,
+        output: String,
+        success: bool,
+        duration_ms: u64,
+    },
     #[serde(rename = "done")]
     Done { 
         message_id: String,
         mood: Option<String>,
         mood_value: Option<f32>,
         energy: Option<f32>,
+        /// How the prompt was fitted into the model's context window
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        context: Option<ContextReport>,
     },
+    /// First event of every generation: the ID to stop it with
+    #[serde(rename = "generation")]
+    Generation { generation_id: String },
+    /// The generation was stopped; the partial reply, if any, is saved as `interrupted`
+    #[serde(rename = "interrupted")]
+    Interrupted { message_id: Option<String> },
     #[serde(rename = "error")]
     Error { message: String },
 }
 
+impl StreamEvent {
+    /// Attribute content and thinking tokens to `persona_id`
+    pub fn with_speaker(self, persona_id: &str) -> Self {
+        match self {
+            StreamEvent::Content { content, .. } => StreamEvent::Content { content, persona_id: Some(persona_id.to_string()) },
+            StreamEvent::Thinking { content, .. } => StreamEvent::Thinking { content, persona_id: Some(persona_id.to_string()) },
+            other => other,
+        }
+    }
+}
+
+/// Token budget and usage for one part of the prompt
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub budget: usize,
+    pub used: usize,
+}
+
+/// Budget breakdown of an assembled prompt (estimated tokens)
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ContextReport {
+    /// Context window the prompt was fitted into
+    pub context_window: usize,
+    /// Tokens left free for the response
+    pub response_reserve: usize,
+    pub system_prompt: TokenUsage,
+    pub memories: TokenUsage,
+    pub session: TokenUsage,
+    pub history: TokenUsage,
+    pub user_message: usize,
+    pub total_used: usize,
+    pub memories_included: usize,
+    pub memories_dropped: usize,
+    pub history_included: usize,
+    pub history_dropped: usize,
+    /// Dropped turns condensed into the "earlier in this conversation" note
+    pub history_summarized: usize,
+}
+
 /// Create chat request
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateChatRequest {
@@ -226,6 +546,8 @@ pub struct CreateChatRequest {
     pub title: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub group_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cast: Option<ChatCast>,
 }
 
 /// Update chat request
@@ -239,6 +561,165 @@ pub struct UpdateChatRequest {
     pub tags: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub current_branch_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cast: Option<ChatCast>,
+}
+
+/// Query for `GET /api/chats`
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ChatListQuery {
+    #[serde(default)]
+    pub sort: ChatSort,
+    #[serde(default)]
+    pub order: SortOrder,
+    /// Page size (default 50, at most 200)
+    pub limit: Option<usize>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+}
+
+impl ChatListQuery {
+    /// Sort and direction as recorded in cursors, e.g. `updated_at:desc`
+    pub fn sort_label(&self) -> String {
+        format!("{}:{}", self.sort.column(), self.order.keyword().to_lowercase())
+    }
+}
+
+/// Chat list sort key
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatSort {
+    /// Most recently active first (the default)
+    #[default]
+    UpdatedAt,
+    CreatedAt,
+    Title,
+}
+
+impl ChatSort {
+    pub fn column(self) -> &'static str {
+        match self {
+            ChatSort::UpdatedAt => "updated_at",
+            ChatSort::CreatedAt => "created_at",
+            ChatSort::Title => "title",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    pub fn keyword(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+/// Query for `GET /api/chats/:id/branches/:bid/messages`
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct MessagePageQuery {
+    /// Return messages older than this message ID (the newest when omitted)
+    pub before: Option<String>,
+    /// Page size (default 50, at most 200)
+    pub limit: Option<usize>,
+}
+
+/// A store a deleted chat has yet to be removed from (see deletion.rs)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeletionJob {
+    pub chat_id: String,
+    pub store: String,
+    /// Failed attempts so far
+    pub attempts: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// What deleting a chat did in one store
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct StoreCleanup {
+    /// `qdrant`, `meilisearch`, `dragonfly` or `images`
+    pub store: String,
+    /// `done`, or `queued` when it failed and will be retried
+    pub status: String,
+    /// Points, keys or files removed, where the store reports them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removed: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Response of `DELETE /api/chats/:id`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatDeletion {
+    pub status: String,
+    pub stores: Vec<StoreCleanup>,
+}
+
+/// Query for `GET /api/memories`
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct MemoryListQuery {
+    /// AI persona the memories belong to
+    pub persona_id: Option<String>,
+    /// `conversation`, `fact`, `dream`, `reflection` or `emotion`
+    #[serde(rename = "type")]
+    pub memory_type: Option<String>,
+    /// Chat the memories came from
+    pub chat_id: Option<String>,
+    /// Day the memories were stored (`YYYY-MM-DD`, UTC)
+    pub date: Option<String>,
+    /// Also list memories that were archived or superseded
+    #[serde(default)]
+    pub include_forgotten: bool,
+    /// Page size (default 50, at most 200)
+    pub limit: Option<usize>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+}
+
+/// Update memory request; omitted fields are left unchanged
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct UpdateMemoryRequest {
+    /// New text (re-embedded)
+    pub content: Option<String>,
+    /// Pinned memories never decay or get forgotten
+    pub pinned: Option<bool>,
+}
+
+/// A memory in `azera_memory`, as the memory API returns it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Memory {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub memory_type: String,
+    pub content: String,
+    /// A list for turns of group chats
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ai_persona_id: Option<serde_json::Value>,
+    /// Owning account (None = shared)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chat_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    pub importance: f32,
+    pub access_count: u64,
+    pub pinned: bool,
+    /// Forgotten by the sweeper: kept, but out of retrieval
+    pub archived: bool,
+    /// Replaced by a newer fact during consolidation
+    pub superseded: bool,
+    /// The rest of the payload (role, message and source IDs, ...)
+    pub metadata: HashMap<String, serde_json::Value>,
 }
 
 /// Create persona request
@@ -259,6 +740,10 @@ pub struct CreatePersonaRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub voice: Option<VoiceConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub llm_provider: Option<ProviderConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reranker: Option<RerankerConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<HashMap<String, String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<Vec<String>>,
@@ -284,11 +769,36 @@ pub struct UpdatePersonaRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub voice: Option<VoiceConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub llm_provider: Option<ProviderConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reranker: Option<RerankerConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<HashMap<String, String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<Vec<String>>,
 }
 
+impl UpdatePersonaRequest {
+    /// Keep `stored`'s API keys where the update leaves them out: responses
+    /// redact them ([`Persona::redacted`]), so a persona sent back as
+    /// received must not wipe them. An empty `api_key` removes the key.
+    pub fn keep_api_keys(&mut self, stored: &Persona) {
+        fn merge(key: &mut Option<String>, stored: Option<&String>) {
+            match key.as_deref() {
+                None => *key = stored.cloned(),
+                Some("") => *key = None,
+                Some(_) => {}
+            }
+        }
+        if let Some(provider) = self.llm_provider.as_mut() {
+            merge(&mut provider.api_key, stored.llm_provider.as_ref().and_then(|p| p.api_key.as_ref()));
+        }
+        if let Some(reranker) = self.reranker.as_mut() {
+            merge(&mut reranker.api_key, stored.reranker.as_ref().and_then(|r| r.api_key.as_ref()));
+        }
+    }
+}
+
 /// Create group request
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateGroupRequest {
@@ -334,6 +844,8 @@ fn default_color() -> String {
 /// Update mood request
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateMoodRequest {
+    /// One of the caller's own personas
+    pub persona_id: String,
     pub mood: String,  // "idle", "thinking", "surprised", "happy"
 }
 
@@ -375,11 +887,22 @@ pub struct StatusResponse {
     pub last_active: Option<DateTime<Utc>>,
 }
 
-/// History response (legacy)
+/// Runtime state of one AI persona
 #[derive(Debug, Serialize, Deserialize)]
-pub struct HistoryResponse {
-    pub session_id: String,
-    pub messages: Vec<ChatMessage>,
+pub struct PersonaStateResponse {
+    pub persona_id: String,
+    pub status: String,  // "awake", "dreaming"
+    pub mood: String,
+    pub mood_value: f32, // -1.0 to 1.0
+    pub energy: f32,     // 0.0 to 1.0
+    pub focus_level: f32,
+    pub is_dreaming: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_active: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_dream: Option<DateTime<Utc>>,
+    /// The caller's recent exchanges with the persona, oldest first
+    pub working_memory: Vec<String>,
 }
 
 /// List response wrapper
@@ -389,6 +912,14 @@ pub struct ListResponse<T> {
     pub total: usize,
 }
 
+/// One page of a keyset-paginated list
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    /// Pass back as `cursor` (or `before`) for the next page; `null` on the last one
+    pub next_cursor: Option<String>,
+}
+
 // ============================================================
 // Ollama API Types
 // ============================================================
@@ -398,6 +929,22 @@ pub struct ListResponse<T> {
 pub struct OllamaMessage {
     pub role: String,
     pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<OllamaToolCall>>,  // Native tool calls requested by the model
+}
+
+/// Tool call emitted by the model (Ollama `message.tool_calls[]`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OllamaToolCall {
+    pub function: OllamaToolFunction,
+}
+
+/// Function name + JSON arguments of a tool call
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OllamaToolFunction {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: serde_json::Value,
 }
 
 /// Ollama API request format
@@ -473,6 +1020,29 @@ pub struct OllamaPullProgress {
     pub completed: Option<u64>,
 }
 
+// ============================================================
+// Tool Execution Types
+// ============================================================
+
+/// Request for sandboxed code execution (POST /api/tools/execute)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExecuteCodeRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wasm_base64: Option<String>,     // Compiled wasm32-wasi module
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wat: Option<String>,             // WebAssembly text format module
+    #[serde(default)]
+    pub stdin: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fuel: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_limit_mb: Option<usize>,
+}
+
 // ============================================================
 // Image Generation Types
 // ============================================================
@@ -596,6 +1166,9 @@ mod tests {
                 global_memory_enabled: true,
                 current_mood: Some("happy".to_string()),
                 voice: None,
+                llm_provider: None,
+                reranker: None,
+                user_id: Some("user-1".to_string()),
                 metadata: HashMap::new(),
                 tags: Some(vec!["test".to_string()]),
                 created_at: Utc::now(),
@@ -608,6 +1181,7 @@ mod tests {
             assert_eq!(persona.id, deserialized.id);
             assert_eq!(persona.name, deserialized.name);
             assert_eq!(persona.persona_type, deserialized.persona_type);
+            assert_eq!(deserialized.user_id.as_deref(), Some("user-1"));
         }
 
         #[test]
@@ -623,6 +1197,9 @@ mod tests {
                 global_memory_enabled: true,
                 current_mood: None,
                 voice: None,
+                llm_provider: None,
+                reranker: None,
+                user_id: None,
                 metadata: HashMap::new(),
                 tags: None,
                 created_at: Utc::now(),
@@ -633,12 +1210,79 @@ mod tests {
             // Should serialize as "type" not "persona_type"
             assert!(json.contains("\"type\":\"ai\""));
             assert!(!json.contains("persona_type"));
+            // Shared personas have no owner in the JSON
+            assert!(!json.contains("user_id"));
+        }
+
+        #[test]
+        fn redacted_persona_drops_api_keys() {
+            let persona = Persona {
+                id: "test".to_string(),
+                name: "Test".to_string(),
+                persona_type: "ai".to_string(),
+                description: "".to_string(),
+                avatar: None,
+                bubble_color: None,
+                system_prompt: None,
+                global_memory_enabled: true,
+                current_mood: None,
+                voice: None,
+                llm_provider: Some(ProviderConfig {
+                    kind: ProviderKind::OpenAI,
+                    base_url: Some("http://localhost:8080".to_string()),
+                    api_key: Some("sk-secret".to_string()),
+                }),
+                reranker: Some(RerankerConfig {
+                    kind: ProviderKind::OpenAI,
+                    model: "bge-reranker".to_string(),
+                    api_key: Some("sk-rerank".to_string()),
+                    ..Default::default()
+                }),
+                user_id: Some("user-1".to_string()),
+                metadata: HashMap::new(),
+                tags: None,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            };
+
+            let json = serde_json::to_string(&persona.redacted()).unwrap();
+            assert!(!json.contains("sk-secret"));
+            assert!(!json.contains("sk-rerank"));
+            assert!(json.contains("http://localhost:8080"));
         }
     }
 
     mod chat_message_tests {
         use super::*;
 
+        #[test]
+        fn redacted_persona_round_trip_keeps_api_keys() {
+            let stored: Persona = serde_json::from_value(serde_json::json!({
+                "id": "test", "name": "Test", "type": "ai", "description": "",
+                "llm_provider": { "kind": "openai", "base_url": "http://localhost:8080", "api_key": "sk-secret" },
+                "reranker": { "kind": "openai", "model": "bge-reranker", "api_key": "sk-rerank" },
+                "metadata": {},
+                "created_at": Utc::now(), "updated_at": Utc::now(),
+            })).unwrap();
+
+            // The client edits the name and sends back the rest as received
+            let mut sent = serde_json::to_value(stored.clone().redacted()).unwrap();
+            sent["name"] = serde_json::json!("Renamed");
+            let mut update: UpdatePersonaRequest = serde_json::from_value(sent).unwrap();
+            update.keep_api_keys(&stored);
+            assert_eq!(update.llm_provider.unwrap().api_key.as_deref(), Some("sk-secret"));
+            assert_eq!(update.reranker.unwrap().api_key.as_deref(), Some("sk-rerank"));
+
+            // A new key replaces the stored one, an empty one removes it
+            let mut update: UpdatePersonaRequest = serde_json::from_value(serde_json::json!({
+                "llm_provider": { "kind": "openai", "api_key": "sk-new" },
+                "reranker": { "kind": "openai", "model": "bge-reranker", "api_key": "" },
+            })).unwrap();
+            update.keep_api_keys(&stored);
+            assert_eq!(update.llm_provider.unwrap().api_key.as_deref(), Some("sk-new"));
+            assert_eq!(update.reranker.unwrap().api_key, None);
+        }
+
         #[test]
         fn chat_message_with_all_fields() {
             let message = ChatMessage {
@@ -650,6 +1294,7 @@ mod tests {
                 ai_persona: Some("ai-1".to_string()),
                 model: Some("llama3.2".to_string()),
                 mood: Some("friendly".to_string()),
+                interrupted: false,
             };
 
             let json = serde_json::to_string(&message).unwrap();
@@ -668,6 +1313,7 @@ mod tests {
                 ai_persona: None,
                 model: None,
                 mood: None,
+                interrupted: false,
             };
 
             let json = serde_json::to_string(&message).unwrap();
@@ -692,6 +1338,8 @@ mod tests {
                 persona_id: Some("azera".to_string()),
                 persona_name: Some("Azera".to_string()),
                 tags: Some(vec!["surreal".to_string(), "peaceful".to_string()]),
+                user_id: None,
+                source_message_ids: Some(vec!["msg_1".to_string()]),
             };
 
             let json = serde_json::to_string(&dream).unwrap();
@@ -745,10 +1393,15 @@ mod tests {
             // Test each variant serializes with correct type tag
             let events = vec![
                 (StreamEvent::ThinkingStart, "thinking_start"),
-                (StreamEvent::Thinking { content: "...".to_string() }, "thinking"),
+                (StreamEvent::Thinking { content: "...".to_string(), persona_id: None }, "thinking"),
                 (StreamEvent::ThinkingEnd, "thinking_end"),
-                (StreamEvent::Content { content: "Hi".to_string() }, "content"),
-                (StreamEvent::Done { message_id: "1".to_string(), mood: None, mood_value: None, energy: None }, "done"),
+                (StreamEvent::Content { content: "Hi".to_string(), persona_id: None }, "content"),
+                (StreamEvent::Branch { branch_id: "b2".to_string(), parent_branch_id: "b1".to_string(), fork_point_message_id: "1".to_string() }, "branch"),
+                (StreamEvent::Speaker { persona_id: "areza".to_string(), name: "Areza".to_string() }, "speaker"),
+                (StreamEvent::SpeakerDone { persona_id: "areza".to_string(), message_id: "2".to_string(), mood: None }, "speaker_done"),
+                (StreamEvent::ToolCall { name: "web_scraper".to_string(), arguments: serde_json::json!({"url": "https://example.com"}) }, "tool_call"),
+                (StreamEvent::ToolResult { name: "web_scraper".to_string(), output: "Example".to_string(), success: true, duration_ms: 12 }, "tool_result"),
+                (StreamEvent::Done { message_id: "1".to_string(), mood: None, mood_value: None, energy: None, context: None }, "done"),
                 (StreamEvent::Error { message: "oops".to_string() }, "error"),
             ];
 
@@ -758,6 +1411,50 @@ mod tests {
                     "Expected type {} in {}", expected_type, json);
             }
         }
+
+        #[test]
+        fn with_speaker_tags_tokens_only() {
+            let tagged = StreamEvent::Content { content: "Hi".to_string(), persona_id: None }.with_speaker("areza");
+            let json = serde_json::to_string(&tagged).unwrap();
+            assert!(json.contains("\"persona_id\":\"areza\""));
+
+            let untagged = serde_json::to_string(&StreamEvent::ThinkingEnd.with_speaker("areza")).unwrap();
+            assert_eq!(untagged, r#"{"type":"thinking_end"}"#);
+            let plain = serde_json::to_string(&StreamEvent::Content { content: "Hi".to_string(), persona_id: None }).unwrap();
+            assert!(!plain.contains("persona_id"));
+        }
+    }
+
+    mod provider_config_tests {
+        use super::*;
+
+        #[test]
+        fn provider_kind_serialization() {
+            assert_eq!(serde_json::to_string(&ProviderKind::Ollama).unwrap(), "\"ollama\"");
+            assert_eq!(serde_json::to_string(&ProviderKind::OpenAI).unwrap(), "\"openai\"");
+        }
+
+        #[test]
+        fn chat_request_with_provider() {
+            let json = r#"{
+                "chat_id": "c1",
+                "branch_id": "b1",
+                "message": "hi",
+                "provider": {"kind": "openai", "base_url": "http://localhost:8080"}
+            }"#;
+            let req: ChatRequest = serde_json::from_str(json).unwrap();
+            let provider = req.provider.unwrap();
+            assert_eq!(provider.kind, ProviderKind::OpenAI);
+            assert_eq!(provider.base_url.as_deref(), Some("http://localhost:8080"));
+            assert!(provider.api_key.is_none());
+        }
+
+        #[test]
+        fn chat_request_without_provider() {
+            let json = r#"{"chat_id": "c1", "branch_id": "b1", "message": "hi"}"#;
+            let req: ChatRequest = serde_json::from_str(json).unwrap();
+            assert!(req.provider.is_none());
+        }
     }
 
     mod ollama_types_tests {
@@ -766,9 +1463,9 @@ mod tests {
         #[test]
         fn ollama_message_roles() {
             let messages = vec![
-                OllamaMessage { role: "system".to_string(), content: "You are helpful.".to_string() },
-                OllamaMessage { role: "user".to_string(), content: "Hello".to_string() },
-                OllamaMessage { role: "assistant".to_string(), content: "Hi there!".to_string() },
+                OllamaMessage { role: "system".to_string(), content: "You are helpful.".to_string(), tool_calls: None },
+                OllamaMessage { role: "user".to_string(), content: "Hello".to_string(), tool_calls: None },
+                OllamaMessage { role: "assistant".to_string(), content: "Hi there!".to_string(), tool_calls: None },
             ];
 
             let request = OllamaRequest {
@@ -782,6 +1479,24 @@ mod tests {
             assert!(json.contains("\"model\":\"llama3.2\""));
         }
 
+        #[test]
+        fn ollama_stream_chunk_with_tool_calls() {
+            let json = r#"{
+                "model": "llama3.2",
+                "created_at": "2026-01-01T00:00:00Z",
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{"function": {"name": "web_scraper", "arguments": {"url": "https://example.com"}}}]
+                },
+                "done": false
+            }"#;
+            let chunk: OllamaStreamChunk = serde_json::from_str(json).unwrap();
+            let calls = chunk.message.unwrap().tool_calls.unwrap();
+            assert_eq!(calls[0].function.name, "web_scraper");
+            assert_eq!(calls[0].function.arguments["url"], "https://example.com");
+        }
+
         #[test]
         fn ollama_pull_progress_partial() {
             // Progress events may not have all fields
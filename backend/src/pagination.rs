@@ -0,0 +1,97 @@
+//! Keyset pagination
+//!
+//! List endpoints return at most one page of rows plus an opaque
+//! `next_cursor`. The cursor records the sort key and ID of the last row
+//! served, so the next page continues strictly after it however many rows
+//! were inserted or deleted in between. Cursors are only valid for the sort
+//! they were issued under.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+/// Page size when the request does not ask for one
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+/// Largest page a request may ask for
+pub const MAX_PAGE_SIZE: usize = 200;
+
+/// Clamp a requested page size to `1..=MAX_PAGE_SIZE`
+pub fn page_size(requested: Option<usize>) -> usize {
+    requested.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+}
+
+/// Position after the last row of a page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+    /// Sort the cursor was issued under (e.g. `updated_at:desc`)
+    #[serde(rename = "s")]
+    pub sort: String,
+    /// Sort key of the last row, as text (RFC 3339 for timestamps)
+    #[serde(rename = "k")]
+    pub key: String,
+    /// ID of the last row, breaking ties between equal keys
+    pub id: String,
+}
+
+impl Cursor {
+    /// URL-safe opaque form handed to clients
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json)
+    }
+
+    /// Parse a client cursor, rejecting garbage and cursors from another sort
+    pub fn decode(raw: &str, sort: &str) -> Result<Self, String> {
+        let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(raw.trim())
+            .map_err(|_| "Invalid cursor".to_string())?;
+        let cursor: Cursor = serde_json::from_slice(&bytes).map_err(|_| "Invalid cursor".to_string())?;
+        if cursor.sort != sort {
+            return Err(format!("Cursor was issued for sort '{}', not '{}'", cursor.sort, sort));
+        }
+        Ok(cursor)
+    }
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod cursor_tests {
+        use super::*;
+
+        fn cursor() -> Cursor {
+            Cursor {
+                sort: "updated_at:desc".to_string(),
+                key: "2026-01-02T03:04:05.123456Z".to_string(),
+                id: "chat-1".to_string(),
+            }
+        }
+
+        #[test]
+        fn round_trips_through_url_safe_text() {
+            let encoded = cursor().encode();
+            assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+            assert_eq!(Cursor::decode(&encoded, "updated_at:desc").unwrap(), cursor());
+        }
+
+        #[test]
+        fn rejects_garbage_and_foreign_sorts() {
+            assert!(Cursor::decode("not a cursor", "updated_at:desc").is_err());
+            assert!(Cursor::decode("e30", "updated_at:desc").is_err());
+            let err = Cursor::decode(&cursor().encode(), "title:asc").unwrap_err();
+            assert!(err.contains("updated_at:desc"));
+        }
+
+        #[test]
+        fn page_size_is_clamped() {
+            assert_eq!(page_size(None), DEFAULT_PAGE_SIZE);
+            assert_eq!(page_size(Some(0)), 1);
+            assert_eq!(page_size(Some(10_000)), MAX_PAGE_SIZE);
+            assert_eq!(page_size(Some(20)), 20);
+        }
+    }
+}
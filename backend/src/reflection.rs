@@ -0,0 +1,284 @@
+//! Daily reflections
+//!
+//! Once a day (and on `POST /api/journal/trigger`) each AI persona looks
+//! back at the 24 hours of its chats — the users' messages and its own
+//! replies across all chats, from `db::persona_messages` — and writes a
+//! journal entry about them. A persona that talked to several users writes
+//! one entry per user, owned by that user, so nobody reads about someone
+//! else's conversations. Entries are saved to CockroachDB (shared ones also
+//! to the journal archive) and indexed in Meilisearch and Qdrant, whichever
+//! path wrote them.
+
+use crate::models::{JournalEntry, OllamaMessage, PersonaMessage};
+use crate::retrieval::truncate_chars;
+use crate::{archive, db, events, handlers, llm, tools, vector, AppState};
+use crate::components::DEFAULT_PERSONA_ID;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+use std::collections::HashMap;
+
+/// Messages reflected on per entry (the latest ones when there are more)
+const MAX_MESSAGES: i64 = 120;
+/// Characters kept from each message in the reflection prompt
+const MESSAGE_CHARS: usize = 600;
+
+/// The 24 hours a reflection for `date` covers: those ending now for today,
+/// those ending at the following local midnight for an earlier day
+pub fn window(date: NaiveDate, now: DateTime<Local>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let end = if date >= now.date_naive() {
+        now.with_timezone(&Utc)
+    } else {
+        let midnight = date.succ_opt().unwrap_or(date).and_hms_opt(0, 0, 0).expect("midnight exists");
+        Local
+            .from_local_datetime(&midnight)
+            .earliest()
+            .map_or_else(|| Utc.from_utc_datetime(&midnight), |t| t.with_timezone(&Utc))
+    };
+    (end - Duration::hours(24), end)
+}
+
+/// Write `persona_id`'s reflections for `date`: one per user it talked to in
+/// the window, or only about `user_id`'s chats when given. Empty when there
+/// was nothing to reflect on.
+pub async fn reflect(
+    state: &AppState,
+    persona_id: &str,
+    persona_name: &str,
+    date: NaiveDate,
+    user_id: Option<&str>,
+) -> Result<Vec<JournalEntry>> {
+    let messages = db::persona_messages(&state.db, persona_id, user_id, window(date, Local::now()), MAX_MESSAGES).await?;
+    let mut entries = Vec::new();
+    for (owner, messages) in by_owner(messages) {
+        tracing::info!("📝 {} reflects on {} messages of {}", persona_name, messages.len(), owner.as_deref().unwrap_or("shared chats"));
+        entries.push(write(state, persona_id, persona_name, date, owner, &messages).await?);
+    }
+    Ok(entries)
+}
+
+/// Group newest-first messages by chat owner, each group in chronological order
+pub fn by_owner(messages: Vec<PersonaMessage>) -> Vec<(Option<String>, Vec<PersonaMessage>)> {
+    let mut groups: HashMap<Option<String>, Vec<PersonaMessage>> = HashMap::new();
+    for message in messages.into_iter().rev() {
+        groups.entry(message.user_id.clone()).or_default().push(message);
+    }
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    groups.sort_by(|a, b| a.0.cmp(&b.0));
+    groups
+}
+
+/// The conversations as the reflection prompt shows them, a blank line
+/// between chats
+pub fn context(messages: &[PersonaMessage], persona_name: &str) -> String {
+    let mut context = String::new();
+    let mut chat_id: Option<&str> = None;
+    for message in messages {
+        if chat_id.is_some_and(|id| id != message.chat_id) {
+            context.push('\n');
+        }
+        chat_id = Some(&message.chat_id);
+        let speaker = if message.role == "user" { "User" } else { persona_name };
+        context.push_str(&format!("{}: {}\n", speaker, truncate_chars(&message.content, MESSAGE_CHARS)));
+    }
+    context.trim_end().to_string()
+}
+
+/// Archive file name (without `.md`) of a persona's reflection on `date`
+fn archive_stem(date: &str, persona_id: &str) -> String {
+    if persona_id == DEFAULT_PERSONA_ID {
+        date.to_string()
+    } else {
+        format!("{}_{}", date, persona_id)
+    }
+}
+
+/// `(date, persona_id)` of an archive file name written by [`archive_stem`]
+pub fn parse_archive_stem(stem: &str) -> Option<(&str, &str)> {
+    let date = stem.get(..10)?;
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    match &stem[10..] {
+        "" => Some((date, DEFAULT_PERSONA_ID)),
+        rest => rest.strip_prefix('_').filter(|id| !id.is_empty()).map(|id| (date, id)),
+    }
+}
+
+async fn write(
+    state: &AppState,
+    persona_id: &str,
+    persona_name: &str,
+    date: NaiveDate,
+    owner: Option<String>,
+    messages: &[PersonaMessage],
+) -> Result<JournalEntry> {
+    let context = context(messages, persona_name);
+    let llm = llm::LLMService::new(state.ollama_host.clone());
+    let reflection = llm.infer(
+        &state.agent.read().await.agent_config.model,
+        vec![OllamaMessage {
+            role: "user".to_string(),
+            content: llm::LLMService::build_reflection_prompt(&context),
+            tool_calls: None,
+        }],
+    ).await?;
+
+    let long_date = date.format("%B %d, %Y");
+    let entry = JournalEntry {
+        id: format!("journal_{}", uuid::Uuid::new_v4()),
+        date: date.format("%Y-%m-%d").to_string(),
+        title: format!("Reflections - {}", long_date),
+        content: reflection.clone(),
+        mood: Some("reflective".to_string()),
+        persona_id: Some(persona_id.to_string()),
+        persona_name: Some(persona_name.to_string()),
+        tags: Some(vec![]),
+        user_id: owner,
+        created_at: Utc::now(),
+    };
+    db::create_journal_entry(&state.db, &entry).await?;
+
+    // Index in Meilisearch (lexical retrieval)
+    {
+        let mu = state.meili_url.clone();
+        let mk = state.meili_key.clone();
+        let e = entry.clone();
+        tokio::spawn(async move {
+            handlers::meili_index_journal(&mu, &mk, &e).await;
+        });
+    }
+
+    // Store in Qdrant (semantic memory) with embedding cache
+    {
+        let vector_service = vector::VectorService::new(state.qdrant_url.clone());
+        let mut metadata: HashMap<String, serde_json::Value> = HashMap::new();
+        metadata.insert("journal_id".to_string(), serde_json::json!(entry.id));
+        metadata.insert("title".to_string(), serde_json::json!(entry.title));
+        metadata.insert("date".to_string(), serde_json::json!(entry.date));
+        metadata.insert("ai_persona_id".to_string(), serde_json::json!(persona_id));
+        if let Some(ref uid) = entry.user_id {
+            metadata.insert("user_id".to_string(), serde_json::json!(uid));
+        }
+        let request = vector::StoreMemoryRequest {
+            collection: "azera_memory".to_string(),
+            id: uuid::Uuid::new_v4().to_string(),
+            content: reflection.clone(),
+            memory_type: vector::MemoryType::Reflection,
+            metadata,
+        };
+        match vector::store_memory_cached(&vector_service, &state.ollama_host, &state.cache, &request).await {
+            Ok(()) => tracing::info!("🧠 Reflection stored in semantic memory"),
+            Err(e) => tracing::warn!("🧠 Failed to store reflection in semantic memory: {}", e),
+        }
+    }
+
+    // Save to legacy logs table
+    let _ = db::save_daily_log(&state.db, &context, &reflection).await;
+
+    // Save shared reflections to file (the archive is re-imported as shared
+    // memory, so a user's private entries stay in the database only); the
+    // default persona keeps the plain `<date>.md` name
+    if entry.user_id.is_none() {
+        let _ = tools::fs_utils::ensure_dir(archive::JOURNAL_DIR);
+        let filename = format!("{}/{}.md", archive::JOURNAL_DIR, archive_stem(&entry.date, persona_id));
+        let file_content = format!("# Daily Reflection - {}\n\n{}", long_date, reflection);
+        let _ = tools::fs_utils::write_file(&filename, &file_content);
+    }
+
+    let _ = db::add_log(&state.db, "info", &format!("Reflection of {} written for {}", persona_name, entry.date)).await;
+    state.events.publish_scoped(entry.user_id.as_deref(), events::AgentEvent::ReflectionWritten {
+        persona_id: persona_id.to_string(),
+        journal_id: entry.id.clone(),
+        date: entry.date.clone(),
+        title: entry.title.clone(),
+    });
+    Ok(entry)
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str, chat_id: &str, user_id: Option<&str>, role: &str, content: &str) -> PersonaMessage {
+        PersonaMessage {
+            id: id.to_string(),
+            chat_id: chat_id.to_string(),
+            user_id: user_id.map(String::from),
+            role: role.to_string(),
+            content: content.to_string(),
+            created_at: Utc::now(),
+        }
+    }
+
+    mod window_tests {
+        use super::*;
+
+        #[test]
+        fn today_covers_the_last_24_hours() {
+            let now = Local::now();
+            let (start, end) = window(now.date_naive(), now);
+            assert_eq!(end, now.with_timezone(&Utc));
+            assert_eq!(end - start, Duration::hours(24));
+        }
+
+        #[test]
+        fn earlier_days_end_at_their_midnight() {
+            let now = Local::now();
+            let day = now.date_naive() - Duration::days(3);
+            let (start, end) = window(day, now);
+            assert_eq!(end.with_timezone(&Local).date_naive(), day.succ_opt().unwrap());
+            assert_eq!(end - start, Duration::hours(24));
+        }
+    }
+
+    mod grouping_tests {
+        use super::*;
+
+        #[test]
+        fn groups_by_owner_in_chronological_order() {
+            // Newest first, as db::persona_messages returns them
+            let messages = vec![
+                message("m4", "c2", Some("bob"), "assistant", "Hi Bob"),
+                message("m3", "c1", Some("alice"), "assistant", "Hello"),
+                message("m2", "c2", Some("bob"), "user", "Hey"),
+                message("m1", "c1", Some("alice"), "user", "Hi"),
+            ];
+            let groups = by_owner(messages);
+            let ids: Vec<(Option<&str>, Vec<&str>)> = groups
+                .iter()
+                .map(|(owner, ms)| (owner.as_deref(), ms.iter().map(|m| m.id.as_str()).collect()))
+                .collect();
+            assert_eq!(ids, vec![(Some("alice"), vec!["m1", "m3"]), (Some("bob"), vec!["m2", "m4"])]);
+        }
+
+        #[test]
+        fn context_names_the_speakers_and_separates_chats() {
+            let messages = vec![
+                message("m1", "c1", None, "user", "Hi"),
+                message("m2", "c1", None, "assistant", "Hello"),
+                message("m3", "c2", None, "user", "Another chat"),
+            ];
+            assert_eq!(context(&messages, "Areza"), "User: Hi\nAreza: Hello\n\nUser: Another chat");
+        }
+    }
+
+    mod archive_tests {
+        use super::*;
+
+        #[test]
+        fn stem_roundtrips_date_and_persona() {
+            let stem = archive_stem("2026-02-02", "persona_1234");
+            assert_eq!(stem, "2026-02-02_persona_1234");
+            assert_eq!(parse_archive_stem(&stem), Some(("2026-02-02", "persona_1234")));
+            assert_eq!(parse_archive_stem("2026-02-02"), Some(("2026-02-02", DEFAULT_PERSONA_ID)));
+        }
+
+        #[test]
+        fn rejects_names_without_a_date() {
+            assert_eq!(parse_archive_stem("notes"), None);
+            assert_eq!(parse_archive_stem("2026-02-02-extra"), None);
+        }
+    }
+}
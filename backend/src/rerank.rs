@@ -0,0 +1,284 @@
+//! Reranking stage for retrieved memories
+//!
+//! Hybrid retrieval ranks by fused source scores, which says little about
+//! whether a memory actually helps answer the current message. A [`Reranker`]
+//! rescores the candidates against the query before they are truncated:
+//!
+//! - `ollama`: a local model rates every candidate 0-10 in one JSON response
+//! - `openai`: an OpenAI-compatible `/v1/rerank` endpoint (cross-encoder)
+//!
+//! Configured per persona via `Persona::reranker`. Failures leave the input
+//! order untouched — reranking is an improvement, never a requirement.
+
+use crate::models::{ProviderKind, RerankerConfig};
+use crate::retrieval::{truncate_chars, RetrievedMemory};
+use crate::AppState;
+use anyhow::Result;
+use serde_json::json;
+use std::time::Duration;
+
+/// Characters of each candidate sent to the reranker
+const MAX_DOCUMENT_CHARS: usize = 600;
+const RERANK_TIMEOUT: Duration = Duration::from_secs(20);
+
+pub struct Reranker {
+    kind: ProviderKind,
+    model: String,
+    base_url: String,
+    api_key: Option<String>,
+    min_score: Option<f32>,
+    client: reqwest::Client,
+}
+
+impl Reranker {
+    pub fn from_config(config: &RerankerConfig, state: &AppState) -> Self {
+        let (default_url, default_key) = match config.kind {
+            ProviderKind::Ollama => (state.ollama_host.clone(), None),
+            ProviderKind::OpenAI => (
+                state.openai_compat_url.clone(),
+                crate::llm::default_openai_key(config.base_url.as_deref(), state),
+            ),
+        };
+        Self {
+            kind: config.kind,
+            model: config.model.clone(),
+            base_url: config
+                .base_url
+                .clone()
+                .unwrap_or(default_url)
+                .trim_end_matches('/')
+                .to_string(),
+            api_key: config.api_key.clone().or(default_key),
+            min_score: config.min_score,
+            client: reqwest::Client::builder()
+                .timeout(RERANK_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Reorder `memories` by relevance to `query`, setting `rerank_score` and
+    /// dropping anything under `min_score`. Returns the input unchanged if the
+    /// reranker fails.
+    pub async fn rerank(&self, query: &str, memories: Vec<RetrievedMemory>) -> Vec<RetrievedMemory> {
+        if memories.len() < 2 && self.min_score.is_none() {
+            return memories;
+        }
+        let documents: Vec<String> = memories
+            .iter()
+            .map(|m| truncate_chars(&m.content, MAX_DOCUMENT_CHARS))
+            .collect();
+
+        let started = std::time::Instant::now();
+        let scores = match self.kind {
+            ProviderKind::Ollama => self.ollama_scores(query, &documents).await,
+            ProviderKind::OpenAI => self.openai_scores(query, &documents).await,
+        };
+        match scores {
+            Ok(scores) => {
+                tracing::debug!(
+                    "🎯 Reranked {} memories with {} in {}ms",
+                    memories.len(),
+                    self.model,
+                    started.elapsed().as_millis()
+                );
+                apply_scores(memories, &scores, self.min_score)
+            }
+            Err(e) => {
+                tracing::warn!("🎯 Reranking with {} failed, keeping fused order: {}", self.model, e);
+                memories
+            }
+        }
+    }
+
+    /// Ask a local model for a 0-10 relevance rating per candidate, normalised to 0-1
+    async fn ollama_scores(&self, query: &str, documents: &[String]) -> Result<Vec<f32>> {
+        let prompt = build_ollama_prompt(query, documents);
+        let body = json!({
+            "model": self.model,
+            "messages": [{ "role": "user", "content": prompt }],
+            "stream": false,
+            "format": "json",
+            "options": { "temperature": 0 }
+        });
+        let response = self
+            .client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let error = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Ollama error ({}): {}", status, error));
+        }
+        let json: serde_json::Value = response.json().await?;
+        let content = json["message"]["content"].as_str().unwrap_or_default();
+        parse_ollama_scores(content, documents.len())
+    }
+
+    /// POST /v1/rerank (Jina/Cohere-style request, as served by llama.cpp, vLLM and TEI)
+    async fn openai_scores(&self, query: &str, documents: &[String]) -> Result<Vec<f32>> {
+        let body = json!({
+            "model": self.model,
+            "query": query,
+            "documents": documents,
+            "top_n": documents.len()
+        });
+        let mut request = self.client.post(format!("{}/v1/rerank", self.base_url)).json(&body);
+        if let Some(ref key) = self.api_key.as_ref().filter(|k| !k.is_empty()) {
+            request = request.bearer_auth(key);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let error = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Rerank endpoint error ({}): {}", status, error));
+        }
+        let json: serde_json::Value = response.json().await?;
+        parse_rerank_results(&json, documents.len())
+    }
+}
+
+fn build_ollama_prompt(query: &str, documents: &[String]) -> String {
+    let passages = documents
+        .iter()
+        .enumerate()
+        .map(|(i, d)| format!("[{}] {}", i, d.replace('\n', " ")))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "Rate how useful each passage is for replying to the message, from 0 (irrelevant) \
+         to 10 (directly relevant).\n\nMessage: {}\n\nPassages:\n{}\n\n\
+         Respond with JSON only: {{\"scores\": [one number per passage, in order]}}",
+        query, passages
+    )
+}
+
+/// Parse `{"scores": [...]}` from the model, normalising 0-10 to 0-1
+fn parse_ollama_scores(content: &str, expected: usize) -> Result<Vec<f32>> {
+    let json: serde_json::Value = serde_json::from_str(content.trim())
+        .map_err(|e| anyhow::anyhow!("Reranker returned invalid JSON: {}", e))?;
+    let scores: Vec<f32> = json["scores"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("Reranker response has no scores"))?
+        .iter()
+        .map(|s| s.as_f64().map(|s| (s as f32 / 10.0).clamp(0.0, 1.0)))
+        .collect::<Option<_>>()
+        .ok_or_else(|| anyhow::anyhow!("Reranker returned a non-numeric score"))?;
+    if scores.len() != expected {
+        anyhow::bail!("Reranker returned {} scores for {} passages", scores.len(), expected);
+    }
+    Ok(scores)
+}
+
+/// Parse `{"results": [{"index", "relevance_score"}]}` into per-document scores
+fn parse_rerank_results(json: &serde_json::Value, expected: usize) -> Result<Vec<f32>> {
+    let results = json["results"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("Rerank response has no results"))?;
+    let mut scores = vec![None; expected];
+    for r in results {
+        let (Some(index), Some(score)) = (r["index"].as_u64(), r["relevance_score"].as_f64()) else {
+            continue;
+        };
+        if let Some(slot) = scores.get_mut(index as usize) {
+            *slot = Some(score as f32);
+        }
+    }
+    // Documents the endpoint left out rank last
+    Ok(scores.into_iter().map(|s| s.unwrap_or(f32::MIN)).collect())
+}
+
+fn apply_scores(memories: Vec<RetrievedMemory>, scores: &[f32], min_score: Option<f32>) -> Vec<RetrievedMemory> {
+    let mut reranked: Vec<RetrievedMemory> = memories
+        .into_iter()
+        .zip(scores)
+        .map(|(m, &score)| RetrievedMemory { rerank_score: Some(score), ..m })
+        .filter(|m| min_score.is_none_or(|min| m.rerank_score.unwrap_or(f32::MIN) >= min))
+        .collect();
+    // Stable sort keeps the fused order among equal scores
+    reranked.sort_by(|a, b| b.rerank_score.unwrap_or(f32::MIN).total_cmp(&a.rerank_score.unwrap_or(f32::MIN)));
+    reranked
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(content: &str) -> RetrievedMemory {
+        RetrievedMemory {
+            id: content.to_string(),
+            content: content.to_string(),
+            memory_type: "fact".to_string(),
+            title: None,
+            role: None,
+            chat_id: None,
+            message_id: None,
+            timestamp: None,
+            score: 0.0,
+            importance: None,
+            access_count: 0,
+            rerank_score: None,
+            provenance: Vec::new(),
+        }
+    }
+
+    mod parse_tests {
+        use super::*;
+
+        #[test]
+        fn ollama_scores_are_normalised() {
+            let scores = parse_ollama_scores(r#"{"scores": [10, 2.5, 0, 14]}"#, 4).unwrap();
+            assert_eq!(scores, vec![1.0, 0.25, 0.0, 1.0]);
+        }
+
+        #[test]
+        fn ollama_score_count_must_match() {
+            assert!(parse_ollama_scores(r#"{"scores": [1, 2]}"#, 3).is_err());
+            assert!(parse_ollama_scores("not json", 1).is_err());
+            assert!(parse_ollama_scores(r#"{"scores": ["high"]}"#, 1).is_err());
+        }
+
+        #[test]
+        fn rerank_results_map_back_by_index() {
+            let json = json!({"results": [
+                {"index": 2, "relevance_score": 0.9},
+                {"index": 0, "relevance_score": 0.1}
+            ]});
+            let scores = parse_rerank_results(&json, 3).unwrap();
+            assert_eq!(scores[0], 0.1);
+            assert_eq!(scores[1], f32::MIN);
+            assert_eq!(scores[2], 0.9);
+        }
+
+        #[test]
+        fn prompt_numbers_passages() {
+            let prompt = build_ollama_prompt("hi", &["a\nb".to_string(), "c".to_string()]);
+            assert!(prompt.contains("[0] a b\n[1] c"));
+        }
+    }
+
+    mod apply_tests {
+        use super::*;
+
+        #[test]
+        fn sorts_by_rerank_score_and_filters() {
+            let memories = vec![memory("a"), memory("b"), memory("c")];
+            let reranked = apply_scores(memories, &[0.2, 0.9, 0.5], Some(0.3));
+            let order: Vec<_> = reranked.iter().map(|m| m.content.as_str()).collect();
+            assert_eq!(order, vec!["b", "c"]);
+            assert_eq!(reranked[0].rerank_score, Some(0.9));
+        }
+
+        #[test]
+        fn ties_keep_fused_order() {
+            let reranked = apply_scores(vec![memory("a"), memory("b")], &[0.5, 0.5], None);
+            assert_eq!(reranked[0].content, "a");
+        }
+    }
+}
@@ -0,0 +1,763 @@
+//! Hybrid memory retrieval
+//!
+//! [`HybridRetriever`] queries Qdrant (semantic) and Meilisearch (lexical:
+//! the `memories` and `chats` indexes) concurrently, fuses the ranked lists
+//! with reciprocal-rank fusion or normalised weighted scores, weights them by
+//! importance and recency and returns [`RetrievedMemory`] items recording
+//! which source found them. Chat RAG, `/api/search` and the tick systems all
+//! share it.
+
+use crate::{forgetting, vector, AppState};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const MEMORY_COLLECTION: &str = "azera_memory";
+/// Characters of normalised content used to recognise the same memory across sources
+const DEDUP_KEY_CHARS: usize = 100;
+
+// ============================================================
+// Types
+// ============================================================
+
+/// Which index produced a hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetrievalSource {
+    /// Qdrant vector similarity
+    Semantic,
+    /// Meilisearch `memories` index (dreams, reflections, facts)
+    Lexical,
+    /// Meilisearch `chats` index (past conversations)
+    Chat,
+}
+
+/// One source's opinion of a memory
+#[derive(Debug, Clone, Serialize)]
+pub struct Provenance {
+    pub source: RetrievalSource,
+    /// ID in the source index
+    pub id: String,
+    /// 1-based position in the source's result list
+    pub rank: usize,
+    /// Score reported by the source (cosine similarity / Meilisearch ranking score)
+    pub raw_score: Option<f32>,
+}
+
+/// A ranked memory with provenance
+#[derive(Debug, Clone, Serialize)]
+pub struct RetrievedMemory {
+    pub id: String,
+    pub content: String,
+    /// `conversation`, `dream`, `reflection`, `fact`, `emotion` or `chat`
+    pub memory_type: String,
+    pub title: Option<String>,
+    pub role: Option<String>,
+    pub chat_id: Option<String>,
+    /// Chat message a conversation memory was stored from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Fused score weighted by importance and recency (higher is better)
+    pub score: f32,
+    /// Stored importance of a Qdrant memory (see `forgetting.rs`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub importance: Option<f32>,
+    /// Retrieval hits so far of a Qdrant memory
+    #[serde(skip)]
+    pub access_count: u64,
+    /// Relevance from the persona's reranker, when one ran
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rerank_score: Option<f32>,
+    pub provenance: Vec<Provenance>,
+}
+
+impl RetrievedMemory {
+    /// Line for the system prompt, e.g. `[dream:Dreams of Dawn] ...`
+    pub fn prompt_line(&self, max_chars: usize) -> String {
+        let label = self
+            .role
+            .as_deref()
+            .or(self.title.as_deref().filter(|t| !t.is_empty()))
+            .unwrap_or("memory");
+        format!(
+            "[{}:{}] {}",
+            self.memory_type,
+            label,
+            truncate_chars(&self.content, max_chars)
+        )
+    }
+
+    pub fn found_by(&self, source: RetrievalSource) -> bool {
+        self.provenance.iter().any(|p| p.source == source)
+    }
+
+    /// Stored importance, or what a new memory like this one would get
+    pub fn importance(&self) -> f32 {
+        self.importance
+            .unwrap_or_else(|| forgetting::importance(&self.memory_type, self.role.as_deref(), &self.content))
+    }
+}
+
+/// Whose memories a query may see
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scope {
+    /// The user's own memories plus shared agent memories
+    User(String),
+    /// Only shared agent memories (dreams, reflections, unowned facts)
+    Shared,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetrievalQuery {
+    pub text: String,
+    pub scope: Scope,
+    /// Restrict to memories of this AI persona
+    pub persona_id: Option<String>,
+    pub memory_type: Option<vector::MemoryType>,
+    /// Skip hits from this chat (avoids echoing the current conversation)
+    pub exclude_chat_id: Option<String>,
+    /// Also search past chats (user scope only)
+    pub include_chats: bool,
+    /// Skip memories younger than this many seconds
+    pub min_age_secs: i64,
+    pub limit: usize,
+}
+
+impl RetrievalQuery {
+    pub fn new(text: impl Into<String>, scope: Scope) -> Self {
+        Self {
+            text: text.into(),
+            scope,
+            persona_id: None,
+            memory_type: None,
+            exclude_chat_id: None,
+            include_chats: false,
+            min_age_secs: 0,
+            limit: 10,
+        }
+    }
+}
+
+/// Retrieved memories plus per-source hit counts
+#[derive(Debug, Default)]
+pub struct RetrievalResult {
+    pub memories: Vec<RetrievedMemory>,
+    pub semantic_hits: usize,
+    pub lexical_hits: usize,
+    pub chat_hits: usize,
+}
+
+// ============================================================
+// Configuration
+// ============================================================
+
+/// How per-source rankings are combined
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fusion {
+    /// Σ 1 / (k + rank) over the sources that returned the memory
+    ReciprocalRank { k: f32 },
+    /// Min-max normalised source scores, weighted per source
+    Weighted { semantic: f32, lexical: f32 },
+}
+
+#[derive(Debug, Clone)]
+pub struct RetrievalConfig {
+    pub fusion: Fusion,
+    /// Semantic hits below this cosine similarity are dropped before fusion
+    pub min_semantic_score: f32,
+    /// Age at which the recency factor halves; `None` disables decay
+    pub recency_half_life_days: Option<f64>,
+    /// Share of the score recency can take away (0 = none, 1 = all)
+    pub recency_weight: f32,
+    /// Share of the score low importance can take away (0 = none, 1 = all)
+    pub importance_weight: f32,
+    /// Candidates requested from each source before fusion
+    pub candidates_per_source: usize,
+}
+
+impl Default for RetrievalConfig {
+    fn default() -> Self {
+        Self {
+            fusion: Fusion::ReciprocalRank { k: 60.0 },
+            min_semantic_score: 0.45,
+            recency_half_life_days: Some(30.0),
+            recency_weight: 0.3,
+            importance_weight: 0.5,
+            candidates_per_source: 20,
+        }
+    }
+}
+
+impl RetrievalConfig {
+    /// `RAG_FUSION` (`rrf` | `weighted`), `RAG_MIN_SEMANTIC_SCORE`,
+    /// `RAG_RECENCY_HALF_LIFE_DAYS` (0 disables decay), `RAG_IMPORTANCE_WEIGHT`
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if std::env::var("RAG_FUSION").is_ok_and(|v| v.eq_ignore_ascii_case("weighted")) {
+            config.fusion = Fusion::Weighted { semantic: 0.6, lexical: 0.4 };
+        }
+        if let Some(score) = env_parse::<f32>("RAG_MIN_SEMANTIC_SCORE") {
+            config.min_semantic_score = score;
+        }
+        if let Some(days) = env_parse::<f64>("RAG_RECENCY_HALF_LIFE_DAYS") {
+            config.recency_half_life_days = (days > 0.0).then_some(days);
+        }
+        if let Some(weight) = env_parse::<f32>("RAG_IMPORTANCE_WEIGHT") {
+            config.importance_weight = weight;
+        }
+        config
+    }
+}
+
+fn env_parse<T: std::str::FromStr>(key: &str) -> Option<T> {
+    std::env::var(key).ok().and_then(|v| v.trim().parse().ok())
+}
+
+// ============================================================
+// Retriever
+// ============================================================
+
+pub struct HybridRetriever {
+    vector: Arc<vector::VectorService>,
+    cache: redis::aio::ConnectionManager,
+    http: reqwest::Client,
+    ollama_host: String,
+    meili_url: String,
+    meili_key: String,
+    config: RetrievalConfig,
+}
+
+impl HybridRetriever {
+    pub fn new(state: &AppState) -> Self {
+        Self {
+            vector: state.vector.clone(),
+            cache: state.cache.clone(),
+            http: reqwest::Client::new(),
+            ollama_host: state.ollama_host.clone(),
+            meili_url: state.meili_url.clone(),
+            meili_key: state.meili_key.clone(),
+            config: state.retrieval.clone(),
+        }
+    }
+
+    /// Run all sources concurrently and return the fused ranking.
+    /// A failing source is logged and contributes nothing.
+    pub async fn retrieve(&self, query: &RetrievalQuery) -> RetrievalResult {
+        let search_chats = query.include_chats && matches!(query.scope, Scope::User(_));
+        let (semantic, lexical, chats) = tokio::join!(
+            self.semantic(query),
+            self.lexical(query),
+            async {
+                if search_chats {
+                    self.chats(query).await
+                } else {
+                    Vec::new()
+                }
+            }
+        );
+
+        let now = Utc::now();
+        let keep = |m: &RetrievedMemory| {
+            let too_recent = m
+                .timestamp
+                .is_some_and(|ts| (now - ts).num_seconds() < query.min_age_secs);
+            let same_chat = query.exclude_chat_id.is_some() && m.chat_id == query.exclude_chat_id;
+            !too_recent && !same_chat
+        };
+        let semantic: Vec<_> = semantic.into_iter().filter(keep).collect();
+        let lexical: Vec<_> = lexical.into_iter().filter(keep).collect();
+        let chats: Vec<_> = chats.into_iter().filter(keep).collect();
+
+        let counts = (semantic.len(), lexical.len(), chats.len());
+        let mut memories = fuse(vec![semantic, lexical, chats], &self.config, now);
+        memories.truncate(query.limit);
+        self.record_access(&memories);
+
+        RetrievalResult {
+            memories,
+            semantic_hits: counts.0,
+            lexical_hits: counts.1,
+            chat_hits: counts.2,
+        }
+    }
+
+    /// Count the hits on Qdrant memories, in the background
+    fn record_access(&self, memories: &[RetrievedMemory]) {
+        let hits: Vec<forgetting::Access> = memories
+            .iter()
+            .filter_map(|m| {
+                let semantic = m.provenance.iter().find(|p| p.source == RetrievalSource::Semantic)?;
+                Some(forgetting::Access { id: semantic.id.clone(), importance: m.importance(), access_count: m.access_count })
+            })
+            .collect();
+        if hits.is_empty() {
+            return;
+        }
+        let vector = self.vector.clone();
+        tokio::spawn(async move {
+            forgetting::record_access(&vector, &hits).await;
+        });
+    }
+
+    async fn semantic(&self, query: &RetrievalQuery) -> Vec<RetrievedMemory> {
+        let mut must = vec![match &query.scope {
+            Scope::User(user_id) => vector::visible_to_user(user_id),
+            Scope::Shared => vector::shared_only(),
+        }];
+        if let Some(ref pid) = query.persona_id {
+            must.push(json!({ "key": "ai_persona_id", "match": { "value": pid } }));
+        }
+        if let Some(ref t) = query.memory_type {
+            must.push(json!({ "key": "type", "match": { "value": t.to_string() } }));
+        }
+        // Replaced facts and forgotten turns stay out of every answer
+        let mut must_not = vec![vector::superseded(), vector::archived()];
+        if let Some(ref cid) = query.exclude_chat_id {
+            must_not.push(json!({ "key": "chat_id", "match": { "value": cid } }));
+        }
+        let filter = json!({ "must": must, "must_not": must_not });
+
+        let results = match vector::search_memories_with_filter_cached(
+            &self.vector,
+            &self.ollama_host,
+            &self.cache,
+            MEMORY_COLLECTION,
+            &query.text,
+            self.config.candidates_per_source,
+            Some(filter),
+        )
+        .await
+        {
+            Ok(results) => results,
+            Err(e) => {
+                tracing::warn!("🧠 Qdrant retrieval failed: {}", e);
+                return Vec::new();
+            }
+        };
+
+        results
+            .into_iter()
+            .filter(|r| r.score >= self.config.min_semantic_score)
+            .enumerate()
+            .filter_map(|(i, r)| {
+                let str_field = |key: &str| r.payload.get(key).and_then(|v| v.as_str()).map(String::from);
+                Some(RetrievedMemory {
+                    content: str_field("content")?,
+                    memory_type: str_field("type").unwrap_or_else(|| "conversation".to_string()),
+                    title: str_field("title"),
+                    role: str_field("role"),
+                    chat_id: str_field("chat_id"),
+                    message_id: str_field("message_id"),
+                    timestamp: str_field("timestamp")
+                        .and_then(|ts| DateTime::parse_from_rfc3339(&ts).ok())
+                        .map(|ts| ts.with_timezone(&Utc)),
+                    score: 0.0,
+                    importance: r.payload.get("importance").and_then(|v| v.as_f64()).map(|v| v as f32),
+                    access_count: r.payload.get("access_count").and_then(|v| v.as_u64()).unwrap_or(0),
+                    rerank_score: None,
+                    provenance: vec![Provenance {
+                        source: RetrievalSource::Semantic,
+                        id: r.id.clone(),
+                        rank: i + 1,
+                        raw_score: Some(r.score),
+                    }],
+                    id: r.id,
+                })
+            })
+            .collect()
+    }
+
+    async fn lexical(&self, query: &RetrievalQuery) -> Vec<RetrievedMemory> {
+        let mut filters = vec![meili_owner_filter(&query.scope)];
+        if let Some(ref t) = query.memory_type {
+            filters.push(format!("memory_type = {}", quote(&t.to_string())));
+        }
+        if let Some(ref pid) = query.persona_id {
+            filters.push(format!("persona_id = {}", quote(pid)));
+        }
+        let body = json!({
+            "q": query.text,
+            "limit": self.config.candidates_per_source,
+            "filter": filters.join(" AND "),
+            "showRankingScore": true,
+            "attributesToRetrieve": ["id", "memory_type", "title", "content", "created_at_ts"]
+        });
+
+        self.meili_search("memories", &body)
+            .await
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, hit)| {
+                let id = hit["id"].as_str()?.to_string();
+                Some(RetrievedMemory {
+                    content: hit["content"].as_str()?.to_string(),
+                    memory_type: hit["memory_type"].as_str().unwrap_or("memory").to_string(),
+                    title: hit["title"].as_str().map(String::from),
+                    role: None,
+                    chat_id: None,
+                    message_id: None,
+                    timestamp: unix_timestamp(&hit["created_at_ts"]),
+                    score: 0.0,
+                    importance: None,
+                    access_count: 0,
+                    rerank_score: None,
+                    provenance: vec![Provenance {
+                        source: RetrievalSource::Lexical,
+                        id: id.clone(),
+                        rank: i + 1,
+                        raw_score: hit["_rankingScore"].as_f64().map(|s| s as f32),
+                    }],
+                    id,
+                })
+            })
+            .collect()
+    }
+
+    async fn chats(&self, query: &RetrievalQuery) -> Vec<RetrievedMemory> {
+        let Scope::User(ref user_id) = query.scope else {
+            return Vec::new();
+        };
+        let mut filter = format!("user_id = {}", quote(user_id));
+        if let Some(ref pid) = query.persona_id {
+            filter.push_str(&format!(" AND ai_persona_id = {}", quote(pid)));
+        }
+        let body = json!({
+            "q": query.text,
+            "limit": self.config.candidates_per_source / 2,
+            "filter": filter,
+            "showRankingScore": true,
+            "attributesToRetrieve": ["id", "title", "messages_text", "created_at_ts"]
+        });
+
+        self.meili_search("chats", &body)
+            .await
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, hit)| {
+                let id = hit["id"].as_str()?.to_string();
+                Some(RetrievedMemory {
+                    content: hit["messages_text"].as_str()?.to_string(),
+                    memory_type: "chat".to_string(),
+                    title: Some(hit["title"].as_str().unwrap_or("past chat").to_string()),
+                    role: None,
+                    chat_id: Some(id.clone()),
+                    message_id: None,
+                    timestamp: unix_timestamp(&hit["created_at_ts"]),
+                    score: 0.0,
+                    importance: None,
+                    access_count: 0,
+                    rerank_score: None,
+                    provenance: vec![Provenance {
+                        source: RetrievalSource::Chat,
+                        id: id.clone(),
+                        rank: i + 1,
+                        raw_score: hit["_rankingScore"].as_f64().map(|s| s as f32),
+                    }],
+                    id,
+                })
+            })
+            .collect()
+    }
+
+    async fn meili_search(&self, index: &str, body: &Value) -> Vec<Value> {
+        let resp = self
+            .http
+            .post(format!("{}/indexes/{}/search", self.meili_url, index))
+            .bearer_auth(&self.meili_key)
+            .json(body)
+            .send()
+            .await;
+        match resp {
+            Ok(resp) if resp.status().is_success() => resp
+                .json::<Value>()
+                .await
+                .ok()
+                .and_then(|json| json["hits"].as_array().cloned())
+                .unwrap_or_default(),
+            Ok(resp) => {
+                tracing::warn!("🔎 Meilisearch {} retrieval failed: {}", index, resp.status());
+                Vec::new()
+            }
+            Err(e) => {
+                tracing::warn!("🔎 Meilisearch {} retrieval failed: {}", index, e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+// ============================================================
+// Fusion
+// ============================================================
+
+/// Merge per-source ranked lists (each memory carrying one provenance entry)
+/// into a single ranking. Hits with the same normalised content are treated
+/// as one memory and their contributions add up.
+pub fn fuse(lists: Vec<Vec<RetrievedMemory>>, config: &RetrievalConfig, now: DateTime<Utc>) -> Vec<RetrievedMemory> {
+    let mut merged: Vec<RetrievedMemory> = Vec::new();
+    let mut by_key: HashMap<String, usize> = HashMap::new();
+
+    for list in lists {
+        let normalised = normalised_scores(&list);
+        for (memory, norm) in list.into_iter().zip(normalised) {
+            let Some(prov) = memory.provenance.first() else { continue };
+            let contribution = match config.fusion {
+                Fusion::ReciprocalRank { k } => 1.0 / (k + prov.rank as f32),
+                Fusion::Weighted { semantic, lexical } => match prov.source {
+                    RetrievalSource::Semantic => semantic * norm,
+                    RetrievalSource::Lexical | RetrievalSource::Chat => lexical * norm,
+                },
+            };
+
+            match by_key.get(&dedup_key(&memory.content)) {
+                Some(&idx) => {
+                    let existing = &mut merged[idx];
+                    existing.score += contribution;
+                    existing.provenance.extend(memory.provenance);
+                    existing.timestamp = existing.timestamp.or(memory.timestamp);
+                    existing.title = existing.title.take().or(memory.title);
+                    existing.role = existing.role.take().or(memory.role);
+                    existing.chat_id = existing.chat_id.take().or(memory.chat_id);
+                    existing.importance = existing.importance.or(memory.importance);
+                }
+                None => {
+                    by_key.insert(dedup_key(&memory.content), merged.len());
+                    merged.push(RetrievedMemory { score: contribution, ..memory });
+                }
+            }
+        }
+    }
+
+    for memory in &mut merged {
+        memory.score *= recency_factor(memory.timestamp, config, now) * importance_factor(memory, config);
+    }
+    merged.sort_by(|a, b| b.score.total_cmp(&a.score));
+    merged
+}
+
+/// Min-max normalise a source's raw scores to [0, 1]; falls back to rank
+/// position when the source reported no scores
+fn normalised_scores(list: &[RetrievedMemory]) -> Vec<f32> {
+    let raw: Vec<Option<f32>> = list
+        .iter()
+        .map(|m| m.provenance.first().and_then(|p| p.raw_score))
+        .collect();
+    if raw.iter().any(Option::is_none) {
+        let n = list.len().max(1) as f32;
+        return (0..list.len()).map(|i| 1.0 - i as f32 / n).collect();
+    }
+    let raw: Vec<f32> = raw.into_iter().flatten().collect();
+    let min = raw.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = raw.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    raw.iter()
+        .map(|s| if max > min { (s - min) / (max - min) } else { 1.0 })
+        .collect()
+}
+
+/// `(1 - w) + w * 0.5^(age / half_life)`; undated memories count as one half-life old
+fn recency_factor(timestamp: Option<DateTime<Utc>>, config: &RetrievalConfig, now: DateTime<Utc>) -> f32 {
+    let Some(half_life) = config.recency_half_life_days else {
+        return 1.0;
+    };
+    let decay = match timestamp {
+        Some(ts) => {
+            let age_days = (now - ts).num_seconds().max(0) as f64 / 86_400.0;
+            0.5f64.powf(age_days / half_life)
+        }
+        None => 0.5,
+    };
+    let w = config.recency_weight.clamp(0.0, 1.0);
+    (1.0 - w) + w * decay as f32
+}
+
+/// `(1 - w) + w * importance`
+fn importance_factor(memory: &RetrievedMemory, config: &RetrievalConfig) -> f32 {
+    let w = config.importance_weight.clamp(0.0, 1.0);
+    (1.0 - w) + w * memory.importance().clamp(0.0, 1.0)
+}
+
+fn dedup_key(content: &str) -> String {
+    content
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .chars()
+        .take(DEDUP_KEY_CHARS)
+        .collect()
+}
+
+// ============================================================
+// Helpers
+// ============================================================
+
+/// Meilisearch filter for memories visible in `scope`
+pub fn meili_owner_filter(scope: &Scope) -> String {
+    match scope {
+        Scope::User(user_id) => format!(
+            "(user_id = {} OR user_id IS NULL OR user_id NOT EXISTS)",
+            quote(user_id)
+        ),
+        Scope::Shared => "(user_id IS NULL OR user_id NOT EXISTS)".to_string(),
+    }
+}
+
+/// Quote a value for a Meilisearch filter expression
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn unix_timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    value.as_i64().and_then(|ts| Utc.timestamp_opt(ts, 0).single())
+}
+
+/// Truncate on a character boundary
+pub fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => format!("{}…", &text[..idx]),
+        None => text.to_string(),
+    }
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(source: RetrievalSource, rank: usize, content: &str, raw: Option<f32>) -> RetrievedMemory {
+        RetrievedMemory {
+            id: format!("{:?}-{}", source, rank),
+            content: content.to_string(),
+            memory_type: "fact".to_string(),
+            title: None,
+            role: None,
+            chat_id: None,
+            message_id: None,
+            timestamp: None,
+            score: 0.0,
+            importance: None,
+            access_count: 0,
+            rerank_score: None,
+            provenance: vec![Provenance {
+                source,
+                id: format!("{:?}-{}", source, rank),
+                rank,
+                raw_score: raw,
+            }],
+        }
+    }
+
+    /// Plain fusion scores: no recency decay, no importance weighting
+    fn no_decay() -> RetrievalConfig {
+        RetrievalConfig { recency_half_life_days: None, importance_weight: 0.0, ..Default::default() }
+    }
+
+    mod fusion_tests {
+        use super::*;
+        use RetrievalSource::*;
+
+        #[test]
+        fn rrf_rewards_agreement_between_sources() {
+            let semantic = vec![hit(Semantic, 1, "cats like boxes", Some(0.9)), hit(Semantic, 2, "the sky is blue", Some(0.8))];
+            let lexical = vec![hit(Lexical, 1, "The sky  is BLUE", Some(0.7))];
+            let fused = fuse(vec![semantic, lexical], &no_decay(), Utc::now());
+
+            assert_eq!(fused.len(), 2);
+            assert_eq!(fused[0].content, "the sky is blue");
+            assert!(fused[0].found_by(Semantic) && fused[0].found_by(Lexical));
+            let expected = 1.0 / 62.0 + 1.0 / 61.0;
+            assert!((fused[0].score - expected).abs() < 1e-6);
+        }
+
+        #[test]
+        fn weighted_normalises_each_source() {
+            let config = RetrievalConfig {
+                fusion: Fusion::Weighted { semantic: 0.6, lexical: 0.4 },
+                ..no_decay()
+            };
+            let semantic = vec![hit(Semantic, 1, "a", Some(0.9)), hit(Semantic, 2, "b", Some(0.5))];
+            let lexical = vec![hit(Lexical, 1, "c", Some(0.99))];
+            let fused = fuse(vec![semantic, lexical], &config, Utc::now());
+
+            let score = |c: &str| fused.iter().find(|m| m.content == c).unwrap().score;
+            assert!((score("a") - 0.6).abs() < 1e-6);
+            assert!(score("b").abs() < 1e-6);
+            assert!((score("c") - 0.4).abs() < 1e-6);
+        }
+
+        #[test]
+        fn missing_raw_scores_fall_back_to_rank() {
+            let list = vec![hit(Lexical, 1, "a", None), hit(Lexical, 2, "b", None)];
+            assert_eq!(normalised_scores(&list), vec![1.0, 0.5]);
+        }
+
+        #[test]
+        fn recency_decay_prefers_newer_memories() {
+            let config = RetrievalConfig { recency_weight: 1.0, ..Default::default() };
+            let now = Utc::now();
+            let mut old = hit(Semantic, 1, "old", Some(0.9));
+            old.timestamp = Some(now - chrono::Duration::days(60));
+            let mut new = hit(Lexical, 1, "new", Some(0.9));
+            new.timestamp = Some(now);
+            let fused = fuse(vec![vec![old], vec![new]], &config, now);
+
+            assert_eq!(fused[0].content, "new");
+            assert!((fused[1].score / fused[0].score - 0.25).abs() < 1e-3);
+        }
+
+        #[test]
+        fn importance_weighs_stored_scores_over_type_defaults() {
+            let config = RetrievalConfig { importance_weight: 1.0, ..no_decay() };
+            let mut trivial = hit(Semantic, 1, "ok thanks", Some(0.9));
+            trivial.importance = Some(0.2);
+            // No stored importance: a fact counts as 0.8
+            let fact = hit(Lexical, 1, "the user prefers rust", Some(0.9));
+            let fused = fuse(vec![vec![trivial], vec![fact]], &config, Utc::now());
+
+            assert_eq!(fused[0].content, "the user prefers rust");
+            assert!((fused[1].score / fused[0].score - 0.25).abs() < 1e-3);
+        }
+
+        #[test]
+        fn recency_weight_bounds_the_penalty() {
+            let config = RetrievalConfig::default();
+            let ancient = Some(Utc::now() - chrono::Duration::days(10_000));
+            let factor = recency_factor(ancient, &config, Utc::now());
+            assert!((factor - 0.7).abs() < 1e-3);
+        }
+    }
+
+    mod helper_tests {
+        use super::*;
+
+        #[test]
+        fn truncation_respects_char_boundaries() {
+            assert_eq!(truncate_chars("héllo wörld", 4), "héll…");
+            assert_eq!(truncate_chars("short", 10), "short");
+        }
+
+        #[test]
+        fn meili_filters_escape_values() {
+            assert_eq!(quote("a\"b"), "\"a\\\"b\"");
+            assert!(meili_owner_filter(&Scope::User("u1".into())).starts_with("(user_id = \"u1\""));
+            assert!(!meili_owner_filter(&Scope::Shared).contains("user_id ="));
+        }
+
+        #[test]
+        fn prompt_line_labels_by_role_then_title() {
+            let mut m = hit(RetrievalSource::Semantic, 1, "remember the lighthouse", None);
+            m.memory_type = "conversation".into();
+            m.role = Some("user".into());
+            assert_eq!(m.prompt_line(100), "[conversation:user] remember the lighthouse");
+            m.role = None;
+            m.title = Some("Trip".into());
+            assert_eq!(m.prompt_line(8), "[conversation:Trip] remember…");
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! Rolling conversation summaries
+//!
+//! Every `SESSION_SUMMARY_EVERY` turns (default 4) the chat stream spawns
+//! [`refresh`], which asks the chat's model to fold the turns since the last
+//! summary into the previous one and to name the active goal and topics. The
+//! result is written to the Dragonfly [`SessionContext`] (working memory used
+//! in the prompt) and to `chat_summaries` in CockroachDB, from which
+//! [`load_session`] restores it after Dragonfly evicts the session.
+
+use crate::cache::{CacheService, SessionContext};
+use crate::llm::{InferenceOptions, LLMService};
+use crate::models::{ChatMessage, ChatSummary, OllamaMessage};
+use crate::retrieval::truncate_chars;
+use crate::db;
+use anyhow::Result;
+use redis::aio::ConnectionManager;
+use serde::Deserialize;
+use sqlx::{Pool, Postgres};
+
+const DEFAULT_INTERVAL: u32 = 4;
+/// Most recent unsummarised messages considered per refresh
+const MAX_NEW_MESSAGES: usize = 24;
+/// Characters kept from each message in the summariser prompt
+const MESSAGE_CHARS: usize = 800;
+const SUMMARY_CHARS: usize = 1500;
+const MAX_TOPICS: usize = 10;
+/// Guards against overlapping refreshes of one chat
+const LOCK_TTL_SECS: usize = 120;
+
+/// Turns between summary refreshes (`SESSION_SUMMARY_EVERY`)
+pub fn interval() -> u32 {
+    std::env::var("SESSION_SUMMARY_EVERY")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_INTERVAL)
+}
+
+/// Whether the turn just recorded should trigger a refresh
+pub fn is_due(session: &SessionContext, interval: u32) -> bool {
+    session.turn_count > 0 && session.turn_count.is_multiple_of(interval)
+}
+
+/// The session context from Dragonfly, falling back to (and re-caching) the
+/// persisted summary when Dragonfly has lost it
+pub async fn load_session(pool: &Pool<Postgres>, cache: &ConnectionManager, chat_id: &str) -> Option<SessionContext> {
+    if let Ok(Some(session)) = CacheService::get_session(cache, chat_id).await {
+        return Some(session);
+    }
+    let summary = match db::get_chat_summary(pool, chat_id).await {
+        Ok(summary) => summary?,
+        Err(e) => {
+            tracing::warn!("📝 Failed to load summary for chat {}: {}", chat_id, e);
+            return None;
+        }
+    };
+    let session = SessionContext {
+        chat_id: summary.chat_id,
+        conversation_summary: summary.summary,
+        active_goal: summary.active_goal,
+        recent_topics: summary.topics,
+        turn_count: summary.turn_count,
+        user_persona_id: None,
+        ai_persona_id: None,
+        last_user_message: String::new(),
+        last_assistant_message: String::new(),
+        summarized_through: summary.summarized_through,
+        updated_at: summary.updated_at,
+    };
+    let _ = CacheService::set_session(cache, &session).await;
+    tracing::debug!("📝 Restored session context for chat {} from CockroachDB", chat_id);
+    Some(session)
+}
+
+/// Fold the branch's new turns into the chat's rolling summary. Runs in the
+/// background; failures are logged and retried at the next interval.
+pub async fn refresh(
+    pool: Pool<Postgres>,
+    cache: ConnectionManager,
+    llm: LLMService,
+    model: String,
+    chat_id: String,
+    branch_id: String,
+) {
+    let lock = CacheService::summary_lock_key(&chat_id);
+    if !CacheService::try_lock(&cache, &lock, LOCK_TTL_SECS).await.unwrap_or(false) {
+        return;
+    }
+    if let Err(e) = refresh_locked(&pool, &cache, &llm, &model, &chat_id, &branch_id).await {
+        tracing::warn!("📝 Summary refresh failed for chat {}: {}", chat_id, e);
+    }
+    let _ = CacheService::del(&cache, &lock).await;
+}
+
+async fn refresh_locked(
+    pool: &Pool<Postgres>,
+    cache: &ConnectionManager,
+    llm: &LLMService,
+    model: &str,
+    chat_id: &str,
+    branch_id: &str,
+) -> Result<()> {
+    let Some(mut session) = load_session(pool, cache, chat_id).await else {
+        return Ok(());
+    };
+    let messages = db::get_branch_history(pool, branch_id).await?;
+    let new_turns = unsummarised(&messages, session.summarized_through.as_deref());
+    let Some(last) = new_turns.last() else {
+        return Ok(());
+    };
+    let last_id = last.id.clone();
+
+    let prompt = build_prompt(&session, new_turns);
+    let options = InferenceOptions { temperature: Some(0.2), max_tokens: Some(600) };
+    let raw = llm
+        .provider
+        .infer_with_options(model, vec![OllamaMessage { role: "user".to_string(), content: prompt, tool_calls: None }], &options)
+        .await?;
+    let update = parse_summary(&raw)?;
+
+    // Re-read so the turn count and last messages recorded meanwhile survive
+    if let Ok(Some(latest)) = CacheService::get_session(cache, chat_id).await {
+        session = latest;
+    }
+    session.conversation_summary = update.summary;
+    session.active_goal = update.active_goal;
+    session.recent_topics = update.topics;
+    session.summarized_through = Some(last_id);
+    session.updated_at = chrono::Utc::now();
+    CacheService::set_session(cache, &session).await?;
+
+    db::upsert_chat_summary(pool, &ChatSummary {
+        chat_id: session.chat_id.clone(),
+        summary: session.conversation_summary.clone(),
+        active_goal: session.active_goal.clone(),
+        topics: session.recent_topics.clone(),
+        turn_count: session.turn_count,
+        summarized_through: session.summarized_through.clone(),
+        updated_at: session.updated_at,
+    })
+    .await?;
+
+    tracing::info!("📝 Summarised chat {} through turn {} ({} topics)", chat_id, session.turn_count, session.recent_topics.len());
+    Ok(())
+}
+
+/// Messages after `through`, or the most recent ones when it isn't on this branch
+fn unsummarised<'a>(messages: &'a [ChatMessage], through: Option<&str>) -> &'a [ChatMessage] {
+    let start = through
+        .and_then(|id| messages.iter().position(|m| m.id == id))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let new = &messages[start..];
+    &new[new.len().saturating_sub(MAX_NEW_MESSAGES)..]
+}
+
+fn build_prompt(session: &SessionContext, new_turns: &[ChatMessage]) -> String {
+    let previous = if session.conversation_summary.is_empty() {
+        "(none yet)".to_string()
+    } else {
+        format!(
+            "{}\nActive goal: {}\nTopics: {}",
+            session.conversation_summary,
+            session.active_goal.as_deref().unwrap_or("none"),
+            session.recent_topics.join(", ")
+        )
+    };
+    let turns = new_turns
+        .iter()
+        .map(|m| format!("{}: {}", m.role, truncate_chars(&m.content, MESSAGE_CHARS)))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    format!(
+        "You maintain a running summary of a conversation between a user and an AI assistant.\n\n\
+         Previous summary:\n{}\n\nNew messages:\n{}\n\n\
+         Update the summary to cover everything so far in at most 8 sentences, keeping names, \
+         decisions, preferences and open questions. Then state what the user is currently trying \
+         to achieve (null if nothing specific) and list up to {} short topics.\n\n\
+         Respond with JSON only: {{\"summary\": \"...\", \"active_goal\": \"...\" or null, \"topics\": [\"...\"]}}",
+        previous, turns, MAX_TOPICS
+    )
+}
+
+#[derive(Debug, PartialEq)]
+struct SummaryUpdate {
+    summary: String,
+    active_goal: Option<String>,
+    topics: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct RawSummary {
+    summary: String,
+    #[serde(default)]
+    active_goal: Option<String>,
+    #[serde(default)]
+    topics: Vec<String>,
+}
+
+/// Parse the model's JSON, tolerating code fences and chatter around it
+fn parse_summary(raw: &str) -> Result<SummaryUpdate> {
+    let start = raw.find('{').ok_or_else(|| anyhow::anyhow!("No JSON object in summary response"))?;
+    let end = raw.rfind('}').filter(|end| *end > start)
+        .ok_or_else(|| anyhow::anyhow!("Unterminated JSON in summary response"))?;
+    let parsed: RawSummary = serde_json::from_str(&raw[start..=end])?;
+
+    let summary = parsed.summary.trim();
+    if summary.is_empty() {
+        anyhow::bail!("Empty summary");
+    }
+    let active_goal = parsed
+        .active_goal
+        .map(|g| g.trim().to_string())
+        .filter(|g| !g.is_empty() && !matches!(g.to_lowercase().as_str(), "none" | "null" | "n/a"));
+    let mut topics: Vec<String> = Vec::new();
+    for topic in parsed.topics {
+        let topic = topic.trim().to_lowercase();
+        if !topic.is_empty() && !topics.contains(&topic) {
+            topics.push(topic);
+        }
+    }
+    topics.truncate(MAX_TOPICS);
+
+    Ok(SummaryUpdate {
+        summary: truncate_chars(summary, SUMMARY_CHARS),
+        active_goal,
+        topics,
+    })
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(id: &str, role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            id: id.to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: None,
+            user_persona: None,
+            ai_persona: None,
+            model: None,
+            mood: None,
+            interrupted: false,
+        }
+    }
+
+    fn session(turn_count: u32) -> SessionContext {
+        SessionContext {
+            chat_id: "chat".to_string(),
+            conversation_summary: String::new(),
+            active_goal: None,
+            recent_topics: Vec::new(),
+            turn_count,
+            user_persona_id: None,
+            ai_persona_id: None,
+            last_user_message: String::new(),
+            last_assistant_message: String::new(),
+            summarized_through: None,
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    mod parse_tests {
+        use super::*;
+
+        #[test]
+        fn parses_fenced_json() {
+            let raw = "Here you go:\n```json\n{\"summary\": \"They planned a trip.\", \"active_goal\": \"Book a hotel\", \"topics\": [\"Travel\", \"travel\", \" Kyoto \"]}\n```";
+            let update = parse_summary(raw).unwrap();
+            assert_eq!(update.summary, "They planned a trip.");
+            assert_eq!(update.active_goal.as_deref(), Some("Book a hotel"));
+            assert_eq!(update.topics, vec!["travel", "kyoto"]);
+        }
+
+        #[test]
+        fn placeholder_goal_is_none() {
+            let update = parse_summary(r#"{"summary": "Small talk.", "active_goal": "None"}"#).unwrap();
+            assert_eq!(update.active_goal, None);
+            assert!(update.topics.is_empty());
+        }
+
+        #[test]
+        fn rejects_missing_or_empty_summary() {
+            assert!(parse_summary("no json here").is_err());
+            assert!(parse_summary(r#"{"summary": "  "}"#).is_err());
+            assert!(parse_summary(r#"{"topics": []}"#).is_err());
+        }
+
+        #[test]
+        fn multibyte_summary_is_truncated_safely() {
+            let long = "日本".repeat(SUMMARY_CHARS);
+            let update = parse_summary(&format!(r#"{{"summary": "{}"}}"#, long)).unwrap();
+            assert!(update.summary.chars().count() <= SUMMARY_CHARS + 1);
+        }
+    }
+
+    mod selection_tests {
+        use super::*;
+
+        #[test]
+        fn takes_messages_after_the_last_summarised() {
+            let messages = vec![msg("a", "user", "1"), msg("b", "assistant", "2"), msg("c", "user", "3")];
+            let new = unsummarised(&messages, Some("b"));
+            assert_eq!(new.len(), 1);
+            assert_eq!(new[0].id, "c");
+            assert!(unsummarised(&messages, Some("c")).is_empty());
+        }
+
+        #[test]
+        fn unknown_marker_takes_recent_messages() {
+            let messages: Vec<ChatMessage> = (0..40).map(|i| msg(&i.to_string(), "user", "x")).collect();
+            let new = unsummarised(&messages, Some("other-branch"));
+            assert_eq!(new.len(), MAX_NEW_MESSAGES);
+            assert_eq!(new.last().unwrap().id, "39");
+        }
+
+        #[test]
+        fn refresh_is_due_every_interval() {
+            assert!(!is_due(&session(0), 4));
+            assert!(!is_due(&session(3), 4));
+            assert!(is_due(&session(4), 4));
+            assert!(is_due(&session(8), 4));
+        }
+
+        #[test]
+        fn prompt_includes_previous_summary_and_turns() {
+            let mut s = session(4);
+            s.conversation_summary = "They met.".to_string();
+            s.active_goal = Some("Plan a trip".to_string());
+            let prompt = build_prompt(&s, &[msg("a", "user", "Where to?")]);
+            assert!(prompt.contains("They met.\nActive goal: Plan a trip"));
+            assert!(prompt.contains("user: Where to?"));
+        }
+    }
+}
@@ -1,27 +1,46 @@
 use crate::*;
+use crate::components::{MentalState, PersonaState, DEFAULT_PERSONA_ID};
 use chrono::Utc;
 use chrono::Timelike;
 use std::time::Duration;
 use tokio::time::sleep;
 
+/// Ticks between reloads of the active personas from the database
+const PERSONA_REFRESH_TICKS: u64 = 30;
+
 /// Run the main tick loop
 pub async fn run_tick_loop(state: AppState) {
     tracing::info!("🌙 Azera Tick Loop starting...");
 
+    let consolidation_ticks = consolidation::interval_minutes() * 60;
+    let forgetting = forgetting::ForgettingConfig::from_env();
     let mut tick_count = 0u64;
     loop {
         tick_count += 1;
 
+        if tick_count % PERSONA_REFRESH_TICKS == 1 {
+            refresh_personas(&state).await;
+        }
+
         // Run all systems in sequence
         input_system(&state).await;
         perception_system(&state).await;
         cognitive_system(&state).await;
         dreaming_system(&state).await;
         reflection_system(&state).await;
+        if consolidation_ticks > 0 && tick_count.is_multiple_of(consolidation_ticks) {
+            consolidation_system(&state).await;
+        }
+        if forgetting.interval_minutes > 0 && tick_count.is_multiple_of(forgetting.interval_minutes * 60) {
+            forgetting_system(&state, &forgetting).await;
+        }
+        if tick_count.is_multiple_of(deletion::RETRY_TICKS) {
+            deletion_system(&state).await;
+        }
         action_system(&state).await;
 
         // Log every 100 ticks
-        if tick_count % 100 == 0 {
+        if tick_count.is_multiple_of(100) {
             tracing::debug!("✨ Tick {}", tick_count);
         }
 
@@ -30,6 +49,41 @@ pub async fn run_tick_loop(state: AppState) {
     }
 }
 
+/// Track every AI persona in the database (of all users)
+async fn refresh_personas(state: &AppState) {
+    match db::list_personas(&state.db, Some("ai"), None).await {
+        Ok(personas) => {
+            let active: Vec<(String, String, Option<String>)> = personas.into_iter().map(|p| (p.id, p.name, p.user_id)).collect();
+            state.agent.write().await.sync_personas(&active);
+        }
+        Err(e) => tracing::warn!("Failed to load active personas: {}", e),
+    }
+}
+
+/// IDs of the tracked personas, in a stable order
+async fn active_personas(state: &AppState) -> Vec<String> {
+    let mut ids: Vec<String> = state.agent.read().await.personas.keys().cloned().collect();
+    ids.sort();
+    ids
+}
+
+/// Write a persona's in-memory mental state to Dragonfly
+async fn save_mental_state(state: &AppState, persona_id: &str, mood_label: String) {
+    let Some(mental_state) = state.agent.read().await.persona(persona_id).map(|p| p.mental_state.clone()) else {
+        return;
+    };
+    let _ = cache::CacheService::set_mental_state(&state.cache, persona_id, &cache::CachedMentalState {
+        mood: mental_state.mood,
+        energy: mental_state.energy,
+        focus_level: mental_state.focus_level,
+        mood_label,
+        is_dreaming: mental_state.is_dreaming,
+        last_active: mental_state.last_active,
+        last_dream: mental_state.last_dream,
+        updated_at: chrono::Utc::now(),
+    }).await;
+}
+
 /// Input System: Check for incoming signals
 async fn input_system(state: &AppState) {
     if let Ok(Some(signal)) = cache::CacheService::dequeue_signal(&state.cache, "input_queue").await {
@@ -42,48 +96,57 @@ async fn input_system(state: &AppState) {
 
 /// Perception System: Sync Dragonfly state → agent, update components
 async fn perception_system(state: &AppState) {
-    // Sync mental state from Dragonfly (working memory) → agent state
-    if let Ok(Some(cached_state)) = cache::CacheService::get_mental_state(&state.cache).await {
-        let mut agent = state.agent.write().await;
-        agent.mental_state.mood = cached_state.mood;
-        agent.mental_state.energy = cached_state.energy;
-        agent.mental_state.focus_level = cached_state.focus_level;
-        agent.mental_state.is_dreaming = cached_state.is_dreaming;
+    for persona_id in active_personas(state).await {
+        perceive(state, &persona_id).await;
     }
-    
+}
+
+/// Sync one persona's mental state from Dragonfly (working memory) and let
+/// it drift while the persona is idle
+async fn perceive(state: &AppState, persona_id: &str) {
+    let cached = cache::CacheService::get_mental_state(&state.cache, persona_id).await.ok().flatten();
+
     let mut agent = state.agent.write().await;
+    let persona = agent.persona_mut(persona_id);
+    let owner = persona.user_id.clone();
+    let mental_state = &mut persona.mental_state;
+    if let Some(ref cached_state) = cached {
+        mental_state.mood = cached_state.mood;
+        mental_state.energy = cached_state.energy;
+        mental_state.focus_level = cached_state.focus_level;
+        mental_state.is_dreaming = cached_state.is_dreaming;
+        // Dragonfly remembers the last dream across restarts
+        mental_state.last_dream = mental_state.last_dream.max(cached_state.last_dream);
+    }
     
     // Natural energy recovery when idle
     let idle_seconds = chrono::Utc::now()
-        .signed_duration_since(agent.mental_state.last_active)
+        .signed_duration_since(mental_state.last_active)
         .num_seconds();
-    if idle_seconds > 60 {
-        // Slowly recover energy when idle (0.01 per tick when idle > 1min)
-        agent.mental_state.energy = (agent.mental_state.energy + 0.001).min(1.0);
-        // Mood drifts toward neutral when idle
-        let neutral = 0.5;
-        let diff = neutral - agent.mental_state.mood;
-        agent.mental_state.mood += diff * 0.001;
-        // Focus drops when idle
-        agent.mental_state.focus_level = (agent.mental_state.focus_level - 0.001).max(0.3);
+    if idle_seconds <= 60 {
+        return;
     }
-    
-    // Write back to Dragonfly periodically (every ~10 seconds via tick)
-    // Only if we made changes (idle drift)
-    if idle_seconds > 60 {
-        let _ = cache::CacheService::set_mental_state(&state.cache, &cache::CachedMentalState {
-            mood: agent.mental_state.mood,
-            energy: agent.mental_state.energy,
-            focus_level: agent.mental_state.focus_level,
-            mood_label: if agent.mental_state.mood > 0.7 { "happy".to_string() }
-                else if agent.mental_state.mood > 0.5 { "content".to_string() }
-                else if agent.mental_state.mood > 0.3 { "thoughtful".to_string() }
-                else { "melancholy".to_string() },
-            is_dreaming: agent.mental_state.is_dreaming,
-            last_active: agent.mental_state.last_active,
-            updated_at: chrono::Utc::now(),
-        }).await;
+    // Slowly recover energy when idle (0.01 per tick when idle > 1min)
+    mental_state.energy = (mental_state.energy + 0.001).min(1.0);
+    // Mood drifts toward neutral when idle
+    let neutral = 0.5;
+    let diff = neutral - mental_state.mood;
+    mental_state.mood += diff * 0.001;
+    // Focus drops when idle
+    mental_state.focus_level = (mental_state.focus_level - 0.001).max(0.3);
+
+    // Write back to Dragonfly (only idle drift changes anything here)
+    let mood_label = mental_state.mood_label();
+    if cached.as_ref().map(|c| c.mood_label.as_str()) != Some(mood_label) {
+        state.events.publish_scoped(owner.as_deref(), events::AgentEvent::MoodChanged {
+            persona_id: persona_id.to_string(),
+            mood: mood_label.to_string(),
+            mood_value: mental_state.mood,
+            energy: mental_state.energy,
+        });
     }
+    drop(agent);
+    save_mental_state(state, persona_id, mood_label.to_string()).await;
 }
 
 /// Cognitive System: Process pending input via LLM
@@ -136,8 +199,9 @@ async fn cognitive_system(state: &AppState) {
 
         // Re-acquire lock
         let mut agent = state.agent.write().await;
-        agent.mental_state.energy = (agent.mental_state.energy - 0.05).max(0.0);
-        agent.mental_state.last_active = Utc::now();
+        let mental_state = &mut agent.persona_mut(DEFAULT_PERSONA_ID).mental_state;
+        mental_state.energy = (mental_state.energy - 0.05).max(0.0);
+        mental_state.last_active = Utc::now();
     }
 }
 
@@ -146,60 +210,58 @@ fn default_system_prompt() -> String {
      Respond with wisdom, empathy, and intellectual rigor. Be concise but meaningful.".to_string()
 }
 
+/// Whether a persona is idle long enough, and long enough past its last
+/// dream, to fall asleep
+fn dream_due(mental_state: &MentalState, threshold_secs: u64, cooldown_hours: i64, now: chrono::DateTime<Utc>) -> bool {
+    let idle_secs = now.signed_duration_since(mental_state.last_active).num_seconds();
+    let rested = match mental_state.last_dream {
+        Some(last_dream) => now.signed_duration_since(last_dream).num_hours() >= cooldown_hours,
+        None => true, // Never dreamed before
+    };
+    idle_secs > threshold_secs as i64 && !mental_state.is_dreaming && rested
+}
+
 /// Dreaming System: Activate when idle (configurable cooldown via DREAM_INTERVAL_HOURS)
+/// At most one persona starts dreaming per tick.
 async fn dreaming_system(state: &AppState) {
-    let agent = state.agent.read().await;
-    
-    let time_since_active = Utc::now()
-        .signed_duration_since(agent.mental_state.last_active)
-        .num_seconds() as u64;
-
     // Check cooldown: configurable via env var (default 7 hours)
     let dream_cooldown_hours: i64 = std::env::var("DREAM_INTERVAL_HOURS")
         .unwrap_or_else(|_| "7".to_string())
         .parse()
         .unwrap_or(7);
-    let can_dream = match agent.mental_state.last_dream {
-        Some(last_dream) => {
-            let hours_since_dream = Utc::now()
-                .signed_duration_since(last_dream)
-                .num_hours();
-            hours_since_dream >= dream_cooldown_hours
-        }
-        None => true, // Never dreamed before
+
+    let agent = state.agent.read().await;
+    let threshold = agent.agent_config.dream_threshold_seconds;
+    let now = Utc::now();
+    let mut due: Vec<&PersonaState> = agent.personas.values()
+        .filter(|p| dream_due(&p.mental_state, threshold, dream_cooldown_hours, now))
+        .collect();
+    due.sort_by(|a, b| a.persona_id.cmp(&b.persona_id));
+    let Some((persona_id, persona_name)) = due.first().map(|p| (p.persona_id.clone(), p.name.clone())) else {
+        return;
     };
+    drop(agent); // Release lock
 
-    if time_since_active > agent.agent_config.dream_threshold_seconds 
-        && !agent.mental_state.is_dreaming 
-        && can_dream 
     {
-        drop(agent); // Release lock
-
-        tracing::info!("💭 Entering dream state...");
+        tracing::info!("💭 {} is entering dream state...", persona_name);
         
         let mut agent = state.agent.write().await;
-        agent.mental_state.is_dreaming = true;
-        agent.mental_state.mood = (agent.mental_state.mood + 0.1).min(1.0);
+        let mental_state = &mut agent.persona_mut(&persona_id).mental_state;
+        mental_state.is_dreaming = true;
+        mental_state.mood = (mental_state.mood + 0.1).min(1.0);
         drop(agent);
 
-        // Generate a dream
-        let dream_concepts = vec![
-            "moonlight", "silence", "memory", "connection", "infinity",
-            "echoes", "starlight", "whispers", "dawn", "twilight",
-            "resonance", "patterns", "threads", "void", "eternity"
-        ];
-        let concept = dream_concepts[rand::random::<usize>() % dream_concepts.len()];
+        // Seed the dream from the persona's own recent conversations
+        let material = dreaming::gather(state, &persona_id).await;
+        let concept = material.concept.as_str();
+        let owner = material.user_id.as_deref();
+        tracing::info!("💭 {} dreams of {} ({} source messages)", persona_name, concept, material.source_message_ids.len());
+        state.events.publish_scoped(owner, events::AgentEvent::DreamStarted {
+            persona_id: persona_id.clone(),
+            concept: concept.to_string(),
+        });
         
-        // Get recent context from logs
-        let recent_context = match db::get_session_messages(&state.db, "default", 5).await {
-            Ok(messages) => messages.iter()
-                .map(|(role, content)| format!("{}: {}", role, content.chars().take(100).collect::<String>()))
-                .collect::<Vec<_>>()
-                .join("\n"),
-            Err(_) => "Recent conversations with the user".to_string(),
-        };
-        
-        let dream_prompt = llm::LLMService::build_dream_prompt(concept, &recent_context);
+        let dream_prompt = llm::LLMService::build_dream_prompt(concept, &material.context);
 
         let llm = llm::LLMService::new(state.ollama_host.clone());
         match llm.infer(
@@ -207,23 +269,19 @@ async fn dreaming_system(state: &AppState) {
             vec![models::OllamaMessage {
                 role: "user".to_string(),
                 content: dream_prompt,
+                tool_calls: None,
             }],
         ).await {
             Ok(dream_content) => {
                 tracing::info!("✨ Dream recorded: {} chars", dream_content.len());
                 
                 // Generate a title for the dream
+                let mut initial = concept.chars();
                 let dream_title = format!("Dreams of {} - {}", 
-                    concept.chars().next().unwrap().to_uppercase().collect::<String>() + &concept[1..],
+                    initial.next().map(|c| c.to_uppercase().collect::<String>()).unwrap_or_default() + initial.as_str(),
                     chrono::Local::now().format("%H:%M")
                 );
                 
-                // Get current persona from agent state
-                let agent_read = state.agent.read().await;
-                let persona_id = agent_read.persona.name.to_lowercase().replace(" ", "_");
-                let persona_name = agent_read.persona.name.clone();
-                drop(agent_read);
-                
                 // Save to database
                 let dream = models::Dream {
                     id: format!("dream_{}", uuid::Uuid::new_v4()),
@@ -231,9 +289,12 @@ async fn dreaming_system(state: &AppState) {
                     title: dream_title.clone(),
                     content: dream_content.clone(),
                     mood: Some("contemplative".to_string()),
-                    persona_id: Some(persona_id),
-                    persona_name: Some(persona_name),
+                    persona_id: Some(persona_id.clone()),
+                    persona_name: Some(persona_name.clone()),
                     tags: Some(vec![]),
+                    // Private to the user whose chats inspired it
+                    user_id: material.user_id.clone(),
+                    source_message_ids: Some(material.source_message_ids.clone()),
                 };
                 let _ = db::create_dream(&state.db, &dream).await;
                 
@@ -250,13 +311,19 @@ async fn dreaming_system(state: &AppState) {
                 // Store dream in Qdrant (semantic memory) with embedding cache
                 {
                     let vector_service = vector::VectorService::new(state.qdrant_url.clone());
-                    let dream_mem_id = format!("dream_{}", uuid::Uuid::new_v4());
+                    let dream_mem_id = uuid::Uuid::new_v4().to_string();
                     let mut metadata: std::collections::HashMap<String, serde_json::Value> = std::collections::HashMap::new();
                     metadata.insert("dream_id".to_string(), serde_json::json!(dream.id));
                     metadata.insert("title".to_string(), serde_json::json!(dream_title.clone()));
                     if let Some(ref pid) = dream.persona_id {
                         metadata.insert("ai_persona_id".to_string(), serde_json::json!(pid));
                     }
+                    if let Some(ref uid) = dream.user_id {
+                        metadata.insert("user_id".to_string(), serde_json::json!(uid));
+                    }
+                    if !material.source_message_ids.is_empty() {
+                        metadata.insert("source_message_ids".to_string(), serde_json::json!(material.source_message_ids));
+                    }
                     let request = vector::StoreMemoryRequest {
                         collection: "azera_memory".to_string(),
                         id: dream_mem_id.clone(),
@@ -273,17 +340,25 @@ async fn dreaming_system(state: &AppState) {
                     tracing::info!("🧠 Dream stored in semantic memory");
                 }
                 
-                // Also save to file
-                let _ = tools::fs_utils::ensure_dir("../archive/dreams");
-                let filename = format!("../archive/dreams/dream_{}.md", chrono::Local::now().format("%Y%m%d_%H%M%S"));
-                let file_content = format!("# {}\n\n*{}*\n\n{}", 
-                    dream_title,
-                    chrono::Local::now().format("%Y-%m-%d %H:%M"),
-                    dream_content
-                );
-                let _ = tools::fs_utils::write_file(&filename, &file_content);
+                // Also save shared dreams to file (the archive is re-imported
+                // as shared memory, so private dreams stay in the database)
+                if dream.user_id.is_none() {
+                    let _ = tools::fs_utils::ensure_dir(archive::DREAMS_DIR);
+                    let filename = format!("{}/{}.md", archive::DREAMS_DIR, archive::dream_stem(&dream.timestamp, &persona_id));
+                    let file_content = format!("# {}\n\n*{}*\n\n{}", 
+                        dream_title,
+                        chrono::Local::now().format("%Y-%m-%d %H:%M"),
+                        dream_content
+                    );
+                    let _ = tools::fs_utils::write_file(&filename, &file_content);
+                }
 
                 let _ = db::add_log(&state.db, "info", &format!("Dream generated: {}", dream_title)).await;
+                state.events.publish_scoped(owner, events::AgentEvent::DreamRecorded {
+                    persona_id: persona_id.clone(),
+                    dream_id: dream.id.clone(),
+                    title: dream_title,
+                });
             }
             Err(e) => {
                 tracing::error!("Dream generation failed: {}", e);
@@ -292,146 +367,184 @@ async fn dreaming_system(state: &AppState) {
         }
 
         let mut agent = state.agent.write().await;
-        agent.mental_state.is_dreaming = false;
-        agent.mental_state.last_dream = Some(Utc::now());  // Mark when we last dreamed
-        agent.mental_state.last_active = Utc::now();       // Reset idle timer
+        let persona = agent.persona_mut(&persona_id);
+        persona.mental_state.is_dreaming = false;
+        persona.mental_state.last_dream = Some(Utc::now());  // Mark when we last dreamed
+        persona.mental_state.last_active = Utc::now();       // Reset idle timer
+        let mood_label = persona.mental_state.mood_label().to_string();
+        drop(agent);
+        save_mental_state(state, &persona_id, mood_label).await;
     }
 }
 
-/// Reflection System: Daily summary at scheduled time
+/// Reflection System: Daily summary at scheduled time, one per persona
 async fn reflection_system(state: &AppState) {
-    let agent = state.agent.read().await;
     let now = chrono::Local::now();
-    let target_hour = agent.agent_config.reflection_hour;
+    let target_hour = state.agent.read().await.agent_config.reflection_hour;
 
     // Check if it's around the reflection time (once per day)
     if now.hour() == target_hour && now.minute() < 2 {
-        drop(agent); // Release lock
-
-        // Check if we already reflected today
-        let today = now.format("%Y-%m-%d").to_string();
-        if let Ok(Some(_)) = cache::CacheService::get(&state.cache, &format!("reflected_{}", today)).await {
-            return; // Already reflected today
+        for persona_id in active_personas(state).await {
+            reflect(state, &persona_id, now.date_naive()).await;
         }
+    }
+}
 
-        tracing::info!("📝 Initiating daily reflection...");
+async fn reflect(state: &AppState, persona_id: &str, today: chrono::NaiveDate) {
+    let Some(persona_name) = state.agent.read().await.persona(persona_id).map(|p| p.name.clone()) else {
+        return;
+    };
 
-        // Get today's chat history
-        match db::get_session_messages(&state.db, "default", 50).await {
-            Ok(messages) => {
-                if messages.is_empty() {
-                    tracing::info!("No messages to reflect on today");
-                    return;
-                }
+    // Check if this persona already reflected today
+    let reflected_key = format!("reflected_{}_{}", persona_id, today.format("%Y-%m-%d"));
+    if let Ok(Some(_)) = cache::CacheService::get(&state.cache, &reflected_key).await {
+        return; // Already reflected today
+    }
 
-                let context = messages
-                    .iter()
-                    .map(|(role, content)| format!("{}: {}", role, content))
-                    .collect::<Vec<_>>()
-                    .join("\n\n");
-
-                let reflection_prompt = llm::LLMService::build_reflection_prompt(&context);
-                let llm = llm::LLMService::new(state.ollama_host.clone());
-
-                match llm.infer(
-                    &state.agent.read().await.agent_config.model,
-                    vec![models::OllamaMessage {
-                        role: "user".to_string(),
-                        content: reflection_prompt,
-                    }],
-                ).await {
-                    Ok(reflection) => {
-                        tracing::info!("✨ Reflection complete");
-                        
-                        // Get current persona from agent state
-                        let agent_read = state.agent.read().await;
-                        let persona_id = agent_read.persona.name.to_lowercase().replace(" ", "_");
-                        let persona_name = agent_read.persona.name.clone();
-                        drop(agent_read);
-                        
-                        // Save to journal
-                        let entry = models::JournalEntry {
-                            id: format!("journal_{}", uuid::Uuid::new_v4()),
-                            date: today.clone(),
-                            title: format!("Reflections - {}", now.format("%B %d, %Y")),
-                            content: reflection.clone(),
-                            mood: Some("reflective".to_string()),
-                            persona_id: Some(persona_id),
-                            persona_name: Some(persona_name),
-                            tags: Some(vec![]),
-                            created_at: Utc::now(),
-                        };
-                        let _ = db::create_journal_entry(&state.db, &entry).await;
-                        
-                        // Index in Meilisearch (lexical retrieval)
-                        {
-                            let mu = state.meili_url.clone();
-                            let mk = state.meili_key.clone();
-                            let e = entry.clone();
-                            tokio::spawn(async move {
-                                handlers::meili_index_journal(&mu, &mk, &e).await;
-                            });
-                        }
-                        
-                        // Store in Qdrant (semantic memory) with embedding cache
-                        {
-                            let vector_service = vector::VectorService::new(state.qdrant_url.clone());
-                            let refl_mem_id = format!("refl_{}", uuid::Uuid::new_v4());
-                            let mut metadata: std::collections::HashMap<String, serde_json::Value> = std::collections::HashMap::new();
-                            metadata.insert("journal_id".to_string(), serde_json::json!(entry.id));
-                            metadata.insert("title".to_string(), serde_json::json!(entry.title));
-                            if let Some(ref pid) = entry.persona_id {
-                                metadata.insert("ai_persona_id".to_string(), serde_json::json!(pid));
-                            }
-                            let request = vector::StoreMemoryRequest {
-                                collection: "azera_memory".to_string(),
-                                id: refl_mem_id.clone(),
-                                content: reflection.clone(),
-                                memory_type: vector::MemoryType::Reflection,
-                                metadata,
-                            };
-                            let _ = vector::store_memory_cached(
-                                &vector_service,
-                                &state.ollama_host,
-                                &state.cache,
-                                &request,
-                            ).await;
-                            tracing::info!("🧠 Reflection stored in semantic memory");
-                        }
-                        
-                        // Save to legacy logs table
-                        let _ = db::save_daily_log(&state.db, &context, &reflection).await;
-                        
-                        // Save to file
-                        let _ = tools::fs_utils::ensure_dir("../archive/journal");
-                        let filename = format!("../archive/journal/{}.md", today);
-                        let file_content = format!("# Daily Reflection - {}\n\n{}", 
-                            now.format("%B %d, %Y"),
-                            reflection
-                        );
-                        let _ = tools::fs_utils::write_file(&filename, &file_content);
-
-                        // Mark as reflected
-                        let _ = cache::CacheService::set(&state.cache, &format!("reflected_{}", today), "true", 86400).await;
-
-                        let _ = db::add_log(&state.db, "info", "Daily reflection completed").await;
-                    }
-                    Err(e) => {
-                        tracing::error!("Reflection failed: {}", e);
-                        let _ = db::add_log(&state.db, "error", &format!("Reflection failed: {}", e)).await;
-                    }
-                }
+    tracing::info!("📝 Initiating daily reflection for {}...", persona_name);
+    match reflection::reflect(state, persona_id, &persona_name, today, None).await {
+        Ok(entries) => {
+            if entries.is_empty() {
+                tracing::info!("No messages for {} to reflect on today", persona_name);
+            } else {
+                tracing::info!("✨ Reflection complete");
             }
-            Err(e) => {
-                tracing::error!("Failed to fetch history for reflection: {}", e);
+            // Mark as reflected
+            let _ = cache::CacheService::set(&state.cache, &reflected_key, "true", 86400).await;
+        }
+        Err(e) => {
+            tracing::error!("Reflection failed: {}", e);
+            let _ = db::add_log(&state.db, "error", &format!("Reflection failed: {}", e)).await;
+        }
+    }
+}
+
+/// Consolidation System: Distil facts from each persona's conversation
+/// memories (every CONSOLIDATION_INTERVAL_MINUTES, default 15)
+async fn consolidation_system(state: &AppState) {
+    for persona_id in active_personas(state).await {
+        let Some(persona_name) = state.agent.read().await.persona(&persona_id).map(|p| p.name.clone()) else {
+            continue;
+        };
+        match consolidation::consolidate(state, &persona_id, &persona_name).await {
+            Ok(report) if report.memories > 0 => {
+                tracing::info!(
+                    "✨ {} consolidated {} memories: {} new facts, {} merged, {} superseded",
+                    persona_name, report.memories, report.facts, report.merged, report.superseded
+                );
+                let _ = db::add_log(&state.db, "info", &format!(
+                    "Consolidated {} memories of {} into {} facts", report.memories, persona_name, report.facts
+                )).await;
             }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("🧩 Consolidation for {} failed: {}", persona_name, e),
         }
     }
 }
 
+/// Forgetting System: Decay memory importance and forget low-value
+/// conversation memories (every MEMORY_SWEEP_INTERVAL_MINUTES, default 60)
+async fn forgetting_system(state: &AppState, config: &forgetting::ForgettingConfig) {
+    match forgetting::sweep(state, config).await {
+        Ok(report) => {
+            tracing::info!(
+                "🍂 Memory sweep: {} scanned, {} decayed, {} forgotten",
+                report.scanned, report.decayed, report.forgotten
+            );
+            if report.forgotten > 0 {
+                let _ = db::add_log(&state.db, "info", &format!("Forgot {} conversation memories", report.forgotten)).await;
+            }
+        }
+        Err(e) => tracing::warn!("🍂 Memory sweep failed: {}", e),
+    }
+}
+
+/// Deletion System: Retry removing deleted chats from the stores that failed
+async fn deletion_system(state: &AppState) {
+    match deletion::retry_due(state).await {
+        Ok((0, 0)) => {}
+        Ok((done, failed)) => tracing::info!("🗑️ Chat deletion retries: {} done, {} still failing", done, failed),
+        Err(e) => tracing::warn!("🗑️ Chat deletion retries failed: {}", e),
+    }
+}
+
 /// Action System: Execute planned tools
-async fn action_system(_state: &AppState) {
-    // Check for pending actions in cache and execute them
-    // This system handles tool execution from the cognitive system
-    // TODO: Implement action queue processing for web scraping, code execution, etc.
+/// Drains one queued action per tick from `action_queue`.
+/// Payload: `{"tool": "web_scraper", "arguments": {"url": "..."}}`
+async fn action_system(state: &AppState) {
+    let Ok(Some(signal)) = cache::CacheService::dequeue_signal(&state.cache, "action_queue").await else {
+        return;
+    };
+
+    let action: serde_json::Value = match serde_json::from_str(&signal) {
+        Ok(v) => v,
+        Err(e) => {
+            tracing::warn!("Discarding malformed action signal: {}", e);
+            return;
+        }
+    };
+    let Some(tool_name) = action["tool"].as_str() else {
+        tracing::warn!("Discarding action signal without a tool name");
+        return;
+    };
+
+    // Only run tools the agent has enabled
+    let enabled = state.agent.read().await.agent_config.tools_enabled.clone();
+    if !enabled.iter().any(|t| t == tool_name) {
+        tracing::warn!("Action for disabled tool ignored: {}", tool_name);
+        return;
+    }
+
+    tracing::info!("🛠️ Action: {}", tool_name);
+    let (output, success) = match tools::execute_tool(tool_name, &action["arguments"]).await {
+        Ok(output) => (output, true),
+        Err(e) => (format!("Error: {}", e), false),
+    };
+
+    let exec = cache::ToolExecution {
+        tool_name: tool_name.to_string(),
+        input_summary: action["arguments"].to_string().chars().take(200).collect(),
+        output_summary: output.chars().take(200).collect(),
+        success,
+        timestamp: chrono::Utc::now(),
+    };
+    if let Err(e) = cache::CacheService::record_tool_execution(&state.cache, &exec).await {
+        tracing::warn!("Failed to record tool execution: {}", e);
+    }
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod dreaming_tests {
+        use super::*;
+
+        fn idle_for(secs: i64, now: chrono::DateTime<Utc>) -> MentalState {
+            MentalState { last_active: now - chrono::Duration::seconds(secs), ..MentalState::default() }
+        }
+
+        #[test]
+        fn dreams_once_idle_past_the_threshold() {
+            let now = Utc::now();
+            assert!(!dream_due(&idle_for(60, now), 300, 7, now));
+            assert!(dream_due(&idle_for(301, now), 300, 7, now));
+        }
+
+        #[test]
+        fn waits_out_the_cooldown_and_never_dreams_twice_at_once() {
+            let now = Utc::now();
+            let mut state = idle_for(3600, now);
+            state.last_dream = Some(now - chrono::Duration::hours(2));
+            assert!(!dream_due(&state, 300, 7, now));
+            state.last_dream = Some(now - chrono::Duration::hours(7));
+            assert!(dream_due(&state, 300, 7, now));
+            state.is_dreaming = true;
+            assert!(!dream_due(&state, 300, 7, now));
+        }
+    }
 }
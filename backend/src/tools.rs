@@ -1,97 +1,952 @@
 use anyhow::Result;
+use serde::Serialize;
+use serde_json::json;
+
+// ============================================================
+// Tool Registry (native tool calling)
+// ============================================================
+
+/// Tool names as listed in `AgentConfig::tools_enabled`
+pub const WEB_SCRAPER: &str = "web_scraper";
+pub const CODE_EXECUTOR: &str = "code_executor";
+
+/// Build the Ollama `tools` definitions for the enabled tools
+pub fn tool_definitions(enabled: &[String]) -> Vec<serde_json::Value> {
+    enabled
+        .iter()
+        .filter_map(|name| match name.as_str() {
+            WEB_SCRAPER => Some(json!({
+                "type": "function",
+                "function": {
+                    "name": WEB_SCRAPER,
+                    "description": "Fetch a web page and return its readable text content.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "url": { "type": "string", "description": "Absolute http(s) URL to fetch" },
+                            "max_chars": { "type": "integer", "description": "Maximum characters of page content to return (default 5000)" }
+                        },
+                        "required": ["url"]
+                    }
+                }
+            })),
+            CODE_EXECUTOR => Some(json!({
+                "type": "function",
+                "function": {
+                    "name": CODE_EXECUTOR,
+                    "description": "Run a WASI command module in a sandbox and return its exit code, stdout and stderr.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "code": { "type": "string", "description": "WebAssembly text format (WAT) module source exporting `_start`" },
+                            "stdin": { "type": "string", "description": "Text passed on standard input" },
+                            "args": { "type": "array", "items": { "type": "string" }, "description": "Command-line arguments" }
+                        },
+                        "required": ["code"]
+                    }
+                }
+            })),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether `name` is among the `definitions` offered to the model
+pub fn is_offered(definitions: &[serde_json::Value], name: &str) -> bool {
+    definitions.iter().any(|d| d["function"]["name"].as_str() == Some(name))
+}
+
+/// Execute a tool call requested by the model, returning its textual output.
+/// Callers check the name against the enabled tools first (see [`is_offered`]).
+pub async fn execute_tool(name: &str, arguments: &serde_json::Value) -> Result<String> {
+    match name {
+        WEB_SCRAPER => {
+            let url = arguments["url"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("web_scraper requires a `url` argument"))?;
+            if !(url.starts_with("http://") || url.starts_with("https://")) {
+                return Err(anyhow::anyhow!("web_scraper only supports http(s) URLs"));
+            }
+            let max_chars = arguments["max_chars"]
+                .as_u64()
+                .map(|n| (n as usize).min(MAX_TOOL_CHARS))
+                .unwrap_or(DEFAULT_MAX_CHARS);
+            WebScraper::new().with_max_chars(max_chars).extract_content(url).await
+        }
+        CODE_EXECUTOR => {
+            let code = arguments["code"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("code_executor requires a `code` argument"))?;
+            let stdin = arguments["stdin"].as_str().unwrap_or_default().as_bytes().to_vec();
+            let args = arguments["args"]
+                .as_array()
+                .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+                .unwrap_or_default();
+            let result = CodeSandbox::execute_wasm(
+                code.as_bytes().to_vec(),
+                stdin,
+                args,
+                ExecutionLimits::default(),
+            ).await?;
+            Ok(result.summary())
+        }
+        other => Err(anyhow::anyhow!("Unknown tool: {}", other)),
+    }
+}
+
+/// Default character budget for extracted page content
+pub const DEFAULT_MAX_CHARS: usize = 5000;
+/// Upper bound a model may request through the web_scraper tool
+const MAX_TOOL_CHARS: usize = 20_000;
+
+/// Redirects the web scraper follows before giving up
+const MAX_REDIRECTS: usize = 5;
 
 /// Web Scraper Tool ("The Eye")
-/// TODO(future): Wire up to agent tool pipeline for autonomous web browsing
-#[allow(dead_code)]
+///
+/// Only fetches public addresses: every hop (redirects are followed by hand)
+/// is resolved first and refused if any address is loopback, private,
+/// link-local or otherwise internal, and the request is pinned to the
+/// checked address. Model output and fetched pages steer the URL, so this
+/// keeps Qdrant, Meilisearch, Dragonfly and cloud metadata out of reach.
 pub struct WebScraper {
-    client: reqwest::Client,
+    max_chars: usize,
+    allow_private: bool,
+}
+
+impl Default for WebScraper {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-#[allow(dead_code)]
 impl WebScraper {
     pub fn new() -> Self {
-        Self {
-            client: reqwest::Client::new(),
+        Self { max_chars: DEFAULT_MAX_CHARS, allow_private: false }
+    }
+
+    /// Override the character budget for extracted markdown
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = max_chars;
+        self
+    }
+
+    /// Also fetch internal addresses (local test servers)
+    #[cfg(test)]
+    fn allow_private_addresses(mut self) -> Self {
+        self.allow_private = true;
+        self
+    }
+
+    /// Fetch a URL and extract its main content as markdown plus metadata
+    pub async fn fetch_page(&self, url: &str) -> Result<extract::ExtractedPage> {
+        tracing::info!("🔍 Scraping: {}", url);
+
+        let mut url = reqwest::Url::parse(url)?;
+        for _ in 0..=MAX_REDIRECTS {
+            if !matches!(url.scheme(), "http" | "https") {
+                anyhow::bail!("web_scraper only supports http(s) URLs");
+            }
+            let mut client = reqwest::Client::builder()
+                .user_agent("Mozilla/5.0 (compatible; Azera/0.1)")
+                .timeout(std::time::Duration::from_secs(20))
+                .redirect(reqwest::redirect::Policy::none());
+            if !self.allow_private {
+                // Pin the connection to the address that was checked
+                let addr = public_addr(&url).await?;
+                if let Some(host) = url.host_str() {
+                    client = client.resolve(host, addr);
+                }
+            }
+            let response = client.build()?.get(url.clone()).send().await?;
+            if response.status().is_redirection() {
+                let location = response
+                    .headers()
+                    .get(reqwest::header::LOCATION)
+                    .and_then(|l| l.to_str().ok())
+                    .ok_or_else(|| anyhow::anyhow!("Redirect without a Location header"))?;
+                url = url.join(location)?;
+                continue;
+            }
+            let response = response.error_for_status()?;
+            // Resolve relative links against the final URL (after redirects)
+            let final_url = response.url().to_string();
+            let body = response.text().await?;
+
+            return Ok(extract::extract_page(&body, &final_url, self.max_chars));
         }
+        anyhow::bail!("Too many redirects")
     }
 
-    /// Fetch and extract main content from a URL
+    /// Fetch and extract main content from a URL, rendered for the model
     pub async fn extract_content(&self, url: &str) -> Result<String> {
-        tracing::info!("🔍 Scraping: {}", url);
+        Ok(self.fetch_page(url).await?.to_markdown())
+    }
+}
 
-        let response = self.client.get(url).send().await?;
-        let body = response.text().await?;
+/// Refuse `url` unless it is http(s) and its host resolves to public
+/// addresses only (also used for client-supplied LLM backend URLs)
+pub async fn check_public_url(url: &str) -> Result<()> {
+    let url = reqwest::Url::parse(url)?;
+    if !matches!(url.scheme(), "http" | "https") {
+        anyhow::bail!("only http(s) URLs are supported");
+    }
+    public_addr(&url).await.map(|_| ())
+}
 
-        // Simple extraction: remove HTML tags and clean up
-        let cleaned = Self::clean_html(&body);
-        
-        // Truncate to reasonable token limit
-        let limited = if cleaned.len() > 5000 {
-            cleaned[..5000].to_string()
-        } else {
-            cleaned
-        };
+/// Resolve `url`'s host, refusing it unless every address is public
+async fn public_addr(url: &reqwest::Url) -> Result<std::net::SocketAddr> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow::anyhow!("URL has no host"))?
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addrs: Vec<std::net::SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+    if let Some(internal) = addrs.iter().find(|a| !is_public_ip(a.ip())) {
+        anyhow::bail!("web_scraper refuses {}: {} is not a public address", host, internal.ip());
+    }
+    addrs.into_iter().next().ok_or_else(|| anyhow::anyhow!("{} did not resolve", host))
+}
+
+/// Whether `ip` is routable on the public internet (not loopback, private,
+/// link-local, unique-local, shared, multicast or unspecified)
+pub fn is_public_ip(ip: std::net::IpAddr) -> bool {
+    use std::net::IpAddr;
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b)))  // Carrier-grade NAT
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || (first & 0xfe00) == 0xfc00   // Unique local
+                || (first & 0xffc0) == 0xfe80)  // Link-local
+        }
+    }
+}
+
+/// HTML → markdown extraction (readability-style main content detection)
+pub mod extract {
+    use scraper::{ElementRef, Html, Node, Selector};
+    use serde::Serialize;
+    use std::collections::{HashMap, HashSet};
+
+    /// Elements never rendered into the output
+    const SKIP_TAGS: &[&str] = &[
+        "script", "style", "noscript", "template", "nav", "footer", "aside", "form",
+        "iframe", "svg", "canvas", "button", "input", "select", "textarea", "head",
+    ];
+
+    /// Placeholder for list indentation; survives whitespace normalization
+    /// and becomes a space in the final output
+    const INDENT: char = '\u{1}';
+
+    /// Elements whose text feeds readability scoring
+    const SCORED_TAGS: &str = "p, pre, td, blockquote, li";
+
+    /// Class/id hints for content vs. boilerplate containers
+    const POSITIVE_HINTS: &[&str] = &["article", "content", "main", "post", "entry", "body", "text", "story"];
+    const NEGATIVE_HINTS: &[&str] = &[
+        "nav", "footer", "header", "sidebar", "comment", "menu", "advert", "ad-", "share",
+        "social", "related", "promo", "banner", "cookie", "popup", "breadcrumb",
+    ];
+
+    /// Outbound link found in the main content
+    #[derive(Debug, Clone, Serialize, PartialEq)]
+    pub struct PageLink {
+        pub text: String,
+        pub url: String,
+        /// Points to a different host than the page
+        pub external: bool,
+    }
+
+    /// Result of extracting a page
+    #[derive(Debug, Clone, Serialize)]
+    pub struct ExtractedPage {
+        pub url: String,
+        pub title: Option<String>,
+        pub canonical_url: Option<String>,
+        /// BCP 47 tag from markup, or ISO 639-3 code detected from the text
+        pub language: Option<String>,
+        pub markdown: String,
+        pub links: Vec<PageLink>,
+        pub truncated: bool,
+    }
+
+    impl ExtractedPage {
+        /// Render as a single markdown document (title, source, body)
+        pub fn to_markdown(&self) -> String {
+            let mut out = String::new();
+            if let Some(ref title) = self.title {
+                out.push_str(&format!("# {}\n\n", title));
+            }
+            out.push_str(&format!(
+                "Source: {}\n\n",
+                self.canonical_url.as_deref().unwrap_or(&self.url)
+            ));
+            out.push_str(&self.markdown);
+            if self.truncated {
+                out.push_str("\n\n[content truncated]");
+            }
+            out
+        }
+    }
+
+    fn selector(css: &str) -> Selector {
+        Selector::parse(css).expect("static selector")
+    }
+
+    /// Extract title, canonical URL, language, main-content markdown and links
+    pub fn extract_page(html: &str, page_url: &str, max_chars: usize) -> ExtractedPage {
+        let document = Html::parse_document(html);
+        let base = url::Url::parse(page_url).ok();
+
+        let title = meta_content(&document, "meta[property=\"og:title\"]")
+            .or_else(|| first_text(&document, "title"))
+            .or_else(|| first_text(&document, "h1"));
+
+        let canonical_url = document
+            .select(&selector("link[rel=\"canonical\"]"))
+            .find_map(|el| el.value().attr("href"))
+            .or_else(|| {
+                document
+                    .select(&selector("meta[property=\"og:url\"]"))
+                    .find_map(|el| el.value().attr("content"))
+            })
+            .and_then(|href| resolve_url(base.as_ref(), href));
+
+        let root = main_content(&document);
+        let mut renderer = Renderer { base: base.as_ref(), links: Vec::new() };
+        let rendered = normalize_markdown(&renderer.block_children(root)).replace(INDENT, " ");
+
+        let language = markup_language(&document).or_else(|| detect_language(&rendered));
 
-        Ok(limited)
-    }
-
-    /// Clean HTML to plain text
-    fn clean_html(html: &str) -> String {
-        // Remove script and style tags
-        let re_script = regex::Regex::new(r"(?s)<script[^>]*>.*?</script>").unwrap();
-        let re_style = regex::Regex::new(r"(?s)<style[^>]*>.*?</style>").unwrap();
-        
-        let mut text = re_script.replace_all(html, "").to_string();
-        text = re_style.replace_all(&text, "").to_string();
-
-        // Remove HTML tags
-        let re_tags = regex::Regex::new(r"<[^>]+>").unwrap();
-        let mut text = re_tags.replace_all(&text, "").to_string();
-
-        // Decode common HTML entities
-        text = text
-            .replace("&amp;", "&")
-            .replace("&lt;", "<")
-            .replace("&gt;", ">")
-            .replace("&quot;", "\"")
-            .replace("&apos;", "'");
-
-        // Clean up whitespace
-        text.lines()
-            .map(|l| l.trim())
+        let (markdown, truncated) = truncate_chars(&rendered, max_chars);
+
+        // Dedupe links, keep first occurrence
+        let page_host = base.as_ref().and_then(|b| b.host_str().map(String::from));
+        let mut seen = HashSet::new();
+        let links = renderer
+            .links
+            .drain(..)
+            .filter(|(_, url)| seen.insert(url.clone()))
+            .map(|(text, url)| {
+                let host = url::Url::parse(&url).ok().and_then(|u| u.host_str().map(String::from));
+                PageLink { external: host != page_host, text, url }
+            })
+            .collect();
+
+        ExtractedPage {
+            url: page_url.to_string(),
+            title,
+            canonical_url,
+            language,
+            markdown,
+            links,
+            truncated,
+        }
+    }
+
+    /// Cut at `max_chars` characters (never inside a UTF-8 sequence),
+    /// backing off to the last whitespace when one is close by
+    pub fn truncate_chars(text: &str, max_chars: usize) -> (String, bool) {
+        match text.char_indices().nth(max_chars) {
+            None => (text.to_string(), false),
+            Some((byte_idx, _)) => {
+                let head = &text[..byte_idx];
+                let cut = head
+                    .rfind(char::is_whitespace)
+                    .filter(|&i| i >= head.len() * 4 / 5)
+                    .unwrap_or(head.len());
+                (head[..cut].trim_end().to_string(), true)
+            }
+        }
+    }
+
+    fn meta_content(document: &Html, css: &str) -> Option<String> {
+        document
+            .select(&selector(css))
+            .find_map(|el| el.value().attr("content"))
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty())
+    }
+
+    fn first_text(document: &Html, css: &str) -> Option<String> {
+        document
+            .select(&selector(css))
+            .map(|el| collapse_whitespace(&el.text().collect::<String>()))
+            .find(|t| !t.is_empty())
+    }
+
+    fn markup_language(document: &Html) -> Option<String> {
+        document
+            .select(&selector("html[lang]"))
+            .find_map(|el| el.value().attr("lang"))
+            .map(String::from)
+            .or_else(|| meta_content(document, "meta[http-equiv=\"content-language\" i]"))
+            .map(|l| l.trim().to_string())
             .filter(|l| !l.is_empty())
-            .collect::<Vec<_>>()
-            .join("\n")
+    }
+
+    fn detect_language(text: &str) -> Option<String> {
+        whatlang::detect(text)
+            .filter(|info| info.is_reliable())
+            .map(|info| info.lang().code().to_string())
+    }
+
+    fn resolve_url(base: Option<&url::Url>, href: &str) -> Option<String> {
+        let href = href.trim();
+        let resolved = match base {
+            Some(b) => b.join(href).ok()?,
+            None => url::Url::parse(href).ok()?,
+        };
+        matches!(resolved.scheme(), "http" | "https").then(|| resolved.to_string())
+    }
+
+    fn collapse_whitespace(text: &str) -> String {
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    fn class_weight(el: &ElementRef) -> f64 {
+        let hints = format!(
+            "{} {}",
+            el.value().attr("class").unwrap_or_default(),
+            el.value().attr("id").unwrap_or_default()
+        )
+        .to_lowercase();
+        let mut weight = 0.0;
+        if POSITIVE_HINTS.iter().any(|h| hints.contains(h)) {
+            weight += 25.0;
+        }
+        if NEGATIVE_HINTS.iter().any(|h| hints.contains(h)) {
+            weight -= 25.0;
+        }
+        weight
+    }
+
+    fn link_density(el: &ElementRef) -> f64 {
+        let text_len = el.text().map(|t| t.trim().len()).sum::<usize>().max(1);
+        let link_len: usize = el
+            .select(&selector("a"))
+            .flat_map(|a| a.text())
+            .map(|t| t.trim().len())
+            .sum();
+        link_len as f64 / text_len as f64
+    }
+
+    /// Pick the main content element: a semantic container if present,
+    /// otherwise the best readability-scored block, otherwise `<body>`
+    fn main_content(document: &Html) -> ElementRef<'_> {
+        let body = document
+            .select(&selector("body"))
+            .next()
+            .unwrap_or_else(|| document.root_element());
+
+        let semantic: Vec<ElementRef> = document
+            .select(&selector("article, main, [role=\"main\"]"))
+            .filter(|el| el.text().map(|t| t.trim().len()).sum::<usize>() >= 140)
+            .collect();
+        if semantic.len() == 1 {
+            return semantic[0];
+        }
+
+        // Readability-style scoring: paragraphs credit their parent fully
+        // and their grandparent by half
+        let mut scores: HashMap<ego_tree::NodeId, f64> = HashMap::new();
+        for para in document.select(&selector(SCORED_TAGS)) {
+            let text = collapse_whitespace(&para.text().collect::<String>());
+            if text.len() < 25 {
+                continue;
+            }
+            let score = 1.0 + text.matches(',').count() as f64 + (text.len() as f64 / 100.0).min(3.0);
+            let parent = para.parent().and_then(ElementRef::wrap);
+            if let Some(p) = parent {
+                *scores.entry(p.id()).or_insert_with(|| class_weight(&p)) += score;
+                if let Some(gp) = p.parent().and_then(ElementRef::wrap) {
+                    *scores.entry(gp.id()).or_insert_with(|| class_weight(&gp)) += score / 2.0;
+                }
+            }
+        }
+
+        scores
+            .into_iter()
+            .filter_map(|(id, score)| {
+                let el = document.tree.get(id).and_then(ElementRef::wrap)?;
+                Some((el, score * (1.0 - link_density(&el))))
+            })
+            .filter(|(el, _)| !matches!(el.value().name(), "html" | "head"))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(el, _)| el)
+            .unwrap_or(body)
+    }
+
+    /// Markdown renderer over the DOM; collects links as it goes
+    struct Renderer<'a> {
+        base: Option<&'a url::Url>,
+        links: Vec<(String, String)>,
+    }
+
+    impl Renderer<'_> {
+        fn block_children(&mut self, el: ElementRef) -> String {
+            let mut out = String::new();
+            for child in el.children() {
+                match child.value() {
+                    Node::Text(text) => out.push_str(&collapse_inline(text)),
+                    Node::Element(_) => {
+                        if let Some(child_el) = ElementRef::wrap(child) {
+                            out.push_str(&self.element(child_el));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            out
+        }
+
+        fn element(&mut self, el: ElementRef) -> String {
+            let name = el.value().name();
+            if SKIP_TAGS.contains(&name) || el.value().attr("hidden").is_some() {
+                return String::new();
+            }
+
+            match name {
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                    let level = name[1..].parse::<usize>().unwrap_or(1);
+                    let text = self.block_children(el);
+                    let text = collapse_whitespace(&text);
+                    if text.is_empty() {
+                        return String::new();
+                    }
+                    format!("\n\n{} {}\n\n", "#".repeat(level), text)
+                }
+                "p" | "div" | "section" | "article" | "main" | "header" | "figure" | "figcaption" | "dl" => {
+                    format!("\n\n{}\n\n", self.block_children(el).trim())
+                }
+                "dt" | "dd" => format!("\n{}\n", self.block_children(el).trim()),
+                "br" => "\n".to_string(),
+                "hr" => "\n\n---\n\n".to_string(),
+                "strong" | "b" => wrap_inline(&self.block_children(el), "**"),
+                "em" | "i" => wrap_inline(&self.block_children(el), "*"),
+                "code" => {
+                    let text: String = el.text().collect();
+                    if text.trim().is_empty() { String::new() } else { format!("`{}`", text.trim()) }
+                }
+                "pre" => {
+                    let text: String = el.text().collect();
+                    let lang = el
+                        .select(&selector("code"))
+                        .next()
+                        .and_then(|c| c.value().attr("class"))
+                        .and_then(|c| c.split_whitespace().find_map(|cls| cls.strip_prefix("language-")))
+                        .unwrap_or_default();
+                    format!("\n\n```{}\n{}\n```\n\n", lang, text.trim_end_matches('\n'))
+                }
+                "blockquote" => {
+                    let inner = normalize_markdown(&self.block_children(el));
+                    let quoted = inner
+                        .lines()
+                        .map(|l| if l.is_empty() { ">".to_string() } else { format!("> {}", l) })
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    format!("\n\n{}\n\n", quoted)
+                }
+                "ul" | "ol" => format!("\n\n{}\n\n", self.list(el, name == "ol")),
+                "table" => format!("\n\n{}\n\n", self.table(el)),
+                "a" => self.link(el),
+                "img" => {
+                    let alt = el.value().attr("alt").map(str::trim).unwrap_or_default();
+                    match el.value().attr("src").and_then(|src| resolve_url(self.base, src)) {
+                        Some(src) if !alt.is_empty() => format!("![{}]({})", alt, src),
+                        _ => String::new(),
+                    }
+                }
+                _ => self.block_children(el),
+            }
+        }
+
+        fn link(&mut self, el: ElementRef) -> String {
+            let text = collapse_whitespace(&self.block_children(el));
+            let href = el.value().attr("href").unwrap_or_default();
+            if href.starts_with('#') {
+                return text;
+            }
+            match resolve_url(self.base, href) {
+                Some(url) if !text.is_empty() => {
+                    self.links.push((text.clone(), url.clone()));
+                    format!("[{}]({})", text, url)
+                }
+                _ => text,
+            }
+        }
+
+        fn list(&mut self, el: ElementRef, ordered: bool) -> String {
+            let mut lines = Vec::new();
+            let items = el
+                .children()
+                .filter_map(ElementRef::wrap)
+                .filter(|c| c.value().name() == "li");
+            for (i, item) in items.enumerate() {
+                let marker = if ordered { format!("{}. ", i + 1) } else { "- ".to_string() };
+                let body = normalize_markdown(&self.block_children(item));
+                let mut body_lines = body.lines();
+                let first = body_lines.next().unwrap_or_default();
+                lines.push(format!("{}{}", marker, first));
+                // Nested content is indented under the marker
+                for rest in body_lines.filter(|l| !l.is_empty()) {
+                    lines.push(format!("{}{}", INDENT.to_string().repeat(marker.len()), rest));
+                }
+            }
+            lines.join("\n")
+        }
+
+        fn table(&mut self, el: ElementRef) -> String {
+            let rows: Vec<Vec<String>> = el
+                .select(&selector("tr"))
+                .map(|tr| {
+                    tr.children()
+                        .filter_map(ElementRef::wrap)
+                        .filter(|c| matches!(c.value().name(), "th" | "td"))
+                        .map(|cell| collapse_whitespace(&self.block_children(cell)).replace('|', "\\|"))
+                        .collect()
+                })
+                .filter(|r: &Vec<String>| !r.is_empty())
+                .collect();
+            let Some(width) = rows.iter().map(|r| r.len()).max() else {
+                return String::new();
+            };
+
+            let render_row = |r: &Vec<String>| {
+                let mut cells = r.clone();
+                cells.resize(width, String::new());
+                format!("| {} |", cells.join(" | "))
+            };
+            let mut out = vec![render_row(&rows[0]), format!("|{}", " --- |".repeat(width))];
+            out.extend(rows[1..].iter().map(render_row));
+            out.join("\n")
+        }
+    }
+
+    /// Collapse runs of whitespace inside inline text, keeping a single
+    /// leading/trailing space so adjacent inline elements stay separated
+    fn collapse_inline(text: &str) -> String {
+        if text.trim().is_empty() {
+            return if text.is_empty() { String::new() } else { " ".to_string() };
+        }
+        let mut out = String::new();
+        if text.starts_with(char::is_whitespace) {
+            out.push(' ');
+        }
+        out.push_str(&collapse_whitespace(text));
+        if text.ends_with(char::is_whitespace) {
+            out.push(' ');
+        }
+        out
+    }
+
+    fn wrap_inline(text: &str, marker: &str) -> String {
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            String::new()
+        } else {
+            format!("{}{}{}", marker, trimmed, marker)
+        }
+    }
+
+    /// Trim trailing spaces, strip spaces at line starts outside code
+    /// fences and collapse runs of blank lines
+    fn normalize_markdown(text: &str) -> String {
+        let mut out: Vec<String> = Vec::new();
+        let mut in_fence = false;
+        for line in text.lines() {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+                out.push(line.trim().to_string());
+                continue;
+            }
+            let line = if in_fence { line.trim_end().to_string() } else { line.trim().to_string() };
+            if !in_fence && line.is_empty() && out.last().is_none_or(|l| l.is_empty()) {
+                continue;
+            }
+            out.push(line);
+        }
+        while out.last().is_some_and(|l| l.is_empty()) {
+            out.pop();
+        }
+        out.join("\n")
+    }
+}
+
+/// Resource limits for a sandboxed execution
+#[derive(Debug, Clone)]
+pub struct ExecutionLimits {
+    pub fuel: u64,
+    pub timeout_ms: u64,
+    pub memory_bytes: usize,
+    pub max_output_bytes: usize,
+}
+
+impl Default for ExecutionLimits {
+    fn default() -> Self {
+        Self {
+            fuel: 100_000_000,
+            timeout_ms: 5_000,
+            memory_bytes: 64 * 1024 * 1024,  // 64 MiB
+            max_output_bytes: 64 * 1024,     // 64 KiB per stream
+        }
+    }
+}
+
+/// How a sandboxed execution ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionStatus {
+    /// Ran to completion (check `exit_code` for the program's verdict)
+    Completed,
+    /// Wall-clock timeout (epoch interruption)
+    Timeout,
+    /// Fuel budget exhausted
+    OutOfFuel,
+    /// Any other trap (unreachable, out-of-bounds, failed allocation, ...)
+    Trap,
+    /// Module failed to compile, link or start
+    Error,
+}
+
+/// Structured result of a sandboxed execution
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutionResult {
+    pub status: ExecutionStatus,
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub fuel_consumed: u64,
+    pub duration_ms: u64,
+    pub output_truncated: bool,
+}
+
+impl ExecutionResult {
+    /// Compact text rendering for feeding back to the model
+    pub fn summary(&self) -> String {
+        let mut out = format!("status: {:?}", self.status);
+        if let Some(code) = self.exit_code {
+            out.push_str(&format!("\nexit_code: {}", code));
+        }
+        if let Some(ref error) = self.error {
+            out.push_str(&format!("\nerror: {}", error));
+        }
+        if !self.stdout.is_empty() {
+            out.push_str(&format!("\nstdout:\n{}", self.stdout));
+        }
+        if !self.stderr.is_empty() {
+            out.push_str(&format!("\nstderr:\n{}", self.stderr));
+        }
+        out
     }
 }
 
+/// Captured guest stream that keeps at most `max_bytes`. Writes past the
+/// cap are accepted and dropped, so a chatty guest can't grow host memory.
+struct CappedBuffer {
+    bytes: Vec<u8>,
+    max_bytes: usize,
+    truncated: bool,
+}
+
+impl CappedBuffer {
+    fn new(max_bytes: usize) -> Self {
+        Self { bytes: Vec::new(), max_bytes, truncated: false }
+    }
+}
+
+impl std::io::Write for CappedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let room = self.max_bytes.saturating_sub(self.bytes.len());
+        if buf.len() > room {
+            self.truncated = true;
+        }
+        self.bytes.extend_from_slice(&buf[..buf.len().min(room)]);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Per-execution store data: WASI context + resource limiter
+struct SandboxState {
+    wasi: wasmtime_wasi::WasiCtx,
+    limits: wasmtime::StoreLimits,
+}
+
 /// Code Executor ("The Atelier")
-/// TODO(future): Wire up to agent tool pipeline for sandboxed code execution
-#[allow(dead_code)]
+/// Runs WASI command modules (wasm32-wasi binaries or WAT text) with
+/// captured stdio, a memory cap, fuel metering and a wall-clock timeout.
 pub struct CodeSandbox;
 
-#[allow(dead_code)]
 impl CodeSandbox {
-    /// Execute Wasm code safely
-    pub async fn execute_wasm(code: &str, fuel: u64) -> Result<String> {
-        tracing::info!("⚗️ Executing WASM with fuel limit: {}", fuel);
-
-        use wasmtime::*;
-
-        let engine = Engine::default();
-        let mut store = Store::new(&engine, ());
-        store.set_fuel(fuel)?;
-        let module = Module::new(&engine, code)?;
-        
-        let instance = Instance::new(&mut store, &module, &[])?;
-
-        // Try to call an exported "main" function
-        if let Ok(main) = instance.get_typed_func::<(), i32>(&mut store, "main") {
-            let result = main.call(&mut store, ())?;
-            Ok(format!("Result: {}", result))
-        } else {
-            Ok("WASM module loaded successfully".to_string())
+    /// Execute a WASI module with the given stdin and argv
+    pub async fn execute_wasm(
+        module: Vec<u8>,
+        stdin: Vec<u8>,
+        args: Vec<String>,
+        limits: ExecutionLimits,
+    ) -> Result<ExecutionResult> {
+        tracing::info!("⚗️ Executing WASM (fuel: {}, timeout: {}ms, memory: {} bytes)",
+            limits.fuel, limits.timeout_ms, limits.memory_bytes);
+
+        let mut config = wasmtime::Config::new();
+        config.consume_fuel(true);
+        config.epoch_interruption(true);
+        let engine = wasmtime::Engine::new(&config)?;
+
+        // Wall-clock timeout: bump the epoch once the deadline passes
+        let timer_engine = engine.clone();
+        let timeout = std::time::Duration::from_millis(limits.timeout_ms);
+        let timer = tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            timer_engine.increment_epoch();
+        });
+
+        let result = tokio::task::spawn_blocking(move || {
+            Self::run_blocking(&engine, &module, stdin, &args, &limits)
+        })
+        .await;
+        timer.abort();
+
+        Ok(result?)
+    }
+
+    fn run_blocking(
+        engine: &wasmtime::Engine,
+        module: &[u8],
+        stdin: Vec<u8>,
+        args: &[String],
+        limits: &ExecutionLimits,
+    ) -> ExecutionResult {
+        use std::sync::{Arc, RwLock};
+        use wasi_common::pipe::{ReadPipe, WritePipe};
+        use wasmtime::{Linker, Module, Store, StoreLimitsBuilder, Trap};
+
+        let started = std::time::Instant::now();
+        let stdout = Arc::new(RwLock::new(CappedBuffer::new(limits.max_output_bytes)));
+        let stderr = Arc::new(RwLock::new(CappedBuffer::new(limits.max_output_bytes)));
+
+        let mut result = ExecutionResult {
+            status: ExecutionStatus::Completed,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            error: None,
+            fuel_consumed: 0,
+            duration_ms: 0,
+            output_truncated: false,
+        };
+
+        let run = || -> Result<(Store<SandboxState>, Result<Option<i32>>)> {
+            let module = Module::new(engine, module)?;
+
+            let mut argv = vec!["main.wasm".to_string()];
+            argv.extend(args.iter().cloned());
+            let wasi = wasmtime_wasi::sync::WasiCtxBuilder::new()
+                .stdin(Box::new(ReadPipe::from(stdin)))
+                .stdout(Box::new(WritePipe::from_shared(stdout.clone())))
+                .stderr(Box::new(WritePipe::from_shared(stderr.clone())))
+                .args(&argv)?
+                .build();
+
+            let state = SandboxState {
+                wasi,
+                limits: StoreLimitsBuilder::new()
+                    .memory_size(limits.memory_bytes)
+                    .instances(1)
+                    .build(),
+            };
+            let mut store = Store::new(engine, state);
+            store.limiter(|s| &mut s.limits);
+            store.set_fuel(limits.fuel)?;
+            store.set_epoch_deadline(1);
+
+            let mut linker = Linker::new(engine);
+            wasmtime_wasi::sync::add_to_linker(&mut linker, |s: &mut SandboxState| &mut s.wasi)?;
+            let instance = linker.instantiate(&mut store, &module)?;
+
+            // WASI command entry point; fall back to a bare `main() -> i32`
+            let outcome = if let Ok(start) = instance.get_typed_func::<(), ()>(&mut store, "_start") {
+                start.call(&mut store, ()).map(|_| Some(0))
+            } else if let Ok(main) = instance.get_typed_func::<(), i32>(&mut store, "main") {
+                main.call(&mut store, ()).map(Some)
+            } else {
+                Err(anyhow::anyhow!("Module exports neither `_start` nor `main`"))
+            };
+            Ok((store, outcome))
+        };
+
+        match run() {
+            Ok((store, outcome)) => {
+                result.fuel_consumed = limits.fuel.saturating_sub(store.get_fuel().unwrap_or(0));
+                match outcome {
+                    Ok(code) => result.exit_code = code,
+                    Err(e) => {
+                        if let Some(exit) = e.downcast_ref::<wasmtime_wasi::I32Exit>() {
+                            result.exit_code = Some(exit.0);
+                        } else {
+                            result.status = match e.downcast_ref::<Trap>() {
+                                Some(Trap::Interrupt) => ExecutionStatus::Timeout,
+                                Some(Trap::OutOfFuel) => ExecutionStatus::OutOfFuel,
+                                _ => ExecutionStatus::Trap,
+                            };
+                            result.error = Some(e.to_string());
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                result.status = ExecutionStatus::Error;
+                result.error = Some(e.to_string());
+            }
+        }
+
+        let (out, out_truncated) = Self::capture(&stdout);
+        let (err, err_truncated) = Self::capture(&stderr);
+        result.stdout = out;
+        result.stderr = err;
+        result.output_truncated = out_truncated || err_truncated;
+        result.duration_ms = started.elapsed().as_millis() as u64;
+        result
+    }
+
+    /// Read a captured stream (UTF-8 safe) and whether it hit its cap
+    fn capture(buf: &std::sync::RwLock<CappedBuffer>) -> (String, bool) {
+        let Ok(buf) = buf.read() else {
+            return (String::new(), false);
+        };
+        let truncated = buf.truncated;
+        let mut text = String::from_utf8_lossy(&buf.bytes).into_owned();
+        if truncated {
+            // Drop a possibly split trailing character
+            if text.ends_with('\u{FFFD}') {
+                text.pop();
+            }
         }
+        (text, truncated)
     }
 }
 
@@ -116,3 +971,304 @@ pub mod fs_utils {
         Ok(std::fs::read_to_string(path)?)
     }
 }
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod tool_registry_tests {
+        use super::*;
+
+        #[test]
+        fn definitions_follow_enabled_tools() {
+            let defs = tool_definitions(&[WEB_SCRAPER.to_string(), "unknown".to_string()]);
+            assert_eq!(defs.len(), 1);
+            assert_eq!(defs[0]["function"]["name"], WEB_SCRAPER);
+            assert_eq!(defs[0]["function"]["parameters"]["required"][0], "url");
+        }
+
+        #[test]
+        fn only_offered_tools_may_run() {
+            let defs = tool_definitions(&[WEB_SCRAPER.to_string()]);
+            assert!(is_offered(&defs, WEB_SCRAPER));
+            assert!(!is_offered(&defs, CODE_EXECUTOR));
+            assert!(!is_offered(&[], WEB_SCRAPER));
+        }
+
+        #[tokio::test]
+        async fn unknown_tool_is_an_error() {
+            let err = execute_tool("teleporter", &serde_json::json!({})).await.unwrap_err();
+            assert!(err.to_string().contains("Unknown tool"));
+        }
+
+        #[tokio::test]
+        async fn web_scraper_rejects_non_http_urls() {
+            let result = execute_tool(WEB_SCRAPER, &serde_json::json!({"url": "file:///etc/passwd"})).await;
+            assert!(result.is_err());
+        }
+
+        #[tokio::test]
+        async fn code_executor_reports_exit_code() {
+            let code = r#"(module (func (export "main") (result i32) i32.const 42))"#;
+            let output = execute_tool(CODE_EXECUTOR, &serde_json::json!({"code": code})).await.unwrap();
+            assert!(output.contains("exit_code: 42"), "{}", output);
+        }
+    }
+
+    mod web_scraper_tests {
+        use super::*;
+        use super::extract::{extract_page, truncate_chars};
+
+        const ARTICLE: &str = include_str!("../tests/fixtures/article.html");
+        const UNICODE: &str = include_str!("../tests/fixtures/unicode.html");
+
+        #[test]
+        fn extracts_metadata() {
+            let page = extract_page(ARTICLE, "https://journal.example.com/a?ref=feed", DEFAULT_MAX_CHARS);
+            assert_eq!(page.title.as_deref(), Some("Night Gardens of the North | Example Journal"));
+            assert_eq!(page.canonical_url.as_deref(), Some("https://journal.example.com/articles/night-gardens"));
+            assert_eq!(page.language.as_deref(), Some("en-US"));
+            assert!(!page.truncated);
+        }
+
+        #[test]
+        fn keeps_main_content_structure() {
+            let page = extract_page(ARTICLE, "https://journal.example.com/a", DEFAULT_MAX_CHARS);
+            let md = &page.markdown;
+            assert!(md.starts_with("# Night Gardens of the North"), "{}", md);
+            assert!(md.contains("## Choosing plants"));
+            assert!(md.contains("[moonflower guide](https://plants.example.org/moonflower)"));
+            assert!(md.contains("- Moonflower, *Ipomoea alba*"));
+            assert!(md.contains("\n  - Oenothera biennis"), "{}", md);
+            assert!(md.contains("| Plant | Scent |\n| --- | --- |\n| Jasmine | Strong |"));
+            assert!(md.contains("```toml\n[garden]\nlights = \"off\"\n```"));
+            assert!(md.contains("Use `mulch` generously"));
+            assert!(md.contains("> The garden at night is a different country."));
+        }
+
+        #[test]
+        fn drops_boilerplate() {
+            let page = extract_page(ARTICLE, "https://journal.example.com/a", DEFAULT_MAX_CHARS);
+            for noise in ["Ten moss facts", "tracking", "Copyright", "Contact", "color: #333"] {
+                assert!(!page.markdown.contains(noise), "found {:?} in {}", noise, page.markdown);
+            }
+        }
+
+        #[test]
+        fn collects_resolved_links() {
+            let page = extract_page(ARTICLE, "https://journal.example.com/a", DEFAULT_MAX_CHARS);
+            assert_eq!(page.links, vec![
+                extract::PageLink {
+                    text: "moonflower guide".to_string(),
+                    url: "https://plants.example.org/moonflower".to_string(),
+                    external: true,
+                },
+                extract::PageLink {
+                    text: "stock primer".to_string(),
+                    url: "https://journal.example.com/guides/stock".to_string(),
+                    external: false,
+                },
+            ]);
+        }
+
+        #[test]
+        fn detects_language_from_text() {
+            let page = extract_page(UNICODE, "https://example.fr/", DEFAULT_MAX_CHARS);
+            assert_eq!(page.language.as_deref(), Some("fra"));
+            assert_eq!(page.title.as_deref(), Some("Éphémère"));
+        }
+
+        #[test]
+        fn truncation_is_char_safe() {
+            let page = extract_page(UNICODE, "https://example.fr/", 200);
+            assert!(page.truncated);
+            assert!(page.markdown.chars().count() <= 200);
+
+            // Cutting inside multi-byte characters must not panic
+            let text = "🌙".repeat(10);
+            for max in 0..12 {
+                let (cut, truncated) = truncate_chars(&text, max);
+                assert_eq!(cut.chars().count(), max.min(10));
+                assert_eq!(truncated, max < 10);
+            }
+        }
+
+        #[tokio::test]
+        async fn fetches_from_local_stub() {
+            let app = axum::Router::new().route(
+                "/articles/night-gardens",
+                axum::routing::get(|| async { axum::response::Html(ARTICLE) }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+            let url = format!("http://{}/articles/night-gardens", addr);
+            let page = WebScraper::new().with_max_chars(120).allow_private_addresses().fetch_page(&url).await.unwrap();
+            assert_eq!(page.canonical_url.as_deref(), Some(url.as_str()));
+            assert!(page.truncated);
+            assert!(page.links.iter().any(|l| l.url == format!("http://{}/guides/stock", addr)));
+
+            let rendered = WebScraper::new().allow_private_addresses().extract_content(&url).await.unwrap();
+            assert!(rendered.starts_with("# Night Gardens of the North | Example Journal\n\nSource: "));
+        }
+
+        #[tokio::test]
+        async fn refuses_internal_addresses() {
+            let app = axum::Router::new().route(
+                "/collections",
+                axum::routing::get(|| async { "secret" }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+            let url = format!("http://127.0.0.1:{}/collections", addr.port());
+            let err = execute_tool(WEB_SCRAPER, &json!({ "url": url })).await.unwrap_err();
+            assert!(err.to_string().contains("not a public address"), "{}", err);
+            let err = WebScraper::new().fetch_page(&format!("http://localhost:{}/collections", addr.port())).await.unwrap_err();
+            assert!(err.to_string().contains("not a public address"), "{}", err);
+        }
+
+        #[tokio::test]
+        async fn check_public_url_refuses_internal_and_non_http() {
+            assert!(check_public_url("http://127.0.0.1:6333/collections").await.is_err());
+            assert!(check_public_url("http://169.254.169.254/latest/meta-data").await.is_err());
+            assert!(check_public_url("http://[::1]:7700/").await.is_err());
+            assert!(check_public_url("file:///etc/passwd").await.is_err());
+        }
+
+        #[test]
+        fn classifies_internal_ips() {
+            for ip in [
+                "127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254",
+                "100.64.0.1", "0.0.0.0", "::1", "fd00::1", "fe80::1", "::ffff:127.0.0.1",
+            ] {
+                assert!(!is_public_ip(ip.parse().unwrap()), "{} should be internal", ip);
+            }
+            for ip in ["93.184.216.34", "1.1.1.1", "2606:4700:4700::1111"] {
+                assert!(is_public_ip(ip.parse().unwrap()), "{} should be public", ip);
+            }
+        }
+    }
+
+    mod sandbox_tests {
+        use super::*;
+
+        /// WASI command that copies stdin to stdout and writes argv[1] to stderr
+        const ECHO_WAT: &str = r#"
+            (module
+              (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+              (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+              (import "wasi_snapshot_preview1" "args_get" (func $args_get (param i32 i32) (result i32)))
+              (memory (export "memory") 1)
+              (func (export "_start")
+                ;; iovec at 0: buf=1024, len=512
+                (i32.store (i32.const 0) (i32.const 1024))
+                (i32.store (i32.const 4) (i32.const 512))
+                (drop (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8)))
+                ;; write the bytes read (count at 8) to stdout
+                (i32.store (i32.const 4) (i32.load (i32.const 8)))
+                (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+                ;; argv pointers at 16, strings at 2048; write argv[1] ("hi") to stderr
+                (drop (call $args_get (i32.const 16) (i32.const 2048)))
+                (i32.store (i32.const 0) (i32.load (i32.const 20)))
+                (i32.store (i32.const 4) (i32.const 2))
+                (drop (call $fd_write (i32.const 2) (i32.const 0) (i32.const 1) (i32.const 8)))))
+        "#;
+
+        async fn run(wat: &str, limits: ExecutionLimits) -> ExecutionResult {
+            CodeSandbox::execute_wasm(wat.as_bytes().to_vec(), Vec::new(), Vec::new(), limits)
+                .await
+                .unwrap()
+        }
+
+        #[tokio::test]
+        async fn captures_stdin_stdout_and_argv() {
+            let result = CodeSandbox::execute_wasm(
+                ECHO_WAT.as_bytes().to_vec(),
+                b"hello sandbox".to_vec(),
+                vec!["hi".to_string()],
+                ExecutionLimits::default(),
+            ).await.unwrap();
+            assert_eq!(result.status, ExecutionStatus::Completed);
+            assert_eq!(result.exit_code, Some(0));
+            assert_eq!(result.stdout, "hello sandbox");
+            assert_eq!(result.stderr, "hi");
+            assert!(result.fuel_consumed > 0);
+        }
+
+        #[tokio::test]
+        async fn proc_exit_sets_exit_code() {
+            let wat = r#"
+                (module
+                  (import "wasi_snapshot_preview1" "proc_exit" (func $exit (param i32)))
+                  (memory (export "memory") 1)
+                  (func (export "_start") (call $exit (i32.const 3))))
+            "#;
+            let result = run(wat, ExecutionLimits::default()).await;
+            assert_eq!(result.status, ExecutionStatus::Completed);
+            assert_eq!(result.exit_code, Some(3));
+        }
+
+        #[tokio::test]
+        async fn infinite_loop_runs_out_of_fuel() {
+            let wat = r#"(module (func (export "_start") (loop (br 0))))"#;
+            let limits = ExecutionLimits { fuel: 10_000, ..Default::default() };
+            let result = run(wat, limits).await;
+            assert_eq!(result.status, ExecutionStatus::OutOfFuel);
+        }
+
+        #[tokio::test]
+        async fn infinite_loop_times_out() {
+            let wat = r#"(module (func (export "_start") (loop (br 0))))"#;
+            let limits = ExecutionLimits { fuel: u64::MAX, timeout_ms: 100, ..Default::default() };
+            let result = run(wat, limits).await;
+            assert_eq!(result.status, ExecutionStatus::Timeout);
+        }
+
+        #[tokio::test]
+        async fn memory_cap_rejects_large_modules() {
+            // 4 MiB initial memory against a 1 MiB cap
+            let wat = r#"(module (memory 64) (func (export "_start")))"#;
+            let limits = ExecutionLimits { memory_bytes: 1024 * 1024, ..Default::default() };
+            let result = run(wat, limits).await;
+            assert_eq!(result.status, ExecutionStatus::Error);
+        }
+
+        #[test]
+        fn capped_buffer_stops_storing_at_the_cap() {
+            use std::io::Write;
+            let mut buf = CappedBuffer::new(4);
+            assert_eq!(buf.write(b"abc").unwrap(), 3);
+            assert!(!buf.truncated);
+            assert_eq!(buf.write(b"defgh").unwrap(), 5);
+            assert_eq!(buf.bytes, b"abcd");
+            assert!(buf.truncated);
+        }
+
+        #[tokio::test]
+        async fn output_past_the_cap_is_truncated() {
+            let limits = ExecutionLimits { max_output_bytes: 5, ..Default::default() };
+            let result = CodeSandbox::execute_wasm(
+                ECHO_WAT.as_bytes().to_vec(),
+                b"hello sandbox".to_vec(),
+                vec!["hi".to_string()],
+                limits,
+            ).await.unwrap();
+            assert_eq!(result.stdout, "hello");
+            assert!(result.output_truncated);
+        }
+
+        #[tokio::test]
+        async fn invalid_module_is_an_error_result() {
+            let result = run("not wasm", ExecutionLimits::default()).await;
+            assert_eq!(result.status, ExecutionStatus::Error);
+            assert!(result.error.is_some());
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::forgetting;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -25,6 +26,40 @@ pub struct SearchResult {
     pub payload: HashMap<String, serde_json::Value>,
 }
 
+/// A stored point without its vector
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredPoint {
+    pub id: String,
+    pub payload: HashMap<String, serde_json::Value>,
+}
+
+/// One page of [`VectorService::scroll`]
+#[derive(Debug)]
+pub struct ScrollPage {
+    pub points: Vec<StoredPoint>,
+    /// Offset of the next page; `None` on the last one
+    pub next_offset: Option<String>,
+}
+
+/// A Qdrant point ID (UUID string or unsigned integer) as a string
+fn point_id(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether `id` can be a Qdrant point ID (a UUID or an unsigned integer)
+pub fn is_point_id(id: &str) -> bool {
+    uuid::Uuid::parse_str(id).is_ok() || id.parse::<u64>().is_ok()
+}
+
+/// Filter matching the points with these IDs
+pub fn has_ids(ids: &[String]) -> serde_json::Value {
+    serde_json::json!({ "must": [{ "has_id": ids }] })
+}
+
 impl VectorService {
     pub fn new(base_url: String) -> Self {
         Self {
@@ -154,6 +189,187 @@ impl VectorService {
         Ok(results)
     }
 
+    /// Page through the points matching `filter` (payloads only), starting
+    /// at `offset` as returned in [`ScrollPage::next_offset`]
+    pub async fn scroll(
+        &self,
+        collection_name: &str,
+        filter: Option<serde_json::Value>,
+        limit: usize,
+        offset: Option<&str>,
+    ) -> Result<ScrollPage> {
+        let url = format!("{}/collections/{}/points/scroll", self.base_url, collection_name);
+
+        let mut body = serde_json::json!({
+            "limit": limit,
+            "with_payload": true,
+            "with_vector": false
+        });
+        if let Some(f) = filter {
+            body["filter"] = f;
+        }
+        if let Some(offset) = offset {
+            // Point IDs are UUIDs or unsigned integers
+            body["offset"] = offset.parse::<u64>().map_or_else(|_| serde_json::json!(offset), |n| serde_json::json!(n));
+        }
+
+        let response = self.client
+            .post(&url)
+            .json(&body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error = response.text().await?;
+            return Err(anyhow::anyhow!("Scroll failed: {}", error));
+        }
+
+        let json: serde_json::Value = response.json().await?;
+        let points = json["result"]["points"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|item| {
+                        Some(StoredPoint {
+                            id: point_id(&item["id"])?,
+                            payload: serde_json::from_value(item["payload"].clone()).ok()?,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(ScrollPage { points, next_offset: point_id(&json["result"]["next_page_offset"]) })
+    }
+
+    /// Number of points matching `filter`
+    pub async fn count(&self, collection_name: &str, filter: serde_json::Value) -> Result<usize> {
+        let url = format!("{}/collections/{}/points/count", self.base_url, collection_name);
+
+        let response = self.client
+            .post(&url)
+            .json(&serde_json::json!({ "filter": filter, "exact": true }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error = response.text().await?;
+            return Err(anyhow::anyhow!("Count failed: {}", error));
+        }
+
+        let json: serde_json::Value = response.json().await?;
+        Ok(json["result"]["count"].as_u64().unwrap_or(0) as usize)
+    }
+
+    /// Fetch one point's payload; `None` when there is no such point
+    pub async fn get(&self, collection_name: &str, id: &str) -> Result<Option<StoredPoint>> {
+        let url = format!("{}/collections/{}/points", self.base_url, collection_name);
+
+        let body = serde_json::json!({
+            "ids": [id],
+            "with_payload": true,
+            "with_vector": false
+        });
+
+        let response = self.client
+            .post(&url)
+            .json(&body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error = response.text().await?;
+            return Err(anyhow::anyhow!("Failed to get point: {}", error));
+        }
+
+        let json: serde_json::Value = response.json().await?;
+        Ok(json["result"]
+            .as_array()
+            .and_then(|arr| arr.first())
+            .and_then(|item| {
+                Some(StoredPoint {
+                    id: point_id(&item["id"])?,
+                    payload: serde_json::from_value(item["payload"].clone()).ok()?,
+                })
+            }))
+    }
+
+    /// Merge `payload` into every point matching `filter`
+    pub async fn set_payload(
+        &self,
+        collection_name: &str,
+        payload: serde_json::Value,
+        filter: serde_json::Value,
+    ) -> Result<()> {
+        let url = format!("{}/collections/{}/points/payload", self.base_url, collection_name);
+
+        let body = serde_json::json!({
+            "payload": payload,
+            "filter": filter
+        });
+
+        let response = self.client
+            .post(&url)
+            .json(&body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error = response.text().await?;
+            return Err(anyhow::anyhow!("Failed to set payload: {}", error));
+        }
+
+        Ok(())
+    }
+
+    /// Merge a different payload into each point, in one request
+    pub async fn set_payloads(
+        &self,
+        collection_name: &str,
+        updates: &[(String, serde_json::Value)],
+    ) -> Result<()> {
+        if updates.is_empty() {
+            return Ok(());
+        }
+        let url = format!("{}/collections/{}/points/batch", self.base_url, collection_name);
+
+        let operations: Vec<serde_json::Value> = updates
+            .iter()
+            .map(|(id, payload)| serde_json::json!({ "set_payload": { "payload": payload, "points": [id] } }))
+            .collect();
+
+        let response = self.client
+            .post(&url)
+            .json(&serde_json::json!({ "operations": operations }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error = response.text().await?;
+            return Err(anyhow::anyhow!("Failed to set payloads: {}", error));
+        }
+
+        Ok(())
+    }
+
+    /// Delete every point matching `filter`
+    pub async fn delete_matching(&self, collection_name: &str, filter: serde_json::Value) -> Result<()> {
+        let url = format!("{}/collections/{}/points/delete", self.base_url, collection_name);
+
+        let response = self.client
+            .post(&url)
+            .json(&serde_json::json!({ "filter": filter }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error = response.text().await?;
+            return Err(anyhow::anyhow!("Failed to delete vectors: {}", error));
+        }
+
+        Ok(())
+    }
+
     /// Delete a vector by ID
     pub async fn delete(&self, collection_name: &str, id: &str) -> Result<()> {
         let url = format!("{}/collections/{}/points/delete", self.base_url, collection_name);
@@ -304,6 +520,7 @@ pub async fn store_memory(
     let mut payload = metadata;
     payload.insert("content".to_string(), serde_json::json!(content));
     payload.insert("type".to_string(), serde_json::json!(memory_type.to_string()));
+    initial_scores(&mut payload, &memory_type, content);
     payload.insert("timestamp".to_string(), serde_json::json!(chrono::Utc::now().to_rfc3339()));
     
     vector_service.upsert(collection, id, embedding, payload).await?;
@@ -325,6 +542,7 @@ pub async fn store_memory_cached(
     let mut payload = request.metadata.clone();
     payload.insert("content".to_string(), serde_json::json!(request.content));
     payload.insert("type".to_string(), serde_json::json!(request.memory_type.to_string()));
+    initial_scores(&mut payload, &request.memory_type, &request.content);
     payload.insert("timestamp".to_string(), serde_json::json!(chrono::Utc::now().to_rfc3339()));
     
     vector_service
@@ -334,8 +552,17 @@ pub async fn store_memory_cached(
     Ok(())
 }
 
+/// Importance (unless the caller set one) and a zero access count for a new memory
+fn initial_scores(payload: &mut HashMap<String, serde_json::Value>, memory_type: &MemoryType, content: &str) {
+    let role = payload.get("role").and_then(|v| v.as_str()).map(String::from);
+    payload
+        .entry("importance".to_string())
+        .or_insert_with(|| serde_json::json!(forgetting::importance(&memory_type.to_string(), role.as_deref(), content)));
+    payload.insert("access_count".to_string(), serde_json::json!(0));
+}
+
 /// Search memories by semantic similarity (non-cached variant)
-/// Prefer `search_memories_cached` for production use; kept for testing/direct access
+/// Prefer `retrieval::HybridRetriever` for production use; kept for testing/direct access
 #[allow(dead_code)]
 pub async fn search_memories(
     vector_service: &VectorService,
@@ -359,30 +586,6 @@ pub async fn search_memories(
     vector_service.search(collection, query_embedding, limit, filter).await
 }
 
-/// Search memories with Dragonfly embedding cache
-pub async fn search_memories_cached(
-    vector_service: &VectorService,
-    ollama_host: &str,
-    cache: &redis::aio::ConnectionManager,
-    collection: &str,
-    query: &str,
-    limit: usize,
-    memory_type: Option<MemoryType>,
-) -> Result<Vec<SearchResult>> {
-    let query_embedding = vector_service.generate_embedding_cached(ollama_host, query, cache).await?;
-    
-    let filter = memory_type.map(|t| {
-        serde_json::json!({
-            "must": [{
-                "key": "type",
-                "match": { "value": t.to_string() }
-            }]
-        })
-    });
-    
-    vector_service.search(collection, query_embedding, limit, filter).await
-}
-
 /// Search memories with a custom filter (for global persona memory, non-cached variant)
 /// Prefer `search_memories_with_filter_cached` for production use; kept for testing/direct access
 #[allow(dead_code)]
@@ -411,3 +614,39 @@ pub async fn search_memories_with_filter_cached(
     let query_embedding = vector_service.generate_embedding_cached(ollama_host, query, cache).await?;
     vector_service.search(collection, query_embedding, limit, filter).await
 }
+
+/// Filter condition for points `user_id` may see: their own, plus shared
+/// agent memories (no owner). Unowned conversation turns stay hidden since
+/// they came from someone's chat.
+pub fn visible_to_user(user_id: &str) -> serde_json::Value {
+    serde_json::json!({
+        "should": [
+            { "key": "user_id", "match": { "value": user_id } },
+            {
+                "must": [{ "is_empty": { "key": "user_id" } }],
+                "must_not": [{ "key": "type", "match": { "value": "conversation" } }]
+            }
+        ]
+    })
+}
+
+/// Filter condition for shared agent memories only (no owner, never
+/// conversation turns)
+pub fn shared_only() -> serde_json::Value {
+    serde_json::json!({
+        "must": [{ "is_empty": { "key": "user_id" } }],
+        "must_not": [{ "key": "type", "match": { "value": "conversation" } }]
+    })
+}
+
+/// Filter condition for facts replaced by a newer one during consolidation
+/// (use under `must_not`)
+pub fn superseded() -> serde_json::Value {
+    serde_json::json!({ "key": "superseded", "match": { "value": true } })
+}
+
+/// Filter condition for memories the forgetting sweeper archived (use under
+/// `must_not`)
+pub fn archived() -> serde_json::Value {
+    serde_json::json!({ "key": "archived", "match": { "value": true } })
+}
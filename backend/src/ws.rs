@@ -0,0 +1,337 @@
+//! WebSocket chat transport (`GET /api/ws`)
+//!
+//! One socket carries any number of chats. The client sends JSON control
+//! messages ([`ClientMessage`]): `send` starts a turn exactly like
+//! `POST /api/chat/stream`, `stop` cancels a generation, `typing` is relayed
+//! to the user's other sockets. The server pushes [`ServerMessage`]s: every
+//! `StreamEvent` of the turns started on the socket (tagged with chat and
+//! generation), typing signals, and the user's agent events (`events.rs`).
+//!
+//! Turns started on a socket behave like SSE streams: when the socket closes
+//! they keep running for the resume grace period and can be picked up with
+//! `GET /api/chat/stream/:generation_id`.
+
+use crate::*;
+use crate::auth::AuthUser;
+use crate::error::ApiError;
+use crate::events::AgentEvent;
+use crate::models::{ChatRequest, StreamEvent};
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::Response;
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinSet;
+
+/// Messages queued for a socket before it counts as stuck
+const OUTBOX_CAPACITY: usize = 256;
+
+/// Control messages from the client
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+pub enum ClientMessage {
+    /// Send a chat message; echoed back in `started`/`error` as `request_id`
+    #[serde(rename = "send")]
+    Send {
+        #[serde(default)]
+        request_id: Option<String>,
+        #[serde(flatten)]
+        request: Box<ChatRequest>,
+    },
+    /// Stop a running generation
+    #[serde(rename = "stop")]
+    Stop { generation_id: String },
+    /// The user is (or stopped) typing in a chat
+    #[serde(rename = "typing")]
+    Typing {
+        chat_id: String,
+        #[serde(default = "default_typing")]
+        typing: bool,
+    },
+}
+
+fn default_typing() -> bool {
+    true
+}
+
+/// Messages pushed to the client
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+pub enum ServerMessage {
+    /// A `send` was accepted and its turn is running
+    #[serde(rename = "started")]
+    Started {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        request_id: Option<String>,
+        chat_id: String,
+        generation_id: String,
+    },
+    /// A stream event of a generation started on this socket; `id` is the
+    /// same event number the SSE stream uses
+    #[serde(rename = "event")]
+    Event {
+        chat_id: String,
+        generation_id: String,
+        id: u64,
+        event: StreamEvent,
+    },
+    /// A generation is stopping (`stopped: false` if it wasn't running)
+    #[serde(rename = "stopping")]
+    Stopping { generation_id: String, stopped: bool },
+    /// Typing signal from another socket of the same user
+    #[serde(rename = "typing")]
+    Typing { chat_id: String, typing: bool },
+    /// Agent event from the event bus
+    #[serde(rename = "notification")]
+    Notification { notification: AgentEvent },
+    /// A control message failed; `code` as in HTTP error bodies
+    #[serde(rename = "error")]
+    Error {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        request_id: Option<String>,
+        code: String,
+        message: String,
+    },
+}
+
+/// A typing signal on its way to the user's other sockets
+#[derive(Debug, Clone)]
+pub struct TypingSignal {
+    user_id: String,
+    socket_id: String,
+    chat_id: String,
+    typing: bool,
+}
+
+/// Fan-out between open sockets
+pub struct SocketHub {
+    typing: broadcast::Sender<TypingSignal>,
+}
+
+impl SocketHub {
+    pub fn new() -> Self {
+        let (typing, _) = broadcast::channel(256);
+        Self { typing }
+    }
+}
+
+/// GET /api/ws - Upgrade to the chat WebSocket
+pub async fn ws_handler(State(state): State<AppState>, user: AuthUser, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| serve(state, user.id, socket))
+}
+
+async fn serve(state: AppState, user_id: String, socket: WebSocket) {
+    let socket_id = uuid::Uuid::new_v4().to_string();
+    tracing::info!("🔌 WebSocket {} opened for {}", socket_id, user_id);
+
+    let (mut sink, mut incoming) = socket.split();
+    let (outbox, mut outgoing) = mpsc::channel::<ServerMessage>(OUTBOX_CAPACITY);
+    let mut writer = tokio::spawn(async move {
+        while let Some(message) = outgoing.recv().await {
+            let Ok(text) = serde_json::to_string(&message) else { continue };
+            if sink.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut notifications = state.events.subscribe();
+    let mut typing = state.sockets.typing.subscribe();
+    // Forwarders of the generations started here; aborted when the socket closes
+    let mut forwarders = JoinSet::new();
+
+    loop {
+        tokio::select! {
+            message = incoming.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    handle_message(&state, &user_id, &socket_id, &text, &outbox, &mut forwarders).await;
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by axum; binary frames mean nothing here
+                Some(Ok(_)) => {}
+            },
+            notification = notifications.recv() => match notification {
+                Ok(envelope) if envelope.visible_to(&user_id) => {
+                    let _ = outbox.try_send(ServerMessage::Notification { notification: envelope.event });
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            signal = typing.recv() => {
+                if let Ok(signal) = signal {
+                    if signal.user_id == user_id && signal.socket_id != socket_id {
+                        let _ = outbox.try_send(ServerMessage::Typing { chat_id: signal.chat_id, typing: signal.typing });
+                    }
+                }
+            },
+            // The client stopped reading
+            _ = &mut writer => break,
+            Some(_) = forwarders.join_next(), if !forwarders.is_empty() => {}
+        }
+    }
+
+    forwarders.abort_all();
+    writer.abort();
+    tracing::info!("🔌 WebSocket {} closed", socket_id);
+}
+
+async fn handle_message(
+    state: &AppState,
+    user_id: &str,
+    socket_id: &str,
+    text: &str,
+    outbox: &mpsc::Sender<ServerMessage>,
+    forwarders: &mut JoinSet<()>,
+) {
+    let message = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(message) => message,
+        Err(e) => {
+            let error = ApiError::BadRequest(format!("Invalid message: {}", e));
+            let _ = outbox.send(error_message(None, &error)).await;
+            return;
+        }
+    };
+
+    match message {
+        ClientMessage::Send { request_id, request } => {
+            let chat_id = request.chat_id.clone();
+            tracing::info!("💬 WebSocket chat request: {}", request.message);
+            match handlers::start_chat_turn(state, user_id, *request).await {
+                Ok((generation_id, events)) => {
+                    let _ = outbox.send(ServerMessage::Started {
+                        request_id,
+                        chat_id: chat_id.clone(),
+                        generation_id: generation_id.clone(),
+                    }).await;
+                    forwarders.spawn(forward_events(chat_id, generation_id, events, outbox.clone()));
+                }
+                Err(e) => {
+                    let _ = outbox.send(error_message(request_id, &e)).await;
+                }
+            }
+        }
+        ClientMessage::Stop { generation_id } => {
+            let stopped = state.generations.stop(&generation_id, user_id);
+            if stopped {
+                tracing::info!("⏹️ Stopping generation {}", generation_id);
+            }
+            let _ = outbox.send(ServerMessage::Stopping { generation_id, stopped }).await;
+        }
+        ClientMessage::Typing { chat_id, typing } => {
+            // Nobody else listening is fine
+            let _ = state.sockets.typing.send(TypingSignal {
+                user_id: user_id.to_string(),
+                socket_id: socket_id.to_string(),
+                chat_id,
+                typing,
+            });
+        }
+    }
+}
+
+fn error_message(request_id: Option<String>, error: &ApiError) -> ServerMessage {
+    ServerMessage::Error { request_id, code: error.code().to_string(), message: error.to_string() }
+}
+
+/// Push a generation's events to the socket until it ends or the socket closes
+async fn forward_events(
+    chat_id: String,
+    generation_id: String,
+    mut events: mpsc::Receiver<generation::NumberedEvent>,
+    outbox: mpsc::Sender<ServerMessage>,
+) {
+    while let Some(numbered) = events.recv().await {
+        let message = ServerMessage::Event {
+            chat_id: chat_id.clone(),
+            generation_id: generation_id.clone(),
+            id: numbered.id,
+            event: numbered.event,
+        };
+        if outbox.send(message).await.is_err() {
+            return;
+        }
+    }
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod message_tests {
+        use super::*;
+
+        #[test]
+        fn send_carries_a_full_chat_request() {
+            let message: ClientMessage = serde_json::from_str(
+                r#"{"type":"send","request_id":"r1","chat_id":"c1","branch_id":"b1","message":"hi","ai_persona_id":"azera"}"#,
+            ).unwrap();
+            match message {
+                ClientMessage::Send { request_id, request } => {
+                    assert_eq!(request_id.as_deref(), Some("r1"));
+                    assert_eq!(request.chat_id, "c1");
+                    assert_eq!(request.branch_id, "b1");
+                    assert_eq!(request.message, "hi");
+                    assert_eq!(request.model, models::default_model());
+                    assert_eq!(request.ai_persona_id.as_deref(), Some("azera"));
+                }
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+
+        #[test]
+        fn typing_defaults_to_started() {
+            let message: ClientMessage = serde_json::from_str(r#"{"type":"typing","chat_id":"c1"}"#).unwrap();
+            assert!(matches!(message, ClientMessage::Typing { typing: true, .. }));
+            let message: ClientMessage = serde_json::from_str(r#"{"type":"typing","chat_id":"c1","typing":false}"#).unwrap();
+            assert!(matches!(message, ClientMessage::Typing { typing: false, .. }));
+        }
+
+        #[test]
+        fn rejects_unknown_and_incomplete_messages() {
+            assert!(serde_json::from_str::<ClientMessage>(r#"{"type":"shout"}"#).is_err());
+            assert!(serde_json::from_str::<ClientMessage>(r#"{"type":"stop"}"#).is_err());
+            assert!(serde_json::from_str::<ClientMessage>(r#"{"type":"send","chat_id":"c1"}"#).is_err());
+        }
+
+        #[test]
+        fn events_wrap_the_stream_payload() {
+            let message = ServerMessage::Event {
+                chat_id: "c1".to_string(),
+                generation_id: "gen_1".to_string(),
+                id: 3,
+                event: StreamEvent::Content { content: "hi".to_string(), persona_id: None },
+            };
+            assert_eq!(
+                serde_json::to_value(&message).unwrap(),
+                serde_json::json!({
+                    "type": "event", "chat_id": "c1", "generation_id": "gen_1", "id": 3,
+                    "event": {"type": "content", "content": "hi"}
+                })
+            );
+        }
+
+        #[test]
+        fn notifications_keep_their_own_type() {
+            let message = ServerMessage::Notification {
+                notification: AgentEvent::DreamRecorded {
+                    persona_id: "azera".to_string(),
+                    dream_id: "dream_1".to_string(),
+                    title: "Dreams of Dawn".to_string(),
+                },
+            };
+            assert_eq!(
+                serde_json::to_value(&message).unwrap(),
+                serde_json::json!({
+                    "type": "notification",
+                    "notification": {"type": "dream_recorded", "persona_id": "azera", "dream_id": "dream_1", "title": "Dreams of Dawn"}
+                })
+            );
+        }
+    }
+}
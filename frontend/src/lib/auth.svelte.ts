@@ -0,0 +1,78 @@
+// Session handling for the backend's local accounts.
+//
+// The backend sets an HttpOnly `azera_session` cookie on login; cross-origin
+// fetches only carry it with `credentials: 'include'`, so every request to
+// the API origin is sent that way.
+
+export const API_BASE = 'http://localhost:3000';
+
+export interface User {
+    id: string;
+    username: string;
+    display_name?: string;
+    created_at: string;
+}
+
+// Send cookies with every request to the backend
+function installCredentialedFetch() {
+    if (typeof window === 'undefined') return;
+    const baseFetch = window.fetch.bind(window);
+    window.fetch = (input: RequestInfo | URL, init?: RequestInit) => {
+        const url = typeof input === 'string' ? input : input instanceof URL ? input.href : input.url;
+        if (url.startsWith(API_BASE)) {
+            return baseFetch(input, { credentials: 'include', ...init });
+        }
+        return baseFetch(input, init);
+    };
+}
+
+installCredentialedFetch();
+
+class AuthState {
+    user = $state<User | null>(null);
+    checked = $state(false);
+
+    // Resolve the current session (if any)
+    async check() {
+        try {
+            const response = await fetch(`${API_BASE}/api/auth/me`);
+            this.user = response.ok ? await response.json() : null;
+        } catch {
+            this.user = null;
+        } finally {
+            this.checked = true;
+        }
+    }
+
+    async login(username: string, password: string) {
+        await this.authenticate('login', { username, password });
+    }
+
+    async register(username: string, password: string) {
+        await this.authenticate('register', { username, password });
+    }
+
+    async logout() {
+        await fetch(`${API_BASE}/api/auth/logout`, { method: 'POST' }).catch(() => {});
+        this.user = null;
+        // Drop everything loaded for the previous account
+        window.location.reload();
+    }
+
+    private async authenticate(action: 'login' | 'register', body: Record<string, string>) {
+        const response = await fetch(`${API_BASE}/api/auth/${action}`, {
+            method: 'POST',
+            headers: { 'Content-Type': 'application/json' },
+            body: JSON.stringify(body),
+        });
+        const data = await response.json().catch(() => ({}));
+        if (!response.ok) {
+            throw new Error(data.message || `Request failed (${response.status})`);
+        }
+        this.user = data.user;
+        // Reload so the app state syncs with this account's data
+        window.location.reload();
+    }
+}
+
+export const authState = new AuthState();
@@ -7,15 +7,37 @@
 const API_URL = 'http://localhost:3000';
 
 export interface StreamEvent {
-    type: 'thinking_start' | 'thinking' | 'thinking_end' | 'content' | 'done' | 'error';
+    type: 'generation' | 'thinking_start' | 'thinking' | 'thinking_end' | 'content' | 'branch' | 'speaker' | 'speaker_done' | 'done' | 'interrupted' | 'error';
+    generation_id?: string;  // Pass to stopGeneration()
     content?: string;
+    persona_id?: string;  // Speaking persona (group chats)
+    name?: string;
+    branch_id?: string;  // Regeneration: branch the reply is written to
     message_id?: string;
     mood?: string;
     mood_value?: number;
     energy?: number;
+    context?: ContextReport;
     message?: string;
 }
 
+/** How the backend fitted the prompt into the model's context window (estimated tokens) */
+export interface ContextReport {
+    context_window: number;
+    response_reserve: number;
+    system_prompt: { budget: number; used: number };
+    memories: { budget: number; used: number };
+    session: { budget: number; used: number };
+    history: { budget: number; used: number };
+    user_message: number;
+    total_used: number;
+    memories_included: number;
+    memories_dropped: number;
+    history_included: number;
+    history_dropped: number;
+    history_summarized: number;
+}
+
 export interface ChatRequest {
     chat_id: string;
     branch_id: string;
@@ -23,14 +45,58 @@ export interface ChatRequest {
     model: string;
     user_persona_id?: string;
     ai_persona_id?: string;
+    cast?: ChatCast;
+}
+
+/** AI personas sharing a group chat and how they take turns */
+export interface ChatCast {
+    persona_ids: string[];
+    turn_policy?: 'round_robin' | 'mention' | 'moderator';
+    speakers_per_turn?: number;
+}
+
+export interface ChatListItem {
+    id: string;
+    title: string;
+    current_branch_id: string;
+    group_id?: string;
+    tags?: string[];
+    cast?: ChatCast;
+    last_message?: { role: string; content: string; timestamp: string };
+    message_count: number;
+    created_at: string;
+    updated_at: string;
+}
+
+/** A stored chat message as the backend returns it */
+export interface BackendChatMessage {
+    id: string;
+    role: 'user' | 'assistant' | 'system';
+    content: string;
+    timestamp?: string;
+    user_persona?: string;
+    ai_persona?: string;
+    model?: string;
+    mood?: string;
+    interrupted?: boolean;
+}
+
+export interface CursorPage<T> {
+    items: T[];
+    next_cursor: string | null;
 }
 
 export interface StreamCallbacks {
     onThinkingStart?: () => void;
     onThinking?: (content: string) => void;
     onThinkingEnd?: () => void;
-    onContent?: (content: string) => void;
+    onContent?: (content: string, personaId?: string) => void;
+    onSpeaker?: (personaId: string, name: string) => void;
+    onSpeakerDone?: (personaId: string, messageId: string, mood?: string) => void;
     onDone?: (messageId: string, mood?: string, moodValue?: number, energy?: number) => void;
+    onGeneration?: (generationId: string) => void;
+    onInterrupted?: (messageId: string | null) => void;
+    onEventId?: (eventId: number) => void;  // Last seen, for resumeStream()
     onError?: (error: string) => void;
 }
 
@@ -51,68 +117,94 @@ export async function sendMessageStream(
             body: JSON.stringify(request),
         });
 
-        if (!response.ok) {
-            const errorText = await response.text();
-            callbacks.onError?.(`API error: ${response.status} - ${errorText}`);
-            return;
-        }
+        await readEventStream(response, callbacks);
+    } catch (error) {
+        callbacks.onError?.(error instanceof Error ? error.message : 'Unknown error');
+    }
+}
 
-        const reader = response.body?.getReader();
-        if (!reader) {
-            callbacks.onError?.('No response body');
-            return;
-        }
+/**
+ * Reattach to a generation's stream after a dropped connection; events after
+ * `lastEventId` are replayed before it continues live
+ */
+export async function resumeStream(
+    generationId: string,
+    lastEventId: number,
+    callbacks: StreamCallbacks
+): Promise<void> {
+    try {
+        const response = await fetch(`${API_URL}/api/chat/stream/${generationId}`, {
+            headers: {
+                'Accept': 'text/event-stream',
+                'Last-Event-ID': String(lastEventId),
+            },
+        });
+        await readEventStream(response, callbacks);
+    } catch (error) {
+        callbacks.onError?.(error instanceof Error ? error.message : 'Unknown error');
+    }
+}
 
-        const decoder = new TextDecoder();
-        let buffer = '';
-
-        while (true) {
-            const { done, value } = await reader.read();
-            
-            if (done) break;
-
-            buffer += decoder.decode(value, { stream: true });
-            
-            // Process complete SSE events
-            const lines = buffer.split('\n');
-            buffer = lines.pop() || ''; // Keep incomplete line in buffer
-
-            for (const line of lines) {
-                if (line.startsWith('data: ')) {
-                    const data = line.slice(6).trim();
-                    if (data) {
-                        try {
-                            const event: StreamEvent = JSON.parse(data);
-                            handleStreamEvent(event, callbacks);
-                        } catch (e) {
-                            console.warn('Failed to parse SSE event:', data);
-                        }
-                    }
-                }
-            }
+async function readEventStream(response: Response, callbacks: StreamCallbacks): Promise<void> {
+    if (!response.ok) {
+        const errorText = await response.text();
+        callbacks.onError?.(`API error: ${response.status} - ${errorText}`);
+        return;
+    }
+
+    const reader = response.body?.getReader();
+    if (!reader) {
+        callbacks.onError?.('No response body');
+        return;
+    }
+
+    const decoder = new TextDecoder();
+    let buffer = '';
+
+    while (true) {
+        const { done, value } = await reader.read();
+        
+        if (done) break;
+
+        buffer += decoder.decode(value, { stream: true });
+        
+        // Process complete SSE events
+        const lines = buffer.split('\n');
+        buffer = lines.pop() || ''; // Keep incomplete line in buffer
+
+        for (const line of lines) {
+            handleStreamLine(line, callbacks);
         }
+    }
 
-        // Process any remaining buffer
-        if (buffer.trim()) {
-            if (buffer.startsWith('data: ')) {
-                const data = buffer.slice(6).trim();
-                if (data) {
-                    try {
-                        const event: StreamEvent = JSON.parse(data);
-                        handleStreamEvent(event, callbacks);
-                    } catch (e) {
-                        console.warn('Failed to parse final SSE event:', data);
-                    }
-                }
+    // Process any remaining buffer
+    if (buffer.trim()) {
+        handleStreamLine(buffer, callbacks);
+    }
+}
+
+function handleStreamLine(line: string, callbacks: StreamCallbacks) {
+    if (line.startsWith('id: ')) {
+        const id = Number(line.slice(4).trim());
+        if (Number.isFinite(id)) callbacks.onEventId?.(id);
+    } else if (line.startsWith('data: ')) {
+        const data = line.slice(6).trim();
+        if (data) {
+            try {
+                const event: StreamEvent = JSON.parse(data);
+                handleStreamEvent(event, callbacks);
+            } catch (e) {
+                console.warn('Failed to parse SSE event:', data);
             }
         }
-    } catch (error) {
-        callbacks.onError?.(error instanceof Error ? error.message : 'Unknown error');
     }
 }
 
 function handleStreamEvent(event: StreamEvent, callbacks: StreamCallbacks) {
     switch (event.type) {
+        case 'generation':
+            callbacks.onGeneration?.(event.generation_id || '');
+            break;
         case 'thinking_start':
             callbacks.onThinkingStart?.();
             break;
@@ -123,20 +215,118 @@ function handleStreamEvent(event: StreamEvent, callbacks: StreamCallbacks) {
             callbacks.onThinkingEnd?.();
             break;
         case 'content':
-            callbacks.onContent?.(event.content || '');
+            callbacks.onContent?.(event.content || '', event.persona_id);
+            break;
+        case 'speaker':
+            callbacks.onSpeaker?.(event.persona_id || '', event.name || '');
+            break;
+        case 'speaker_done':
+            callbacks.onSpeakerDone?.(event.persona_id || '', event.message_id || '', event.mood);
             break;
         case 'done':
             callbacks.onDone?.(event.message_id || '', event.mood, event.mood_value, event.energy);
             break;
+        case 'interrupted':
+            callbacks.onInterrupted?.(event.message_id ?? null);
+            break;
         case 'error':
             callbacks.onError?.(event.message || 'Unknown error');
             break;
     }
 }
 
+/** Agent event from GET /api/events, or a WebSocket notification */
+export type AgentEvent =
+    | { type: 'mood_changed'; persona_id: string; mood: string; mood_value: number; energy: number }
+    | { type: 'dream_started'; persona_id: string; concept: string }
+    | { type: 'dream_recorded'; persona_id: string; dream_id: string; title: string }
+    | { type: 'reflection_written'; persona_id: string; journal_id: string; date: string; title: string }
+    | { type: 'image_generated'; filename: string; url: string; prompt: string; persona_id?: string }
+    | { type: 'model_pulled'; model: string };
+
+/**
+ * Subscribe to live agent events; returns a function that unsubscribes
+ */
+export function subscribeAgentEvents(onEvent: (event: AgentEvent) => void): () => void {
+    const source = new EventSource(`${API_URL}/api/events`, { withCredentials: true });
+    source.onmessage = (message) => {
+        try {
+            onEvent(JSON.parse(message.data));
+        } catch (e) {
+            console.warn('Failed to parse agent event:', message.data);
+        }
+    };
+    return () => source.close();
+}
+
+export interface ChatSocketHandlers {
+    onStarted?: (chatId: string, generationId: string, requestId?: string) => void;
+    /** Stream callbacks for a chat's events */
+    eventsFor: (chatId: string) => StreamCallbacks;
+    onTyping?: (chatId: string, typing: boolean) => void;
+    onNotification?: (event: AgentEvent) => void;
+    onError?: (message: string, requestId?: string) => void;
+    onClose?: () => void;
+}
+
+/**
+ * Open the chat WebSocket: several chats share one connection
+ */
+export function connectChatSocket(handlers: ChatSocketHandlers) {
+    const socket = new WebSocket(`${API_URL.replace(/^http/, 'ws')}/api/ws`);
+    const ready = new Promise<void>((resolve) => socket.addEventListener('open', () => resolve()));
+
+    socket.addEventListener('message', (message) => {
+        let data;
+        try {
+            data = JSON.parse(message.data);
+        } catch (e) {
+            console.warn('Failed to parse socket message:', message.data);
+            return;
+        }
+        switch (data.type) {
+            case 'started':
+                handlers.onStarted?.(data.chat_id, data.generation_id, data.request_id);
+                break;
+            case 'event': {
+                const callbacks = handlers.eventsFor(data.chat_id);
+                callbacks.onEventId?.(data.id);
+                handleStreamEvent(data.event, callbacks);
+                break;
+            }
+            case 'typing':
+                handlers.onTyping?.(data.chat_id, data.typing);
+                break;
+            case 'notification':
+                handlers.onNotification?.(data.notification);
+                break;
+            case 'error':
+                handlers.onError?.(data.message, data.request_id);
+                break;
+        }
+    });
+    socket.addEventListener('close', () => handlers.onClose?.());
+
+    const post = async (message: object) => {
+        await ready;
+        socket.send(JSON.stringify(message));
+    };
+    return {
+        send: (request: ChatRequest, requestId?: string) => post({ type: 'send', request_id: requestId, ...request }),
+        stop: (generationId: string) => post({ type: 'stop', generation_id: generationId }),
+        typing: (chatId: string, typing = true) => post({ type: 'typing', chat_id: chatId, typing }),
+        close: () => socket.close(),
+    };
+}
+
 /**
  * Fetch available models from Ollama
  */
+export async function stopGeneration(generationId: string): Promise<void> {
+    const response = await fetch(`${API_URL}/api/chat/${generationId}/stop`, { method: 'POST' });
+    if (!response.ok && response.status !== 404) throw new Error('Failed to stop generation');
+}
+
 export async function fetchModels(): Promise<string[]> {
     try {
         const response = await fetch(`${API_URL}/api/models`);
@@ -176,14 +366,14 @@ export async function fetchStatus(): Promise<{
 }
 
 /**
- * Update AI mood
+ * Update the mood of one of the user's personas
  */
-export async function updateMood(mood: string): Promise<void> {
+export async function updateMood(personaId: string, mood: string): Promise<void> {
     try {
         await fetch(`${API_URL}/api/status/mood`, {
             method: 'POST',
             headers: { 'Content-Type': 'application/json' },
-            body: JSON.stringify({ mood }),
+            body: JSON.stringify({ persona_id: personaId, mood }),
         });
     } catch (error) {
         console.error('Failed to update mood:', error);
@@ -194,11 +384,37 @@ export async function updateMood(mood: string): Promise<void> {
 // CRUD Operations for Chats
 // ============================================================
 
-export async function fetchChats() {
-    const response = await fetch(`${API_URL}/api/chats`);
+export async function fetchChats(params: {
+    sort?: 'updated_at' | 'created_at' | 'title';
+    order?: 'asc' | 'desc';
+    limit?: number;
+    cursor?: string;
+} = {}): Promise<CursorPage<ChatListItem>> {
+    const query = new URLSearchParams();
+    for (const [key, value] of Object.entries(params)) {
+        if (value !== undefined) query.set(key, String(value));
+    }
+    const response = await fetch(`${API_URL}/api/chats?${query}`);
     if (!response.ok) throw new Error('Failed to fetch chats');
-    const data = await response.json();
-    return data.items || [];
+    return await response.json();
+}
+
+/**
+ * One page of a branch's history, oldest first. Pass the page's
+ * `next_cursor` back as `before` for the messages preceding it.
+ */
+export async function fetchBranchMessages(
+    chatId: string,
+    branchId: string,
+    before?: string,
+    limit?: number
+): Promise<CursorPage<BackendChatMessage>> {
+    const query = new URLSearchParams();
+    if (before) query.set('before', before);
+    if (limit) query.set('limit', String(limit));
+    const response = await fetch(`${API_URL}/api/chats/${chatId}/branches/${branchId}/messages?${query}`);
+    if (!response.ok) throw new Error('Failed to fetch messages');
+    return await response.json();
 }
 
 export async function createChat(title?: string, groupId?: string) {
@@ -259,6 +475,26 @@ export async function fetchPersonas() {
     return data.items || [];
 }
 
+/** Mental state and working memory of one AI persona */
+export interface PersonaState {
+    persona_id: string;
+    status: string;
+    mood: string;
+    mood_value: number;
+    energy: number;
+    focus_level: number;
+    is_dreaming: boolean;
+    last_active?: string;
+    last_dream?: string;
+    working_memory: string[];
+}
+
+export async function fetchPersonaState(personaId: string): Promise<PersonaState> {
+    const response = await fetch(`${API_URL}/api/personas/${encodeURIComponent(personaId)}/state`);
+    if (!response.ok) throw new Error('Failed to fetch persona state');
+    return await response.json();
+}
+
 export async function fetchPersonaTemplate(): Promise<string> {
     const response = await fetch(`${API_URL}/api/personas/template`);
     if (!response.ok) throw new Error('Failed to fetch persona template');
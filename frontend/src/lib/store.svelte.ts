@@ -1,3 +1,6 @@
+import './auth.svelte'; // Installs credentialed fetch before any backend call
+import { fromBackendMessage, prependOlder } from './store.utils';
+import type { ChatListItem } from './llm_service';
 // Type definitions
 export type Mood = 'idle' | 'thinking' | 'happy' | 'surprised' | 'content' | 'thoughtful' | 'melancholy' | 'curious' | 'excited' | 'calm' | 'concerned';
 
@@ -78,6 +81,7 @@ export interface ChatMessage {
     aiPersona?: string;
     model?: string;
     mood?: string;  // AI's mood when message was sent (idle, thinking, surprised, happy)
+    interrupted?: boolean;  // Generation was stopped before the reply finished
     thinking?: string;  // Captured thinking/reasoning content
 }
 
@@ -87,6 +91,7 @@ export interface ChatBranch {
     parentBranchId: string | null;  // null = main branch
     forkPointMessageId: string | null;  // Message ID where this branch diverged
     messages: ChatMessage[];
+    olderCursor?: string | null;  // `before` for the previous page; null once the start is loaded, unset until fetched
     createdAt: string;
 }
 
@@ -187,6 +192,7 @@ export class AppState {
     sidebarView = $state<'history' | 'dreams' | 'logs' | 'settings' | 'personas' | 'journal'>('history');
     scrollToBottomSignal = $state(0); // Counter to trigger scroll
     isLoading = $state(false);
+    isLoadingOlder = $state(false);
     status = $state<'awake' | 'dreaming' | 'thinking'>('awake');
     sessionId = $state(generateSessionId());
     
@@ -253,11 +259,11 @@ export class AppState {
     currentMood = $state<Mood>('idle');
     
     // Dreams (hallucinations from when the AI is idle)
-    dreams = $state<{ id: string; timestamp: string; title: string; content: string; mood?: string; personaId?: string; personaName?: string; tags?: string[] }[]>([]);
+    dreams = $state<{ id: string; timestamp: string; title: string; content: string; mood?: string; personaId?: string; personaName?: string; tags?: string[]; sourceMessageIds?: string[] }[]>([]);
     
     // Dream viewer state
     dreamViewerOpen = $state(false);
-    viewingDream = $state<{ id: string; timestamp: string; title: string; content: string; mood?: string; personaId?: string; personaName?: string; tags?: string[] } | null>(null);
+    viewingDream = $state<{ id: string; timestamp: string; title: string; content: string; mood?: string; personaId?: string; personaName?: string; tags?: string[]; sourceMessageIds?: string[] } | null>(null);
     
     // Logs (system and debug information)
     logs = $state<{ id: string; timestamp: string; level: 'info' | 'debug' | 'warn' | 'error'; message: string }[]>([
@@ -369,6 +375,9 @@ export class AppState {
             // Sync dreams, journal, personas, etc. from backend
             this.syncWithBackend();
             
+            // List the chats saved on the backend; messages load when one is opened
+            this.loadHistory();
+            
             // Mark as initialized
             this.isInitialized = true;
         }
@@ -484,6 +493,7 @@ export class AppState {
                 parentBranchId: null,
                 forkPointMessageId: null,
                 messages: [],
+                olderCursor: null,  // Nothing on the backend yet
                 createdAt: new Date().toISOString(),
             }],
             currentBranchId: mainBranchId,
@@ -548,6 +558,7 @@ export class AppState {
             this.saveCurrentChatId();
             // Scroll to bottom after loading chat
             this.triggerScrollToBottom();
+            if (branch) this.loadBranchMessages(chat, branch);
         }
     }
 
@@ -1028,22 +1039,84 @@ export class AppState {
     async loadHistory() {
         try {
             const { fetchChats } = await import('./llm_service');
-            const chats = await fetchChats();
-            if (chats && chats.length > 0) {
-                // Merge with existing chats, preferring backend data
+            // Walk every page of the chat list
+            const items: ChatListItem[] = [];
+            let cursor: string | undefined;
+            do {
+                const page = await fetchChats({ cursor });
+                items.push(...page.items);
+                cursor = page.next_cursor ?? undefined;
+            } while (cursor);
+            if (items.length > 0) {
+                // Add backend chats we don't have yet; their messages load per branch
                 const existingIds = new Set(this.chats.map(c => c.id));
-                for (const chat of chats) {
-                    if (!existingIds.has(chat.id)) {
-                        this.chats.push(chat);
+                for (const item of items) {
+                    if (!existingIds.has(item.id)) {
+                        this.chats.push({
+                            id: item.id,
+                            title: item.title,
+                            createdAt: item.created_at,
+                            currentBranchId: item.current_branch_id,
+                            groupId: item.group_id,
+                            tags: item.tags,
+                            branches: [{
+                                id: item.current_branch_id,
+                                name: 'Main',
+                                parentBranchId: null,
+                                forkPointMessageId: null,
+                                messages: [],
+                                createdAt: item.created_at
+                            }]
+                        });
                     }
                 }
                 this.saveChats();
             }
+            const chat = this.currentChat;
+            const branch = this.currentBranch();
+            if (chat && branch) this.loadBranchMessages(chat, branch);
         } catch (error) {
             console.error('Failed to load history:', error);
         }
     }
 
+    /**
+     * Fetch the latest page of a branch the first time it is opened;
+     * older pages follow through loadOlderMessages()
+     */
+    async loadBranchMessages(chat: Chat, branch: ChatBranch) {
+        if (branch.olderCursor !== undefined || branch.messages.length > 0) return;
+        try {
+            const { fetchBranchMessages } = await import('./llm_service');
+            const page = await fetchBranchMessages(chat.id, branch.id);
+            branch.messages = prependOlder(page.items.map(fromBackendMessage), branch.messages);
+            branch.olderCursor = page.next_cursor;
+            this.saveChats();
+            if (chat.id === this.currentChatId) this.triggerScrollToBottom();
+        } catch (error) {
+            console.error('Failed to load messages:', error);
+        }
+    }
+
+    /** Page the current branch back by one page */
+    async loadOlderMessages() {
+        const chat = this.currentChat;
+        const branch = this.currentBranch();
+        if (!chat || !branch?.olderCursor || this.isLoadingOlder) return;
+        this.isLoadingOlder = true;
+        try {
+            const { fetchBranchMessages } = await import('./llm_service');
+            const page = await fetchBranchMessages(chat.id, branch.id, branch.olderCursor);
+            branch.messages = prependOlder(page.items.map(fromBackendMessage), branch.messages);
+            branch.olderCursor = page.next_cursor;
+            this.saveChats();
+        } catch (error) {
+            console.error('Failed to load older messages:', error);
+        } finally {
+            this.isLoadingOlder = false;
+        }
+    }
+
     async getStatus() {
         try {
             const { fetchStatus } = await import('./llm_service');
@@ -1174,6 +1247,7 @@ export class AppState {
                     personaId: d.persona_id,
                     personaName: d.persona_name,
                     tags: d.tags || [],
+                    sourceMessageIds: d.source_message_ids || [],
                 }));
             }
 
@@ -1280,6 +1354,7 @@ export class AppState {
             this.currentChat.currentBranchId = branchId;
             this.saveChats();
             this.triggerScrollToBottom();
+            this.loadBranchMessages(this.currentChat, branch);
         }
     }
     
@@ -1,5 +1,5 @@
 import { describe, test, expect } from 'bun:test';
-import { generateSessionId, type Chat, type ChatBranch, type ChatMessage, type Persona, type Tag, type ChatGroup, type GeneratedImage, type ImageModel } from './store.utils';
+import { generateSessionId, fromBackendMessage, prependOlder, type Chat, type ChatBranch, type ChatMessage, type Persona, type Tag, type ChatGroup, type GeneratedImage, type ImageModel } from './store.utils';
 
 describe('Store Utilities', () => {
     describe('generateSessionId', () => {
@@ -37,6 +37,38 @@ describe('Store Utilities', () => {
         });
     });
 
+    describe('fromBackendMessage', () => {
+        test('maps snake_case fields', () => {
+            const message = fromBackendMessage({
+                id: 'msg-1',
+                role: 'assistant',
+                content: 'Hi',
+                user_persona: 'Sam',
+                ai_persona: 'Azera',
+                model: 'llama3.2',
+                interrupted: true,
+            });
+            expect(message.userPersona).toBe('Sam');
+            expect(message.aiPersona).toBe('Azera');
+            expect(message.model).toBe('llama3.2');
+            expect(message.interrupted).toBe(true);
+        });
+    });
+
+    describe('prependOlder', () => {
+        const msg = (id: string): ChatMessage => ({ id, role: 'user', content: id });
+
+        test('puts the older page first', () => {
+            const merged = prependOlder([msg('a'), msg('b')], [msg('c')]);
+            expect(merged.map(m => m.id)).toEqual(['a', 'b', 'c']);
+        });
+
+        test('skips messages already loaded', () => {
+            const merged = prependOlder([msg('a'), msg('b')], [msg('b'), msg('c')]);
+            expect(merged.map(m => m.id)).toEqual(['a', 'b', 'c']);
+        });
+    });
+
     describe('Type Structures', () => {
         describe('Chat type', () => {
             test('has required properties', () => {
@@ -11,6 +11,42 @@ export function generateSessionId(): string {
     return `session_${Date.now()}_${Math.random().toString(36).substring(2, 11)}`;
 }
 
+/**
+ * Converts a message from the backend (snake_case) to the store's shape
+ */
+export function fromBackendMessage(msg: {
+    id: string;
+    role: 'user' | 'assistant' | 'system';
+    content: string;
+    timestamp?: string;
+    user_persona?: string;
+    ai_persona?: string;
+    model?: string;
+    mood?: string;
+    interrupted?: boolean;
+}): ChatMessage {
+    return {
+        id: msg.id,
+        role: msg.role,
+        content: msg.content,
+        timestamp: msg.timestamp,
+        userPersona: msg.user_persona,
+        aiPersona: msg.ai_persona,
+        model: msg.model,
+        mood: msg.mood,
+        interrupted: msg.interrupted,
+    };
+}
+
+/**
+ * Puts an older page of messages in front of the loaded ones,
+ * skipping any that are already there
+ */
+export function prependOlder(older: ChatMessage[], loaded: ChatMessage[]): ChatMessage[] {
+    const known = new Set(loaded.map(m => m.id));
+    return [...older.filter(m => !known.has(m.id)), ...loaded];
+}
+
 /**
  * Type definitions for the store (extracted for testing)
  */
@@ -71,6 +107,7 @@ export interface ChatMessage {
     aiPersona?: string;
     model?: string;
     mood?: string;
+    interrupted?: boolean;
     thinking?: string;
 }
 
@@ -80,6 +117,7 @@ export interface ChatBranch {
     parentBranchId: string | null;
     forkPointMessageId: string | null;
     messages: ChatMessage[];
+    olderCursor?: string | null;
     createdAt: string;
 }
 